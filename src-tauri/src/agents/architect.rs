@@ -2,7 +2,7 @@
 // PRD Section 2 (Phase A): Decomposes PRD into Atomic Micro-Tasks
 
 use super::{Agent, AgentContext, ArchitectureOutput, LayerSpec, MicroTask, PlanOutput};
-use crate::llm::{LlmConfig, LlmProvider, Message};
+use crate::llm::{AgentRole, LlmConfig, LlmProvider, LlmRouter, Message};
 use serde::{Deserialize, Serialize};
 
 /// The Architect Agent
@@ -21,7 +21,7 @@ impl Default for Architect {
         Self {
             max_decomposition_depth: 3,
             target_task_complexity: 2,
-            llm_config: LlmConfig::cerebras(), // Default to Cerebras for speed
+            llm_config: LlmRouter::load().config_for(AgentRole::Architect),
         }
     }
 }
@@ -3,14 +3,24 @@
 // Takes a ContextPackage from L3 and executes a focused LLM call
 
 use super::context_engineer::ContextPackage;
+use super::response_cache::ResponseCache;
 use crate::grits;
-use crate::llm::{LlmConfig, LlmProvider, Message, SystemPrompts};
+use crate::llm::limiter::{estimate_tokens, ProviderLimiter};
+use crate::llm::{
+    pricing, LlmConfig, LlmError, LlmProvider, LlmResponse, Message, ProviderLimits, ResponseFormat, StreamChunk,
+    SystemPrompts,
+};
 use crate::maker_core::{AtomResult, AtomType, SpawnFlags};
+use futures::StreamExt;
 use grits_core::topology::virtual_apply::{ChangeType, ProposedChange, VirtualApply};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Instant;
 
+/// Schema errors reported back to the model in a repair round
+const MAX_REPORTED_SCHEMA_ERRORS: usize = 10;
+
 /// Input to an Atom execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AtomInput {
@@ -24,6 +34,9 @@ pub struct AtomInput {
     pub flags: SpawnFlags,
     /// Additional variables for prompt templating
     pub variables: HashMap<String, String>,
+    /// Always call the LLM, even if the executor has a response cache (e.g. voting samples)
+    #[serde(default)]
+    pub bypass_cache: bool,
 }
 
 impl AtomInput {
@@ -34,6 +47,7 @@ impl AtomInput {
             context: None,
             flags: SpawnFlags::default(),
             variables: HashMap::new(),
+            bypass_cache: false,
         }
     }
 
@@ -51,6 +65,11 @@ impl AtomInput {
         self.variables.insert(key.to_string(), value.to_string());
         self
     }
+
+    pub fn without_cache(mut self) -> Self {
+        self.bypass_cache = true;
+        self
+    }
 }
 
 /// Parsed output from an atom execution
@@ -116,30 +135,237 @@ pub struct PlanStep {
     pub atom_type: Option<String>,
 }
 
+/// Callback receiving streamed output deltas
+pub type DeltaCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Options for `AtomExecutor::execute_stream`
+#[derive(Clone, Default)]
+pub struct StreamOptions {
+    /// Abort once the output exceeds this many words (see `GovernanceConfig::max_tokens`)
+    pub verbosity_limit: Option<usize>,
+    /// Called with each output delta as it arrives
+    pub on_delta: Option<DeltaCallback>,
+}
+
+impl StreamOptions {
+    pub fn with_verbosity_limit(mut self, limit: usize) -> Self {
+        self.verbosity_limit = Some(limit);
+        self
+    }
+
+    pub fn with_on_delta(mut self, on_delta: DeltaCallback) -> Self {
+        self.on_delta = Some(on_delta);
+        self
+    }
+}
+
 /// The L4 Atom Executor
 /// Responsible for executing a single, focused LLM call with one tool/output type
 pub struct AtomExecutor {
     llm_config: LlmConfig,
+    /// Optional response cache consulted by `execute`
+    cache: Option<ResponseCache>,
+    /// Concurrency, rate limits and circuit breaker shared by every call to the endpoint
+    limiter: Arc<ProviderLimiter>,
 }
 
 impl AtomExecutor {
+    /// Calls are limited by the settings' `provider_limits` for the config's endpoint
     pub fn new(llm_config: LlmConfig) -> Self {
-        Self { llm_config }
+        Self {
+            limiter: ProviderLimiter::from_settings(&llm_config),
+            llm_config,
+            cache: None,
+        }
+    }
+
+    /// Limit calls with `limits` instead (see `ProviderLimiter::shared`)
+    pub fn with_limits(mut self, limits: &ProviderLimits) -> Self {
+        self.limiter = ProviderLimiter::shared(&self.llm_config, limits);
+        self
+    }
+
+    /// Serve repeated identical calls from `cache` (see `AtomInput::without_cache`)
+    pub fn with_cache(mut self, cache: ResponseCache) -> Self {
+        self.cache = Some(cache);
+        self
     }
 
     /// Execute an atom with the given input
     pub async fn execute(&self, input: AtomInput) -> Result<AtomResult, String> {
         let start = Instant::now();
+        let schema = Self::output_validator(&input)?;
+
+        // Look up before creating the provider: a hit needs no API key or connection
+        let cached = self.cache_key(&input);
+        if let Some(result) = self.cache_hit(&input, schema.as_ref(), cached.as_ref(), start) {
+            return Ok(result);
+        }
+        let (provider, messages) = self.prepare(&input)?;
+
+        // Execute the LLM call
+        let response = self
+            .complete(&provider, messages.clone())
+            .await
+            .map_err(|e| format!("LLM call failed: {}", e))?;
+
+        let (result, response) = self
+            .finish_with_repairs(&provider, messages, &input, schema.as_ref(), response, start)
+            .await;
+        Self::cache_store(cached, &input, &result, &response);
+        Ok(result)
+    }
+
+    /// Execute an atom, streaming output deltas as they arrive
+    /// If the output grows past `options.verbosity_limit` words the stream is dropped
+    /// (cancelling the request) and a red-flagged failure marked `aborted` is returned.
+    /// A cache hit is replayed as a single delta.
+    pub async fn execute_stream(&self, input: AtomInput, options: StreamOptions) -> Result<AtomResult, String> {
+        let start = Instant::now();
+        let schema = Self::output_validator(&input)?;
+
+        let cached = self.cache_key(&input);
+        if let Some(result) = self.cache_hit(&input, schema.as_ref(), cached.as_ref(), start) {
+            if let Some(on_delta) = &options.on_delta {
+                on_delta(&result.output);
+            }
+            return Ok(result);
+        }
+        let (provider, messages) = self.prepare(&input)?;
+
+        // The permit holds the concurrency slot until the stream is finished or dropped
+        let request = messages.clone();
+        let streaming = &provider;
+        let (permit, mut stream) = self
+            .limiter
+            .call(Self::estimate(&messages), |permit| async move {
+                Ok((permit, streaming.complete_stream(request).await?))
+            })
+            .await
+            .map_err(|e| format!("LLM call failed: {}", e))?;
+
+        let mut content = String::new();
+        while let Some(chunk) = stream.next().await {
+            match chunk.map_err(|e| format!("LLM call failed: {}", e))? {
+                StreamChunk::Delta(text) => {
+                    content.push_str(&text);
+                    if let Some(on_delta) = &options.on_delta {
+                        on_delta(&text);
+                    }
+
+                    // Same word-count heuristic as the governance verbosity check
+                    if let Some(limit) = options.verbosity_limit {
+                        let words = content.split_whitespace().count();
+                        if words > limit {
+                            let reason = format!("Response too verbose ({} tokens > {})", words, limit);
+                            let mut result = AtomResult::failure(input.atom_type, content, vec![reason.clone()]);
+                            result.execution_time_ms = start.elapsed().as_millis() as u64;
+                            result.metadata.insert("aborted".to_string(), "true".to_string());
+                            result.set_red_flagged(&reason);
+                            return Ok(result);
+                        }
+                    }
+                }
+                StreamChunk::Done(response) => {
+                    permit.settle(response.tokens_used);
+                    drop(permit);
+                    // Repair rounds are not streamed; the deltas above are the first attempt
+                    let (result, response) = self
+                        .finish_with_repairs(&provider, messages, &input, schema.as_ref(), response, start)
+                        .await;
+                    Self::cache_store(cached, &input, &result, &response);
+                    return Ok(result);
+                }
+            }
+        }
+
+        Err("LLM stream ended without a final response".to_string())
+    }
+
+    /// One completion under the endpoint's concurrency, rate limits and circuit breaker
+    async fn complete(&self, provider: &LlmProvider, messages: Vec<Message>) -> Result<LlmResponse, LlmError> {
+        self.limiter
+            .call(Self::estimate(&messages), |permit| async move {
+                let response = provider.complete(messages).await?;
+                permit.settle(response.tokens_used);
+                Ok(response)
+            })
+            .await
+    }
+
+    /// Prompt tokens a request is expected to use, for the tokens-per-minute limit
+    fn estimate(messages: &[Message]) -> u32 {
+        estimate_tokens(messages.iter().map(|m| m.content.len()).sum())
+    }
+
+    /// The cache and key for an atom call, or None if caching does not apply
+    fn cache_key(&self, input: &AtomInput) -> Option<(&ResponseCache, String)> {
+        let cache = self.cache.as_ref().filter(|_| !input.bypass_cache)?;
+        let key = ResponseCache::key(
+            input.atom_type,
+            &self.build_system_prompt(input),
+            &self.build_user_prompt(input),
+            &self.llm_config,
+        );
+        Some((cache, key))
+    }
 
+    /// The cached result for an atom call, counting the lookup; None on a miss or without a cache
+    fn cache_hit(
+        &self,
+        input: &AtomInput,
+        schema: Option<&jsonschema::Validator>,
+        cached: Option<&(&ResponseCache, String)>,
+        start: Instant,
+    ) -> Option<AtomResult> {
+        let (cache, key) = cached?;
+        let hit = cache.get(key);
+        crate::record_cache_lookup(hit.is_some());
+        let mut response = hit?;
+
+        // A hit costs nothing, so it must not count towards token totals
+        response.tokens_used = None;
+        response.prompt_tokens = None;
+        response.completion_tokens = None;
+        let mut result = self.finish(input, schema, response, start);
+        result.metadata.insert("cache".to_string(), "hit".to_string());
+        Some(result)
+    }
+
+    /// Cache usable output; a rejected response should be retried, not replayed
+    fn cache_store(
+        cached: Option<(&ResponseCache, String)>,
+        input: &AtomInput,
+        result: &AtomResult,
+        response: &LlmResponse,
+    ) {
+        let Some((cache, key)) = cached else {
+            return;
+        };
+        if result.valid && !result.is_red_flagged() {
+            if let Err(e) = cache.put(&key, input.atom_type, response) {
+                eprintln!("Warning: Failed to cache atom response: {}", e);
+            }
+        }
+    }
+
+    /// Create the provider and prompt messages for an atom
+    fn prepare(&self, input: &AtomInput) -> Result<(LlmProvider, Vec<Message>), String> {
         // Build the system prompt for this atom type
-        let system_prompt = self.build_system_prompt(&input);
+        let system_prompt = self.build_system_prompt(input);
 
         // Build the user prompt with context and task
-        let user_prompt = self.build_user_prompt(&input);
+        let user_prompt = self.build_user_prompt(input);
 
-        // Create LLM provider
-        let provider = LlmProvider::new(self.llm_config.clone())
+        // Create LLM provider, in native JSON mode when the output must be JSON
+        let mut provider = LlmProvider::new(self.llm_config.clone())
             .map_err(|e| format!("Failed to create LLM provider: {}", e))?;
+        if input.flags.expects_json() {
+            provider = provider.with_response_format(match input.flags.schema_for(input.atom_type) {
+                Some(schema) => ResponseFormat::JsonSchema(schema),
+                None => ResponseFormat::Json,
+            });
+        }
 
         // Build messages
         let messages = vec![
@@ -147,32 +373,111 @@ impl AtomExecutor {
             Message::user(&user_prompt),
         ];
 
-        // Execute the LLM call
-        let response = provider
-            .complete(messages)
-            .await
-            .map_err(|e| format!("LLM call failed: {}", e))?;
+        Ok((provider, messages))
+    }
+
+    /// Finish a response, asking the model to fix output that is not valid JSON or fails its schema
+    /// Each round sends the rejected output and its errors back to the model, up to
+    /// `flags.max_repairs` times. The returned result includes the usage of every round.
+    async fn finish_with_repairs(
+        &self,
+        provider: &LlmProvider,
+        mut messages: Vec<Message>,
+        input: &AtomInput,
+        schema: Option<&jsonschema::Validator>,
+        mut response: LlmResponse,
+        start: Instant,
+    ) -> (AtomResult, LlmResponse) {
+        let mut result = self.finish(input, schema, response.clone(), start);
+        let mut repairs = 0;
+
+        while !result.valid && input.flags.expects_json() && repairs < input.flags.max_repairs {
+            messages.push(Message::assistant(&response.content));
+            messages.push(Message::user(&format!(
+                "Your response was rejected:\n- {}\n\nReturn the corrected JSON only.",
+                result.errors.join("\n- ")
+            )));
+
+            let repaired = match self.complete(provider, messages.clone()).await {
+                Ok(repaired) => repaired,
+                Err(e) => {
+                    eprintln!("Warning: Repair call for {} atom failed: {}", input.atom_type.as_str(), e);
+                    break;
+                }
+            };
+            repairs += 1;
+
+            let previous = result;
+            result = self.finish(input, schema, repaired.clone(), start);
+            result.tokens_used += previous.tokens_used;
+            result.prompt_tokens += previous.prompt_tokens;
+            result.completion_tokens += previous.completion_tokens;
+            result.cost_usd += previous.cost_usd;
+            response = repaired;
+        }
+
+        if repairs > 0 {
+            result.metadata.insert("repairs".to_string(), repairs.to_string());
+        }
+        (result, response)
+    }
 
+    /// The compiled output schema, when the output must be JSON and has one
+    /// Compiled once per input, then reused for the first answer and every repair round.
+    fn output_validator(input: &AtomInput) -> Result<Option<jsonschema::Validator>, String> {
+        if !input.flags.expects_json() {
+            return Ok(None);
+        }
+        input
+            .flags
+            .schema_for(input.atom_type)
+            .map(|schema| jsonschema::validator_for(&schema))
+            .transpose()
+            .map_err(|e| format!("Invalid output schema: {}", e))
+    }
+
+    /// Turn a completed LLM response into a validated, red-flag-checked AtomResult
+    fn finish(
+        &self,
+        input: &AtomInput,
+        schema: Option<&jsonschema::Validator>,
+        response: LlmResponse,
+        start: Instant,
+    ) -> AtomResult {
         let execution_time_ms = start.elapsed().as_millis() as u64;
         let tokens_used = response.tokens_used.unwrap_or(0) as usize;
 
         // Parse and validate the output
-        let (output, valid, errors) = self.parse_output(&input.atom_type, &response.content, &input.flags);
+        let (output, valid, errors) = self.parse_output(&input.atom_type, &response.content, &input.flags, schema);
 
         let mut result = if valid {
             AtomResult::success(input.atom_type, output, execution_time_ms, tokens_used)
         } else {
             AtomResult::failure(input.atom_type, output, errors)
         };
+        // Invalid outputs still cost tokens, so record usage on both paths
+        result.execution_time_ms = execution_time_ms;
+        result.tokens_used = tokens_used;
+        result.prompt_tokens = response.prompt_tokens.unwrap_or(0) as usize;
+        result.completion_tokens = response.completion_tokens.unwrap_or(0) as usize;
+        result.cost_usd = pricing().cost(
+            &self.llm_config.model,
+            result.prompt_tokens,
+            result.completion_tokens,
+            tokens_used,
+        );
+        if let Some(reason) = &response.finish_reason {
+            result.metadata.insert("finish_reason".to_string(), reason.clone());
+        }
 
         // Check for red flags if enabled
         if input.flags.red_flag_check {
-            if let Some(reason) = self.check_red_flags(&result, &input) {
+            if let Some(reason) = self.check_red_flags(&result, input) {
                 result.set_red_flagged(&reason);
             }
         }
 
-        Ok(result)
+        result
     }
 
     /// Build the system prompt for the atom type
@@ -191,14 +496,20 @@ impl AtomExecutor {
         };
 
         // Add JSON output instruction if required
-        if input.flags.require_json {
-            format!(
-                "{}\n\n## Output Format\nYou MUST return valid JSON only. No markdown, no explanations.",
-                base_prompt
-            )
-        } else {
-            base_prompt.to_string()
+        if !input.flags.expects_json() {
+            return base_prompt.to_string();
         }
+        let mut prompt = format!(
+            "{}\n\n## Output Format\nYou MUST return valid JSON only. No markdown, no explanations.",
+            base_prompt
+        );
+        if let Some(schema) = input.flags.schema_for(input.atom_type) {
+            prompt.push_str(&format!(
+                "\nThe JSON must match this JSON Schema:\n{}",
+                serde_json::to_string_pretty(&schema).unwrap_or_default()
+            ));
+        }
+        prompt
     }
 
     /// Build the user prompt with context and task
@@ -239,24 +550,24 @@ impl AtomExecutor {
         atom_type: &AtomType,
         raw_output: &str,
         flags: &SpawnFlags,
+        schema: Option<&jsonschema::Validator>,
     ) -> (String, bool, Vec<String>) {
         let mut errors = Vec::new();
 
-        // If JSON is required, validate it
-        if flags.require_json {
-            match serde_json::from_str::<serde_json::Value>(raw_output) {
-                Ok(_) => return (raw_output.to_string(), true, errors),
-                Err(e) => {
-                    // Try to extract JSON from markdown code blocks
-                    if let Some(json_str) = self.extract_json_from_markdown(raw_output) {
-                        if serde_json::from_str::<serde_json::Value>(&json_str).is_ok() {
-                            return (json_str, true, errors);
-                        }
-                    }
-                    errors.push(format!("Invalid JSON output: {}", e));
-                    return (raw_output.to_string(), false, errors);
-                }
+        // If JSON is required, it must parse and match the output schema
+        if flags.expects_json() {
+            let Some((json_str, json)) = self.extract_json(raw_output) else {
+                let reason = serde_json::from_str::<serde_json::Value>(raw_output)
+                    .err()
+                    .map(|e| e.to_string())
+                    .unwrap_or_default();
+                errors.push(format!("Invalid JSON output: {}", reason));
+                return (raw_output.to_string(), false, errors);
+            };
+            if let Some(schema) = schema {
+                errors = schema_errors(schema, &json);
             }
+            return (json_str, errors.is_empty(), errors);
         }
 
         // Atom-specific validation
@@ -267,25 +578,14 @@ impl AtomExecutor {
                     errors.push("Coder output should contain code blocks".to_string());
                 }
             }
-            AtomType::Reviewer => {
-                // Reviewer should return JSON with approved field
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(raw_output) {
-                    if json.get("approved").is_none() {
-                        errors.push("Reviewer output missing 'approved' field".to_string());
-                    }
-                } else if let Some(json_str) = self.extract_json_from_markdown(raw_output) {
-                    if let Ok(json) = serde_json::from_str::<serde_json::Value>(&json_str) {
-                        if json.get("approved").is_some() {
-                            return (json_str, true, errors);
-                        }
-                    }
-                }
-            }
-            AtomType::Validator => {
-                // Validator should return JSON with valid field
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(raw_output) {
-                    if json.get("valid").is_none() {
-                        errors.push("Validator output missing 'valid' field".to_string());
+            AtomType::Reviewer | AtomType::Validator => {
+                // Without a JSON request only the verdict field is required, as before schemas
+                let field = if *atom_type == AtomType::Reviewer { "approved" } else { "valid" };
+                if let Some((json_str, json)) = self.extract_json(raw_output) {
+                    if json.get(field).is_none() {
+                        errors.push(format!("{} output missing '{}' field", atom_type.as_str(), field));
+                    } else {
+                        return (json_str, true, errors);
                     }
                 }
             }
@@ -295,6 +595,16 @@ impl AtomExecutor {
         (raw_output.to_string(), errors.is_empty(), errors)
     }
 
+    /// Parse output as JSON, directly or from a markdown code block
+    fn extract_json(&self, raw_output: &str) -> Option<(String, serde_json::Value)> {
+        if let Ok(json) = serde_json::from_str(raw_output) {
+            return Some((raw_output.to_string(), json));
+        }
+        let json_str = self.extract_json_from_markdown(raw_output)?;
+        let json = serde_json::from_str(&json_str).ok()?;
+        Some((json_str, json))
+    }
+
     /// Extract JSON from markdown code blocks
     fn extract_json_from_markdown(&self, text: &str) -> Option<String> {
         // Look for ```json ... ``` blocks
@@ -358,47 +668,17 @@ impl AtomExecutor {
         let graph = grits::get_cached_graph()?;
 
         // Parse code output into proposed changes
-        let code_changes = self.parse_code_output(code_output);
-        if code_changes.is_empty() {
+        let proposed_changes = self.proposed_changes(code_output);
+        if proposed_changes.is_empty() {
             return None;
         }
 
-        // Convert CodeChange to ProposedChange for VirtualApply
-        let proposed_changes: Vec<ProposedChange> = code_changes
-            .iter()
-            .map(|change| ProposedChange {
-                file_path: change.file_path.clone(),
-                // Determine change type based on file existence
-                change_type: if std::path::Path::new(&change.file_path).exists() {
-                    ChangeType::ModifyFile
-                } else {
-                    ChangeType::CreateFile
-                },
-                code_content: change.content.clone(),
-                language: change.language.clone().unwrap_or_else(|| {
-                    // Infer language from file extension
-                    change.file_path
-                        .rsplit('.')
-                        .next()
-                        .map(|ext| match ext {
-                            "rs" => "rust",
-                            "ts" | "tsx" => "typescript",
-                            "js" | "jsx" => "javascript",
-                            "py" => "python",
-                            "go" => "go",
-                            _ => "unknown",
-                        })
-                        .unwrap_or("unknown")
-                        .to_string()
-                }),
-            })
-            .collect();
-
-        // Load layer config if available
-        let layer_config = grits::get_cached_workspace_path()
-            .and_then(|ws_path| {
-                grits_core::topology::layers::load_layer_config(std::path::Path::new(&ws_path)).ok()
-            });
+        // Load layer config if available; a broken layers.yaml is reported rather than ignored
+        let workspace_path = grits::get_cached_workspace_path();
+        let layer_config = match grits::load_workspace_layer_config(workspace_path.as_deref()) {
+            Ok(config) => config,
+            Err(e) => return Some(format!("Architectural red flags: {}", e)),
+        };
 
         // Run virtual apply validation
         let virtual_apply = VirtualApply::new(graph, layer_config);
@@ -430,6 +710,41 @@ impl AtomExecutor {
         None
     }
 
+    /// Convert a Coder atom's output into proposed changes for VirtualApply
+    pub fn proposed_changes(&self, code_output: &str) -> Vec<ProposedChange> {
+        let code_changes = self.parse_code_output(code_output);
+        // Convert CodeChange to ProposedChange for VirtualApply
+        code_changes
+            .iter()
+            .map(|change| ProposedChange {
+                file_path: change.file_path.clone(),
+                // Determine change type based on file existence
+                change_type: if std::path::Path::new(&change.file_path).exists() {
+                    ChangeType::ModifyFile
+                } else {
+                    ChangeType::CreateFile
+                },
+                code_content: change.content.clone(),
+                language: change.language.clone().unwrap_or_else(|| {
+                    // Infer language from file extension
+                    change.file_path
+                        .rsplit('.')
+                        .next()
+                        .map(|ext| match ext {
+                            "rs" => "rust",
+                            "ts" | "tsx" => "typescript",
+                            "js" | "jsx" => "javascript",
+                            "py" => "python",
+                            "go" => "go",
+                            _ => "unknown",
+                        })
+                        .unwrap_or("unknown")
+                        .to_string()
+                }),
+            })
+            .collect()
+    }
+
     /// Parse code output into structured CodeChange objects
     pub fn parse_code_output(&self, raw_output: &str) -> Vec<CodeChange> {
         let mut changes = Vec::new();
@@ -552,3 +867,193 @@ impl AtomExecutor {
     }
 }
 
+/// Validation errors of a JSON value against a schema, as "path: message" lines
+fn schema_errors(schema: &jsonschema::Validator, json: &serde_json::Value) -> Vec<String> {
+    schema
+        .iter_errors(json)
+        .take(MAX_REPORTED_SCHEMA_ERRORS)
+        .map(|e| {
+            let path = e.instance_path().to_string();
+            format!("{}: {}", if path.is_empty() { "(root)" } else { &path }, e)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::replay::{register_mock, unregister_mock};
+    use serde_json::json;
+
+    #[test]
+    fn test_output_is_checked_against_schema() {
+        let executor = AtomExecutor::new(LlmConfig::mock("unused"));
+        let parse = |atom_type: AtomType, raw: &str, flags: &SpawnFlags| {
+            let input = AtomInput::new(atom_type, "task").with_flags(flags.clone());
+            let schema = AtomExecutor::output_validator(&input).unwrap();
+            executor.parse_output(&atom_type, raw, flags, schema.as_ref())
+        };
+        let json_flags = SpawnFlags { require_json: true, ..Default::default() };
+
+        let fenced = "Here you go:\n```json\n{\"valid\": true, \"violations\": []}\n```";
+        let (output, valid, errors) = parse(AtomType::Validator, fenced, &json_flags);
+        assert!(valid, "{:?}", errors);
+        assert_eq!(output, "{\"valid\": true, \"violations\": []}");
+
+        let (_, valid, errors) = parse(AtomType::Reviewer, r#"{"approved": "yes"}"#, &json_flags);
+        assert!(!valid);
+        assert!(errors.iter().any(|e| e.starts_with("/approved:")), "{:?}", errors);
+        assert!(errors.iter().any(|e| e.contains("\"issues\" is a required property")), "{:?}", errors);
+
+        // A custom schema replaces the atom type's and implies JSON
+        let custom = SpawnFlags {
+            output_schema: Some(json!({ "type": "object", "required": ["answer"] })),
+            ..Default::default()
+        };
+        assert!(parse(AtomType::Planner, r#"{"answer": 42}"#, &custom).1);
+        assert!(!parse(AtomType::Planner, "forty-two", &custom).1);
+
+        // Without require_json, a reviewer's prose is still accepted as before
+        let (_, valid, _) = parse(AtomType::Reviewer, "Looks good to me", &SpawnFlags::default());
+        assert!(valid);
+        // and its JSON only needs the verdict field, not the full schema
+        let (output, valid, _) = parse(AtomType::Reviewer, r#"{"approved": "yes"}"#, &SpawnFlags::default());
+        assert!(valid);
+        assert_eq!(output, r#"{"approved": "yes"}"#);
+        let (_, valid, errors) = parse(AtomType::Validator, r#"{"violations": []}"#, &SpawnFlags::default());
+        assert!(!valid);
+        assert_eq!(errors, vec!["Validator output missing 'valid' field".to_string()]);
+
+        // A broken custom schema fails before any call is made
+        let broken = SpawnFlags { output_schema: Some(json!({ "type": 12 })), ..Default::default() };
+        let input = AtomInput::new(AtomType::Planner, "task").with_flags(broken);
+        assert!(AtomExecutor::output_validator(&input).unwrap_err().starts_with("Invalid output schema"));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_output_is_repaired_with_schema_errors() {
+        register_mock(
+            "schema_repair",
+            Arc::new(|_: &str, conversation: &[Message]| {
+                if conversation.len() == 1 {
+                    return Ok(r#"{"approved": "yes"}"#.to_string());
+                }
+                // The repair request carries the validation errors
+                let feedback = &conversation.last().unwrap().content;
+                assert!(feedback.contains("/approved"), "{}", feedback);
+                Ok(r#"{"approved": true, "issues": []}"#.to_string())
+            }),
+        );
+
+        let executor = AtomExecutor::new(LlmConfig::mock("schema_repair"));
+        let input = AtomInput::new(AtomType::Reviewer, "Review this").with_flags(SpawnFlags {
+            require_json: true,
+            ..Default::default()
+        });
+        let result = executor.execute(input.clone()).await.unwrap();
+        assert!(result.valid, "{:?}", result.errors);
+        assert_eq!(result.metadata.get("repairs").map(String::as_str), Some("1"));
+
+        // With repairs disabled the first answer stands
+        let no_repairs = input.with_flags(SpawnFlags {
+            require_json: true,
+            max_repairs: 0,
+            ..Default::default()
+        });
+        let result = executor.execute(no_repairs).await.unwrap();
+        assert!(!result.valid);
+        assert!(result.metadata.get("repairs").is_none());
+
+        unregister_mock("schema_repair");
+    }
+
+    #[tokio::test]
+    async fn test_failing_provider_opens_circuit_for_later_atoms() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = calls.clone();
+        register_mock(
+            "executor_breaker",
+            Arc::new(move |_: &str, _: &[Message]| {
+                counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Err("status code 503: down".to_string())
+            }),
+        );
+        let limits = ProviderLimits {
+            breaker: crate::llm::CircuitBreakerConfig { failure_threshold: 1, open_secs: 60 },
+            ..Default::default()
+        };
+
+        let executor = AtomExecutor::new(LlmConfig::mock("executor_breaker")).with_limits(&limits);
+        let first = executor.execute(AtomInput::new(AtomType::Coder, "a")).await.unwrap_err();
+        assert!(first.contains("503"), "{}", first);
+
+        // Any executor for the same endpoint is refused without calling the provider
+        let other = AtomExecutor::new(LlmConfig::mock("executor_breaker")).with_limits(&limits);
+        let second = other.execute(AtomInput::new(AtomType::Coder, "b")).await.unwrap_err();
+        assert!(second.contains("probe"), "{}", second);
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        unregister_mock("executor_breaker");
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_misses_and_bypass() {
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = calls.clone();
+        register_mock(
+            "executor_cache",
+            Arc::new(move |_: &str, _: &[Message]| {
+                counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok("found foo in src/foo.rs".to_string())
+            }),
+        );
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::open(dir.path().to_str().unwrap(), Default::default()).unwrap();
+        let executor = AtomExecutor::new(LlmConfig::mock("executor_cache")).with_cache(cache.clone());
+        let input = AtomInput::new(AtomType::Search, "find foo");
+        let count = || calls.load(std::sync::atomic::Ordering::SeqCst);
+
+        let miss = executor.execute(input.clone()).await.unwrap();
+        assert_eq!(count(), 1);
+        assert!(miss.metadata.get("cache").is_none());
+
+        // Streaming atoms (spawn_atom) are served from the same entries
+        let deltas = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = deltas.clone();
+        let options = StreamOptions::default()
+            .with_on_delta(Arc::new(move |delta: &str| seen.lock().unwrap().push(delta.to_string())));
+        let hit = executor.execute_stream(input.clone(), options).await.unwrap();
+        assert_eq!(count(), 1);
+        assert_eq!(hit.metadata.get("cache").map(String::as_str), Some("hit"));
+        assert_eq!(hit.tokens_used, 0);
+        assert_eq!(*deltas.lock().unwrap(), vec![miss.output.clone()]);
+
+        let bypass = executor.execute(input.without_cache()).await.unwrap();
+        assert_eq!(count(), 2);
+        assert!(bypass.metadata.get("cache").is_none());
+
+        // A hit is served before the provider is created, so it needs no API key
+        let keyless = LlmConfig { api_key: None, ..LlmConfig::default() };
+        let offline = AtomExecutor::new(keyless.clone()).with_cache(cache.clone());
+        let input = AtomInput::new(AtomType::Search, "find bar");
+        let key = ResponseCache::key(
+            AtomType::Search,
+            &offline.build_system_prompt(&input),
+            &offline.build_user_prompt(&input),
+            &keyless,
+        );
+        let response = LlmResponse {
+            content: "bar is in src/bar.rs".to_string(),
+            model: keyless.model.clone(),
+            tokens_used: Some(10),
+            prompt_tokens: None,
+            completion_tokens: None,
+            finish_reason: None,
+        };
+        cache.put(&key, AtomType::Search, &response).unwrap();
+        let result = offline.execute(input).await.unwrap();
+        assert_eq!(result.output, "bar is in src/bar.rs");
+
+        unregister_mock("executor_cache");
+    }
+}
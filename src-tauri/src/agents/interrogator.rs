@@ -2,7 +2,7 @@
 // PRD Section 2 (Phase A): Scans user requests for "Known Unknowns"
 
 use super::{Agent, AgentContext, QuestionOutput};
-use crate::llm::{LlmConfig, LlmProvider, Message};
+use crate::llm::{AgentRole, LlmConfig, LlmProvider, LlmRouter, Message};
 use serde::{Deserialize, Serialize};
 
 /// The Interrogator Agent
@@ -19,7 +19,7 @@ impl Default for Interrogator {
     fn default() -> Self {
         Self {
             ambiguity_threshold: 0.7,
-            llm_config: LlmConfig::cerebras(), // Default to Cerebras for speed
+            llm_config: LlmRouter::load().config_for(AgentRole::Interrogator),
         }
     }
 }
@@ -6,15 +6,23 @@ pub mod architect;
 pub mod orchestrator;
 pub mod context_engineer;
 pub mod atom_executor;
+pub mod plan_executor;
+pub mod response_cache;
+pub mod run_store;
 
 // Re-exports
 pub use interrogator::Interrogator;
 pub use architect::Architect;
 pub use orchestrator::Orchestrator;
 pub use context_engineer::ContextEngineer;
-pub use atom_executor::{AtomExecutor, AtomInput, AtomOutput, CodeChange, ReviewResult, ValidationResult};
+pub use atom_executor::{AtomExecutor, AtomInput, AtomOutput, CodeChange, DeltaCallback, ReviewResult, StreamOptions, ValidationResult};
+pub use plan_executor::{PipelineTaskRunner, PlanExecutionReport, TaskOutcome, TaskReport, TaskRunner};
+pub use response_cache::{CacheConfig, ResponseCache};
+pub use run_store::{RunState, RunStore};
 
+use crate::maker_core::{AtomType, KSelection};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Base trait for all agents
 pub trait Agent: Send + Sync {
@@ -107,6 +115,8 @@ pub struct AgentContext {
     pub workspace_path: String,
     pub issue_id: Option<String>,
     pub previous_outputs: Vec<AgentOutput>,
+    /// Consensus k chosen for the current plan, per atom type; missing types use the default
+    pub consensus_k: HashMap<AtomType, usize>,
 }
 
 impl AgentContext {
@@ -115,6 +125,7 @@ impl AgentContext {
             workspace_path: workspace_path.to_string(),
             issue_id: None,
             previous_outputs: Vec::new(),
+            consensus_k: HashMap::new(),
         }
     }
     
@@ -122,5 +133,18 @@ impl AgentContext {
         self.issue_id = Some(issue_id.to_string());
         self
     }
+
+    /// Use the k of each selection for consensus votes of its atom type
+    pub fn with_consensus_k(mut self, selections: &[KSelection]) -> Self {
+        for selection in selections {
+            self.consensus_k.insert(selection.atom_type, selection.k);
+        }
+        self
+    }
+
+    /// The k chosen for `atom_type`, if any
+    pub fn consensus_k_for(&self, atom_type: AtomType) -> Option<usize> {
+        self.consensus_k.get(&atom_type).copied()
+    }
 }
 
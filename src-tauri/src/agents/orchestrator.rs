@@ -2,11 +2,19 @@
 // PRD Section 2 (Phase B): PLAN.md → script.rhai
 // Takes refined requirements from L1 and generates executable Rhai scripts
 
+use super::plan_executor::{PlanExecutionReport, TaskReport, TaskRunner};
+use super::run_store::{RunState, RunStore};
 use super::{Agent, AgentContext, MicroTask, PlanOutput};
 use crate::generators::{GeneratorRegistry, GenerationResult, GeneratorError, RhaiScriptGenerator, TaskScriptGenerator};
 use crate::llm::{PromptContext, SystemPrompts};
+use crate::maker_core::runtime::rhai_string_literal;
+use crate::maker_core::shadow_git::Snapshot;
+use crate::maker_core::{AdaptiveK, AtomType, Budget, BudgetTracker, CodeModeRuntime, CostEntry, KSelection, ShadowGit, VotingLedger};
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::sync::RwLock;
 
 /// Execution state for a task
@@ -44,6 +52,23 @@ pub struct Orchestrator {
     max_retries: usize,
     /// HIGH-6: Delay between retries in milliseconds
     retry_delay_ms: u64,
+    /// Maximum number of micro-tasks executed concurrently by execute_plan
+    max_parallel_tasks: usize,
+    /// Limits for a whole execute_plan run
+    budget: Budget,
+    /// Choose consensus k per atom type from voting history, for the plan's step count
+    adaptive_k: Option<AdaptiveK>,
+    /// Runtime used to statically check generated scripts, with the workspace it was built for
+    script_checker: Arc<std::sync::Mutex<Option<(String, CodeModeRuntime)>>>,
+}
+
+/// Persisted state of a plan run in progress, handed to `run_plan`
+struct PlanRun {
+    store: RunStore,
+    shadow: ShadowGit,
+    state: RunState,
+    /// Reports of tasks completed before this run started, keyed by task ID
+    reports: HashMap<String, TaskReport>,
 }
 
 impl Default for Orchestrator {
@@ -73,6 +98,10 @@ impl Orchestrator {
             task_states: Arc::new(RwLock::new(std::collections::HashMap::new())),
             max_retries: 3,
             retry_delay_ms: 1000,
+            max_parallel_tasks: 4,
+            budget: Budget::default(),
+            adaptive_k: None,
+            script_checker: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 
@@ -83,6 +112,24 @@ impl Orchestrator {
         self
     }
 
+    /// Configure how many micro-tasks execute_plan may run at once (minimum 1)
+    pub fn with_max_parallel_tasks(mut self, max_parallel_tasks: usize) -> Self {
+        self.max_parallel_tasks = max_parallel_tasks.max(1);
+        self
+    }
+
+    /// Limit the spend of execute_plan; once exceeded, tasks not yet started are skipped
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Choose consensus k from the voting ledger so the whole plan meets a reliability target
+    pub fn with_adaptive_k(mut self, adaptive_k: AdaptiveK) -> Self {
+        self.adaptive_k = Some(adaptive_k);
+        self
+    }
+
     /// Initialize the orchestrator with default generators
     pub async fn init(&self) {
         // Register default generators
@@ -97,16 +144,29 @@ impl Orchestrator {
 
     /// Log an execution event
     async fn log_event(&self, event_type: &str, task_id: &str, message: &str, data: Option<serde_json::Value>) {
-        let event = ExecutionEvent {
+        let event = Self::new_event(event_type, task_id, message, data);
+        let mut log = self.execution_log.write().await;
+        log.push(event);
+    }
+
+    /// Log an execution event and append it to a persisted run's event log
+    async fn log_run_event(&self, store: &RunStore, event_type: &str, task_id: &str, message: &str, data: Option<serde_json::Value>) {
+        let event = Self::new_event(event_type, task_id, message, data);
+        if let Err(e) = store.append_event(&event) {
+            eprintln!("Warning: Failed to persist execution event: {}", e);
+        }
+        let mut log = self.execution_log.write().await;
+        log.push(event);
+    }
+
+    fn new_event(event_type: &str, task_id: &str, message: &str, data: Option<serde_json::Value>) -> ExecutionEvent {
+        ExecutionEvent {
             event_type: event_type.to_string(),
             task_id: task_id.to_string(),
             timestamp: chrono::Utc::now().to_rfc3339(),
             message: message.to_string(),
             data,
-        };
-
-        let mut log = self.execution_log.write().await;
-        log.push(event);
+        }
     }
 
     /// Update task state
@@ -122,10 +182,13 @@ impl Orchestrator {
         self.set_task_state(&task.id, TaskState::Running).await;
 
         // Build prompt context from agent context
-        let prompt_context = PromptContext::new()
+        let mut prompt_context = PromptContext::new()
             .with_workspace(&context.workspace_path)
             .with_var("task_id", &task.id)
             .with_var("task_description", &task.description);
+        if let Some(k) = AtomType::from_str(&task.atom_type).and_then(|t| context.consensus_k_for(t)) {
+            prompt_context = prompt_context.with_var("k_threshold", &k.to_string());
+        }
 
         // HIGH-6: Retry loop for transient failures
         let mut last_error: Option<GeneratorError> = None;
@@ -145,6 +208,21 @@ impl Orchestrator {
 
             match result {
                 Ok(gen_result) => {
+                    // Reject scripts that call functions the runtime does not register
+                    if let Err(e) = self.check_generated_script(&gen_result.script.rhai_code, context) {
+                        self.log_event(
+                            "script_check_failed",
+                            &task.id,
+                            &format!("Script from {} failed static check: {}", gen_result.metadata.generator_name, e),
+                            Some(serde_json::json!({"attempt": attempt + 1})),
+                        ).await;
+                        last_error = Some(GeneratorError::GenerationFailed(format!(
+                            "Script failed static check: {}",
+                            e
+                        )));
+                        continue;
+                    }
+
                     self.log_event(
                         "script_generation_completed",
                         &task.id,
@@ -179,6 +257,8 @@ impl Orchestrator {
     /// Generate scripts for all tasks in a plan
     pub async fn generate_execution_plan(&self, plan: &PlanOutput, context: &AgentContext) -> Vec<Result<GenerationResult, GeneratorError>> {
         self.log_event("plan_execution_started", &plan.plan_id, &plan.title, None).await;
+        let consensus_k = self.select_consensus_k(plan, context);
+        let context = &context.clone().with_consensus_k(&consensus_k);
 
         let mut results: Vec<Result<GenerationResult, GeneratorError>> = Vec::with_capacity(plan.micro_tasks.len());
         let mut result_map: std::collections::HashMap<String, Result<GenerationResult, GeneratorError>> =
@@ -231,6 +311,9 @@ impl Orchestrator {
         // Convert result_map to ordered results vector
         for task in &plan.micro_tasks {
             if let Some(result) = result_map.remove(&task.id) {
+                if let Ok(gen_result) = &result {
+                    Self::persist_script(context, &plan.plan_id, &task.id, &gen_result.script.rhai_code);
+                }
                 results.push(result);
             }
         }
@@ -239,6 +322,375 @@ impl Orchestrator {
         results
     }
 
+    /// Execute a plan as a dependency DAG
+    /// Independent tasks run concurrently (up to max_parallel_tasks); a failed task
+    /// only causes its transitive dependents to be skipped. Progress is persisted to
+    /// .maker/runs/<plan_id>/ so an interrupted run can be picked up with `resume`.
+    pub async fn execute_plan(
+        &self,
+        plan: &PlanOutput,
+        context: &AgentContext,
+        runner: Arc<dyn TaskRunner>,
+    ) -> Result<PlanExecutionReport, String> {
+        let store = RunStore::open(&context.workspace_path, &plan.plan_id)?;
+        store.save_plan(plan)?;
+
+        // Snapshots left by an earlier run of this plan can no longer be resumed
+        let mut shadow = Self::open_shadow(&context.workspace_path)?;
+        shadow.release_run(&plan.plan_id);
+
+        // Baseline to roll back to if the run is interrupted before any task completes
+        let baseline = Self::run_snapshot(&mut shadow, &plan.plan_id,
+            &format!("MAKER {}: before execution", plan.plan_id))
+            .map_err(|e| format!("Failed to snapshot the workspace before execution: {}", e))?;
+        let mut state = RunState::new(&plan.plan_id);
+        state.last_good_snapshot = Some(baseline.id);
+
+        let run = PlanRun { store, shadow, state, reports: HashMap::new() };
+        Ok(self.run_plan(plan, context, runner, run).await)
+    }
+
+    /// Open the workspace's ShadowGit repository, initialising one if needed, so that
+    /// snapshots are real commits a resume can roll back to
+    fn open_shadow(workspace_path: &str) -> Result<ShadowGit, String> {
+        let mut shadow = ShadowGit::new(workspace_path);
+        shadow.init()
+            .map_err(|e| format!("Failed to open ShadowGit repository in {}: {}", workspace_path, e))?;
+        Ok(shadow)
+    }
+
+    /// Resume a persisted run
+    /// Completed tasks covered by a snapshot are kept; everything else runs again after the
+    /// workspace is restored to the last good ShadowGit snapshot.
+    pub async fn resume(
+        &self,
+        plan_id: &str,
+        context: &AgentContext,
+        runner: Arc<dyn TaskRunner>,
+    ) -> Result<PlanExecutionReport, String> {
+        let store = RunStore::open_existing(&context.workspace_path, plan_id)?;
+        let plan = store.load_plan()?;
+        let state = store.load_state()?;
+
+        // Work from tasks that finished after the last snapshot is rolled back below
+        let completed: HashSet<String> = state.completed_tasks().into_iter()
+            .filter(|id| state.task_snapshots.contains_key(id))
+            .collect();
+        let mut previous: HashMap<String, TaskReport> = HashMap::new();
+        for task in plan.micro_tasks.iter().filter(|t| completed.contains(&t.id)) {
+            let report = store.load_result(&task.id).unwrap_or_else(|| TaskReport {
+                task_id: task.id.clone(),
+                description: task.description.clone(),
+                state: TaskState::Completed,
+                reason: None,
+                outcome: None,
+                duration_ms: 0,
+            });
+            previous.insert(task.id.clone(), report);
+        }
+
+        self.log_run_event(&store, "plan_resume_started", plan_id,
+            &format!("{} of {} tasks already completed", previous.len(), plan.micro_tasks.len()),
+            state.last_good_snapshot.as_ref().map(|id| serde_json::json!({ "snapshot_id": id }))).await;
+
+        // Discard whatever the interrupted tasks left behind
+        let mut shadow = Self::open_shadow(&context.workspace_path)?;
+        if previous.len() < plan.micro_tasks.len() {
+            if let Some(snapshot_id) = &state.last_good_snapshot {
+                shadow.rollback_to(snapshot_id)
+                    .map_err(|e| format!("Failed to restore snapshot {}: {}", snapshot_id, e))?;
+            }
+        }
+
+        let run = PlanRun { store, shadow, state, reports: previous };
+        Ok(self.run_plan(&plan, context, runner, run).await)
+    }
+
+    /// Schedule the plan's tasks, skipping those already in `reports` (completed earlier)
+    async fn run_plan(
+        &self,
+        plan: &PlanOutput,
+        context: &AgentContext,
+        runner: Arc<dyn TaskRunner>,
+        run: PlanRun,
+    ) -> PlanExecutionReport {
+        let PlanRun { store, mut shadow, mut state, mut reports } = run;
+        let (store, shadow) = (&store, &mut shadow);
+        let started = Instant::now();
+        self.log_run_event(store, "plan_execution_started", &plan.plan_id, &plan.title,
+            Some(serde_json::json!({ "max_parallel_tasks": self.max_parallel_tasks }))).await;
+
+        let consensus_k = self.select_consensus_k(plan, context);
+        if !consensus_k.is_empty() {
+            let summary: Vec<String> = consensus_k.iter()
+                .map(|s| format!("{:?}: k={}", s.atom_type, s.k))
+                .collect();
+            self.log_run_event(store, "consensus_k_selected", &plan.plan_id, &summary.join(", "),
+                serde_json::to_value(&consensus_k).ok()).await;
+        }
+
+        // Runners and the scripts they generate vote with the chosen k
+        let context = &context.clone().with_consensus_k(&consensus_k);
+
+        let dep_graph = self.build_dependency_graph(plan);
+
+        let mut completed: HashSet<String> = reports.keys().cloned().collect();
+        for task in &plan.micro_tasks {
+            let initial = if completed.contains(&task.id) { TaskState::Completed } else { TaskState::Pending };
+            self.set_task_state(&task.id, initial.clone()).await;
+            state.task_states.insert(task.id.clone(), initial);
+        }
+        Self::save_run_state(store, &mut state);
+
+        let mut pending: Vec<&MicroTask> = plan.micro_tasks.iter()
+            .filter(|t| !completed.contains(&t.id))
+            .collect();
+        // Failed or skipped: anything depending on these can never run
+        let mut blocked: HashSet<String> = HashSet::new();
+        let mut in_flight = FuturesUnordered::new();
+        let mut tracker = BudgetTracker::new(self.budget.clone());
+
+        loop {
+            // Skip tasks whose dependencies failed (repeat so skips propagate transitively)
+            loop {
+                let newly_blocked: Vec<(String, String)> = pending.iter()
+                    .filter_map(|t| {
+                        dep_graph.get(&t.id)
+                            .and_then(|deps| deps.iter().find(|d| blocked.contains(*d)))
+                            .map(|dep| (t.id.clone(), dep.clone()))
+                    })
+                    .collect();
+                if newly_blocked.is_empty() {
+                    break;
+                }
+                for (task_id, dep) in newly_blocked {
+                    let reason = format!("Dependency {} did not complete", dep);
+                    pending.retain(|t| t.id != task_id);
+                    self.skip_task(plan, store, &mut state, &task_id, &reason, &mut reports).await;
+                    blocked.insert(task_id);
+                }
+            }
+
+            // Start ready tasks as soon as a slot is free, up to the parallelism limit
+            while in_flight.len() < self.max_parallel_tasks && !pending.is_empty() {
+                // Out of budget: running tasks finish, but nothing new starts
+                if let Err(reason) = tracker.admit() {
+                    for task in std::mem::take(&mut pending) {
+                        self.skip_task(plan, store, &mut state, &task.id, &reason, &mut reports).await;
+                        blocked.insert(task.id.clone());
+                    }
+                    break;
+                }
+                // Tasks still running count against the atom limit
+                if tracker.remaining_atoms().is_some_and(|left| left <= in_flight.len()) {
+                    break;
+                }
+                let Some(index) = pending.iter()
+                    .position(|t| self.dependencies_satisfied(&t.id, &dep_graph, &completed))
+                else {
+                    break;
+                };
+                let task = pending.remove(index).clone();
+                self.update_run_task(store, &mut state, &task.id, TaskState::Running).await;
+                self.log_run_event(store, "task_started", &task.id, &task.description, None).await;
+
+                let runner = runner.clone();
+                let context = context.clone();
+                in_flight.push(async move {
+                    let task_started = Instant::now();
+                    let result = runner.run(&task, &context).await;
+                    (task, result, task_started.elapsed().as_millis() as u64)
+                });
+            }
+
+            let Some((task, result, duration_ms)) = in_flight.next().await else {
+                break;
+            };
+
+            let (task_state, reason, outcome) = match result {
+                Ok(outcome) => match outcome.failure_reason() {
+                    None => (TaskState::Completed, None, Some(outcome)),
+                    Some(reason) => (TaskState::Failed(reason.clone()), Some(reason), Some(outcome)),
+                },
+                Err(e) => (TaskState::Failed(e.clone()), Some(e), None),
+            };
+            if let Some(outcome) = &outcome {
+                let atom_result = &outcome.atom_result;
+                tracker.charge(CostEntry {
+                    source: task.id.clone(),
+                    atom_type: atom_result.atom_type,
+                    atoms: 1,
+                    tokens: atom_result.tokens_used as u64,
+                    cost_usd: atom_result.cost_usd,
+                });
+            }
+
+            let report = TaskReport {
+                task_id: task.id.clone(),
+                description: task.description.clone(),
+                state: task_state.clone(),
+                reason: reason.clone(),
+                outcome,
+                duration_ms,
+            };
+            if let Err(e) = store.save_result(&report) {
+                eprintln!("Warning: Failed to persist result for {}: {}", task.id, e);
+            }
+
+            if matches!(task_state, TaskState::Completed) {
+                completed.insert(task.id.clone());
+                self.log_run_event(store, "task_completed", &task.id, "Task completed",
+                    Some(serde_json::json!({ "duration_ms": duration_ms }))).await;
+            } else {
+                blocked.insert(task.id.clone());
+                self.log_run_event(store, "task_failed", &task.id, reason.as_deref().unwrap_or_default(),
+                    Some(serde_json::json!({ "duration_ms": duration_ms }))).await;
+            }
+            let finished = matches!(task_state, TaskState::Completed);
+            self.update_run_task(store, &mut state, &task.id, task_state).await;
+            reports.insert(task.id.clone(), report);
+
+            // Snapshot as each task finishes; the snapshot may also hold partial output of
+            // tasks still running, which resume does not count as completed and runs again
+            if finished {
+                self.snapshot_task(plan, store, shadow, &mut state, &task.id).await;
+            }
+        }
+
+        // Anything left waits on a cycle or on a task that is not in the plan
+        for task in pending {
+            self.skip_task(plan, store, &mut state, &task.id, "Unresolvable dependencies", &mut reports).await;
+        }
+
+        let tasks: Vec<TaskReport> = plan.micro_tasks.iter()
+            .filter_map(|t| reports.remove(&t.id))
+            .collect();
+        let count = |f: fn(&TaskState) -> bool| tasks.iter().filter(|t| f(&t.state)).count();
+        let report = PlanExecutionReport {
+            plan_id: plan.plan_id.clone(),
+            completed: count(|s| matches!(s, TaskState::Completed)),
+            failed: count(|s| matches!(s, TaskState::Failed(_))),
+            skipped: count(|s| matches!(s, TaskState::Skipped)),
+            tokens_used: tracker.spend().tokens,
+            cost_usd: tracker.spend().cost_usd,
+            consensus_k,
+            tasks,
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+
+        // A fully completed run has nothing left to resume
+        if report.success() {
+            shadow.release_run(&plan.plan_id);
+        }
+
+        self.log_run_event(store, "plan_execution_completed", &plan.plan_id,
+            &format!("{} completed, {} failed, {} skipped", report.completed, report.failed, report.skipped),
+            Some(serde_json::json!({
+                "duration_ms": report.duration_ms,
+                "tokens_used": report.tokens_used,
+                "cost_usd": report.cost_usd,
+            }))).await;
+        report
+    }
+
+    /// Consensus k for each atom type in the plan, sized for the plan's number of micro-tasks
+    fn select_consensus_k(&self, plan: &PlanOutput, context: &AgentContext) -> Vec<KSelection> {
+        let Some(adaptive) = &self.adaptive_k else {
+            return Vec::new();
+        };
+        let adaptive = AdaptiveK { plan_steps: plan.micro_tasks.len().max(1), ..adaptive.clone() };
+        let entries = VotingLedger::open(&context.workspace_path).entries().unwrap_or_else(|e| {
+            eprintln!("Warning: Voting history unavailable: {}", e);
+            Vec::new()
+        });
+
+        let mut atom_types: Vec<AtomType> = Vec::new();
+        for task in &plan.micro_tasks {
+            if let Some(atom_type) = AtomType::from_str(&task.atom_type) {
+                if !atom_types.contains(&atom_type) {
+                    atom_types.push(atom_type);
+                }
+            }
+        }
+        atom_types.into_iter()
+            .map(|atom_type| adaptive.select(&entries, atom_type, self.default_k_threshold))
+            .collect()
+    }
+
+    /// Mark a task as skipped and record it in the report
+    async fn skip_task(
+        &self,
+        plan: &PlanOutput,
+        store: &RunStore,
+        state: &mut RunState,
+        task_id: &str,
+        reason: &str,
+        reports: &mut HashMap<String, TaskReport>,
+    ) {
+        self.update_run_task(store, state, task_id, TaskState::Skipped).await;
+        self.log_run_event(store, "task_skipped", task_id, reason, None).await;
+        let description = plan.micro_tasks.iter()
+            .find(|t| t.id == task_id)
+            .map(|t| t.description.clone())
+            .unwrap_or_default();
+        reports.insert(task_id.to_string(), TaskReport {
+            task_id: task_id.to_string(),
+            description,
+            state: TaskState::Skipped,
+            reason: Some(reason.to_string()),
+            outcome: None,
+            duration_ms: 0,
+        });
+    }
+
+    /// Update a task's state in memory and in the persisted run
+    async fn update_run_task(&self, store: &RunStore, state: &mut RunState, task_id: &str, task_state: TaskState) {
+        self.set_task_state(task_id, task_state.clone()).await;
+        state.task_states.insert(task_id.to_string(), task_state);
+        Self::save_run_state(store, state);
+    }
+
+    /// Snapshot the workspace after a task completes
+    /// A snapshot without a commit cannot be restored, so it is reported as a failure
+    async fn snapshot_task(
+        &self,
+        plan: &PlanOutput,
+        store: &RunStore,
+        shadow: &mut ShadowGit,
+        state: &mut RunState,
+        task_id: &str,
+    ) {
+        let message = format!("MAKER {}: {} completed", plan.plan_id, task_id);
+        match Self::run_snapshot(shadow, &plan.plan_id, &message) {
+            Ok(snapshot) => {
+                state.task_snapshots.insert(task_id.to_string(), snapshot.id.clone());
+                state.last_good_snapshot = Some(snapshot.id.clone());
+                Self::save_run_state(store, state);
+                self.log_run_event(store, "snapshot_taken", task_id, &snapshot.message,
+                    Some(serde_json::json!({ "snapshot_id": snapshot.id }))).await;
+            }
+            Err(e) => {
+                self.log_run_event(store, "snapshot_failed", task_id, &e, None).await;
+            }
+        }
+    }
+
+    /// Snapshot owned by the plan run, so other snapshots cannot evict it before a resume
+    fn run_snapshot(shadow: &mut ShadowGit, plan_id: &str, message: &str) -> Result<Snapshot, String> {
+        let snapshot = shadow.snapshot_for_run(message, plan_id).map_err(|e| e.to_string())?;
+        match snapshot.commit_hash {
+            Some(_) => Ok(snapshot),
+            None => Err("Snapshot did not produce a commit".to_string()),
+        }
+    }
+
+    fn save_run_state(store: &RunStore, state: &mut RunState) {
+        state.updated_at = chrono::Utc::now().to_rfc3339();
+        if let Err(e) = store.save_state(state) {
+            eprintln!("Warning: Failed to persist run state: {}", e);
+        }
+    }
+
     /// Build a dependency graph from the plan
     fn build_dependency_graph(&self, plan: &PlanOutput) -> std::collections::HashMap<String, Vec<String>> {
         let mut graph: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
@@ -526,31 +978,40 @@ impl Orchestrator {
 
     /// Generate a master Rhai script that orchestrates all tasks
     /// This is the main L2 output
-    pub async fn generate_master_script(&self, plan: &PlanOutput, _context: &AgentContext) -> Result<String, String> {
+    pub async fn generate_master_script(&self, plan: &PlanOutput, context: &AgentContext) -> Result<String, String> {
         self.log_event("l2_generation_started", &plan.plan_id, "Generating master Rhai script", None).await;
 
         let mut script = String::new();
 
         // Header
         script.push_str("// Auto-generated by L2 Technical Orchestrator\n");
-        script.push_str(&format!("// Plan: {}\n", plan.title));
+        script.push_str(&format!("// Plan: {}\n", plan.title.lines().next().unwrap_or_default()));
         script.push_str(&format!("// Generated: {}\n\n", chrono::Utc::now().to_rfc3339()));
 
-        // Import MAKER preamble
-        script.push_str("// MAKER Standard Library\n");
-        script.push_str("let k_threshold = 3;\n\n");
-
         // Generate task execution blocks
+        // Calls must match the registered API: spawn_atom(AtomType, &str) -> AtomResult
         for task in &plan.micro_tasks {
-            script.push_str(&format!("// Task: {} - {}\n", task.id, task.description));
-            script.push_str(&format!("let {}_result = spawn_atom(\"{}\", \"{}\", k_threshold);\n",
-                task.id.replace('-', "_"),
-                task.atom_type,
-                task.description.replace('"', "\\\"")
+            let atom_type = AtomType::from_str(&task.atom_type).ok_or_else(|| {
+                format!("Task {} has unknown atom type '{}'", task.id, task.atom_type)
+            })?;
+            let var = Self::script_identifier(&task.id);
+
+            script.push_str(&format!(
+                "// Task: {} - {}\n",
+                task.id,
+                task.description.lines().next().unwrap_or_default()
+            ));
+            script.push_str(&format!(
+                "let {} = spawn_atom(AtomType::{}, {});\n",
+                var,
+                atom_type.as_str(),
+                rhai_string_literal(&task.description)
             ));
-            script.push_str(&format!("if !{}_result.success {{ return #{{ error: \"Task {} failed\" }}; }}\n\n",
-                task.id.replace('-', "_"),
-                task.id
+            script.push_str(&format!(
+                "if !{}.valid {{ return #{{ success: false, error: {}, errors: {}.errors }}; }}\n\n",
+                var,
+                rhai_string_literal(&format!("Task {} failed", task.id)),
+                var
             ));
         }
 
@@ -560,12 +1021,52 @@ impl Orchestrator {
         script.push_str(&plan.micro_tasks.len().to_string());
         script.push_str(" }\n");
 
+        if let Err(e) = self.check_generated_script(&script, context) {
+            self.log_event("l2_generation_failed", &plan.plan_id, &format!("Master script failed static check: {}", e), None).await;
+            return Err(format!("Master script failed static check: {}", e));
+        }
+
+        Self::persist_script(context, &plan.plan_id, "master", &script);
         self.log_event("l2_generation_completed", &plan.plan_id, "Master script generated",
             Some(serde_json::json!({ "script_length": script.len() }))).await;
 
         Ok(script)
     }
 
+    /// Turn a task ID into a valid Rhai variable name
+    fn script_identifier(task_id: &str) -> String {
+        let sanitized: String = task_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("task_{}", sanitized)
+    }
+
+    /// Save a generated script under the plan's run directory
+    fn persist_script(context: &AgentContext, plan_id: &str, name: &str, script: &str) {
+        let saved = RunStore::open(&context.workspace_path, plan_id)
+            .and_then(|store| store.save_script(name, script));
+        if let Err(e) = saved {
+            eprintln!("Warning: Failed to persist script {}: {}", name, e);
+        }
+    }
+
+    /// Compile a generated script and check every call against the runtime's registered API
+    /// The checking runtime is built once per workspace and reused for later scripts
+    fn check_generated_script(&self, script: &str, context: &AgentContext) -> Result<(), String> {
+        let mut checker = self.script_checker.lock().map_err(|e| e.to_string())?;
+        if checker.as_ref().is_none_or(|(workspace, _)| *workspace != context.workspace_path) {
+            let runtime = CodeModeRuntime::new(&context.workspace_path)
+                .map_err(|e| format!("Failed to create runtime: {}", e))?;
+            *checker = Some((context.workspace_path.clone(), runtime));
+        }
+        let (_, runtime) = checker.as_ref().expect("checker was just set");
+        runtime
+            .check_script(script)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+
     /// Full L2 pipeline: PLAN.md → Rhai script
     pub async fn process_plan(&self, plan_content: &str, context: &AgentContext) -> Result<String, String> {
         // Step 1: Parse PLAN.md
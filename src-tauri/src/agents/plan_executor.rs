@@ -0,0 +1,572 @@
+// Cerebras-MAKER: Plan Executor
+// Runs PlanOutput micro-tasks as a dependency DAG: L3 Context → L4 Atom → Governance
+// Scheduling lives in Orchestrator::execute_plan; this module defines the per-task pipeline and report
+
+use super::atom_executor::{AtomExecutor, AtomInput};
+use super::context_engineer::ContextEngineer;
+use super::orchestrator::TaskState;
+use super::response_cache::{CacheConfig, ResponseCache};
+use super::{AgentContext, MicroTask};
+use crate::grits;
+use crate::handlers::governance::{check_governance, GovernanceConfig, RedFlagResult};
+use crate::llm::{LlmConfig, LlmRouter};
+use crate::maker_core::{AtomResult, AtomType, KSelection};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Result of running a single micro-task through the pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskOutcome {
+    /// Output of the L4 atom
+    pub atom_result: AtomResult,
+    /// Governance verdict on the atom output
+    pub governance: RedFlagResult,
+    /// Why L3 context was not attached, if it wasn't
+    pub context_error: Option<String>,
+}
+
+impl TaskOutcome {
+    /// Reason the task should be treated as failed, or None if it passed
+    pub fn failure_reason(&self) -> Option<String> {
+        if !self.atom_result.valid {
+            return Some(format!(
+                "Atom output invalid: {}",
+                self.atom_result.errors.join("; ")
+            ));
+        }
+        if self.atom_result.is_red_flagged() {
+            let reason = self
+                .atom_result
+                .metadata
+                .get("red_flag_reason")
+                .cloned()
+                .unwrap_or_else(|| "unspecified".to_string());
+            return Some(format!("Atom output red-flagged: {}", reason));
+        }
+        if !self.governance.approved {
+            return Some(format!(
+                "Rejected by governance: {}",
+                self.governance
+                    .rejection_reason
+                    .as_deref()
+                    .unwrap_or("unspecified")
+            ));
+        }
+        None
+    }
+}
+
+/// Executes one micro-task. Implemented by the real pipeline and by test doubles.
+#[async_trait]
+pub trait TaskRunner: Send + Sync {
+    async fn run(&self, task: &MicroTask, context: &AgentContext) -> Result<TaskOutcome, String>;
+}
+
+/// The default runner: ContextEngineer → AtomExecutor → governance check
+/// Runs one atom per task, so `AgentContext::consensus_k` does not apply to it
+pub struct PipelineTaskRunner {
+    /// Shared with the blocking task that extracts context
+    context_engineer: Arc<ContextEngineer>,
+    /// Picks the LLM config for each task's atom type
+    router: LlmRouter,
+    governance_config: GovernanceConfig,
+    /// Cache atom responses under the workspace's .maker/cache when set
+    cache_config: Option<CacheConfig>,
+}
+
+impl PipelineTaskRunner {
+    /// Run every task with the same LLM config
+    pub fn new(llm_config: LlmConfig) -> Self {
+        Self::from_router(LlmRouter::fixed(llm_config))
+    }
+
+    /// Route each task to the provider configured for its atom type
+    pub fn from_router(router: LlmRouter) -> Self {
+        Self {
+            context_engineer: Arc::new(ContextEngineer::new()),
+            router,
+            governance_config: GovernanceConfig::default(),
+            cache_config: None,
+        }
+    }
+
+    pub fn with_context_engineer(mut self, context_engineer: ContextEngineer) -> Self {
+        self.context_engineer = Arc::new(context_engineer);
+        self
+    }
+
+    pub fn with_governance_config(mut self, config: GovernanceConfig) -> Self {
+        self.governance_config = config;
+        self
+    }
+
+    pub fn with_response_cache(mut self, config: CacheConfig) -> Self {
+        self.cache_config = Some(config);
+        self
+    }
+}
+
+#[async_trait]
+impl TaskRunner for PipelineTaskRunner {
+    async fn run(&self, task: &MicroTask, context: &AgentContext) -> Result<TaskOutcome, String> {
+        let atom_type = AtomType::from_str(&task.atom_type)
+            .ok_or_else(|| format!("Unknown atom type '{}'", task.atom_type))?;
+
+        // L3: Extract context, reusing the cached graph when one is loaded.
+        // Atoms can still run from the task description alone, so a missing context is not fatal.
+        // Scanning the workspace is blocking I/O; keep it off the async workers
+        let (context_engineer, owned_task, workspace_path) = (
+            Arc::clone(&self.context_engineer),
+            task.clone(),
+            context.workspace_path.clone(),
+        );
+        let package = tokio::task::spawn_blocking(move || {
+            let workspace_path = Path::new(&workspace_path);
+            match grits::get_cached_graph() {
+                Some(graph) => {
+                    context_engineer.extract_context_with_graph(&owned_task, &graph, workspace_path)
+                }
+                None => context_engineer.extract_context(&owned_task, workspace_path),
+            }
+        })
+        .await
+        .unwrap_or_else(|e| Err(format!("Context extraction panicked: {}", e)));
+
+        let mut input = AtomInput::new(atom_type, &task.description).with_var("task_id", &task.id);
+        let context_error = match package {
+            Ok(package) => {
+                input = input.with_context(package);
+                None
+            }
+            Err(e) => Some(e),
+        };
+
+        // L4: Execute the atom
+        let mut executor = AtomExecutor::new(self.router.config_for_atom(atom_type));
+        if let Some(config) = &self.cache_config {
+            // A broken cache only costs speed, so fall back to uncached execution
+            match ResponseCache::open(&context.workspace_path, config.clone()) {
+                Ok(cache) => executor = executor.with_cache(cache),
+                Err(e) => eprintln!("Warning: Response cache disabled: {}", e),
+            }
+        }
+        let atom_result = executor.execute(input).await?;
+
+        // Governance: architectural checks need a cached graph and only apply to code output
+        let changes = if matches!(atom_type, AtomType::Coder) && grits::get_cached_graph().is_some()
+        {
+            let changes = executor.proposed_changes(&atom_result.output);
+            if changes.is_empty() {
+                None
+            } else {
+                Some(changes)
+            }
+        } else {
+            None
+        };
+        let governance = check_governance(
+            atom_result.output.clone(),
+            changes,
+            Some(self.governance_config.clone()),
+        )?;
+
+        Ok(TaskOutcome {
+            atom_result,
+            governance,
+            context_error,
+        })
+    }
+}
+
+/// Per-task entry in the final execution report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskReport {
+    pub task_id: String,
+    pub description: String,
+    pub state: TaskState,
+    /// Failure or skip reason
+    pub reason: Option<String>,
+    /// Pipeline output, if the task ran to completion
+    pub outcome: Option<TaskOutcome>,
+    pub duration_ms: u64,
+}
+
+impl TaskReport {
+    /// Dollar cost of the task's atom, 0 if it never ran
+    pub fn cost_usd(&self) -> f64 {
+        self.outcome
+            .as_ref()
+            .map(|o| o.atom_result.cost_usd)
+            .unwrap_or_default()
+    }
+}
+
+/// Final report for a plan execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanExecutionReport {
+    pub plan_id: String,
+    /// One entry per micro-task, in plan order
+    pub tasks: Vec<TaskReport>,
+    pub completed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Tokens and dollars spent by the tasks run in this execution
+    #[serde(default)]
+    pub tokens_used: u64,
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// Consensus k chosen per atom type for this plan, when adaptive k is enabled
+    #[serde(default)]
+    pub consensus_k: Vec<KSelection>,
+    pub duration_ms: u64,
+}
+
+impl PlanExecutionReport {
+    /// True if every task completed
+    pub fn success(&self) -> bool {
+        self.failed == 0 && self.skipped == 0
+    }
+
+    /// Look up the report for a task
+    pub fn task(&self, task_id: &str) -> Option<&TaskReport> {
+        self.tasks.iter().find(|t| t.task_id == task_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{Orchestrator, PlanOutput, RunStore};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Runner that fails the listed tasks and records peak concurrency
+    struct ScriptedRunner {
+        fail: HashSet<String>,
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        order: Mutex<Vec<String>>,
+        /// Consensus k each task was handed for its atom type
+        consensus_k: Mutex<Vec<Option<usize>>>,
+        /// Run time per task in milliseconds (20 when not listed)
+        delay_ms: HashMap<String, u64>,
+    }
+
+    impl ScriptedRunner {
+        fn new(fail: &[&str]) -> Self {
+            Self {
+                fail: fail.iter().map(|s| s.to_string()).collect(),
+                running: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                order: Mutex::new(Vec::new()),
+                consensus_k: Mutex::new(Vec::new()),
+                delay_ms: HashMap::new(),
+            }
+        }
+
+        fn with_delay(mut self, task_id: &str, delay_ms: u64) -> Self {
+            self.delay_ms.insert(task_id.to_string(), delay_ms);
+            self
+        }
+    }
+
+    #[async_trait]
+    impl TaskRunner for ScriptedRunner {
+        async fn run(
+            &self,
+            task: &MicroTask,
+            context: &AgentContext,
+        ) -> Result<TaskOutcome, String> {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            let delay_ms = self.delay_ms.get(&task.id).copied().unwrap_or(20);
+            tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.order.lock().unwrap().push(task.id.clone());
+            self.consensus_k
+                .lock()
+                .unwrap()
+                .push(AtomType::from_str(&task.atom_type).and_then(|t| context.consensus_k_for(t)));
+
+            if self.fail.contains(&task.id) {
+                return Err(format!("{} exploded", task.id));
+            }
+            let output =
+                std::path::Path::new(&context.workspace_path).join(format!("{}.txt", task.id));
+            std::fs::write(output, "done").map_err(|e| e.to_string())?;
+            Ok(TaskOutcome {
+                atom_result: AtomResult::success(AtomType::Coder, "ok".to_string(), 0, 0),
+                governance: check_governance("ok".to_string(), None, None)?,
+                context_error: None,
+            })
+        }
+    }
+
+    fn plan(ids: &[&str], deps: &[(&str, &str)]) -> PlanOutput {
+        PlanOutput {
+            plan_id: "plan_test".to_string(),
+            title: "Test".to_string(),
+            description: String::new(),
+            micro_tasks: ids
+                .iter()
+                .map(|id| MicroTask {
+                    id: id.to_string(),
+                    description: format!("Task {}", id),
+                    atom_type: "Coder".to_string(),
+                    estimated_complexity: 1,
+                    seed_symbols: Vec::new(),
+                })
+                .collect(),
+            dependencies: deps
+                .iter()
+                .map(|(t, d)| (t.to_string(), d.to_string()))
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_runs_independent_tasks_concurrently() {
+        let workspace = tempfile::tempdir().unwrap();
+        let context = AgentContext::new(workspace.path().to_str().unwrap());
+        let orchestrator = Orchestrator::new().with_max_parallel_tasks(2);
+        let runner = Arc::new(ScriptedRunner::new(&[]));
+        let plan = plan(&["t1", "t2", "t3", "t4"], &[("t4", "t1")]);
+
+        let report = orchestrator
+            .execute_plan(&plan, &context, runner.clone())
+            .await
+            .unwrap();
+
+        assert!(report.success());
+        assert_eq!(report.completed, 4);
+        assert_eq!(runner.peak.load(Ordering::SeqCst), 2);
+        let order = runner.order.lock().unwrap().clone();
+        let pos = |id: &str| order.iter().position(|t| t == id).unwrap();
+        assert!(pos("t1") < pos("t4"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_starts_ready_tasks_when_a_slot_frees() {
+        let workspace = tempfile::tempdir().unwrap();
+        let context = AgentContext::new(workspace.path().to_str().unwrap());
+        let orchestrator = Orchestrator::new().with_max_parallel_tasks(2);
+        let runner = Arc::new(ScriptedRunner::new(&[]).with_delay("t1", 300));
+        let plan = plan(&["t1", "t2", "t3"], &[]);
+
+        let report = orchestrator
+            .execute_plan(&plan, &context, runner.clone())
+            .await
+            .unwrap();
+
+        // t3 takes t2's slot while t1 is still running
+        assert_eq!(report.completed, 3);
+        assert_eq!(*runner.order.lock().unwrap(), vec!["t2", "t3", "t1"]);
+        let state = RunStore::open_existing(&context.workspace_path, "plan_test")
+            .unwrap()
+            .load_state()
+            .unwrap();
+        assert_eq!(state.task_snapshots.len(), 3);
+        assert_eq!(
+            state.last_good_snapshot,
+            state.task_snapshots.get("t1").cloned()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_skips_only_dependents_of_failed_task() {
+        let workspace = tempfile::tempdir().unwrap();
+        let context = AgentContext::new(workspace.path().to_str().unwrap());
+        let orchestrator = Orchestrator::new();
+        let runner = Arc::new(ScriptedRunner::new(&["t1"]));
+        // t3 depends on t2 depends on t1; t4 is independent
+        let plan = plan(&["t1", "t2", "t3", "t4"], &[("t2", "t1"), ("t3", "t2")]);
+
+        let report = orchestrator
+            .execute_plan(&plan, &context, runner)
+            .await
+            .unwrap();
+
+        assert!(!report.success());
+        assert_eq!((report.completed, report.failed, report.skipped), (1, 1, 2));
+        assert!(matches!(
+            report.task("t1").unwrap().state,
+            TaskState::Failed(_)
+        ));
+        assert!(matches!(
+            report.task("t3").unwrap().state,
+            TaskState::Skipped
+        ));
+        assert!(matches!(
+            report.task("t4").unwrap().state,
+            TaskState::Completed
+        ));
+
+        let states = orchestrator.get_task_states().await;
+        assert!(matches!(states["t2"], TaskState::Skipped));
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_stops_starting_tasks_over_budget() {
+        let workspace = tempfile::tempdir().unwrap();
+        let context = AgentContext::new(workspace.path().to_str().unwrap());
+        let orchestrator = Orchestrator::new().with_budget(crate::maker_core::Budget {
+            max_atoms: Some(2),
+            ..Default::default()
+        });
+        let plan = plan(&["t1", "t2", "t3", "t4"], &[]);
+
+        let report = orchestrator
+            .execute_plan(&plan, &context, Arc::new(ScriptedRunner::new(&[])))
+            .await
+            .unwrap();
+
+        assert_eq!((report.completed, report.skipped), (2, 2));
+        let skipped = report.task("t4").unwrap();
+        assert!(skipped.reason.as_deref().unwrap().contains("Atom budget"));
+        assert_eq!(skipped.cost_usd(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_execute_plan_reports_adaptive_consensus_k() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ws = workspace.path().to_str().unwrap();
+        let context = AgentContext::new(ws);
+        // Coder candidates agreed with the winner 18 of 20 times: p = 19 / 22
+        let entry: crate::maker_core::LedgerEntry = serde_json::from_value(serde_json::json!({
+            "timestamp": "", "task_hash": "", "atom_type": "Coder", "k_threshold": 3,
+            "model_mix": {}, "candidates": [], "class_votes": { "a": 18, "b": 2 },
+            "red_flag_discards": 0, "atoms_spawned": 20, "elapsed_ms": 0, "total_tokens": 0,
+            "cost_usd": 0.0, "reached": true, "winner_hash": "a", "winner_sample": null
+        }))
+        .unwrap();
+        crate::maker_core::VotingLedger::open(ws)
+            .append(&entry)
+            .unwrap();
+
+        // k = 2 gives a 3-step plan reliability of about 0.93; the default k is 3
+        let orchestrator = Orchestrator::new().with_adaptive_k(crate::maker_core::AdaptiveK {
+            target_reliability: 0.9,
+            ..Default::default()
+        });
+        let plan = plan(&["t1", "t2", "t3"], &[]);
+        let runner = Arc::new(ScriptedRunner::new(&[]));
+        let report = orchestrator
+            .execute_plan(&plan, &context, runner.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(report.consensus_k.len(), 1);
+        let selection = &report.consensus_k[0];
+        assert_eq!(
+            (selection.atom_type, selection.plan_steps),
+            (AtomType::Coder, 3)
+        );
+        assert_eq!(selection.samples, 20);
+        assert!(selection.meets_target);
+        assert_eq!(selection.k, 2);
+        assert!(selection.plan_reliability.unwrap() >= 0.9);
+
+        // The chosen k reaches the runners and the scripts generated for the plan
+        assert_eq!(*runner.consensus_k.lock().unwrap(), vec![Some(2); 3]);
+        orchestrator.init().await;
+        for generated in orchestrator.generate_execution_plan(&plan, &context).await {
+            let script = generated.unwrap().script.rhai_code;
+            assert!(script.contains("run_consensus(AtomType::Coder, task_prompt, 2)"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resume_reruns_only_unfinished_tasks() {
+        let workspace = tempfile::tempdir().unwrap();
+        let context = AgentContext::new(workspace.path().to_str().unwrap());
+        let plan = plan(&["t1", "t2", "t3"], &[("t2", "t1")]);
+
+        let first = Orchestrator::new()
+            .execute_plan(&plan, &context, Arc::new(ScriptedRunner::new(&["t1"])))
+            .await
+            .unwrap();
+        assert_eq!((first.completed, first.failed, first.skipped), (1, 1, 1));
+
+        let store = RunStore::open_existing(&context.workspace_path, "plan_test").unwrap();
+        let state = store.load_state().unwrap();
+        assert!(matches!(state.task_states["t1"], TaskState::Failed(_)));
+        assert!(state.task_snapshots.contains_key("t3"));
+        assert_eq!(
+            state.last_good_snapshot,
+            state.task_snapshots.get("t3").cloned()
+        );
+
+        // Leftovers of an interrupted task are discarded by the rollback
+        std::fs::write(workspace.path().join("t3.txt"), "half-written").unwrap();
+
+        // A fresh orchestrator (as after a restart) picks up from disk
+        let runner = Arc::new(ScriptedRunner::new(&[]));
+        let resumed = Orchestrator::new()
+            .resume("plan_test", &context, runner.clone())
+            .await
+            .unwrap();
+
+        assert!(resumed.success());
+        assert_eq!(*runner.order.lock().unwrap(), vec!["t1", "t2"]);
+        assert!(resumed.task("t3").unwrap().outcome.is_some());
+        assert!(matches!(
+            store.load_state().unwrap().task_states["t2"],
+            TaskState::Completed
+        ));
+        let restored = std::fs::read_to_string(workspace.path().join("t3.txt")).unwrap();
+        assert_eq!(restored, "done");
+
+        // .maker/ is not part of the snapshots, so the rollback kept the first run's log
+        let finished = store
+            .load_events()
+            .unwrap()
+            .into_iter()
+            .filter(|e| e.event_type == "plan_execution_completed")
+            .count();
+        assert_eq!(finished, 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_rolls_back_to_baseline_kept_from_eviction() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ws = workspace.path().to_str().unwrap();
+        let context = AgentContext::new(ws);
+        let plan = plan(&["t1"], &[]);
+        std::fs::write(workspace.path().join("main.rs"), "fn main() {}").unwrap();
+
+        // Interrupted before any task completed: only the baseline exists
+        Orchestrator::new()
+            .execute_plan(&plan, &context, Arc::new(ScriptedRunner::new(&["t1"])))
+            .await
+            .unwrap();
+        let store = RunStore::open_existing(ws, "plan_test").unwrap();
+        let state = store.load_state().unwrap();
+        assert!(state.task_snapshots.is_empty());
+        let baseline = state.last_good_snapshot.unwrap();
+
+        // Script snapshots filling the shared limit do not evict the run's baseline
+        let mut shadow = crate::maker_core::ShadowGit::new(ws);
+        for i in 0..100 {
+            shadow.snapshot(&format!("script {}", i)).unwrap();
+        }
+        assert!(shadow.get_snapshots().iter().any(|s| s.id == baseline));
+
+        std::fs::write(workspace.path().join("main.rs"), "half-written").unwrap();
+        let resumed = Orchestrator::new()
+            .resume("plan_test", &context, Arc::new(ScriptedRunner::new(&[])))
+            .await
+            .unwrap();
+
+        assert!(resumed.success());
+        let restored = std::fs::read_to_string(workspace.path().join("main.rs")).unwrap();
+        assert_eq!(restored, "fn main() {}");
+        // A finished run releases its snapshots
+        let remaining = crate::maker_core::ShadowGit::new(ws)
+            .get_snapshots()
+            .to_vec();
+        assert!(remaining.iter().all(|s| s.run_id.is_none()));
+    }
+}
@@ -0,0 +1,300 @@
+// Cerebras-MAKER: Atom Response Cache
+// Content-addressed on-disk cache of LLM responses in .maker/cache/<key>.json
+//
+// Keys hash the atom type, system prompt, user prompt, provider endpoint, model, temperature
+// and max_tokens, so any change to the prompt, provider or sampling settings is a miss.
+// Entries expire after a TTL, and the oldest entries are evicted once the cache exceeds its
+// entry or byte limit.
+
+use crate::llm::replay::content_hash;
+use crate::llm::{LlmConfig, LlmResponse};
+use crate::maker_core::AtomType;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Limits for the response cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    /// Entries older than this are treated as misses and deleted
+    pub ttl_secs: u64,
+    /// Maximum number of cached responses
+    pub max_entries: usize,
+    /// Maximum total size of the cache directory
+    pub max_bytes: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 24 * 60 * 60,
+            max_entries: 1000,
+            max_bytes: 50 * 1024 * 1024,
+        }
+    }
+}
+
+/// A cached LLM response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    key: String,
+    atom_type: AtomType,
+    model: String,
+    created_at_secs: u64,
+    response: LlmResponse,
+}
+
+/// On-disk response cache for one workspace
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    config: CacheConfig,
+}
+
+impl ResponseCache {
+    /// Directory holding the cache for a workspace
+    pub fn cache_dir(workspace_path: &str) -> PathBuf {
+        Path::new(workspace_path).join(".maker").join("cache")
+    }
+
+    /// Open (creating if needed) the cache for a workspace
+    pub fn open(workspace_path: &str, config: CacheConfig) -> Result<Self, String> {
+        let dir = Self::cache_dir(workspace_path);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create cache directory {:?}: {}", dir, e))?;
+        Ok(Self { dir, config })
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
+    /// Cache key for an atom call
+    pub fn key(
+        atom_type: AtomType,
+        system_prompt: &str,
+        user_prompt: &str,
+        llm_config: &LlmConfig,
+    ) -> String {
+        let canonical = serde_json::json!({
+            "atom_type": atom_type,
+            "system": system_prompt,
+            "user": user_prompt,
+            "provider": format!("{:?}", llm_config.provider),
+            "base_url": llm_config.base_url,
+            "model": llm_config.model,
+            "temperature": llm_config.temperature,
+            "max_tokens": llm_config.max_tokens,
+        });
+        content_hash(&canonical.to_string())
+    }
+
+    /// Look up a response, deleting it if it has expired
+    pub fn get(&self, key: &str) -> Option<LlmResponse> {
+        let path = self.entry_path(key);
+        let json = std::fs::read_to_string(&path).ok()?;
+        let entry: CacheEntry = match serde_json::from_str(&json) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!(
+                    "Warning: Discarding unreadable cache entry {:?}: {}",
+                    path, e
+                );
+                let _ = std::fs::remove_file(&path);
+                return None;
+            }
+        };
+
+        if entry.key != key || self.is_expired(entry.created_at_secs) {
+            let _ = std::fs::remove_file(&path);
+            return None;
+        }
+        Some(entry.response)
+    }
+
+    /// Store a response, then evict expired and excess entries
+    pub fn put(
+        &self,
+        key: &str,
+        atom_type: AtomType,
+        response: &LlmResponse,
+    ) -> Result<(), String> {
+        let entry = CacheEntry {
+            key: key.to_string(),
+            atom_type,
+            model: response.model.clone(),
+            created_at_secs: now_secs(),
+            response: response.clone(),
+        };
+        let content = serde_json::to_string(&entry)
+            .map_err(|e| format!("Failed to serialize cache entry: {}", e))?;
+
+        // Write via a temp file so concurrent readers never see a partial entry
+        let path = self.entry_path(key);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content).map_err(|e| format!("Failed to write {:?}: {}", tmp, e))?;
+        std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+
+        self.prune()
+    }
+
+    /// Remove expired entries, then the oldest entries until the cache is within its limits
+    pub fn prune(&self) -> Result<(), String> {
+        let mut entries: Vec<(PathBuf, SystemTime, u64)> = std::fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to read {:?}: {}", self.dir, e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((entry.path(), metadata.modified().ok()?, metadata.len()))
+            })
+            .collect();
+
+        let ttl = Duration::from_secs(self.config.ttl_secs);
+        entries.retain(|(path, modified, _)| {
+            let expired = modified.elapsed().map(|age| age > ttl).unwrap_or(false);
+            if expired {
+                let _ = std::fs::remove_file(path);
+            }
+            !expired
+        });
+
+        // Oldest first
+        entries.sort_by_key(|(_, modified, _)| *modified);
+        let mut total_bytes: u64 = entries.iter().map(|(_, _, len)| len).sum();
+        let mut count = entries.len();
+        for (path, _, len) in entries {
+            if count <= self.config.max_entries && total_bytes <= self.config.max_bytes {
+                break;
+            }
+            if std::fs::remove_file(&path).is_ok() {
+                count -= 1;
+                total_bytes = total_bytes.saturating_sub(len);
+            }
+        }
+        Ok(())
+    }
+
+    /// Delete every cached response
+    pub fn clear(&self) -> Result<(), String> {
+        if self.dir.exists() {
+            std::fs::remove_dir_all(&self.dir)
+                .map_err(|e| format!("Failed to clear {:?}: {}", self.dir, e))?;
+        }
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create cache directory {:?}: {}", self.dir, e))
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    fn is_expired(&self, created_at_secs: u64) -> bool {
+        now_secs().saturating_sub(created_at_secs) > self.config.ttl_secs
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(content: &str) -> LlmResponse {
+        LlmResponse {
+            content: content.to_string(),
+            model: "test-model".to_string(),
+            tokens_used: Some(42),
+            prompt_tokens: Some(30),
+            completion_tokens: Some(12),
+            finish_reason: Some("stop".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_key_covers_prompt_and_sampling() {
+        let config = LlmConfig::mock("cache");
+        let key = ResponseCache::key(AtomType::Search, "sys", "find foo", &config);
+        assert_eq!(
+            key,
+            ResponseCache::key(AtomType::Search, "sys", "find foo", &config)
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key(AtomType::Reviewer, "sys", "find foo", &config)
+        );
+        assert_ne!(
+            key,
+            ResponseCache::key(AtomType::Search, "sys", "find bar", &config)
+        );
+
+        let mut hotter = config.clone();
+        hotter.temperature += 0.5;
+        assert_ne!(
+            key,
+            ResponseCache::key(AtomType::Search, "sys", "find foo", &hotter)
+        );
+
+        let mut elsewhere = config.clone();
+        elsewhere.base_url = Some("http://gpu-box:11434".to_string());
+        assert_ne!(
+            key,
+            ResponseCache::key(AtomType::Search, "sys", "find foo", &elsewhere)
+        );
+        let mut shorter = config.clone();
+        shorter.max_tokens /= 2;
+        assert_ne!(
+            key,
+            ResponseCache::key(AtomType::Search, "sys", "find foo", &shorter)
+        );
+    }
+
+    #[test]
+    fn test_put_get_and_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().to_str().unwrap();
+        let cache = ResponseCache::open(workspace, CacheConfig::default()).unwrap();
+
+        assert!(cache.get("abc").is_none());
+        cache
+            .put("abc", AtomType::Search, &response("hit"))
+            .unwrap();
+        assert_eq!(cache.get("abc").unwrap().content, "hit");
+
+        // Rewrite the entry as if it were created two days ago
+        let path = cache.entry_path("abc");
+        let mut entry: CacheEntry =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        entry.created_at_secs -= 2 * 24 * 60 * 60;
+        std::fs::write(&path, serde_json::to_string(&entry).unwrap()).unwrap();
+
+        assert!(cache.get("abc").is_none());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_prune_evicts_oldest_over_entry_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().to_str().unwrap();
+        let config = CacheConfig {
+            max_entries: 2,
+            ..Default::default()
+        };
+        let cache = ResponseCache::open(workspace, config).unwrap();
+
+        for key in ["first", "second", "third"] {
+            cache.put(key, AtomType::Reviewer, &response(key)).unwrap();
+            // Distinct modification times so eviction order is deterministic
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        assert!(cache.get("first").is_none());
+        assert!(cache.get("second").is_some());
+        assert!(cache.get("third").is_some());
+    }
+}
@@ -0,0 +1,261 @@
+// Cerebras-MAKER: Run Store
+// Persists plan execution state to .maker/runs/<plan_id>/ so a crashed run can be resumed
+//
+// Layout:
+//   plan.json            - the PlanOutput being executed
+//   state.json           - task states and ShadowGit snapshot IDs
+//   events.jsonl         - execution log, one ExecutionEvent per line
+//   scripts/<task>.rhai  - generated Rhai scripts
+//   results/<task>.json  - per-task reports (atom result + governance verdict)
+
+use super::orchestrator::{ExecutionEvent, TaskState};
+use super::plan_executor::TaskReport;
+use super::PlanOutput;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Persisted state of a plan run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunState {
+    pub plan_id: String,
+    /// Last known state of every task
+    pub task_states: HashMap<String, TaskState>,
+    /// Snapshot taken after each completed task (task_id -> snapshot_id)
+    pub task_snapshots: HashMap<String, String>,
+    /// Most recent snapshot; resume rolls back to it and reruns every task not completed in it
+    pub last_good_snapshot: Option<String>,
+    pub updated_at: String,
+}
+
+impl RunState {
+    pub fn new(plan_id: &str) -> Self {
+        Self {
+            plan_id: plan_id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// IDs of tasks that completed in a previous attempt
+    pub fn completed_tasks(&self) -> Vec<String> {
+        self.task_states
+            .iter()
+            .filter(|(_, state)| matches!(state, TaskState::Completed))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+/// On-disk store for a single plan run
+pub struct RunStore {
+    dir: PathBuf,
+}
+
+impl RunStore {
+    /// Directory holding all runs for a workspace
+    pub fn runs_dir(workspace_path: &str) -> PathBuf {
+        Path::new(workspace_path).join(".maker").join("runs")
+    }
+
+    /// Open (creating if needed) the store for a plan
+    pub fn open(workspace_path: &str, plan_id: &str) -> Result<Self, String> {
+        Self::validate_id(plan_id)?;
+        let dir = Self::runs_dir(workspace_path).join(plan_id);
+        for sub in ["scripts", "results"] {
+            std::fs::create_dir_all(dir.join(sub))
+                .map_err(|e| format!("Failed to create run directory {:?}: {}", dir, e))?;
+        }
+        Ok(Self { dir })
+    }
+
+    /// Open the store for an existing run, failing if it was never started
+    pub fn open_existing(workspace_path: &str, plan_id: &str) -> Result<Self, String> {
+        Self::validate_id(plan_id)?;
+        let dir = Self::runs_dir(workspace_path).join(plan_id);
+        if !dir.join("plan.json").exists() {
+            return Err(format!("No persisted run found for plan {}", plan_id));
+        }
+        Ok(Self { dir })
+    }
+
+    /// List plan IDs with a persisted run
+    pub fn list_runs(workspace_path: &str) -> Result<Vec<String>, String> {
+        let runs_dir = Self::runs_dir(workspace_path);
+        if !runs_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut runs: Vec<String> = std::fs::read_dir(&runs_dir)
+            .map_err(|e| format!("Failed to read {:?}: {}", runs_dir, e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join("plan.json").exists())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        runs.sort();
+        Ok(runs)
+    }
+
+    /// The run directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn save_plan(&self, plan: &PlanOutput) -> Result<(), String> {
+        self.write_json(&self.dir.join("plan.json"), plan)
+    }
+
+    pub fn load_plan(&self) -> Result<PlanOutput, String> {
+        self.read_json(&self.dir.join("plan.json"))
+    }
+
+    pub fn save_state(&self, state: &RunState) -> Result<(), String> {
+        self.write_json(&self.dir.join("state.json"), state)
+    }
+
+    /// Load the run state, or a fresh one if none was written yet
+    pub fn load_state(&self) -> Result<RunState, String> {
+        let path = self.dir.join("state.json");
+        if !path.exists() {
+            let plan_id = self
+                .dir
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            return Ok(RunState::new(&plan_id));
+        }
+        self.read_json(&path)
+    }
+
+    pub fn save_script(&self, name: &str, script: &str) -> Result<(), String> {
+        Self::validate_id(name)?;
+        let path = self.dir.join("scripts").join(format!("{}.rhai", name));
+        Self::write_atomic(&path, script.as_bytes())
+    }
+
+    pub fn load_script(&self, name: &str) -> Option<String> {
+        Self::validate_id(name).ok()?;
+        std::fs::read_to_string(self.dir.join("scripts").join(format!("{}.rhai", name))).ok()
+    }
+
+    pub fn save_result(&self, report: &TaskReport) -> Result<(), String> {
+        Self::validate_id(&report.task_id)?;
+        let path = self
+            .dir
+            .join("results")
+            .join(format!("{}.json", report.task_id));
+        self.write_json(&path, report)
+    }
+
+    pub fn load_result(&self, task_id: &str) -> Option<TaskReport> {
+        Self::validate_id(task_id).ok()?;
+        self.read_json(&self.dir.join("results").join(format!("{}.json", task_id)))
+            .ok()
+    }
+
+    /// Append an event to the run's execution log
+    pub fn append_event(&self, event: &ExecutionEvent) -> Result<(), String> {
+        let line = serde_json::to_string(event)
+            .map_err(|e| format!("Failed to serialize event: {}", e))?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dir.join("events.jsonl"))
+            .map_err(|e| format!("Failed to open event log: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to append event: {}", e))
+    }
+
+    /// Load the run's execution log
+    pub fn load_events(&self) -> Result<Vec<ExecutionEvent>, String> {
+        let path = self.dir.join("events.jsonl");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read event log: {}", e))?;
+        // A crash can leave a truncated last line; skip anything that does not parse
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Plan and task IDs become file names, so keep them to a safe character set
+    fn validate_id(id: &str) -> Result<(), String> {
+        let valid = !id.is_empty()
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+            && id != "."
+            && id != "..";
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid run identifier: '{}'", id))
+        }
+    }
+
+    fn write_json<T: Serialize>(&self, path: &Path, value: &T) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(value)
+            .map_err(|e| format!("Failed to serialize {:?}: {}", path, e))?;
+        Self::write_atomic(path, content.as_bytes())
+    }
+
+    fn read_json<T: for<'de> Deserialize<'de>>(&self, path: &Path) -> Result<T, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+    }
+
+    /// Write via a temp file and rename so a crash never leaves a half-written file
+    fn write_atomic(path: &Path, content: &[u8]) -> Result<(), String> {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, content).map_err(|e| format!("Failed to write {:?}: {}", tmp, e))?;
+        std::fs::rename(&tmp, path).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_store_round_trip() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ws = workspace.path().to_str().unwrap();
+        assert!(RunStore::open_existing(ws, "plan_1").is_err());
+
+        let store = RunStore::open(ws, "plan_1").unwrap();
+        store
+            .save_plan(&PlanOutput {
+                plan_id: "plan_1".to_string(),
+                title: "Plan".to_string(),
+                description: String::new(),
+                micro_tasks: Vec::new(),
+                dependencies: Vec::new(),
+            })
+            .unwrap();
+        store.save_script("t1", "let x = 1;").unwrap();
+
+        let mut state = store.load_state().unwrap();
+        assert_eq!(state.plan_id, "plan_1");
+        state
+            .task_states
+            .insert("t1".to_string(), TaskState::Completed);
+        store.save_state(&state).unwrap();
+
+        let reopened = RunStore::open_existing(ws, "plan_1").unwrap();
+        assert_eq!(reopened.load_state().unwrap().completed_tasks(), vec!["t1"]);
+        assert_eq!(reopened.load_script("t1").as_deref(), Some("let x = 1;"));
+        assert_eq!(RunStore::list_runs(ws).unwrap(), vec!["plan_1"]);
+    }
+
+    #[test]
+    fn test_run_store_rejects_path_like_ids() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ws = workspace.path().to_str().unwrap();
+        assert!(RunStore::open(ws, "../escape").is_err());
+        assert!(RunStore::open(ws, "..").is_err());
+        let store = RunStore::open(ws, "plan_1").unwrap();
+        assert!(store.save_script("a/b", "").is_err());
+    }
+}
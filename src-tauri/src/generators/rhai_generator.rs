@@ -62,7 +62,8 @@ Generate ONLY the Rhai script code, no explanations."#,
         vars.insert("task_description".to_string(), task.description.clone());
         vars.insert("atom_type".to_string(), task.atom_type.clone());
         vars.insert("seed_symbols".to_string(), task.seed_symbols.join(", "));
-        vars.insert("k_threshold".to_string(), self.default_k.to_string());
+        // A k chosen for the plan arrives as a prompt variable and wins over the default
+        vars.entry("k_threshold".to_string()).or_insert_with(|| self.default_k.to_string());
 
         if !vars.contains_key("code_context") {
             vars.insert("code_context".to_string(), "(no context provided)".to_string());
@@ -4,6 +4,7 @@
 use super::{GenerationMetadata, GenerationResult, GeneratorError, ScriptGenerator};
 use crate::agents::{MicroTask, ScriptOutput};
 use crate::llm::PromptContext;
+use crate::maker_core::runtime::rhai_string_literal;
 use async_trait::async_trait;
 use std::time::Instant;
 
@@ -24,96 +25,110 @@ impl TaskScriptGenerator {
         Self { default_k: 3 }
     }
 
+    /// The k chosen for this task (the `k_threshold` prompt variable), else `default`
+    fn k_threshold(context: &PromptContext, default: usize) -> usize {
+        context
+            .custom_vars
+            .get("k_threshold")
+            .and_then(|k| k.parse().ok())
+            .unwrap_or(default)
+    }
+
     /// Generate script for Search tasks
     fn generate_search_script(&self, task: &MicroTask) -> String {
         format!(
             r#"// Search Task: {}
 // Generated by TaskScriptGenerator
 
-let query = "{}";
+let query = {};
 let result = spawn_atom(AtomType::Search, query);
 
-if result.is_err {{
-    log_error("Search failed: " + result.error);
-    throw result.error;
+if !result.valid {{
+    log("Search failed: " + result.errors);
+    throw "Search failed: " + result.errors;
 }}
 
-log("Search completed: " + result.value.len() + " results");
-result.value
+log("Search completed");
+result.output
 "#,
             task.id,
-            task.description.replace('"', r#"\""#)
+            rhai_string_literal(&task.description)
         )
     }
 
     /// Generate script for Coder tasks
-    fn generate_coder_script(&self, task: &MicroTask) -> String {
+    fn generate_coder_script(&self, task: &MicroTask, k: usize) -> String {
         format!(
             r#"// Coder Task: {}
 // Generated by TaskScriptGenerator
 
 // 1. Create snapshot
-snapshot("Before: {}");
+snapshot({});
 
 // 2. Gather context
-let context = spawn_atom(AtomType::Search, "Find code related to: {}");
+let context = spawn_atom(AtomType::Search, {});
 
 // 3. Generate code with consensus
-let task_prompt = "{}" + "\n\nContext:\n" + context.value;
-let result = vote(AtomType::Coder, task_prompt, {});
+let task_prompt = {} + "\n\nContext:\n" + context.output;
+let result = run_consensus(AtomType::Coder, task_prompt, {});
 
-if result.is_err {{
-    log_error("Consensus failed: " + result.error);
+if !result.reached {{
+    log("Consensus failed: " + result.error);
     rollback();
-    throw result.error;
+    throw "Consensus failed: " + result.error;
 }}
 
-// 4. Red-flag check
-let flags = check_red_flags(result.value);
-if flags.introduced_cycle {{
-    log_error("Architectural violation detected");
+// 4. Red-flag check (true when red flags are detected)
+if check_red_flags(result.winner) {{
+    log("Architectural violation detected");
     rollback();
-    throw "Red flag: dependency cycle introduced";
+    throw "Red flag: architectural violation";
 }}
 
 // 5. Success
-log("Code generation completed for: {}");
-result.value
+log({});
+result.winner
 "#,
             task.id,
-            task.id,
-            task.seed_symbols.join(", "),
-            task.description.replace('"', r#"\""#),
-            self.default_k,
-            task.id
+            rhai_string_literal(&format!("Before: {}", task.id)),
+            rhai_string_literal(&format!(
+                "Find code related to: {}",
+                task.seed_symbols.join(", ")
+            )),
+            rhai_string_literal(&task.description),
+            k,
+            rhai_string_literal(&format!("Code generation completed for: {}", task.id))
         )
     }
 
     /// Generate script for Reviewer tasks
-    fn generate_reviewer_script(&self, task: &MicroTask) -> String {
+    fn generate_reviewer_script(&self, task: &MicroTask, k: usize) -> String {
         format!(
             r#"// Reviewer Task: {}
 // Generated by TaskScriptGenerator
 
-let code_to_review = "{}";
-let review = vote(AtomType::Reviewer, "Review this code:\n" + code_to_review, 2);
+let code_to_review = {};
+let review = run_consensus(AtomType::Reviewer, "Review this code:\n" + code_to_review, {});
 
-if review.is_err {{
-    log_error("Review failed: " + review.error);
-    throw review.error;
+if !review.reached {{
+    log("Review failed: " + review.error);
+    throw "Review failed: " + review.error;
 }}
 
-if review.value.contains("REJECT") {{
-    log_error("Code review rejected");
-    throw "Code review failed: " + review.value;
+// The Reviewer answers with JSON: #{{approved: bool, issues: [...]}}
+let verdict = parse_json(review.winner);
+if verdict.approved == false {{
+    log("Code review rejected");
+    throw "Code review failed: " + review.winner;
 }}
 
-log("Review passed for: {}");
-review.value
+log({});
+review.winner
 "#,
             task.id,
-            task.description.replace('"', r#"\""#),
-            task.id
+            rhai_string_literal(&task.description),
+            k,
+            rhai_string_literal(&format!("Review passed for: {}", task.id))
         )
     }
 
@@ -123,25 +138,20 @@ review.value
             r#"// Validator Task: {}
 // Generated by TaskScriptGenerator
 
-let validation_target = "{}";
+let validation_target = {};
 let result = spawn_atom(AtomType::Validator, validation_target);
 
-if result.is_err {{
-    log_error("Validation failed: " + result.error);
-    throw result.error;
+if !result.valid {{
+    log("Validation did not pass: " + result.errors);
+    throw "Validation failed: " + result.errors;
 }}
 
-if !result.value.is_valid {{
-    log_error("Validation did not pass");
-    throw "Validation failed: " + result.value.reason;
-}}
-
-log("Validation passed for: {}");
-result.value
+log({});
+result.output
 "#,
             task.id,
-            task.description.replace('"', r#"\""#),
-            task.id
+            rhai_string_literal(&task.description),
+            rhai_string_literal(&format!("Validation passed for: {}", task.id))
         )
     }
 }
@@ -171,18 +181,21 @@ impl ScriptGenerator for TaskScriptGenerator {
     async fn generate(
         &self,
         task: &MicroTask,
-        _context: &PromptContext,
+        context: &PromptContext,
     ) -> Result<GenerationResult, GeneratorError> {
         let start = Instant::now();
 
         let rhai_code = match task.atom_type.as_str() {
             "Search" => self.generate_search_script(task),
-            "Coder" => self.generate_coder_script(task),
-            "Reviewer" => self.generate_reviewer_script(task),
+            "Coder" => self.generate_coder_script(task, Self::k_threshold(context, self.default_k)),
+            "Reviewer" => self.generate_reviewer_script(task, Self::k_threshold(context, 2)),
             "Validator" => self.generate_validator_script(task),
-            _ => return Err(GeneratorError::InvalidTask(
-                format!("Unknown task type: {}", task.atom_type)
-            )),
+            _ => {
+                return Err(GeneratorError::InvalidTask(format!(
+                    "Unknown task type: {}",
+                    task.atom_type
+                )))
+            }
         };
 
         let elapsed = start.elapsed();
@@ -205,4 +218,3 @@ impl ScriptGenerator for TaskScriptGenerator {
         })
     }
 }
-
@@ -2,7 +2,7 @@
 use serde::{Deserialize, Serialize};
 
 // Re-export grits-core types needed for the API
-pub use grits_core::topology::analysis::LayerViolation;
+pub use grits_core::topology::virtual_apply::LayerViolationDetail;
 pub use grits_core::topology::virtual_apply::ProposedChange;
 
 use crate::grits;
@@ -15,8 +15,9 @@ pub struct RedFlagResult {
     // Architectural Flags (Local PRD)
     pub introduced_cycle: bool,
     pub has_layer_violations: bool,
-    pub cycles_detected: Vec<Vec<String>>,
-    pub layer_violations: Vec<LayerViolation>,
+    pub cycles_detected: Vec<Vec<String>>, // Symbol paths, each starting and ending at the same symbol
+    pub layer_violations: Vec<LayerViolationDetail>,
+    pub betti_1_delta: i64, // Change in Betti_1 after virtual apply
 
     // Unreliability Flags (MAKER Paper)
     pub is_verbose: bool,   // Response > threshold
@@ -56,6 +57,7 @@ pub fn check_governance(
         has_layer_violations: false,
         cycles_detected: Vec::new(),
         layer_violations: Vec::new(),
+        betti_1_delta: 0,
         is_verbose: false,
         is_malformed: false,
         approved: true,
@@ -94,19 +96,17 @@ pub fn check_governance(
             grits::get_cached_graph().ok_or("No cached graph. Call load_symbol_graph first.")?;
         let workspace_path = grits::get_cached_workspace_path();
 
-        let result = grits::virtual_red_flag_check(&graph, &changes, workspace_path.as_deref());
+        let result = grits::virtual_red_flag_check(&graph, &changes, workspace_path.as_deref())?;
 
         flags.introduced_cycle = result.introduced_cycle;
         flags.has_layer_violations = result.has_layer_violations;
         flags.cycles_detected = result.cycles_detected;
         flags.layer_violations = result.layer_violations;
+        flags.betti_1_delta = result.betti_1_delta;
 
-        if flags.introduced_cycle {
+        if !result.approved {
             flags.approved = false;
-            flags.rejection_reason = Some("Introduced circular dependency".to_string());
-        } else if flags.has_layer_violations {
-            flags.approved = false;
-            flags.rejection_reason = Some("Architectural layer violation".to_string());
+            flags.rejection_reason = result.rejection_reason;
         }
     }
 
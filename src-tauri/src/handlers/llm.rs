@@ -62,3 +62,28 @@ pub fn execute_script(script: String) -> Result<serde_json::Value, String> {
 
     Ok(serde_json::json!(result.to_string()))
 }
+
+/// List models available on a local Ollama or llama.cpp server
+/// `provider` is the settings ID ("ollama" or "llamacpp"); an empty `base_url` uses the default.
+#[tauri::command]
+pub async fn list_local_models(
+    provider: String,
+    base_url: Option<String>,
+) -> Result<Vec<String>, String> {
+    let base_url = base_url.filter(|url| !url.trim().is_empty());
+    let config = match provider.as_str() {
+        "ollama" => {
+            let default = llm::LlmConfig::ollama("");
+            llm::LlmConfig {
+                base_url: base_url.or(default.base_url.clone()),
+                ..default
+            }
+        }
+        "llamacpp" => llm::LlmConfig::llama_cpp(base_url.as_deref(), ""),
+        other => return Err(format!("'{}' is not a local provider", other)),
+    };
+
+    llm::list_local_models(&config)
+        .await
+        .map_err(|e| format!("Failed to list models: {}", e))
+}
@@ -1,5 +1,6 @@
 pub mod git;
 pub mod governance;
 pub mod llm;
+pub mod plan;
 pub mod system;
 pub mod testing;
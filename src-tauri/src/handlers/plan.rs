@@ -0,0 +1,45 @@
+use crate::agents::{
+    AgentContext, Orchestrator, PipelineTaskRunner, PlanExecutionReport, TaskRunner,
+};
+use crate::llm::LlmRouter;
+use std::sync::Arc;
+
+/// Orchestrator and pipeline runner configured from the cached settings
+fn plan_executor() -> (Orchestrator, Arc<dyn TaskRunner>) {
+    let settings = crate::load_cached_settings();
+    let budget = settings
+        .as_ref()
+        .map(|s| s.budget.clone())
+        .unwrap_or_default();
+    let response_cache = settings.as_ref().and_then(|s| s.response_cache.clone());
+
+    let mut runner = PipelineTaskRunner::from_router(LlmRouter::from_settings(settings));
+    if let Some(config) = response_cache {
+        runner = runner.with_response_cache(config);
+    }
+    (Orchestrator::new().with_budget(budget), Arc::new(runner))
+}
+
+/// Run a PLAN.md as a dependency DAG in `workspace_path`
+/// Progress is saved under .maker/runs/<plan_id>/ so `resume_plan` can pick up an interrupted run.
+#[tauri::command]
+pub async fn execute_plan(
+    plan_content: String,
+    workspace_path: String,
+) -> Result<PlanExecutionReport, String> {
+    let (orchestrator, runner) = plan_executor();
+    let plan = orchestrator.parse_plan_md(&plan_content)?;
+    let context = AgentContext::new(&workspace_path);
+    orchestrator.execute_plan(&plan, &context, runner).await
+}
+
+/// Resume an interrupted plan run from its last snapshot
+#[tauri::command]
+pub async fn resume_plan(
+    plan_id: String,
+    workspace_path: String,
+) -> Result<PlanExecutionReport, String> {
+    let (orchestrator, runner) = plan_executor();
+    let context = AgentContext::new(&workspace_path);
+    orchestrator.resume(&plan_id, &context, runner).await
+}
@@ -1,4 +1,8 @@
-use crate::{get_settings_path, AppSettings, ExecutionMetrics, EXECUTION_METRICS};
+use crate::maker_core::{LedgerStats, VotingLedger};
+use crate::{
+    cache_settings, get_settings_path, AppSettings, ExecutionMetrics, VotingState,
+    EXECUTION_METRICS,
+};
 // Note: We are still referencing globals from lib.rs for now to minimize breakage
 // during the initial split. Ideally these should be moving to Tauri State.
 
@@ -17,6 +21,30 @@ pub fn get_execution_metrics() -> Result<ExecutionMetrics, String> {
     Ok(metrics.clone())
 }
 
+/// Voting state of a consensus run, or of the latest run when no task id is given
+#[tauri::command]
+pub fn get_voting_state(task_id: Option<String>) -> Result<VotingState, String> {
+    Ok(crate::voting_state(task_id.as_deref()))
+}
+
+/// Voting state of every tracked consensus run, oldest first
+#[tauri::command]
+pub fn get_voting_states() -> Result<Vec<VotingState>, String> {
+    Ok(crate::voting_states())
+}
+
+#[tauri::command]
+pub fn clear_voting_state() -> Result<(), String> {
+    crate::clear_voting_states();
+    Ok(())
+}
+
+/// Convergence and red-flag statistics from the workspace's voting ledger
+#[tauri::command]
+pub fn get_consensus_stats(workspace_path: String) -> Result<LedgerStats, String> {
+    VotingLedger::open(&workspace_path).stats()
+}
+
 #[tauri::command]
 pub fn save_settings(settings: AppSettings) -> Result<(), String> {
     let path = get_settings_path();
@@ -28,8 +56,8 @@ pub fn save_settings(settings: AppSettings) -> Result<(), String> {
         .map_err(|e| format!("Failed to serialize settings: {}", e))?;
     std::fs::write(&path, &json).map_err(|e| format!("Failed to write settings: {}", e))?;
 
-    // Update global cache if we can access it (defined in lib.rs)
-    // For now, simpler to just write to disk.
+    // Keep per-agent routing in sync with what was just saved
+    cache_settings(settings);
     Ok(())
 }
 
@@ -1,6 +1,7 @@
 use crate::agents::atom_executor::{AtomExecutor, AtomInput};
+use crate::llm::LlmRouter;
 use crate::maker_core::SpawnFlags;
-use crate::{AtomType, LlmConfig};
+use crate::AtomType;
 use rust_embed::RustEmbed;
 
 #[derive(RustEmbed)]
@@ -20,35 +21,58 @@ pub struct TestFrameworkInfo {
     pub framework: String,
     pub test_command: String,
     pub test_pattern: String,
+    /// Build/type-check command, if the stack has one; `{files}` stands for the changed files
+    #[serde(default)]
+    pub check_command: Option<String>,
+    /// Runner `run_tests` uses, or None if its results cannot be parsed
+    #[serde(default)]
+    pub runner: Option<TestRunner>,
 }
 
 #[tauri::command]
 pub async fn detect_test_framework(workspace_path: String) -> Result<TestFrameworkInfo, String> {
-    use std::path::Path;
-    let ws = Path::new(&workspace_path);
-
-    if ws.join("Cargo.toml").exists() {
-        return Ok(TestFrameworkInfo {
-            framework: "rust-cargo".to_string(),
-            test_command: "cargo test".to_string(),
-            test_pattern: "#[test]".to_string(),
-        });
-    }
+    Ok(detect_framework(std::path::Path::new(&workspace_path)))
+}
 
-    // ... (simplified detection for brevity, assuming standard stacks)
-    if ws.join("package.json").exists() {
-        return Ok(TestFrameworkInfo {
-            framework: "node".to_string(),
-            test_command: "npm test".to_string(),
-            test_pattern: "*.test.js".to_string(),
-        });
-    }
+/// Detect the workspace's stack from its manifest files
+/// Follows `TestRunner::detect`, so the stack described here is the one `run_tests` runs.
+pub fn detect_framework(ws: &std::path::Path) -> TestFrameworkInfo {
+    let runner = TestRunner::detect(ws);
+    let info =
+        |framework: &str, test_command: &str, test_pattern: &str, check_command: Option<&str>| {
+            TestFrameworkInfo {
+                framework: framework.to_string(),
+                test_command: test_command.to_string(),
+                test_pattern: test_pattern.to_string(),
+                check_command: check_command.map(str::to_string),
+                runner,
+            }
+        };
+    let cargo_check = Some("cargo check --all-targets --message-format short");
+    // Plain JavaScript has nothing to type-check
+    let tsc = ws
+        .join("tsconfig.json")
+        .exists()
+        .then_some("npx tsc --noEmit");
 
-    Ok(TestFrameworkInfo {
-        framework: "unknown".to_string(),
-        test_command: "echo 'No test framework detected'".to_string(),
-        test_pattern: "".to_string(),
-    })
+    match runner {
+        Some(TestRunner::Cargo) => info("rust-cargo", "cargo test", "#[test]", cargo_check),
+        Some(TestRunner::Nextest) => {
+            info("rust-nextest", "cargo nextest run", "#[test]", cargo_check)
+        }
+        Some(TestRunner::Jest) => info("jest", "npx jest", "*.test.*", tsc),
+        Some(TestRunner::Vitest) => info("vitest", "npx vitest run", "*.test.*", tsc),
+        Some(TestRunner::GoTest) => info("go", "go test ./...", "*_test.go", Some("go vet ./...")),
+        Some(TestRunner::Pytest) => info(
+            "python",
+            "python -m pytest",
+            "test_*.py",
+            Some("python -m py_compile {files}"),
+        ),
+        // A package.json without jest or vitest still has `npm test`
+        None if ws.join("package.json").exists() => info("node", "npm test", "*.test.js", tsc),
+        None => info("unknown", "echo 'No test framework detected'", "", None),
+    }
 }
 
 #[tauri::command]
@@ -84,9 +108,10 @@ pub async fn generate_tests(
         temperature: 0.3,
         max_tokens: Some(4000),
         red_flag_check: false, // Don't check red flags on test generation itself
+        ..Default::default()
     });
 
-    let config = LlmConfig::cerebras(); // Default to Cerebras for speed
+    let config = LlmRouter::load().config_for_atom(AtomType::Tester);
     let executor = AtomExecutor::new(config);
     let result = executor
         .execute(input)
@@ -98,3 +123,1192 @@ pub async fn generate_tests(
         "language": language
     }))
 }
+
+/// Test runners whose results can be parsed per test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestRunner {
+    /// `cargo test`, with libtest's stable human-readable output
+    Cargo,
+    /// `cargo nextest run`, with libtest-compatible JSON output
+    Nextest,
+    Jest,
+    Vitest,
+    /// `pytest`, with a JUnit XML report
+    Pytest,
+    /// `go test -json`
+    GoTest,
+}
+
+impl TestRunner {
+    /// Runner for the workspace's stack, or None if its results cannot be parsed
+    pub fn detect(ws: &std::path::Path) -> Option<Self> {
+        if ws.join("Cargo.toml").exists() {
+            if ws.join(".config").join("nextest.toml").exists() {
+                return Some(Self::Nextest);
+            }
+            return Some(Self::Cargo);
+        }
+        if let Ok(package) = std::fs::read_to_string(ws.join("package.json")) {
+            let package: serde_json::Value = serde_json::from_str(&package).unwrap_or_default();
+            let depends_on = |name: &str| {
+                ["dependencies", "devDependencies"]
+                    .iter()
+                    .any(|section| package[section].get(name).is_some())
+            };
+            if depends_on("vitest") {
+                return Some(Self::Vitest);
+            }
+            if depends_on("jest") {
+                return Some(Self::Jest);
+            }
+            return None;
+        }
+        if ws.join("go.mod").exists() {
+            return Some(Self::GoTest);
+        }
+        if [
+            "pyproject.toml",
+            "setup.py",
+            "requirements.txt",
+            "pytest.ini",
+            "conftest.py",
+        ]
+        .iter()
+        .any(|manifest| ws.join(manifest).exists())
+        {
+            return Some(Self::Pytest);
+        }
+        None
+    }
+
+    /// Program, arguments and environment for a run
+    /// `selection` holds test files, packages or (for cargo) name filters; None runs everything.
+    fn command(
+        &self,
+        report: &std::path::Path,
+        selection: Option<&[String]>,
+        pattern: Option<&str>,
+    ) -> (&'static str, Vec<String>, Vec<(&'static str, &'static str)>) {
+        let report = report.to_string_lossy().to_string();
+        let selection = selection.unwrap_or_default();
+        let mut args: Vec<String> = Vec::new();
+        match self {
+            Self::Cargo => {
+                args.extend(["test", "--no-fail-fast", "--"].map(String::from));
+                // libtest ORs its filters, so a pattern only filters when nothing else does
+                match pattern.filter(|_| selection.is_empty()) {
+                    Some(pattern) => args.push(pattern.to_string()),
+                    None => args.extend_from_slice(selection),
+                }
+                // libtest's JSON format is unstable, so the default output is parsed instead
+                ("cargo", args, Vec::new())
+            }
+            Self::Nextest => {
+                args.extend(
+                    [
+                        "nextest",
+                        "run",
+                        "--no-fail-fast",
+                        "--message-format",
+                        "libtest-json",
+                    ]
+                    .map(String::from),
+                );
+                match pattern.filter(|_| selection.is_empty()) {
+                    Some(pattern) => args.push(pattern.to_string()),
+                    None => args.extend_from_slice(selection),
+                }
+                (
+                    "cargo",
+                    args,
+                    vec![("NEXTEST_EXPERIMENTAL_LIBTEST_JSON", "1")],
+                )
+            }
+            Self::Jest | Self::Vitest => {
+                if *self == Self::Jest {
+                    args.extend(
+                        ["jest", "--json", "--outputFile", report.as_str()].map(String::from),
+                    );
+                } else {
+                    args.extend(
+                        [
+                            "vitest",
+                            "run",
+                            "--reporter=json",
+                            "--outputFile",
+                            report.as_str(),
+                        ]
+                        .map(String::from),
+                    );
+                }
+                if let Some(pattern) = pattern {
+                    args.extend(["-t".to_string(), pattern.to_string()]);
+                }
+                args.extend_from_slice(selection);
+                ("npx", args, Vec::new())
+            }
+            Self::Pytest => {
+                args.extend(
+                    ["-m", "pytest", "-q", "--junitxml", report.as_str()].map(String::from),
+                );
+                if let Some(pattern) = pattern {
+                    args.extend(["-k".to_string(), pattern.to_string()]);
+                }
+                args.extend_from_slice(selection);
+                ("python", args, Vec::new())
+            }
+            Self::GoTest => {
+                args.extend(["test", "-json"].map(String::from));
+                if let Some(pattern) = pattern {
+                    args.extend(["-run".to_string(), pattern.to_string()]);
+                }
+                if selection.is_empty() {
+                    args.push("./...".to_string());
+                } else {
+                    args.extend_from_slice(selection);
+                }
+                ("go", args, Vec::new())
+            }
+        }
+    }
+
+    /// Report file the runner writes, relative to `.maker/test-results`
+    fn report_file(&self) -> Option<&'static str> {
+        match self {
+            Self::Jest => Some("jest.json"),
+            Self::Vitest => Some("vitest.json"),
+            Self::Pytest => Some("pytest.xml"),
+            Self::Cargo | Self::Nextest | Self::GoTest => None,
+        }
+    }
+
+    /// Per-test results from the runner's stdout or report file
+    fn parse(&self, stdout: &str, report: Option<&str>) -> Vec<TestCaseResult> {
+        match self {
+            Self::Cargo => parse_libtest_pretty(stdout),
+            Self::Nextest => parse_libtest_json(stdout),
+            Self::Jest | Self::Vitest => report.map(parse_jest_json).unwrap_or_default(),
+            Self::Pytest => report.map(parse_junit_xml).unwrap_or_default(),
+            Self::GoTest => parse_go_test_json(stdout),
+        }
+    }
+
+    /// Tests to run for a change, as this runner selects them; None runs everything
+    fn select(&self, graph: &crate::SymbolGraph, affected: &[String]) -> Option<Vec<String>> {
+        let mut selection: Vec<String> = Vec::new();
+        for file in affected {
+            match self {
+                Self::Cargo | Self::Nextest => {
+                    if !file.ends_with(".rs") {
+                        continue;
+                    }
+                    match rust_module(file) {
+                        // A crate root's tests cover the whole crate
+                        Some(RustModule::CrateRoot) => return None,
+                        Some(RustModule::Module(module)) => selection.push(module),
+                        // Integration test names carry no module path, so filter by function
+                        Some(RustModule::IntegrationTest) => selection.extend(
+                            graph
+                                .nodes
+                                .values()
+                                .filter(|s| &s.file_path == file && s.kind.contains("function"))
+                                .map(|s| s.name.clone()),
+                        ),
+                        None => {}
+                    }
+                }
+                Self::Jest | Self::Vitest => {
+                    if file.contains(".test.")
+                        || file.contains(".spec.")
+                        || file.contains("__tests__/")
+                    {
+                        selection.push(file.clone());
+                    }
+                }
+                Self::Pytest => {
+                    let name = file.rsplit('/').next().unwrap_or(file);
+                    if name.ends_with(".py")
+                        && (name.starts_with("test_") || name.ends_with("_test.py"))
+                    {
+                        selection.push(file.clone());
+                    }
+                }
+                Self::GoTest => {
+                    if file.ends_with(".go") {
+                        let package = match file.rsplit_once('/') {
+                            Some((dir, _)) => format!("./{}", dir),
+                            None => ".".to_string(),
+                        };
+                        selection.push(package);
+                    }
+                }
+            }
+        }
+        selection.sort();
+        selection.dedup();
+        Some(selection)
+    }
+}
+
+/// Where a Rust file's tests live in libtest's test names
+enum RustModule {
+    CrateRoot,
+    /// Module path, e.g. `handlers::testing` for `src/handlers/testing.rs`
+    Module(String),
+    IntegrationTest,
+}
+
+fn rust_module(file: &str) -> Option<RustModule> {
+    let segments: Vec<&str> = file.trim_end_matches(".rs").split('/').collect();
+    let root = segments
+        .iter()
+        .rposition(|s| *s == "src" || *s == "tests")?;
+    if segments[root] == "tests" {
+        return Some(RustModule::IntegrationTest);
+    }
+    let mut module: Vec<&str> = segments[root + 1..].to_vec();
+    if module.last() == Some(&"mod") {
+        module.pop();
+    }
+    match module.as_slice() {
+        [] | ["lib"] | ["main"] => Some(RustModule::CrateRoot),
+        _ => Some(RustModule::Module(module.join("::"))),
+    }
+}
+
+/// Workspace files that depend on the changed files, directly or transitively, per the
+/// grits symbol graph. The changed files themselves are included.
+pub fn affected_files(graph: &crate::SymbolGraph, changed: &[String]) -> Vec<String> {
+    let normalize = |path: &str| path.replace('\\', "/").trim_start_matches("./").to_string();
+    let file_of = |id: &str| {
+        graph
+            .nodes
+            .get(id)
+            .map(|symbol| symbol.file_path.clone())
+            .unwrap_or_else(|| id.to_string())
+    };
+
+    let mut affected: std::collections::BTreeSet<String> =
+        changed.iter().map(|f| normalize(f)).collect();
+    let mut queue: Vec<String> = affected.iter().cloned().collect();
+    while let Some(file) = queue.pop() {
+        // What other files call or import: the file, its module name and its symbols
+        let mut segments = file.rsplit('/');
+        let name = segments.next().unwrap_or(&file);
+        let mut stem = name.split('.').next().unwrap_or("");
+        // Directory modules are imported by the directory's name
+        if matches!(stem, "mod" | "index" | "__init__") {
+            stem = segments.next().unwrap_or("");
+        }
+        let stem = stem.to_string();
+        let mut provides: std::collections::HashSet<String> = graph
+            .nodes
+            .values()
+            .filter(|symbol| symbol.file_path == file && symbol.kind != "file")
+            .flat_map(|symbol| [symbol.id.clone(), symbol.name.clone()])
+            .collect();
+        provides.insert(file.clone());
+
+        for (from, to, edge) in &graph.edges {
+            if edge.relation != "calls" && edge.relation != "imports" {
+                continue;
+            }
+            let last = to.rsplit([':', '/', '.']).find(|s| !s.is_empty());
+            if provides.contains(to) || (!stem.is_empty() && last == Some(stem.as_str())) {
+                let dependent = file_of(from);
+                if affected.insert(dependent.clone()) {
+                    queue.push(dependent);
+                }
+            }
+        }
+    }
+    affected.into_iter().collect()
+}
+
+/// Outcome of a single test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// One test's result
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TestCaseResult {
+    pub name: String,
+    /// Test file, or package for Go
+    pub file: Option<String>,
+    pub status: TestStatus,
+    pub duration_ms: Option<u64>,
+    /// Failure message and stack trace, or skip reason
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FailedTest {
+    pub name: String,
+    pub file: Option<String>,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct TestExecutionResult {
+    /// The run exited cleanly and no test failed
+    pub success: bool,
+    pub runner: Option<TestRunner>,
+    pub command: String,
+    pub total_tests: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration_ms: u64,
+    /// Runner output without machine-readable lines, truncated from the front
+    pub output: String,
+    pub tests: Vec<TestCaseResult>,
+    pub failed_tests: Vec<FailedTest>,
+    /// Files whose tests were selected when filtering by changed files
+    #[serde(default)]
+    pub affected_files: Option<Vec<String>>,
+}
+
+impl TestExecutionResult {
+    fn new(
+        runner: TestRunner,
+        command: String,
+        tests: Vec<TestCaseResult>,
+        exited_ok: bool,
+    ) -> Self {
+        let count = |status| tests.iter().filter(|t| t.status == status).count();
+        let failed = count(TestStatus::Failed);
+        Self {
+            success: exited_ok && failed == 0,
+            runner: Some(runner),
+            command,
+            total_tests: tests.len(),
+            passed: count(TestStatus::Passed),
+            failed,
+            skipped: count(TestStatus::Skipped),
+            duration_ms: 0,
+            output: String::new(),
+            failed_tests: tests
+                .iter()
+                .filter(|t| t.status == TestStatus::Failed)
+                .map(|t| FailedTest {
+                    name: t.name.clone(),
+                    file: t.file.clone(),
+                    error: t.message.clone().unwrap_or_default(),
+                })
+                .collect(),
+            tests,
+            affected_files: None,
+        }
+    }
+}
+
+/// Options for `run_test_suite`
+#[derive(Debug, Clone)]
+pub struct TestRunOptions {
+    /// Test name filter, passed to the runner
+    pub test_pattern: Option<String>,
+    /// Only run tests affected by these workspace-relative files
+    pub changed_files: Option<Vec<String>>,
+    pub timeout: std::time::Duration,
+}
+
+impl Default for TestRunOptions {
+    fn default() -> Self {
+        Self {
+            test_pattern: None,
+            changed_files: None,
+            timeout: std::time::Duration::from_secs(300),
+        }
+    }
+}
+
+/// Characters of runner output kept in `TestExecutionResult::output`
+const TEST_OUTPUT_CHARS: usize = 20_000;
+
+#[tauri::command]
+pub async fn run_tests(
+    workspace_path: String,
+    test_pattern: Option<String>,
+    timeout_seconds: Option<u64>,
+    changed_files: Option<Vec<String>>,
+) -> Result<TestExecutionResult, String> {
+    let mut options = TestRunOptions {
+        test_pattern: test_pattern.filter(|p| !p.trim().is_empty()),
+        changed_files,
+        ..Default::default()
+    };
+    if let Some(seconds) = timeout_seconds {
+        options.timeout = std::time::Duration::from_secs(seconds);
+    }
+    run_test_suite(&workspace_path, &options).await
+}
+
+/// What a run needs before the runner starts
+struct PreparedRun {
+    runner: TestRunner,
+    /// Affected files when filtering by changed files
+    affected: Option<Vec<String>>,
+    selection: Option<Vec<String>>,
+    report_dir: std::path::PathBuf,
+    report: Option<std::path::PathBuf>,
+}
+
+/// Detect the runner, select tests for the changed files and clear the report directory
+/// Loads the symbol graph and touches the file system, so it runs on a blocking thread.
+fn prepare_run(workspace_path: &str, options: &TestRunOptions) -> Result<PreparedRun, String> {
+    let ws = std::path::Path::new(workspace_path);
+    let runner = TestRunner::detect(ws)
+        .ok_or_else(|| format!("No supported test runner detected in {}", workspace_path))?;
+
+    let mut affected = None;
+    let mut selection = None;
+    if let Some(changed) = &options.changed_files {
+        let graph = match crate::grits::get_cached_graph().filter(|_| {
+            crate::grits::get_cached_workspace_path().as_deref() == Some(workspace_path)
+        }) {
+            Some(graph) => graph,
+            None => crate::grits::load_workspace_graph(workspace_path)?,
+        };
+        let files = affected_files(&graph, changed);
+        selection = runner.select(&graph, &files);
+        affected = Some(files);
+    }
+
+    let report_dir = ws.join(".maker").join("test-results");
+    let report = runner.report_file().map(|file| report_dir.join(file));
+    if let Some(report) = &report {
+        std::fs::create_dir_all(&report_dir)
+            .map_err(|e| format!("Failed to create {:?}: {}", report_dir, e))?;
+        // A stale report from an earlier run would pass for this one's
+        let _ = std::fs::remove_file(report);
+    }
+    Ok(PreparedRun {
+        runner,
+        affected,
+        selection,
+        report_dir,
+        report,
+    })
+}
+
+/// Run the workspace's tests with the detected runner and parse the results per test
+pub async fn run_test_suite(
+    workspace_path: &str,
+    options: &TestRunOptions,
+) -> Result<TestExecutionResult, String> {
+    let ws = std::path::Path::new(workspace_path);
+    let (path, owned) = (workspace_path.to_string(), options.clone());
+    let PreparedRun {
+        runner,
+        affected,
+        selection,
+        report_dir,
+        report,
+    } = tokio::task::spawn_blocking(move || prepare_run(&path, &owned))
+        .await
+        .map_err(|e| format!("Test preparation failed: {}", e))??;
+
+    let (program, args, env) = runner.command(
+        report.as_deref().unwrap_or(report_dir.as_path()),
+        selection.as_deref(),
+        options.test_pattern.as_deref(),
+    );
+    let command = format!("{} {}", program, args.join(" "));
+
+    if selection.as_ref().is_some_and(|s| s.is_empty()) {
+        let mut result = TestExecutionResult::new(runner, command, Vec::new(), true);
+        result.output = "No tests are affected by the changed files".to_string();
+        result.affected_files = affected;
+        return Ok(result);
+    }
+
+    let start = std::time::Instant::now();
+    let mut process = tokio::process::Command::new(program);
+    process
+        .args(&args)
+        .envs(env)
+        .current_dir(ws)
+        .kill_on_drop(true);
+    let output = tokio::time::timeout(options.timeout, process.output())
+        .await
+        .map_err(|_| format!("Tests timed out after {}s", options.timeout.as_secs()))?
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let report_content = match report {
+        Some(report) => tokio::fs::read_to_string(report).await.ok(),
+        None => None,
+    };
+    let mut tests = runner.parse(&stdout, report_content.as_deref());
+    // Cargo filters could not take the pattern alongside the affected modules, so apply it here
+    if let (Some(pattern), Some(_), TestRunner::Cargo | TestRunner::Nextest) =
+        (&options.test_pattern, &selection, runner)
+    {
+        tests.retain(|test| test.name.contains(pattern.as_str()));
+    }
+
+    let mut result = TestExecutionResult::new(runner, command, tests, output.status.success());
+    result.duration_ms = start.elapsed().as_millis() as u64;
+    result.output = readable_output(&stdout, &stderr);
+    result.affected_files = affected;
+    Ok(result)
+}
+
+/// Runner output meant for people: stderr and stdout without JSON event lines, last part kept
+fn readable_output(stdout: &str, stderr: &str) -> String {
+    let text: Vec<&str> = stdout
+        .lines()
+        .chain(stderr.lines())
+        .filter(|line| {
+            !(line.starts_with('{') && serde_json::from_str::<serde_json::Value>(line).is_ok())
+        })
+        .collect();
+    let text = text.join("\n");
+    let skip = text.chars().count().saturating_sub(TEST_OUTPUT_CHARS);
+    text.chars().skip(skip).collect()
+}
+
+fn millis(seconds: f64) -> u64 {
+    (seconds * 1000.0).round() as u64
+}
+
+/// Results from libtest's default output (`cargo test`)
+/// Each test reports `test <name> ... ok|FAILED|ignored`; a failure's captured output follows
+/// later under `---- <name> stdout ----`. This format carries no durations.
+fn parse_libtest_pretty(stdout: &str) -> Vec<TestCaseResult> {
+    let mut tests: Vec<TestCaseResult> = Vec::new();
+    let mut failure: Option<(String, Vec<&str>)> = None;
+    let mut messages: std::collections::HashMap<String, String> = Default::default();
+
+    for line in stdout.lines() {
+        if let Some(rest) = line.strip_prefix("---- ") {
+            if let Some((name, output)) = failure.take() {
+                messages.insert(name, output.join("\n").trim().to_string());
+            }
+            if let Some(name) = rest.strip_suffix(" stdout ----") {
+                failure = Some((name.to_string(), Vec::new()));
+            }
+            continue;
+        }
+        if let Some((name, output)) = failure.as_mut() {
+            // The list of failed names and the summary close the captured output
+            if line == "failures:" || line.starts_with("test result:") {
+                messages.insert(std::mem::take(name), output.join("\n").trim().to_string());
+                failure = None;
+            } else {
+                output.push(line);
+            }
+            continue;
+        }
+
+        let Some((name, outcome)) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.split_once(" ... "))
+        else {
+            continue;
+        };
+        let (status, message) = match outcome {
+            "ok" => (TestStatus::Passed, None),
+            "FAILED" => (TestStatus::Failed, None),
+            "ignored" => (TestStatus::Skipped, None),
+            other => match other.strip_prefix("ignored, ") {
+                Some(reason) => (TestStatus::Skipped, Some(reason.to_string())),
+                None => continue,
+            },
+        };
+        tests.push(TestCaseResult {
+            name: name.trim_end_matches(" - should panic").to_string(),
+            file: None,
+            status,
+            duration_ms: None,
+            message,
+        });
+    }
+    if let Some((name, output)) = failure {
+        messages.insert(name, output.join("\n").trim().to_string());
+    }
+
+    for test in tests.iter_mut().filter(|t| t.status == TestStatus::Failed) {
+        test.message = messages.remove(&test.name).filter(|m| !m.is_empty());
+    }
+    tests
+}
+
+/// Results from libtest's JSON events (`cargo nextest`)
+fn parse_libtest_json(stdout: &str) -> Vec<TestCaseResult> {
+    stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|event| event["type"] == "test")
+        .filter_map(|event| {
+            let status = match event["event"].as_str()? {
+                "ok" => TestStatus::Passed,
+                "failed" | "timeout" => TestStatus::Failed,
+                "ignored" => TestStatus::Skipped,
+                _ => return None,
+            };
+            let name = event["name"].as_str()?;
+            // nextest prefixes names with `crate::binary$`
+            let name = name.split_once('$').map(|(_, test)| test).unwrap_or(name);
+            let message = event["stdout"]
+                .as_str()
+                .or_else(|| event["message"].as_str())
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty());
+            Some(TestCaseResult {
+                name: name.to_string(),
+                file: None,
+                status,
+                duration_ms: event["exec_time"].as_f64().map(millis),
+                message,
+            })
+        })
+        .collect()
+}
+
+/// Results from a jest `--json` or vitest `--reporter=json` report
+fn parse_jest_json(report: &str) -> Vec<TestCaseResult> {
+    let report: serde_json::Value = serde_json::from_str(report).unwrap_or_default();
+    let mut tests = Vec::new();
+    for file in report["testResults"].as_array().into_iter().flatten() {
+        let path = file["name"].as_str().map(str::to_string);
+        let assertions = file["assertionResults"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        // A suite that fails to load reports no assertions, only a message
+        if assertions.is_empty() && file["status"] == "failed" {
+            tests.push(TestCaseResult {
+                name: path.clone().unwrap_or_default(),
+                file: path.clone(),
+                status: TestStatus::Failed,
+                duration_ms: None,
+                message: file["message"].as_str().map(str::to_string),
+            });
+        }
+        for assertion in assertions {
+            let status = match assertion["status"].as_str() {
+                Some("passed") => TestStatus::Passed,
+                Some("failed") => TestStatus::Failed,
+                _ => TestStatus::Skipped,
+            };
+            let failures: Vec<&str> = assertion["failureMessages"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|m| m.as_str())
+                .collect();
+            tests.push(TestCaseResult {
+                name: assertion["fullName"]
+                    .as_str()
+                    .or_else(|| assertion["title"].as_str())
+                    .unwrap_or_default()
+                    .to_string(),
+                file: path.clone(),
+                status,
+                duration_ms: assertion["duration"].as_f64().map(|ms| ms.round() as u64),
+                message: (!failures.is_empty()).then(|| failures.join("\n")),
+            });
+        }
+    }
+    tests
+}
+
+/// Results from a JUnit XML report, as pytest's `--junitxml` writes it
+fn parse_junit_xml(xml: &str) -> Vec<TestCaseResult> {
+    let mut tests = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<testcase") {
+        rest = &rest[start + "<testcase".len()..];
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let attributes = &rest[..tag_end];
+        let body = if attributes.ends_with('/') {
+            ""
+        } else {
+            let end = rest.find("</testcase>").unwrap_or(rest.len());
+            &rest[tag_end + 1..end.max(tag_end + 1)]
+        };
+
+        let (status, message) = if let Some(failure) = ["<failure", "<error"]
+            .iter()
+            .find_map(|tag| body.find(tag).map(|at| &body[at..]))
+        {
+            let tag_end = failure.find('>').unwrap_or(failure.len());
+            let summary = xml_attribute(&failure[..tag_end], "message").unwrap_or_default();
+            let text = failure
+                .get(tag_end + 1..)
+                .and_then(|text| text.split("</").next())
+                .map(xml_unescape)
+                .unwrap_or_default();
+            let message = [summary, text.trim().to_string()]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            (TestStatus::Failed, Some(message))
+        } else if let Some(at) = body.find("<skipped") {
+            let skipped = &body[at..];
+            let tag_end = skipped.find('>').unwrap_or(skipped.len());
+            (
+                TestStatus::Skipped,
+                xml_attribute(&skipped[..tag_end], "message"),
+            )
+        } else {
+            (TestStatus::Passed, None)
+        };
+
+        let name = xml_attribute(attributes, "name").unwrap_or_default();
+        let name = match xml_attribute(attributes, "classname").filter(|c| !c.is_empty()) {
+            Some(class) => format!("{}.{}", class, name),
+            None => name,
+        };
+        tests.push(TestCaseResult {
+            name,
+            file: xml_attribute(attributes, "file"),
+            status,
+            duration_ms: xml_attribute(attributes, "time")
+                .and_then(|t| t.parse::<f64>().ok())
+                .map(millis),
+            message,
+        });
+    }
+    tests
+}
+
+/// Value of an attribute in the inside of an XML start tag
+fn xml_attribute(tag: &str, name: &str) -> Option<String> {
+    let needle = format!("{}=\"", name);
+    let mut offset = 0;
+    while let Some(at) = tag[offset..].find(&needle) {
+        let start = offset + at;
+        offset = start + needle.len();
+        // Skip longer attribute names ending in `name`, e.g. `classname`
+        if start > 0 && !tag[..start].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let value = &tag[offset..];
+        return value.find('"').map(|end| xml_unescape(&value[..end]));
+    }
+    None
+}
+
+fn xml_unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&#10;", "\n")
+        .replace("&amp;", "&")
+}
+
+/// Results from `go test -json` events
+fn parse_go_test_json(stdout: &str) -> Vec<TestCaseResult> {
+    let mut output: std::collections::HashMap<(String, String), String> =
+        std::collections::HashMap::new();
+    let mut tests = Vec::new();
+    let mut failed_packages = Vec::new();
+    for event in stdout
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+    {
+        let package = event["Package"].as_str().unwrap_or_default().to_string();
+        let test = event["Test"].as_str().map(str::to_string);
+        let key = (package.clone(), test.clone().unwrap_or_default());
+        let status = match event["Action"].as_str() {
+            Some("output") => {
+                let text = event["Output"].as_str().unwrap_or_default();
+                output.entry(key).or_default().push_str(text);
+                continue;
+            }
+            Some("pass") => TestStatus::Passed,
+            Some("fail") => TestStatus::Failed,
+            Some("skip") => TestStatus::Skipped,
+            _ => continue,
+        };
+        let Some(name) = test else {
+            if status == TestStatus::Failed {
+                failed_packages.push(package);
+            }
+            continue;
+        };
+        let message = (status != TestStatus::Passed)
+            .then(|| output.get(&key).map(|o| o.trim().to_string()))
+            .flatten();
+        tests.push(TestCaseResult {
+            name,
+            file: Some(package),
+            status,
+            duration_ms: event["Elapsed"].as_f64().map(millis),
+            message,
+        });
+    }
+
+    // A package that fails without a failing test did not build
+    for package in failed_packages {
+        let has_failure = tests
+            .iter()
+            .any(|t| t.file.as_deref() == Some(package.as_str()) && t.status == TestStatus::Failed);
+        if !has_failure {
+            tests.push(TestCaseResult {
+                name: package.clone(),
+                message: output
+                    .get(&(package.clone(), String::new()))
+                    .map(|o| o.trim().to_string()),
+                file: Some(package),
+                status: TestStatus::Failed,
+                duration_ms: None,
+            });
+        }
+    }
+    tests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Symbol, SymbolGraph};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parse_libtest_pretty_from_cargo() {
+        let stdout = "
+running 5 tests
+test tests::adds ... ok
+test tests::divides ... FAILED
+test tests::net ... ignored, needs network
+test tests::panics - should panic ... ok
+test tests::slow ... ignored
+
+failures:
+
+---- tests::divides stdout ----
+some output
+
+thread 'tests::divides' (23262) panicked at src/lib.rs:5:53:
+assertion `left == right` failed: bad sum
+  left: 2
+ right: 3
+
+
+failures:
+    tests::divides
+
+test result: FAILED. 2 passed; 1 failed; 2 ignored; 0 measured; 0 filtered out; finished in 0.02s
+
+
+running 1 test
+test integration ... FAILED
+
+failures:
+
+---- integration stdout ----
+
+thread 'integration' (23265) panicked at tests/it.rs:1:28:
+integration broke
+
+failures:
+    integration
+
+test result: FAILED. 0 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+";
+        let tests = parse_libtest_pretty(stdout);
+        let names: Vec<&str> = tests.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "tests::adds",
+                "tests::divides",
+                "tests::net",
+                "tests::panics",
+                "tests::slow",
+                "integration"
+            ]
+        );
+        let divides = tests[1].message.as_deref().unwrap();
+        assert!(divides.starts_with("some output"));
+        assert!(divides.ends_with("right: 3"));
+        assert_eq!(
+            (tests[2].status, tests[2].message.as_deref()),
+            (TestStatus::Skipped, Some("needs network"))
+        );
+        assert_eq!(tests[3].status, TestStatus::Passed);
+        assert!(tests[5]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("tests/it.rs:1:28"));
+
+        let result =
+            TestExecutionResult::new(TestRunner::Cargo, "cargo test".to_string(), tests, false);
+        assert_eq!((result.passed, result.failed, result.skipped), (2, 2, 2));
+        let (program, args, env) = TestRunner::Cargo.command(std::path::Path::new("r"), None, None);
+        assert_eq!(
+            (program, args.join(" ")),
+            ("cargo", "test --no-fail-fast --".to_string())
+        );
+        assert!(env.is_empty());
+    }
+
+    #[test]
+    fn test_parse_libtest_json_from_nextest() {
+        let stdout = r#"{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "math::tests::adds" }
+{ "type": "test", "name": "math::tests::adds", "event": "ok", "exec_time": 0.0021 }
+{ "type": "test", "name": "math::tests::divides", "event": "failed", "exec_time": 0.01, "stdout": "thread 'math::tests::divides' panicked at src/math.rs:9:5:\nassertion `left == right` failed\n" }
+{ "type": "test", "name": "demo::tests$math::tests::slow", "event": "ignored" }
+running 3 tests"#;
+        let tests = parse_libtest_json(stdout);
+        assert_eq!(tests.len(), 3);
+        assert_eq!(tests[0].status, TestStatus::Passed);
+        assert_eq!(tests[0].duration_ms, Some(2));
+        assert_eq!(tests[1].status, TestStatus::Failed);
+        assert!(tests[1]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("src/math.rs:9:5"));
+        assert_eq!(
+            (tests[2].name.as_str(), tests[2].status),
+            ("math::tests::slow", TestStatus::Skipped)
+        );
+
+        let result = TestExecutionResult::new(
+            TestRunner::Nextest,
+            "cargo nextest run".to_string(),
+            tests,
+            false,
+        );
+        assert!(!result.success);
+        assert_eq!(
+            (
+                result.total_tests,
+                result.passed,
+                result.failed,
+                result.skipped
+            ),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(result.failed_tests[0].name, "math::tests::divides");
+    }
+
+    #[test]
+    fn test_parse_jest_json() {
+        let report = r#"{
+            "numTotalTests": 3,
+            "testResults": [
+                { "name": "/ws/src/broken.test.ts", "status": "failed", "message": "Cannot find module './missing'", "assertionResults": [] },
+                {
+                    "name": "/ws/src/sum.test.ts",
+                    "status": "failed",
+                    "assertionResults": [
+                        { "fullName": "sum adds", "title": "adds", "status": "passed", "duration": 3, "failureMessages": [] },
+                        { "fullName": "sum carries", "title": "carries", "status": "failed", "duration": 5.4,
+                          "failureMessages": ["Error: expect(received).toBe(expected)\n    at sum.test.ts:8:17"] },
+                        { "fullName": "sum later", "title": "later", "status": "todo", "duration": null, "failureMessages": [] }
+                    ]
+                }
+            ]
+        }"#;
+        let tests = parse_jest_json(report);
+        assert_eq!(tests.len(), 4);
+        assert_eq!(tests[0].name, "/ws/src/broken.test.ts");
+        assert_eq!(
+            tests[0].message.as_deref(),
+            Some("Cannot find module './missing'")
+        );
+        assert_eq!(
+            (tests[1].status, tests[1].duration_ms),
+            (TestStatus::Passed, Some(3))
+        );
+        assert_eq!(tests[2].status, TestStatus::Failed);
+        assert!(tests[2]
+            .message
+            .as_deref()
+            .unwrap()
+            .contains("sum.test.ts:8:17"));
+        assert_eq!(tests[3].status, TestStatus::Skipped);
+        assert_eq!(tests[3].file.as_deref(), Some("/ws/src/sum.test.ts"));
+    }
+
+    #[test]
+    fn test_parse_pytest_junit_xml() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites><testsuite name="pytest" errors="0" failures="1" skipped="1" tests="3" time="0.05">
+<testcase classname="tests.test_math" name="test_add" time="0.001" />
+<testcase classname="tests.test_math" name="test_div" time="0.012"><failure message="assert 1 == 2">def test_div():
+&gt;       assert divide(2, 2) == 2
+E       assert 1 == 2
+
+tests/test_math.py:9: AssertionError</failure></testcase>
+<testcase classname="tests.test_math" name="test_slow" time="0.000"><skipped type="pytest.skip" message="too slow">tests/test_math.py:12: too slow</skipped></testcase>
+</testsuite></testsuites>"#;
+        let tests = parse_junit_xml(xml);
+        assert_eq!(tests.len(), 3);
+        assert_eq!(tests[0].name, "tests.test_math.test_add");
+        assert_eq!(
+            (tests[0].status, tests[0].duration_ms),
+            (TestStatus::Passed, Some(1))
+        );
+        assert_eq!(tests[1].status, TestStatus::Failed);
+        let message = tests[1].message.as_deref().unwrap();
+        assert!(message.starts_with("assert 1 == 2\n"));
+        assert!(message.contains(">       assert divide(2, 2) == 2"));
+        assert_eq!(
+            (tests[2].status, tests[2].message.as_deref()),
+            (TestStatus::Skipped, Some("too slow"))
+        );
+    }
+
+    #[test]
+    fn test_parse_go_test_json() {
+        let stdout = r#"{"Action":"run","Package":"example.com/calc","Test":"TestAdd"}
+{"Action":"output","Package":"example.com/calc","Test":"TestAdd","Output":"=== RUN   TestAdd\n"}
+{"Action":"pass","Package":"example.com/calc","Test":"TestAdd","Elapsed":0.01}
+{"Action":"output","Package":"example.com/calc","Test":"TestDiv","Output":"    calc_test.go:14: got 1, want 2\n"}
+{"Action":"fail","Package":"example.com/calc","Test":"TestDiv","Elapsed":0}
+{"Action":"fail","Package":"example.com/calc","Elapsed":0.02}
+{"Action":"output","Package":"example.com/broken","Output":"broken.go:3:1: syntax error\n"}
+{"Action":"fail","Package":"example.com/broken","Elapsed":0}"#;
+        let tests = parse_go_test_json(stdout);
+        assert_eq!(tests.len(), 3);
+        assert_eq!(
+            (tests[0].name.as_str(), tests[0].duration_ms),
+            ("TestAdd", Some(10))
+        );
+        assert_eq!(
+            tests[1].message.as_deref(),
+            Some("calc_test.go:14: got 1, want 2")
+        );
+        assert_eq!(tests[2].name, "example.com/broken");
+        assert_eq!(
+            tests[2].message.as_deref(),
+            Some("broken.go:3:1: syntax error")
+        );
+    }
+
+    fn add_file(graph: &mut SymbolGraph, file: &str, functions: &[&str]) {
+        let symbol = |id: String, name: &str, kind: &str| Symbol {
+            id,
+            name: name.to_string(),
+            file_path: file.to_string(),
+            package: None,
+            language: "rust".to_string(),
+            kind: kind.to_string(),
+            byte_range: None,
+            metadata: HashMap::new(),
+        };
+        graph.add_symbol(symbol(file.to_string(), file, "file"));
+        for function in functions {
+            let id = format!("{}::{}", file, function);
+            graph.add_symbol(symbol(id.clone(), function, "function_item"));
+            graph.add_dependency(&id, file, "defined_in");
+        }
+    }
+
+    #[test]
+    fn test_affected_files_follow_callers_transitively() {
+        let mut graph = SymbolGraph::new();
+        add_file(&mut graph, "src/math.rs", &["divide"]);
+        add_file(&mut graph, "src/stats/mod.rs", &["mean"]);
+        add_file(&mut graph, "src/report.rs", &["render"]);
+        add_file(&mut graph, "tests/stats.rs", &["mean_of_pair"]);
+        add_file(&mut graph, "src/lib.rs", &[]);
+        graph.add_dependency("src/stats/mod.rs", "divide", "calls");
+        graph.add_dependency("tests/stats.rs", "mean", "calls");
+        graph.add_dependency("src/lib.rs", "report", "imports");
+
+        let affected = affected_files(&graph, &["./src/math.rs".to_string()]);
+        assert_eq!(
+            affected,
+            vec!["src/math.rs", "src/stats/mod.rs", "tests/stats.rs"]
+        );
+        assert_eq!(
+            TestRunner::Cargo.select(&graph, &affected),
+            Some(vec![
+                "math".to_string(),
+                "mean_of_pair".to_string(),
+                "stats".to_string()
+            ])
+        );
+
+        // Changing a module the crate root imports reaches the crate root, which selects everything
+        let affected = affected_files(&graph, &["src/report.rs".to_string()]);
+        assert!(affected.contains(&"src/lib.rs".to_string()));
+        assert_eq!(TestRunner::Cargo.select(&graph, &affected), None);
+
+        let files = [
+            "web/sum.test.ts",
+            "web/sum.ts",
+            "pkg/calc/calc.go",
+            "tests/test_io.py",
+        ]
+        .map(String::from);
+        assert_eq!(
+            TestRunner::Jest.select(&graph, &files),
+            Some(vec!["web/sum.test.ts".to_string()])
+        );
+        assert_eq!(
+            TestRunner::Pytest.select(&graph, &files),
+            Some(vec!["tests/test_io.py".to_string()])
+        );
+        assert_eq!(
+            TestRunner::GoTest.select(&graph, &files),
+            Some(vec!["./pkg/calc".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_detects_runner_from_manifests() {
+        let workspace = tempfile::tempdir().unwrap();
+        assert_eq!(TestRunner::detect(workspace.path()), None);
+        std::fs::write(workspace.path().join("package.json"), "{}").unwrap();
+        let node = detect_framework(workspace.path());
+        assert_eq!((node.framework.as_str(), node.runner), ("node", None));
+
+        std::fs::write(
+            workspace.path().join("package.json"),
+            r#"{"devDependencies": {"vitest": "^2.0.0"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            TestRunner::detect(workspace.path()),
+            Some(TestRunner::Vitest)
+        );
+        let vitest = detect_framework(workspace.path());
+        assert_eq!(
+            (vitest.test_command.as_str(), vitest.runner),
+            ("npx vitest run", Some(TestRunner::Vitest))
+        );
+        std::fs::write(
+            workspace.path().join("Cargo.toml"),
+            "[package]\nname = \"demo\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            TestRunner::detect(workspace.path()),
+            Some(TestRunner::Cargo)
+        );
+        assert_eq!(
+            detect_framework(workspace.path()).runner,
+            Some(TestRunner::Cargo)
+        );
+
+        // pytest-only markers are a Python stack for both
+        let python = tempfile::tempdir().unwrap();
+        std::fs::write(python.path().join("conftest.py"), "").unwrap();
+        let framework = detect_framework(python.path());
+        assert_eq!(
+            (framework.framework.as_str(), framework.runner),
+            ("python", Some(TestRunner::Pytest))
+        );
+    }
+}
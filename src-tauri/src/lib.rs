@@ -113,6 +113,12 @@ pub struct ExecutionMetrics {
     pub shadow_commits: usize,
     /// Timestamp of last update
     pub last_updated_ms: u64,
+    /// Atom executions answered from the response cache
+    #[serde(default)]
+    pub cache_hits: u64,
+    /// Cacheable atom executions that had to call the LLM
+    #[serde(default)]
+    pub cache_misses: u64,
 }
 
 /// A voting candidate for display
@@ -124,6 +130,9 @@ pub struct VotingCandidate {
     pub red_flags: Vec<String>,
     pub status: String, // "pending", "accepted", "rejected"
     pub votes: usize,
+    /// Hash of the candidate's equivalence class; None if it was discarded
+    #[serde(default)]
+    pub hash: Option<String>,
 }
 
 /// Current voting state
@@ -145,24 +154,167 @@ static EXECUTION_METRICS: Mutex<ExecutionMetrics> = Mutex::new(ExecutionMetrics
     red_flag_count: 0,
     shadow_commits: 0,
     last_updated_ms: 0,
+    cache_hits: 0,
+    cache_misses: 0,
 });
 
-// Global voting state
-#[allow(dead_code)]
-static VOTING_STATE: Mutex<VotingState> = Mutex::new(VotingState {
-    task_id: String::new(),
-    task_description: String::new(),
-    candidates: Vec::new(),
-    is_voting: false,
-    winner_id: None,
-});
+/// Add LLM token usage to the session metrics
+/// Called by the LLM provider with the counts the provider actually reported.
+pub fn record_token_usage(tokens: u64, elapsed: std::time::Duration) {
+    let Ok(mut metrics) = EXECUTION_METRICS.lock() else {
+        return;
+    };
+    metrics.total_tokens += tokens;
+
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        let rate = tokens as f64 / secs;
+        // Exponential moving average keeps the dashboard reading stable
+        metrics.tokens_per_second = if metrics.tokens_per_second == 0.0 {
+            rate
+        } else {
+            0.8 * metrics.tokens_per_second + 0.2 * rate
+        };
+    }
+
+    metrics.last_updated_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+}
+
+/// Count a response cache lookup in the session metrics
+pub fn record_cache_lookup(hit: bool) {
+    let Ok(mut metrics) = EXECUTION_METRICS.lock() else {
+        return;
+    };
+    if hit {
+        metrics.cache_hits += 1;
+    } else {
+        metrics.cache_misses += 1;
+    }
+}
+
+/// Finished consensus runs kept for the dashboard; running ones are never dropped
+const MAX_VOTING_RUNS: usize = 16;
+
+// Global voting state, one entry per consensus run keyed by task id, in the order they began.
+// Concurrent runs (e.g. parallel plan tasks) each update their own entry.
+static VOTING_STATE: Mutex<Vec<VotingState>> = Mutex::new(Vec::new());
+
+/// Start showing a new consensus vote
+pub fn begin_voting(task_id: &str, task_description: &str) {
+    let Ok(mut runs) = VOTING_STATE.lock() else {
+        return;
+    };
+    runs.retain(|run| run.task_id != task_id);
+    runs.push(VotingState {
+        task_id: task_id.to_string(),
+        task_description: task_description.to_string(),
+        is_voting: true,
+        ..Default::default()
+    });
+    while runs.len() > MAX_VOTING_RUNS {
+        match runs.iter().position(|run| !run.is_voting) {
+            Some(finished) => runs.remove(finished),
+            None => break,
+        };
+    }
+}
+
+/// Add a counted candidate to a run's voting state and refresh every candidate's votes
+pub fn record_vote(task_id: &str, vote: &maker_core::CandidateVote) {
+    let Ok(mut runs) = VOTING_STATE.lock() else {
+        return;
+    };
+    let Some(state) = runs.iter_mut().rev().find(|run| run.task_id == task_id) else {
+        return;
+    };
+    state.candidates.push(VotingCandidate {
+        id: vote.candidate,
+        snippet: vote.snippet.clone(),
+        score: 0.0,
+        red_flags: vote
+            .red_flag
+            .iter()
+            .chain(vote.error.iter())
+            .cloned()
+            .collect(),
+        status: if vote.hash.is_some() {
+            "pending"
+        } else {
+            "rejected"
+        }
+        .to_string(),
+        votes: 0,
+        hash: vote.hash.clone(),
+    });
+
+    let total: usize = vote.tally.values().sum();
+    for candidate in state.candidates.iter_mut() {
+        if let Some(votes) = candidate.hash.as_ref().and_then(|h| vote.tally.get(h)) {
+            candidate.votes = *votes;
+            candidate.score = *votes as f64 / total.max(1) as f64;
+        }
+    }
+}
+
+/// Mark the winning class once a run's consensus vote ends
+pub fn end_voting(task_id: &str, result: &maker_core::ConsensusResult) {
+    let Ok(mut runs) = VOTING_STATE.lock() else {
+        return;
+    };
+    let Some(state) = runs.iter_mut().rev().find(|run| run.task_id == task_id) else {
+        return;
+    };
+    let winning_hash = result.winner_class().map(|class| class.hash.clone());
+    for candidate in state.candidates.iter_mut() {
+        let won = winning_hash.is_some() && candidate.hash == winning_hash;
+        candidate.status = if won { "accepted" } else { "rejected" }.to_string();
+    }
+    state.winner_id = state
+        .candidates
+        .iter()
+        .find(|candidate| candidate.status == "accepted")
+        .map(|candidate| candidate.id);
+    state.is_voting = false;
+}
+
+/// Voting state of a run, or of the run that began last when no task id is given
+pub fn voting_state(task_id: Option<&str>) -> VotingState {
+    let Ok(runs) = VOTING_STATE.lock() else {
+        return VotingState::default();
+    };
+    match task_id {
+        Some(task_id) => runs.iter().rev().find(|run| run.task_id == task_id),
+        None => runs.last(),
+    }
+    .cloned()
+    .unwrap_or_default()
+}
+
+/// Voting state of every tracked run, oldest first
+pub fn voting_states() -> Vec<VotingState> {
+    VOTING_STATE
+        .lock()
+        .map(|runs| runs.clone())
+        .unwrap_or_default()
+}
+
+/// Forget every tracked run
+pub fn clear_voting_states() {
+    if let Ok(mut runs) = VOTING_STATE.lock() {
+        runs.clear();
+    }
+}
 
 /// Module for grits-core integration functionality
 pub mod grits {
     use super::*;
-    // use grits_core::topology::analysis::{InvariantResult, LayerViolation};
-    // use grits_core::topology::layers::load_layer_config;
+    use grits_core::topology::analysis::LayerConfig;
+    use grits_core::topology::layers::load_layer_config;
     use grits_core::topology::scanner::DirectoryScanner;
+    use grits_core::topology::virtual_apply::VirtualApply;
     use std::sync::Mutex;
 
     /// Cached SymbolGraph for the workspace (thread-safe)
@@ -207,27 +359,105 @@ pub mod grits {
     // Note: virtual_red_flag_check and others are used by handlers/governance.rs
     // We keep this mod here for now to support the handlers.
 
+    /// Load the workspace's layers.yaml (or the grits default).
+    /// A layers.yaml that cannot be read or parsed is an error rather than no rules.
+    pub fn load_workspace_layer_config(
+        workspace_path: Option<&str>,
+    ) -> Result<Option<LayerConfig>, String> {
+        workspace_path
+            .map(|ws_path| {
+                load_layer_config(Path::new(ws_path))
+                    .map_err(|e| format!("Failed to load layer config: {:#}", e))
+            })
+            .transpose()
+    }
+
+    /// Virtually apply proposed changes to the graph and report architectural red flags.
+    /// Layer rules come from the workspace's layers.yaml (or the grits default).
     pub fn virtual_red_flag_check(
-        _graph: &SymbolGraph,
-        _proposed_changes: &[crate::handlers::governance::ProposedChange],
-        _workspace_path: Option<&str>,
-    ) -> crate::handlers::governance::RedFlagResult {
-        // ... (This function remains but returns the new RedFlagResult type)
-        // Implementation omitted for brevity, logic identical to previous but mapping types
-        // For the sake of this refactor, let's assume this delegates to a core util or runs locally
-
-        // Mock return for compilation until we move deep logic
-        crate::handlers::governance::RedFlagResult {
-            introduced_cycle: false,
-            has_layer_violations: false,
-            cycles_detected: vec![],
-            layer_violations: vec![],
+        graph: &SymbolGraph,
+        proposed_changes: &[crate::handlers::governance::ProposedChange],
+        workspace_path: Option<&str>,
+    ) -> Result<crate::handlers::governance::RedFlagResult, String> {
+        let layer_config = load_workspace_layer_config(workspace_path)?;
+
+        let result = VirtualApply::new(graph.clone(), layer_config).validate(proposed_changes);
+
+        let betti_1_delta = result.new_betti_1 as i64 - result.original_betti_1 as i64;
+        let cycles_detected = if result.introduces_cycles {
+            find_introduced_cycles(graph, &result.new_dependencies)
+        } else {
+            Vec::new()
+        };
+        let has_layer_violations = !result.layer_violations.is_empty();
+
+        let rejection_reason = if result.introduces_cycles {
+            Some(format!(
+                "Introduced circular dependency (Betti_1: {} -> {})",
+                result.original_betti_1, result.new_betti_1
+            ))
+        } else if has_layer_violations {
+            Some(format!(
+                "Architectural layer violation: {}",
+                result
+                    .layer_violations
+                    .iter()
+                    .map(|v| v.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ))
+        } else {
+            None
+        };
+
+        Ok(crate::handlers::governance::RedFlagResult {
+            introduced_cycle: result.introduces_cycles,
+            has_layer_violations,
+            cycles_detected,
+            layer_violations: result.layer_violations,
+            betti_1_delta,
             is_verbose: false,
             is_malformed: false,
-            approved: true,
-            rejection_reason: None,
+            approved: rejection_reason.is_none(),
+            rejection_reason,
+        })
+    }
+
+    /// Find the cycles closed by each new dependency as symbol paths.
+    /// A new edge `from -> to` closes a cycle when `to` can already reach `from`;
+    /// the returned path starts and ends at `from`.
+    fn find_introduced_cycles(
+        graph: &SymbolGraph,
+        new_dependencies: &[(String, String, String)],
+    ) -> Vec<Vec<String>> {
+        let mut virtual_graph = graph.clone();
+        for (from, to, relation) in new_dependencies {
+            virtual_graph.add_dependency(from, to, relation);
         }
+
+        let mut cycles: Vec<Vec<String>> = Vec::new();
+        for (from, to, _) in new_dependencies {
+            let cycle = if from == to {
+                Some(vec![from.clone(), to.clone()])
+            } else {
+                TopologicalAnalysis::get_path(&virtual_graph, to, from).map(|path| {
+                    let mut cycle = Vec::with_capacity(path.len() + 1);
+                    cycle.push(from.clone());
+                    cycle.extend(path);
+                    cycle
+                })
+            };
+
+            if let Some(cycle) = cycle {
+                if !cycles.contains(&cycle) {
+                    cycles.push(cycle);
+                }
+            }
+        }
+
+        cycles
     }
+
     pub fn assemble_context(
         graph: &SymbolGraph,
         seed_symbols: Vec<String>,
@@ -263,12 +493,25 @@ pub struct ApiKeys {
     pub anthropic: String,
     pub cerebras: String,
     pub ollama_url: String,
+    #[serde(default)]
+    pub llamacpp_url: String,
+    #[serde(default)]
+    pub openrouter: String,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct AppSettings {
     pub agent_config: AgentConfig,
     pub api_keys: ApiKeys,
+    /// Limits applied to every script run
+    #[serde(default)]
+    pub budget: maker_core::Budget,
+    /// Concurrency, rate limits and circuit breaker for each provider endpoint
+    #[serde(default)]
+    pub provider_limits: llm::ProviderLimits,
+    /// Cache spawn_atom responses in each workspace's .maker/cache; None disables the cache
+    #[serde(default)]
+    pub response_cache: Option<agents::CacheConfig>,
 }
 
 pub fn get_settings_path() -> std::path::PathBuf {
@@ -291,12 +534,15 @@ pub use std::sync::Mutex as StdMutex;
 /// Global settings cache (legacy position)
 static CACHED_SETTINGS: StdMutex<Option<AppSettings>> = StdMutex::new(None);
 
-/// Get LLM provider from cached settings or disk
-pub fn get_llm_provider() -> Result<LlmProvider, String> {
-    // First, try to get from cache
-    let mut cached = CACHED_SETTINGS
-        .lock()
-        .map_err(|_| "Failed to lock settings while getting LLM provider".to_string())?;
+/// Get settings from the cache, loading them from disk on first use
+pub fn load_cached_settings() -> Option<AppSettings> {
+    let mut cached = match CACHED_SETTINGS.lock() {
+        Ok(cached) => cached,
+        Err(_) => {
+            eprintln!("Warning: Failed to lock settings cache");
+            return None;
+        }
+    };
 
     // If cache is empty, try to load from disk
     if cached.is_none() {
@@ -310,34 +556,19 @@ pub fn get_llm_provider() -> Result<LlmProvider, String> {
         }
     }
 
-    // Use settings if available, otherwise default config
-    let config = if let Some(s) = cached.as_ref() {
-        let interrogator_config = &s.agent_config.interrogator;
-        let api_key = match interrogator_config.provider.as_str() {
-            "openai" => Some(s.api_keys.openai.clone()),
-            "anthropic" => Some(s.api_keys.anthropic.clone()),
-            "cerebras" => Some(s.api_keys.cerebras.clone()),
-            _ => None,
-        };
+    cached.clone()
+}
 
-        LlmConfig {
-            provider: match interrogator_config.provider.as_str() {
-                "anthropic" => crate::llm::ProviderType::Anthropic,
-                "cerebras" => crate::llm::ProviderType::Cerebras,
-                "openrouter" => crate::llm::ProviderType::OpenRouter,
-                _ => crate::llm::ProviderType::OpenAI,
-            },
-            model: interrogator_config.model.clone(),
-            api_key,
-            base_url: None,
-            temperature: interrogator_config.temperature,
-            max_tokens: 4096,
-            timeout_secs: 120,
-        }
-    } else {
-        LlmConfig::default()
-    };
+/// Replace the cached settings so routing picks up changes without a restart
+pub fn cache_settings(settings: AppSettings) {
+    if let Ok(mut cached) = CACHED_SETTINGS.lock() {
+        *cached = Some(settings);
+    }
+}
 
+/// Get the Interrogator's LLM provider from cached settings or disk
+pub fn get_llm_provider() -> Result<LlmProvider, String> {
+    let config = llm::LlmRouter::load().config_for(llm::AgentRole::Interrogator);
     LlmProvider::new(config).map_err(|e| e.to_string())
 }
 
@@ -364,15 +595,117 @@ pub fn run() {
             // System
             handlers::system::get_cwd,
             handlers::system::get_execution_metrics,
+            handlers::system::get_voting_state,
+            handlers::system::get_voting_states,
+            handlers::system::clear_voting_state,
+            handlers::system::get_consensus_stats,
             handlers::system::save_settings,
             handlers::system::load_settings,
             // LLM
             handlers::llm::analyze_prd,
             handlers::llm::execute_script,
+            handlers::llm::list_local_models,
+            // Plans
+            handlers::plan::execute_plan,
+            handlers::plan::resume_plan,
             // Testing
             handlers::testing::detect_test_framework,
             handlers::testing::generate_tests,
+            handlers::testing::run_tests,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ProviderType;
+    use crate::maker_core::{CandidateVote, SampleConfig};
+    use std::collections::HashMap;
+
+    fn vote(candidate: usize, hash: &str, votes: usize) -> CandidateVote {
+        CandidateVote {
+            candidate,
+            sample: SampleConfig {
+                provider: ProviderType::Replay,
+                model: "mock".to_string(),
+                temperature: 0.1,
+                paraphrase: 0,
+            },
+            hash: Some(hash.to_string()),
+            snippet: String::new(),
+            red_flag: None,
+            error: None,
+            tally: HashMap::from([(hash.to_string(), votes)]),
+        }
+    }
+
+    #[test]
+    fn test_concurrent_votes_keep_their_own_state() {
+        begin_voting("vote-state-a", "first task");
+        begin_voting("vote-state-b", "second task");
+        record_vote("vote-state-a", &vote(0, "x", 1));
+        record_vote("vote-state-b", &vote(0, "y", 1));
+        record_vote("vote-state-b", &vote(1, "y", 2));
+
+        let first = voting_state(Some("vote-state-a"));
+        assert_eq!(first.task_description, "first task");
+        assert_eq!(first.candidates.len(), 1);
+        let second = voting_state(Some("vote-state-b"));
+        assert_eq!(second.candidates.len(), 2);
+        assert!(second.candidates.iter().all(|c| c.votes == 2));
+        assert!(first.is_voting && second.is_voting);
+        assert!(voting_state(Some("vote-state-unknown")).task_id.is_empty());
+    }
+
+    fn symbol(id: &str, file_path: &str) -> Symbol {
+        Symbol {
+            id: id.to_string(),
+            name: id.to_string(),
+            file_path: file_path.to_string(),
+            package: None,
+            language: "rust".to_string(),
+            kind: "module".to_string(),
+            byte_range: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_virtual_red_flag_check_reports_layer_violations() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("layers.yaml"),
+            "layers:\n  - name: domain\n    patterns: [\"src/domain/\"]\n    allowed_deps: []\n  - name: infrastructure\n    patterns: [\"src/db/\"]\n    allowed_deps: [domain]\n",
+        )
+        .unwrap();
+        let workspace = dir.path().to_str();
+
+        let mut graph = SymbolGraph::new();
+        graph.add_symbol(symbol("src_domain_user_rs", "src/domain/user.rs"));
+        graph.add_symbol(symbol("db", "src/db/mod.rs"));
+        let changes = vec![crate::handlers::governance::ProposedChange {
+            file_path: "src/domain/user.rs".to_string(),
+            change_type: grits_core::topology::virtual_apply::ChangeType::ModifyFile,
+            code_content: "use db::Pool;\n".to_string(),
+            language: "rust".to_string(),
+        }];
+
+        let result = grits::virtual_red_flag_check(&graph, &changes, workspace).unwrap();
+        assert!(!result.approved);
+        assert!(result.has_layer_violations);
+        assert!(!result.introduced_cycle);
+        let violation = &result.layer_violations[0];
+        assert_eq!(violation.from_layer, "domain");
+        assert_eq!(violation.to_layer, "infrastructure");
+        assert!(result
+            .rejection_reason
+            .unwrap()
+            .contains("Disallowed dependency: domain -> infrastructure"));
+
+        std::fs::write(dir.path().join("layers.yaml"), "layers: [unclosed").unwrap();
+        let err = grits::virtual_red_flag_check(&graph, &changes, workspace).unwrap_err();
+        assert!(err.contains("Failed to parse"), "{}", err);
+    }
+}
@@ -0,0 +1,477 @@
+// Cerebras-MAKER: LLM Error Taxonomy
+// Structured errors returned by the provider layer. ResilientLlmProvider decides whether to
+// retry, how long to wait and whether to fall back from the variant, not from the message text.
+//
+// Errors are classified once, where they leave LlmProvider: typed errors (reqwest, timeouts,
+// JSON, rig's HTTP status errors, cerebras-rs errors) map directly; provider error bodies are
+// read for their API error code (e.g. "rate_limit_error", "context_length_exceeded").
+// Neither rig nor cerebras-rs returns response headers, so completion calls only see a retry
+// hint the body carries; `Retry-After` is read where we make the request ourselves.
+
+use cerebras_rs::Error as CerebrasError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use rig::completion::CompletionError;
+use rig::http_client::Error as HttpClientError;
+use std::time::Duration;
+
+/// Why an LLM call failed
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum LlmError {
+    /// 429 or a provider rate-limit error; `retry_after` is the server's hint, if it gave one
+    #[error("Rate limited: {message}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        message: String,
+    },
+
+    /// Missing, invalid or unauthorized API key
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+
+    /// The prompt does not fit the model's context window
+    #[error("Context length exceeded: {message}")]
+    ContextLengthExceeded {
+        limit: Option<usize>,
+        message: String,
+    },
+
+    /// The request or the whole call timed out
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// 5xx or provider overload
+    #[error("Server error ({status}): {message}")]
+    Server { status: u16, message: String },
+
+    /// The response could not be parsed or ended early
+    #[error("Malformed response: {0}")]
+    Malformed(String),
+
+    /// The provider rejected the request itself (4xx other than auth and rate limits)
+    #[error("Invalid request: {message}")]
+    InvalidRequest {
+        status: Option<u16>,
+        message: String,
+    },
+
+    /// Connection failures
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// The consumer dropped the stream
+    #[error("Request cancelled")]
+    Cancelled,
+
+    /// The provider's circuit breaker is open after repeated failures
+    #[error("Circuit open: {0}")]
+    CircuitOpen(String),
+
+    /// Anything not recognized above
+    #[error("{0}")]
+    Other(String),
+}
+
+static RETRY_AFTER: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#"(?i)(?:retry[-_ ]after"?\s*[:=]?|try again in)\s*(\d+(?:\.\d+)?)\s*(ms|s)?"#)
+        .unwrap()
+});
+static CONTEXT_LIMIT: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?i)(?:maximum context length is|context window of|maximum of)\s*(\d+)").unwrap()
+});
+
+impl LlmError {
+    /// Whether the same request to the same provider may succeed later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited { .. }
+            | Self::Timeout(_)
+            | Self::Server { .. }
+            | Self::Malformed(_)
+            | Self::Network(_)
+            | Self::Other(_) => true,
+            Self::Auth(_)
+            | Self::ContextLengthExceeded { .. }
+            | Self::InvalidRequest { .. }
+            | Self::Cancelled
+            | Self::CircuitOpen(_) => false,
+        }
+    }
+
+    /// Whether the error says the provider itself is unhealthy (counts toward its circuit breaker)
+    /// Errors caused by the request or the model's output say nothing about the provider.
+    pub fn is_provider_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::RateLimited { .. }
+                | Self::Auth(_)
+                | Self::Timeout(_)
+                | Self::Server { .. }
+                | Self::Network(_)
+                | Self::Other(_)
+        )
+    }
+
+    /// Whether another provider is worth trying; each has its own keys, limits and context window
+    pub fn should_fall_back(&self) -> bool {
+        !matches!(self, Self::Cancelled)
+    }
+
+    /// How long the server asked us to wait before retrying
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+
+    /// Classify an error raised inside a provider call
+    pub fn classify(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<LlmError>() {
+            Ok(llm_error) => return llm_error,
+            Err(error) => error,
+        };
+
+        for cause in error.chain() {
+            if let Some(e) = cause.downcast_ref::<LlmError>() {
+                return e.clone();
+            }
+            // HttpError and Instance wrap the cause that follows them in the chain
+            if let Some(e) = cause.downcast_ref::<CompletionError>() {
+                if let Some(classified) = Self::from_completion(e) {
+                    return classified;
+                }
+            }
+            if let Some(e) = cause.downcast_ref::<HttpClientError>() {
+                if let Some(classified) = Self::from_http_client(e) {
+                    return classified;
+                }
+            }
+            if let Some(e) = cause.downcast_ref::<CerebrasError>() {
+                return Self::from_cerebras(e);
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return Self::from_reqwest(e);
+            }
+            if cause.is::<tokio::time::error::Elapsed>() {
+                return Self::Timeout(format!("{:#}", error));
+            }
+            if cause.is::<serde_json::Error>() {
+                return Self::Malformed(format!("{:#}", error));
+            }
+        }
+
+        Self::from_message(&format!("{:#}", error))
+    }
+
+    /// Classify an HTTP error status and response body
+    pub fn from_status(status: u16, message: &str, retry_after: Option<Duration>) -> Self {
+        let message = message.to_string();
+        match status {
+            429 => Self::RateLimited {
+                retry_after: retry_after.or_else(|| parse_retry_after(&message)),
+                message,
+            },
+            401 | 403 => Self::Auth(message),
+            408 => Self::Timeout(message),
+            413 => Self::context_length(message),
+            400..=499 if is_context_length_message(&message) => Self::context_length(message),
+            400..=499 => Self::InvalidRequest {
+                status: Some(status),
+                message,
+            },
+            _ => Self::Server { status, message },
+        }
+    }
+
+    /// Parse a `Retry-After` header value (delay in seconds)
+    pub fn parse_retry_after_header(value: &str) -> Option<Duration> {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(Duration::from_secs_f64)
+    }
+
+    /// Classify a non-success response, honouring its `Retry-After` header
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Self::parse_retry_after_header);
+        let body = response.text().await.unwrap_or_default();
+        Self::from_status(status, &body, retry_after)
+    }
+
+    /// `reqwest::Error` carries no headers; use `from_response` where the response is at hand
+    fn from_reqwest(error: &reqwest::Error) -> Self {
+        if let Some(status) = error.status() {
+            return Self::from_status(status.as_u16(), &error.to_string(), None);
+        }
+        if error.is_timeout() {
+            Self::Timeout(error.to_string())
+        } else if error.is_decode() || error.is_body() {
+            Self::Malformed(error.to_string())
+        } else {
+            Self::Network(error.to_string())
+        }
+    }
+
+    /// rig reports non-success statuses as `ProviderError` with the response body
+    fn from_completion(error: &CompletionError) -> Option<Self> {
+        let message = error.to_string();
+        match error {
+            CompletionError::HttpError(_) => None,
+            CompletionError::JsonError(_) | CompletionError::ResponseError(_) => {
+                Some(Self::Malformed(message))
+            }
+            CompletionError::UrlError(_) | CompletionError::RequestError(_) => {
+                Some(Self::InvalidRequest {
+                    status: None,
+                    message,
+                })
+            }
+            CompletionError::ProviderError(body) => Some(Self::from_message(body)),
+        }
+    }
+
+    /// Status errors from rig's HTTP client (streaming requests)
+    fn from_http_client(error: &HttpClientError) -> Option<Self> {
+        let message = error.to_string();
+        match error {
+            HttpClientError::InvalidStatusCode(status) => {
+                Some(Self::from_status(status.as_u16(), &message, None))
+            }
+            HttpClientError::InvalidStatusCodeWithMessage(status, body) => {
+                Some(Self::from_status(status.as_u16(), body, None))
+            }
+            HttpClientError::Instance(_) => None,
+            HttpClientError::StreamEnded
+            | HttpClientError::NoHeaders
+            | HttpClientError::InvalidContentType(_) => Some(Self::Malformed(message)),
+            HttpClientError::Protocol(_) | HttpClientError::InvalidHeaderValue(_) => {
+                Some(Self::InvalidRequest {
+                    status: None,
+                    message,
+                })
+            }
+        }
+    }
+
+    fn from_cerebras(error: &CerebrasError) -> Self {
+        let message = error.to_string();
+        match error {
+            CerebrasError::Http(e) => Self::from_reqwest(e),
+            // Unmapped statuses arrive as "HTTP 503 Service Unavailable: <body>"
+            CerebrasError::Api(detail) => match detail
+                .strip_prefix("HTTP ")
+                .and_then(|rest| rest.get(..3))
+                .and_then(|status| status.parse::<u16>().ok())
+            {
+                Some(status) => Self::from_status(status, &message, None),
+                None => Self::from_message(&message),
+            },
+            CerebrasError::ApiError { code, .. } => {
+                Self::from_error_code(code, &message).unwrap_or(Self::Other(message))
+            }
+            CerebrasError::RateLimit(secs) => Self::RateLimited {
+                retry_after: (*secs > 0).then(|| Duration::from_secs(*secs)),
+                message,
+            },
+            CerebrasError::Authentication => Self::Auth(message),
+            CerebrasError::NotFound(_) => Self::InvalidRequest {
+                status: Some(404),
+                message,
+            },
+            CerebrasError::InvalidRequest(_)
+            | CerebrasError::InvalidModel(_)
+            | CerebrasError::Configuration(_) => Self::InvalidRequest {
+                status: None,
+                message,
+            },
+            CerebrasError::ServerError(_) => Self::Server {
+                status: 500,
+                message,
+            },
+            CerebrasError::Serialization(_) | CerebrasError::Streaming(_) => {
+                Self::Malformed(message)
+            }
+            CerebrasError::Timeout => Self::Timeout(message),
+            CerebrasError::Unknown => Self::Other(message),
+        }
+    }
+
+    /// Classify by the API error code in a provider error body
+    fn from_message(message: &str) -> Self {
+        error_codes(message)
+            .iter()
+            .find_map(|code| Self::from_error_code(code, message))
+            .unwrap_or_else(|| Self::Other(message.to_string()))
+    }
+
+    fn from_error_code(code: &str, message: &str) -> Option<Self> {
+        match code.to_lowercase().as_str() {
+            "rate_limit_error" | "rate_limit_exceeded" => {
+                Some(Self::from_status(429, message, None))
+            }
+            "authentication_error" | "permission_error" | "invalid_api_key" => {
+                Some(Self::Auth(message.to_string()))
+            }
+            "context_length_exceeded" => Some(Self::context_length(message.to_string())),
+            "overloaded_error" => Some(Self::from_status(529, message, None)),
+            "api_error" | "server_error" => Some(Self::from_status(500, message, None)),
+            _ => None,
+        }
+    }
+
+    fn context_length(message: String) -> Self {
+        let limit = CONTEXT_LIMIT
+            .captures(&message)
+            .and_then(|c| c[1].parse().ok());
+        Self::ContextLengthExceeded { limit, message }
+    }
+}
+
+/// `type` / `code` fields of the first JSON object in a provider error body, outermost first
+/// e.g. `{"type":"error","error":{"type":"rate_limit_error"}}`
+fn error_codes(message: &str) -> Vec<String> {
+    let Some(body) = message.match_indices('{').find_map(|(start, _)| {
+        serde_json::Deserializer::from_str(&message[start..])
+            .into_iter::<serde_json::Value>()
+            .next()
+            .and_then(Result::ok)
+    }) else {
+        return Vec::new();
+    };
+    [&body, &body["error"]]
+        .into_iter()
+        .flat_map(|value| ["type", "code"].map(|key| value[key].as_str()))
+        .flatten()
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_context_length_message(message: &str) -> bool {
+    let message = message.to_lowercase();
+    [
+        "context length",
+        "context window",
+        "prompt is too long",
+        "too many tokens",
+    ]
+    .iter()
+    .any(|phrase| message.contains(phrase))
+}
+
+/// Retry hint in a provider message, e.g. `"retry_after": 20` or "Please try again in 1.5s"
+fn parse_retry_after(message: &str) -> Option<Duration> {
+    let captures = RETRY_AFTER.captures(message)?;
+    let value: f64 = captures[1].parse().ok()?;
+    match captures.get(2).map(|unit| unit.as_str()) {
+        Some("ms") => Some(Duration::from_secs_f64(value / 1000.0)),
+        _ => Some(Duration::from_secs_f64(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(message: &str) -> LlmError {
+        LlmError::classify(anyhow::anyhow!("{}", message))
+    }
+
+    #[test]
+    fn test_classifies_provider_error_bodies() {
+        let anthropic = classify(
+            r#"ProviderError: {"type":"error","error":{"type":"rate_limit_error","message":"slow down"}}"#,
+        );
+        assert!(matches!(anthropic, LlmError::RateLimited { .. }));
+
+        let openai = classify(
+            r#"Invalid status code 429 Too Many Requests: {"error":{"code":"rate_limit_exceeded","message":"Please try again in 1.5s"}}"#,
+        );
+        assert_eq!(openai.retry_after(), Some(Duration::from_millis(1500)));
+
+        let context = classify(
+            r#"{"error":{"code":"context_length_exceeded","message":"This model's maximum context length is 8192 tokens"}}"#,
+        );
+        assert!(matches!(
+            context,
+            LlmError::ContextLengthExceeded {
+                limit: Some(8192),
+                ..
+            }
+        ));
+        assert!(!context.is_retryable());
+    }
+
+    #[test]
+    fn test_classifies_typed_client_errors() {
+        // rig streaming requests fail with the status; the error reaches us wrapped
+        let unauthorized = anyhow::Error::new(CompletionError::HttpError(
+            HttpClientError::InvalidStatusCode(reqwest::StatusCode::UNAUTHORIZED),
+        ));
+        assert!(matches!(
+            LlmError::classify(unauthorized),
+            LlmError::Auth(_)
+        ));
+        let provider = anyhow::Error::new(CompletionError::ProviderError(
+            r#"{"type":"error","error":{"type":"overloaded_error","message":"busy"}}"#.to_string(),
+        ));
+        assert!(matches!(
+            LlmError::classify(provider),
+            LlmError::Server { status: 529, .. }
+        ));
+
+        let rate_limited = LlmError::classify(anyhow::Error::new(CerebrasError::RateLimit(3)));
+        assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(3)));
+        let unavailable = CerebrasError::Api("HTTP 503 Service Unavailable: busy".to_string());
+        assert!(matches!(
+            LlmError::classify(anyhow::Error::new(unavailable)),
+            LlmError::Server { status: 503, .. }
+        ));
+
+        // A status number in free text is not a status
+        assert!(matches!(
+            classify("status code 503: upstream unavailable"),
+            LlmError::Other(_)
+        ));
+    }
+
+    #[test]
+    fn test_message_words_alone_do_not_make_errors_permanent() {
+        // Used to match "invalid" and be treated as a permanent bad request
+        let error = classify("invalid JSON in model output");
+        assert!(matches!(error, LlmError::Other(_)));
+        assert!(error.is_retryable());
+
+        // Typed errors keep their variant through anyhow
+        let typed = LlmError::classify(anyhow::Error::new(LlmError::Cancelled));
+        assert_eq!(typed, LlmError::Cancelled);
+        assert!(!typed.should_fall_back());
+    }
+
+    #[test]
+    fn test_from_status() {
+        let rate_limited = LlmError::from_status(429, "slow down", Some(Duration::from_secs(7)));
+        assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(7)));
+        assert!(matches!(
+            LlmError::from_status(400, "prompt is too long", None),
+            LlmError::ContextLengthExceeded { limit: None, .. }
+        ));
+        assert!(matches!(
+            LlmError::from_status(422, "unknown field", None),
+            LlmError::InvalidRequest {
+                status: Some(422),
+                ..
+            }
+        ));
+        assert_eq!(
+            LlmError::parse_retry_after_header("20"),
+            Some(Duration::from_secs(20))
+        );
+    }
+}
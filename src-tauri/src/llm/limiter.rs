@@ -0,0 +1,496 @@
+// Cerebras-MAKER: Provider Limits
+// Per-provider concurrency semaphore, token-bucket rate limits (requests and tokens per minute)
+// and circuit breaker used by ResilientLlmProvider and AtomExecutor.
+//
+// Limiter state is shared process-wide per provider endpoint and limits, so every atom and
+// resilient provider talking to the same provider (parallel voting, concurrent DAG tasks) with
+// the same limits draws from one budget and sees one breaker. Edited limits get a new limiter.
+
+use super::{LlmConfig, LlmError};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits applied to each provider of a ResilientLlmProvider; `None` means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderLimits {
+    /// Maximum requests in flight at once
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    #[serde(default)]
+    pub requests_per_minute: Option<u32>,
+    /// Prompt tokens are estimated up front; the rest of the usage is charged after the response
+    #[serde(default)]
+    pub tokens_per_minute: Option<u32>,
+    #[serde(default)]
+    pub breaker: CircuitBreakerConfig,
+}
+
+/// When the circuit breaker opens and how long it stays open
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a half-open probe is allowed
+    pub open_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_secs: 30,
+        }
+    }
+}
+
+/// Circuit breaker state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests are refused and routed to the fallbacks
+    Open,
+    /// One probe request is allowed; its outcome closes or re-opens the circuit
+    HalfOpen,
+}
+
+/// Breaker state of one provider, as reported in `ResilienceStats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerStatus {
+    pub provider: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub times_opened: u64,
+}
+
+/// Token bucket refilled continuously up to its per-minute capacity
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn per_minute(limit: u32, now: Instant) -> Self {
+        let capacity = limit.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            per_sec: capacity / 60.0,
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.available = (self.available + elapsed * self.per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    /// Take `amount` (capped at capacity), or report how long until it is available
+    fn try_take(&mut self, amount: f64, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            self.available -= amount;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (amount - self.available) / self.per_sec,
+            ))
+        }
+    }
+
+    /// Charge usage after the fact; the bucket may go negative, delaying later requests
+    fn debit(&mut self, amount: f64, now: Instant) {
+        self.refill(now);
+        self.available -= amount;
+    }
+}
+
+#[derive(Debug)]
+struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: CircuitState,
+    consecutive_failures: u32,
+    times_opened: u64,
+    opened_at: Option<Instant>,
+    probe_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            times_opened: 0,
+            opened_at: None,
+            probe_started: None,
+        }
+    }
+
+    fn open_for(&self) -> Duration {
+        Duration::from_secs(self.config.open_secs)
+    }
+
+    /// Whether a request may go to this provider
+    /// After `open_secs` one probe is let through; a probe that never reports back (its
+    /// request was dropped) is replaced after another `open_secs`.
+    fn allow(&mut self, now: Instant) -> bool {
+        let expired = |since: Option<Instant>| {
+            since.is_none_or(|t| now.saturating_duration_since(t) >= self.open_for())
+        };
+        match self.state {
+            CircuitState::Closed => true,
+            CircuitState::Open if expired(self.opened_at) => {
+                self.state = CircuitState::HalfOpen;
+                self.probe_started = Some(now);
+                true
+            }
+            CircuitState::Open => false,
+            CircuitState::HalfOpen if expired(self.probe_started) => {
+                self.probe_started = Some(now);
+                true
+            }
+            CircuitState::HalfOpen => false,
+        }
+    }
+
+    fn record_success(&mut self) {
+        self.state = CircuitState::Closed;
+        self.consecutive_failures = 0;
+        self.opened_at = None;
+        self.probe_started = None;
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        let trips = match self.state {
+            CircuitState::HalfOpen => true,
+            CircuitState::Closed => self.consecutive_failures >= self.config.failure_threshold,
+            CircuitState::Open => false,
+        };
+        if trips {
+            self.state = CircuitState::Open;
+            self.opened_at = Some(now);
+            self.probe_started = None;
+            self.times_opened += 1;
+        }
+    }
+
+    /// A probe that ended without a verdict (e.g. cancelled) frees the slot for another
+    fn release_probe(&mut self) {
+        if self.state == CircuitState::HalfOpen {
+            self.probe_started = None;
+        }
+    }
+}
+
+/// Limits and breaker for one provider endpoint
+#[derive(Debug)]
+pub struct ProviderLimiter {
+    provider: String,
+    limits: ProviderLimits,
+    semaphore: Option<Arc<Semaphore>>,
+    requests: Option<Mutex<TokenBucket>>,
+    tokens: Option<Mutex<TokenBucket>>,
+    breaker: Mutex<CircuitBreaker>,
+}
+
+/// Limiters by provider endpoint, one for each set of limits used with it
+static LIMITERS: Lazy<Mutex<HashMap<String, Vec<Arc<ProviderLimiter>>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+impl ProviderLimiter {
+    pub fn new(provider: &str, limits: ProviderLimits) -> Self {
+        let now = Instant::now();
+        Self {
+            provider: provider.to_string(),
+            semaphore: limits
+                .max_concurrent
+                .map(|n| Arc::new(Semaphore::new(n.max(1)))),
+            requests: limits
+                .requests_per_minute
+                .map(|n| Mutex::new(TokenBucket::per_minute(n, now))),
+            tokens: limits
+                .tokens_per_minute
+                .map(|n| Mutex::new(TokenBucket::per_minute(n, now))),
+            breaker: Mutex::new(CircuitBreaker::new(limits.breaker.clone())),
+            limits,
+        }
+    }
+
+    /// The process-wide limiter for a provider endpoint and `limits`
+    /// Callers with the same limits share it; changed limits (e.g. edited settings) get a
+    /// fresh limiter, so they apply without a restart.
+    pub fn shared(config: &LlmConfig, limits: &ProviderLimits) -> Arc<Self> {
+        let provider = Self::key(config);
+        let mut limiters = LIMITERS.lock().unwrap();
+        let endpoint = limiters.entry(provider.clone()).or_default();
+        match endpoint.iter().find(|limiter| limiter.limits == *limits) {
+            Some(limiter) => limiter.clone(),
+            None => {
+                let limiter = Arc::new(Self::new(&provider, limits.clone()));
+                endpoint.push(limiter.clone());
+                limiter
+            }
+        }
+    }
+
+    /// The process-wide limiter for a provider endpoint, with the limits from settings
+    pub fn from_settings(config: &LlmConfig) -> Arc<Self> {
+        let limits = crate::load_cached_settings()
+            .map(|settings| settings.provider_limits)
+            .unwrap_or_default();
+        Self::shared(config, &limits)
+    }
+
+    /// Provider endpoint a config talks to, e.g. `Ollama@http://localhost:11434`
+    /// Replay configs are keyed by their mock name or fixture.
+    pub fn key(config: &LlmConfig) -> String {
+        let endpoint = match &config.replay {
+            Some(replay) => replay.mock.clone().or_else(|| {
+                replay
+                    .fixture_path
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+            }),
+            None => config.server_url(),
+        };
+        format!("{:?}@{}", config.provider, endpoint.unwrap_or_default())
+    }
+
+    pub fn limits(&self) -> &ProviderLimits {
+        &self.limits
+    }
+
+    /// Ask the circuit breaker whether this provider may be called
+    pub fn admit(&self) -> bool {
+        self.breaker.lock().unwrap().allow(Instant::now())
+    }
+
+    /// Wait for rate-limit capacity, then for a concurrency slot
+    /// Waiting on the rate limits first keeps a request from holding a slot while it sleeps.
+    pub async fn acquire(self: &Arc<Self>, estimated_tokens: u32) -> Permit {
+        if let Some(bucket) = &self.requests {
+            Self::take(bucket, 1.0).await;
+        }
+        if let Some(bucket) = &self.tokens {
+            Self::take(bucket, estimated_tokens as f64).await;
+        }
+        let permit = match &self.semaphore {
+            Some(semaphore) => semaphore.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        Permit {
+            _slot: permit,
+            limiter: self.clone(),
+            estimated_tokens,
+        }
+    }
+
+    async fn take(bucket: &Mutex<TokenBucket>, amount: f64) {
+        loop {
+            let wait = match bucket.lock().unwrap().try_take(amount, Instant::now()) {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Run one request under the limits and report its outcome to the circuit breaker
+    /// Refused with `LlmError::CircuitOpen` while the circuit is open. `request` gets the
+    /// permit so it can settle token usage, or keep the slot for as long as a stream runs.
+    pub async fn call<T, F, Fut>(
+        self: &Arc<Self>,
+        estimated_tokens: u32,
+        request: F,
+    ) -> Result<T, LlmError>
+    where
+        F: FnOnce(Permit) -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
+        if !self.admit() {
+            return Err(LlmError::CircuitOpen(format!(
+                "{} failed repeatedly; waiting for a probe to succeed",
+                self.provider
+            )));
+        }
+        let permit = self.acquire(estimated_tokens).await;
+        let result = request(permit).await;
+        match &result {
+            Ok(_) => self.record_success(),
+            Err(e) if e.is_provider_failure() => self.record_failure(),
+            Err(_) => self.release_probe(),
+        }
+        result
+    }
+
+    pub fn record_success(&self) {
+        self.breaker.lock().unwrap().record_success();
+    }
+
+    pub fn record_failure(&self) {
+        self.breaker.lock().unwrap().record_failure(Instant::now());
+    }
+
+    /// Free a half-open probe slot for a request that ended without success or failure
+    pub fn release_probe(&self) {
+        self.breaker.lock().unwrap().release_probe();
+    }
+
+    pub fn status(&self) -> BreakerStatus {
+        let breaker = self.breaker.lock().unwrap();
+        BreakerStatus {
+            provider: self.provider.clone(),
+            state: breaker.state,
+            consecutive_failures: breaker.consecutive_failures,
+            times_opened: breaker.times_opened,
+        }
+    }
+}
+
+/// A granted request slot; dropping it frees the concurrency slot
+#[derive(Debug)]
+pub struct Permit {
+    _slot: Option<OwnedSemaphorePermit>,
+    limiter: Arc<ProviderLimiter>,
+    estimated_tokens: u32,
+}
+
+impl Permit {
+    /// Charge the tokens the request actually used beyond the up-front estimate
+    pub fn settle(&self, tokens_used: Option<u32>) {
+        let (Some(bucket), Some(used)) = (&self.limiter.tokens, tokens_used) else {
+            return;
+        };
+        let extra = used.saturating_sub(self.estimated_tokens);
+        if extra > 0 {
+            bucket.lock().unwrap().debit(extra as f64, Instant::now());
+        }
+    }
+}
+
+/// Rough prompt size in tokens (4 characters per token)
+pub fn estimate_tokens(text_len: usize) -> u32 {
+    (text_len / 4).min(u32::MAX as usize) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refills_per_minute() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(60, start);
+
+        assert!(bucket.try_take(60.0, start).is_ok());
+        let wait = bucket.try_take(1.0, start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert!(bucket.try_take(1.0, start + Duration::from_secs(1)).is_ok());
+
+        // Usage charged after the fact pushes the next request out
+        bucket.debit(30.0, start + Duration::from_secs(1));
+        let wait = bucket
+            .try_take(1.0, start + Duration::from_secs(1))
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(31));
+    }
+
+    #[test]
+    fn test_breaker_opens_probes_and_closes() {
+        let start = Instant::now();
+        let mut breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 3,
+            open_secs: 10,
+        });
+
+        for _ in 0..3 {
+            assert!(breaker.allow(start));
+            breaker.record_failure(start);
+        }
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert!(!breaker.allow(start + Duration::from_secs(5)));
+
+        // One probe after the cooldown; others wait for its verdict
+        let probe_at = start + Duration::from_secs(10);
+        assert!(breaker.allow(probe_at));
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+        assert!(!breaker.allow(probe_at));
+
+        // A failed probe re-opens the circuit
+        breaker.record_failure(probe_at);
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert_eq!(breaker.times_opened, 2);
+
+        let probe_at = probe_at + Duration::from_secs(10);
+        assert!(breaker.allow(probe_at));
+        breaker.record_success();
+        assert_eq!(breaker.state, CircuitState::Closed);
+        assert!(breaker.allow(probe_at));
+    }
+
+    #[tokio::test]
+    async fn test_shared_limiter_bounds_concurrency() {
+        let config = LlmConfig::mock("limiter_concurrency");
+        let limits = ProviderLimits {
+            max_concurrent: Some(2),
+            ..Default::default()
+        };
+        let limiter = ProviderLimiter::shared(&config, &limits);
+        assert!(Arc::ptr_eq(
+            &limiter,
+            &ProviderLimiter::shared(&config, &limits)
+        ));
+
+        let first = limiter.acquire(0).await;
+        let _second = limiter.acquire(0).await;
+        let third = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(0)).await;
+        assert!(third.is_err(), "third request waits for a slot");
+
+        drop(first);
+        let third = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(0)).await;
+        assert!(third.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_limiter_is_shared_per_endpoint_and_waits_without_a_slot() {
+        let config = LlmConfig::mock("limiter_endpoint");
+        let limits = ProviderLimits {
+            max_concurrent: Some(1),
+            requests_per_minute: Some(1),
+            ..Default::default()
+        };
+        let limiter = ProviderLimiter::shared(&config, &limits);
+        assert!(Arc::ptr_eq(
+            &limiter,
+            &ProviderLimiter::shared(&config, &limits)
+        ));
+        // Changed limits for the same endpoint take effect with their own buckets
+        let edited = ProviderLimits {
+            max_concurrent: Some(3),
+            ..limits.clone()
+        };
+        let other = ProviderLimiter::shared(&config, &edited);
+        assert!(!Arc::ptr_eq(&limiter, &other));
+        assert_eq!(other.limits(), &edited);
+        assert_eq!(other.semaphore.as_ref().unwrap().available_permits(), 3);
+
+        drop(limiter.acquire(0).await);
+        // The next request sleeps on the request bucket without taking the only slot
+        let waiting = tokio::time::timeout(Duration::from_millis(20), limiter.acquire(0)).await;
+        assert!(waiting.is_err());
+        let semaphore = limiter.semaphore.as_ref().unwrap();
+        assert_eq!(semaphore.available_permits(), 1);
+    }
+}
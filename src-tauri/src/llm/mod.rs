@@ -1,10 +1,22 @@
 // Cerebras-MAKER: Unified LLM API Layer
 // Provides a consistent interface for all LLM operations using rig-core
 
+pub mod error;
+pub mod limiter;
+pub mod pricing;
 pub mod provider;
 pub mod prompts;
+pub mod replay;
+pub mod resilient;
+pub mod router;
 
-pub use provider::{LlmProvider, LlmConfig, LlmResponse, Message, Role, ProviderType};
+pub use error::LlmError;
+pub use limiter::{BreakerStatus, CircuitBreakerConfig, CircuitState, ProviderLimits};
+pub use pricing::{pricing, ModelPrice, PricingTable};
+pub use provider::{list_local_models, LlmProvider, LlmConfig, LlmResponse, LlmStream, Message, ResponseFormat, Role, ProviderType, StreamChunk};
+pub use replay::{register_mock, MockResponder, ReplayConfig, ReplayMode};
+pub use resilient::{ResilienceStats, ResilientLlmProvider, ResilientLlmProviderBuilder, RetryConfig};
+pub use router::{AgentRole, LlmRouter};
 pub use prompts::{PromptTemplate, PromptContext, SystemPrompts};
 
 use std::sync::Arc;
@@ -32,7 +44,13 @@ pub async fn get_provider() -> Option<LlmProvider> {
 pub async fn complete(messages: Vec<Message>) -> Result<LlmResponse, anyhow::Error> {
     let guard = LLM_PROVIDER.read().await;
     let provider = guard.as_ref().ok_or_else(|| anyhow::anyhow!("LLM provider not initialized"))?;
-    provider.complete(messages).await
+    Ok(provider.complete(messages).await?)
+}
+
+/// Stream a completion using the global provider
+pub async fn complete_stream(messages: Vec<Message>) -> Result<LlmStream, anyhow::Error> {
+    let provider = get_provider().await.ok_or_else(|| anyhow::anyhow!("LLM provider not initialized"))?;
+    Ok(provider.complete_stream(messages).await?)
 }
 
 /// Complete with a system prompt and user message
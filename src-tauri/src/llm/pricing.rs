@@ -0,0 +1,173 @@
+// Cerebras-MAKER: Model Pricing
+// Maps model names to $/1M input and output tokens so atom, consensus and run costs can be
+// reported and budgeted. Built-in prices can be overridden or extended in
+// <config_dir>/cerebras-maker/pricing.json:
+//
+//   { "models": { "gpt-4o": { "input_per_million": 2.5, "output_per_million": 10.0 } } }
+//
+// Models with no entry (e.g. local Ollama / llama.cpp models) cost nothing.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Price of a model in US dollars per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+}
+
+/// Model name → price table
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PricingTable {
+    /// Keys match a model exactly, or as a prefix (longest prefix wins)
+    pub models: HashMap<String, ModelPrice>,
+}
+
+impl PricingTable {
+    /// Default prices for the providers' preset models
+    pub fn builtin() -> Self {
+        let models = [
+            ("gpt-4o", ModelPrice::new(2.5, 10.0)),
+            ("gpt-4o-mini", ModelPrice::new(0.15, 0.6)),
+            ("claude-sonnet-4", ModelPrice::new(3.0, 15.0)),
+            ("claude-opus-4", ModelPrice::new(15.0, 75.0)),
+            ("claude-3-5-haiku", ModelPrice::new(0.8, 4.0)),
+            (
+                "llama-4-scout-17b-16e-instruct",
+                ModelPrice::new(0.65, 0.85),
+            ),
+            ("llama3.1-8b", ModelPrice::new(0.1, 0.1)),
+            ("llama-3.3-70b", ModelPrice::new(0.85, 1.2)),
+        ]
+        .into_iter()
+        .map(|(model, price)| (model.to_string(), price))
+        .collect();
+        Self { models }
+    }
+
+    /// Location of the user's pricing overrides
+    pub fn config_path() -> PathBuf {
+        let config_dir = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+        config_dir.join("cerebras-maker").join("pricing.json")
+    }
+
+    /// Built-in prices merged with the user's pricing file, if there is one
+    pub fn load() -> Self {
+        let mut table = Self::builtin();
+        let path = Self::config_path();
+        if path.exists() {
+            match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| Self::from_json(&json))
+            {
+                Ok(overrides) => table.models.extend(overrides.models),
+                Err(e) => eprintln!("Warning: Ignoring pricing file {:?}: {}", path, e),
+            }
+        }
+        table
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Failed to parse pricing table: {}", e))
+    }
+
+    /// Price for a model; OpenRouter-style `vendor/model` names also match on `model`
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        self.lookup(model).or_else(|| {
+            model
+                .split_once('/')
+                .and_then(|(_, name)| self.lookup(name))
+        })
+    }
+
+    /// Cost in US dollars of a call
+    /// When the provider only reported a total, all tokens are billed at the output rate.
+    pub fn cost(
+        &self,
+        model: &str,
+        prompt_tokens: usize,
+        completion_tokens: usize,
+        total_tokens: usize,
+    ) -> f64 {
+        let Some(price) = self.price(model) else {
+            return 0.0;
+        };
+        let (input, output) = if prompt_tokens + completion_tokens == 0 {
+            (0, total_tokens)
+        } else {
+            (prompt_tokens, completion_tokens)
+        };
+        (input as f64 * price.input_per_million + output as f64 * price.output_per_million)
+            / 1_000_000.0
+    }
+
+    fn lookup(&self, model: &str) -> Option<ModelPrice> {
+        if let Some(price) = self.models.get(model) {
+            return Some(*price);
+        }
+        self.models
+            .iter()
+            .filter(|(key, _)| model.starts_with(key.as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, price)| *price)
+    }
+}
+
+/// Pricing table loaded once per process
+static PRICING: Lazy<PricingTable> = Lazy::new(PricingTable::load);
+
+/// The process-wide pricing table
+pub fn pricing() -> &'static PricingTable {
+    &PRICING
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_lookup_prefers_longest_prefix() {
+        let table = PricingTable::builtin();
+        assert_eq!(
+            table.price("gpt-4o-mini-2024-07-18"),
+            Some(ModelPrice::new(0.15, 0.6))
+        );
+        assert_eq!(
+            table.price("gpt-4o-2024-08-06"),
+            Some(ModelPrice::new(2.5, 10.0))
+        );
+        assert_eq!(
+            table.price("claude-sonnet-4-20250514"),
+            Some(ModelPrice::new(3.0, 15.0))
+        );
+        assert_eq!(
+            table.price("anthropic/claude-sonnet-4"),
+            Some(ModelPrice::new(3.0, 15.0))
+        );
+        assert_eq!(table.price("qwen2.5-coder"), None);
+    }
+
+    #[test]
+    fn test_cost() {
+        let table = PricingTable::from_json(
+            r#"{ "models": { "m": { "input_per_million": 1.0, "output_per_million": 4.0 } } }"#,
+        )
+        .unwrap();
+        assert!((table.cost("m", 1_000_000, 500_000, 1_500_000) - 3.0).abs() < 1e-9);
+        // Total only: billed at the output rate
+        assert!((table.cost("m", 0, 0, 250_000) - 1.0).abs() < 1e-9);
+        assert_eq!(table.cost("unknown", 1_000, 1_000, 2_000), 0.0);
+    }
+}
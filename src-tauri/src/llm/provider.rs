@@ -3,9 +3,15 @@
 // - Cerebras: via cerebras-rs (native high-speed client)
 // - Anthropic: via rig-core
 // - OpenAI/OpenRouter/Compatible: via rig-core with custom base_url
+// - Ollama/llama.cpp: local servers via their OpenAI-compatible endpoints (no API key)
+// - Replay: recorded or mocked responses for offline tests (see replay.rs)
 
+use super::error::LlmError;
+use super::replay::{self, ReplayConfig, ReplayMode};
+use futures::stream::{BoxStream, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::path::PathBuf;
 
 /// Supported LLM providers
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
@@ -18,6 +24,33 @@ pub enum ProviderType {
     OpenRouter,
     /// Any OpenAI-compatible API (requires base_url)
     OpenAICompatible,
+    /// Local Ollama server (no API key)
+    Ollama,
+    /// Local llama.cpp server (no API key)
+    LlamaCpp,
+    /// Recorded or mocked responses for offline tests (configured by `LlmConfig::replay`)
+    Replay,
+}
+
+impl ProviderType {
+    /// Whether calls to this provider need an API key
+    pub fn requires_api_key(&self) -> bool {
+        !self.is_local() && *self != Self::Replay
+    }
+
+    /// Whether this provider runs on a local server
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::Ollama | Self::LlamaCpp)
+    }
+
+    /// Server URL used when the config has no base_url
+    pub fn default_base_url(&self) -> Option<&'static str> {
+        match self {
+            Self::Ollama => Some("http://localhost:11434"),
+            Self::LlamaCpp => Some("http://localhost:8080"),
+            _ => None,
+        }
+    }
 }
 
 /// LLM configuration
@@ -32,6 +65,9 @@ pub struct LlmConfig {
     /// HIGH-4: Timeout in seconds for LLM calls (default: 120)
     #[serde(default = "default_timeout")]
     pub timeout_secs: u64,
+    /// Record/replay/mock settings, used when `provider` is `Replay`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replay: Option<ReplayConfig>,
 }
 
 fn default_timeout() -> u64 { 120 }
@@ -46,6 +82,7 @@ impl Default for LlmConfig {
             temperature: 0.7,
             max_tokens: 4096,
             timeout_secs: 120,
+            replay: None,
         }
     }
 }
@@ -61,6 +98,7 @@ impl LlmConfig {
             temperature: 0.7,
             max_tokens: 8192,
             timeout_secs: 120,
+            replay: None,
         }
     }
 
@@ -74,6 +112,7 @@ impl LlmConfig {
             temperature: 0.7,
             max_tokens: 4096,
             timeout_secs: 120,
+            replay: None,
         }
     }
 
@@ -87,6 +126,7 @@ impl LlmConfig {
             temperature: 0.7,
             max_tokens: 4096,
             timeout_secs: 120,
+            replay: None,
         }
     }
 
@@ -100,6 +140,101 @@ impl LlmConfig {
             temperature: 0.7,
             max_tokens: 4096,
             timeout_secs: 120,
+            replay: None,
+        }
+    }
+
+    /// Local Ollama configuration (OLLAMA_HOST overrides the default server URL)
+    pub fn ollama(model: &str) -> Self {
+        Self {
+            provider: ProviderType::Ollama,
+            model: model.to_string(),
+            api_key: None,
+            base_url: env::var("OLLAMA_HOST").ok(),
+            temperature: 0.7,
+            max_tokens: 4096,
+            // Local models are slower than hosted ones, especially on first load
+            timeout_secs: 300,
+            replay: None,
+        }
+    }
+
+    /// Local llama.cpp server configuration (`llama-server` serves a single model)
+    pub fn llama_cpp(base_url: Option<&str>, model: &str) -> Self {
+        Self {
+            provider: ProviderType::LlamaCpp,
+            model: model.to_string(),
+            api_key: None,
+            base_url: base_url.map(str::to_string),
+            temperature: 0.7,
+            max_tokens: 4096,
+            timeout_secs: 300,
+            replay: None,
+        }
+    }
+
+    /// Server URL for this config, falling back to the provider default
+    /// A bare host such as OLLAMA_HOST=127.0.0.1:11434 is given an http:// scheme.
+    pub fn server_url(&self) -> Option<String> {
+        self.base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .or_else(|| self.provider.default_base_url())
+            .map(|url| {
+                let url = url.trim_end_matches('/');
+                if url.contains("://") {
+                    url.to_string()
+                } else {
+                    format!("http://{}", url)
+                }
+            })
+    }
+
+    /// Record this provider's responses to a fixture file while still calling it
+    /// Model settings are kept so the same config `.replaying()` the fixture matches its keys.
+    pub fn recording(self, fixture_path: impl Into<PathBuf>) -> Self {
+        let upstream = Box::new(self.clone());
+        self.wrap_replay(ReplayConfig {
+            mode: ReplayMode::Record,
+            fixture_path: Some(fixture_path.into()),
+            upstream: Some(upstream),
+            mock: None,
+        })
+    }
+
+    /// Serve responses recorded by `.recording()` instead of calling this provider
+    pub fn replaying(self, fixture_path: impl Into<PathBuf>) -> Self {
+        self.wrap_replay(ReplayConfig {
+            mode: ReplayMode::Replay,
+            fixture_path: Some(fixture_path.into()),
+            upstream: None,
+            mock: None,
+        })
+    }
+
+    /// Answer every request from the responder registered with `replay::register_mock(name, ..)`
+    pub fn mock(name: &str) -> Self {
+        Self {
+            model: "mock".to_string(),
+            api_key: None,
+            ..Self::default()
+        }
+        .wrap_replay(ReplayConfig {
+            mode: ReplayMode::Mock,
+            fixture_path: None,
+            upstream: None,
+            mock: Some(name.to_string()),
+        })
+    }
+
+    fn wrap_replay(self, replay: ReplayConfig) -> Self {
+        Self {
+            provider: ProviderType::Replay,
+            api_key: None,
+            base_url: None,
+            replay: Some(replay),
+            ..self
         }
     }
 
@@ -144,79 +279,207 @@ impl Message {
 pub struct LlmResponse {
     pub content: String,
     pub model: String,
+    /// Total tokens (prompt + completion) reported by the provider
     pub tokens_used: Option<u32>,
+    /// Input tokens reported by the provider
+    #[serde(default)]
+    pub prompt_tokens: Option<u32>,
+    /// Output tokens reported by the provider
+    #[serde(default)]
+    pub completion_tokens: Option<u32>,
+    /// Provider stop reason (e.g. "stop", "length", "end_turn", "max_tokens")
     pub finish_reason: Option<String>,
 }
 
+impl LlmResponse {
+    /// Build a response, reading token usage and the finish reason from the provider's raw JSON
+    /// Understands OpenAI-style (`usage.prompt_tokens`, `choices[0].finish_reason`),
+    /// Anthropic-style (`usage.input_tokens`, `stop_reason`) and Responses-style (`status`) payloads.
+    pub fn from_raw(content: String, model: &str, raw: &serde_json::Value) -> Self {
+        let usage = &raw["usage"];
+        let count = |keys: &[&str]| {
+            keys.iter()
+                .find_map(|key| usage[*key].as_u64())
+                .map(|n| n.min(u32::MAX as u64) as u32)
+        };
+        let prompt_tokens = count(&["prompt_tokens", "input_tokens"]);
+        let completion_tokens = count(&["completion_tokens", "output_tokens"]);
+        let tokens_used = count(&["total_tokens"]).or(match (prompt_tokens, completion_tokens) {
+            (None, None) => None,
+            (p, c) => Some(p.unwrap_or(0).saturating_add(c.unwrap_or(0))),
+        });
+
+        let finish_reason = [
+            &raw["choices"][0]["finish_reason"],
+            &raw["stop_reason"],
+            &raw["incomplete_details"]["reason"],
+            &raw["status"],
+        ]
+        .iter()
+        .find_map(|value| value.as_str())
+        .map(str::to_string);
+
+        Self {
+            content,
+            model: raw["model"].as_str().unwrap_or(model).to_string(),
+            tokens_used,
+            prompt_tokens,
+            completion_tokens,
+            finish_reason,
+        }
+    }
+}
+
+/// Native structured-output mode requested from the provider
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ResponseFormat {
+    /// Any JSON object
+    Json,
+    /// JSON matching a schema
+    JsonSchema(serde_json::Value),
+}
+
+/// An incremental completion event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StreamChunk {
+    /// Newly generated text
+    Delta(String),
+    /// The stream finished; carries the full content, usage and finish reason
+    Done(LlmResponse),
+}
+
+/// A stream of completion events, as returned by `complete_stream`
+pub type LlmStream = BoxStream<'static, Result<StreamChunk, LlmError>>;
+
+type ChunkSender = futures::channel::mpsc::UnboundedSender<Result<StreamChunk, LlmError>>;
+
 /// Unified LLM provider
 #[derive(Debug, Clone)]
 pub struct LlmProvider {
     config: LlmConfig,
+    response_format: Option<ResponseFormat>,
 }
 
 impl LlmProvider {
-    pub fn new(config: LlmConfig) -> Result<Self, anyhow::Error> {
-        if config.api_key.is_none() {
-            anyhow::bail!("API key not configured for {:?}", config.provider);
+    pub fn new(config: LlmConfig) -> Result<Self, LlmError> {
+        if config.provider.requires_api_key() && config.api_key.is_none() {
+            return Err(LlmError::Auth(format!("API key not configured for {:?}", config.provider)));
         }
-        Ok(Self { config })
+        Ok(Self {
+            config,
+            response_format: None,
+        })
+    }
+
+    /// Ask for JSON output using the provider's native JSON / structured-output mode
+    /// Providers without one (Cerebras, Anthropic) rely on the prompt alone.
+    pub fn with_response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
     }
 
     /// Complete a chat conversation
+    /// System messages are merged into the system prompt; user and assistant turns are
+    /// forwarded in order, so multi-turn conversations reach the provider intact.
     /// HIGH-4: Now includes configurable timeout
-    pub async fn complete(&self, messages: Vec<Message>) -> Result<LlmResponse, anyhow::Error> {
-        // Build the prompt from messages
-        let system_prompt = messages.iter()
-            .find(|m| matches!(m.role, Role::System))
-            .map(|m| m.content.as_str())
-            .unwrap_or("");
-
-        let user_messages: Vec<&str> = messages.iter()
-            .filter(|m| matches!(m.role, Role::User))
-            .map(|m| m.content.as_str())
-            .collect();
+    pub async fn complete(&self, messages: Vec<Message>) -> Result<LlmResponse, LlmError> {
+        let (system_prompt, conversation) = split_messages(&messages)?;
+        self.run_with_timeout(&system_prompt, &conversation, None).await
+    }
 
-        let user_prompt = user_messages.join("\n\n");
+    /// Stream a chat conversation
+    /// Yields `StreamChunk::Delta` for each piece of generated text, then a final
+    /// `StreamChunk::Done` with the full response. Dropping the stream cancels the request.
+    pub async fn complete_stream(&self, messages: Vec<Message>) -> Result<LlmStream, LlmError> {
+        let (system_prompt, conversation) = split_messages(&messages)?;
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+
+        let provider = self.clone();
+        tokio::spawn(async move {
+            let result = provider
+                .run_with_timeout(&system_prompt, &conversation, Some(&tx))
+                .await
+                .map(StreamChunk::Done);
+            // The receiver may already be gone if the consumer aborted
+            let _ = tx.unbounded_send(result);
+        });
+
+        Ok(rx.boxed())
+    }
 
-        // HIGH-4: Wrap LLM call with timeout to prevent indefinite hanging
+    /// HIGH-4: Wrap LLM call with timeout to prevent indefinite hanging
+    /// This is where provider errors are classified into `LlmError`.
+    async fn run_with_timeout(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, LlmError> {
+        let start = std::time::Instant::now();
         let timeout_duration = std::time::Duration::from_secs(self.config.timeout_secs);
-        let llm_future = self.call_llm(system_prompt, &user_prompt);
+        let llm_future = self.call_llm(system, conversation, sink);
 
-        let content = tokio::time::timeout(timeout_duration, llm_future)
+        let response = tokio::time::timeout(timeout_duration, llm_future)
             .await
-            .map_err(|_| anyhow::anyhow!(
+            .map_err(|_| LlmError::Timeout(format!(
                 "LLM call timed out after {} seconds",
                 self.config.timeout_secs
-            ))??;
+            )))?
+            .map_err(LlmError::classify)?;
 
-        Ok(LlmResponse {
-            content,
-            model: self.config.model.clone(),
-            tokens_used: None,
-            finish_reason: Some("stop".to_string()),
-        })
+        if let Some(tokens) = response.tokens_used {
+            crate::record_token_usage(tokens as u64, start.elapsed());
+        }
+
+        Ok(response)
     }
 
-    async fn call_llm(&self, system: &str, user: &str) -> Result<String, anyhow::Error> {
+    /// Dispatch to the configured provider; streams deltas into `sink` when one is given
+    async fn call_llm(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
         match self.config.provider {
-            ProviderType::Cerebras => self.call_cerebras(system, user).await,
-            ProviderType::Anthropic => self.call_anthropic(system, user).await,
-            ProviderType::OpenAI => self.call_openai(system, user, None).await,
-            ProviderType::OpenRouter => self.call_openrouter(system, user).await,
+            ProviderType::Cerebras => self.call_cerebras(system, conversation, sink).await,
+            ProviderType::Anthropic => self.call_anthropic(system, conversation, sink).await,
+            ProviderType::OpenAI => self.call_openai(system, conversation, None, sink).await,
+            ProviderType::OpenRouter => self.call_openrouter(system, conversation, sink).await,
             ProviderType::OpenAICompatible => {
                 let base_url = self.config.base_url.as_deref()
                     .ok_or_else(|| anyhow::anyhow!("base_url required for OpenAICompatible"))?;
-                self.call_openai(system, user, Some(base_url)).await
+                self.call_openai(system, conversation, Some(base_url), sink).await
             }
+            ProviderType::Ollama | ProviderType::LlamaCpp => self.call_local(system, conversation, sink).await,
+            ProviderType::Replay => self.call_replay(system, conversation, sink).await,
         }
     }
 
+    /// Answer from a fixture or mock, or record the upstream provider's answer
+    async fn call_replay(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
+        let config = self.config.replay.as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Replay provider has no replay config"))?;
+        let fixture_path = || config.fixture_path.as_deref()
+            .ok_or_else(|| anyhow::anyhow!("{:?} mode requires a fixture_path", config.mode));
+
+        let response = match config.mode {
+            ReplayMode::Mock => replay::mock_response(&self.config, config, system, conversation)?,
+            ReplayMode::Replay => replay::replay_response(&self.config, fixture_path()?, system, conversation)?,
+            ReplayMode::Record => {
+                let upstream = config.upstream.as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("Record mode requires an upstream provider"))?;
+                let mut upstream = LlmProvider::new(upstream.clone())?;
+                upstream.response_format = self.response_format.clone();
+                // The upstream streams straight into the sink, so nothing is re-sent below
+                let response = Box::pin(upstream.call_llm(system, conversation, sink)).await?;
+                replay::record_response(&self.config, fixture_path()?, system, conversation, &response)?;
+                return Ok(response);
+            }
+        };
+
+        if let Some(tx) = sink {
+            send_delta(tx, &response.content)?;
+        }
+        Ok(response)
+    }
+
     /// Call Cerebras using native cerebras-rs client (high-speed)
-    async fn call_cerebras(&self, system: &str, user: &str) -> Result<String, anyhow::Error> {
+    async fn call_cerebras(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
         use cerebras_rs::{Client, ChatCompletionRequest, ModelIdentifier};
 
         let api_key = self.config.api_key.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Cerebras API key not configured"))?;
+            .ok_or_else(|| LlmError::Auth("Cerebras API key not configured".to_string()))?;
 
         let client = Client::new(api_key);
 
@@ -230,74 +493,94 @@ impl LlmProvider {
             _ => ModelIdentifier::Llama4Scout17b16eInstruct, // Default
         };
 
-        let request = ChatCompletionRequest::builder(model)
-            .system_message(system)
-            .user_message(user)
+        let mut builder = ChatCompletionRequest::builder(model);
+        if !system.is_empty() {
+            builder = builder.system_message(system);
+        }
+        for message in conversation {
+            builder = match message.role {
+                Role::Assistant => builder.assistant_message(&message.content),
+                _ => builder.user_message(&message.content),
+            };
+        }
+        let request = builder
             .temperature(self.config.temperature as f64)
             .max_tokens(self.config.max_tokens)
             .build();
 
-        let response = client.chat_completion(request).await?;
+        let Some(tx) = sink else {
+            let response = client.chat_completion(request).await?;
+            let raw = serde_json::to_value(&response).unwrap_or_default();
+
+            // Extract content from response
+            let content = response.choices
+                .and_then(|choices| choices.into_iter().next())
+                .and_then(|choice| choice.message)
+                .map(|msg| msg.content)
+                .unwrap_or_default();
 
-        // Extract content from response
-        let content = response.choices
-            .and_then(|choices| choices.into_iter().next())
-            .and_then(|choice| choice.message)
-            .map(|msg| msg.content)
-            .unwrap_or_default();
+            return Ok(LlmResponse::from_raw(content, &self.config.model, &raw));
+        };
 
-        Ok(content)
+        // Streamed chunks follow the OpenAI shape: choices[0].delta.content, usage on the last chunk
+        let mut stream = Box::pin(client.chat_completion_stream(request).await?);
+        let mut content = String::new();
+        let mut finish_reason = serde_json::Value::Null;
+        let mut usage = serde_json::Value::Null;
+        while let Some(chunk) = stream.next().await {
+            let chunk = serde_json::to_value(&chunk?).unwrap_or_default();
+            if let Some(text) = chunk["choices"][0]["delta"]["content"].as_str() {
+                send_delta(tx, text)?;
+                content.push_str(text);
+            }
+            if chunk["choices"][0]["finish_reason"].is_string() {
+                finish_reason = chunk["choices"][0]["finish_reason"].clone();
+            }
+            if chunk["usage"].is_object() {
+                usage = chunk["usage"].clone();
+            }
+        }
+
+        let raw = serde_json::json!({ "usage": usage, "choices": [{ "finish_reason": finish_reason }] });
+        Ok(LlmResponse::from_raw(content, &self.config.model, &raw))
     }
 
     /// Call Anthropic using rig-core
-    async fn call_anthropic(&self, system: &str, user: &str) -> Result<String, anyhow::Error> {
+    async fn call_anthropic(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
         use rig::client::{CompletionClient, ProviderClient};
-        use rig::completion::Prompt;
         use rig::providers::anthropic;
 
         let api_key = self.config.api_key.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("Anthropic API key not configured"))?;
+            .ok_or_else(|| LlmError::Auth("Anthropic API key not configured".to_string()))?;
 
         // Set API key in environment for rig-core
         std::env::set_var("ANTHROPIC_API_KEY", api_key);
 
         let client = anthropic::Client::from_env();
-        let agent = client
-            .agent(&self.config.model)
-            .preamble(system)
-            .build();
-
-        let response = agent.prompt(user).await?;
-        Ok(response)
+        let model = client.completion_model(&self.config.model);
+        self.complete_with_rig(model, system, conversation, sink).await
     }
 
     /// Call OpenRouter using rig-core's native openrouter provider
-    async fn call_openrouter(&self, system: &str, user: &str) -> Result<String, anyhow::Error> {
+    async fn call_openrouter(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
         use rig::client::CompletionClient;
-        use rig::completion::Prompt;
         use rig::providers::openrouter;
 
         let api_key = self.config.api_key.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("OpenRouter API key not configured"))?;
+            .ok_or_else(|| LlmError::Auth("OpenRouter API key not configured".to_string()))?;
 
         let client: openrouter::Client = openrouter::Client::new(api_key)?;
-        let agent = client
-            .agent(&self.config.model)
-            .preamble(system)
-            .build();
-
-        let response = agent.prompt(user).await?;
-        Ok(response)
+        let model = client.completion_model(&self.config.model);
+        self.complete_with_rig(model, system, conversation, sink).await
     }
 
     /// Call OpenAI or OpenAI-compatible API using rig-core
-    async fn call_openai(&self, system: &str, user: &str, base_url: Option<&str>) -> Result<String, anyhow::Error> {
+    async fn call_openai(&self, system: &str, conversation: &[Message], base_url: Option<&str>, sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
         use rig::client::CompletionClient;
-        use rig::completion::Prompt;
         use rig::providers::openai;
 
         let api_key = self.config.api_key.as_ref()
-            .ok_or_else(|| anyhow::anyhow!("OpenAI API key not configured"))?;
+            .ok_or_else(|| LlmError::Auth("OpenAI API key not configured".to_string()))?;
 
         // Create client with optional custom base URL using builder pattern
         let client: openai::Client = match base_url {
@@ -308,13 +591,351 @@ impl LlmProvider {
             None => openai::Client::new(api_key)?,
         };
 
-        let agent = client
-            .agent(&self.config.model)
-            .preamble(system)
-            .build();
+        let model = client.completion_model(&self.config.model);
+        self.complete_with_rig(model, system, conversation, sink).await
+    }
 
-        let response = agent.prompt(user).await?;
-        Ok(response)
+    /// Call a local Ollama or llama.cpp server through its OpenAI-compatible endpoint
+    /// Both servers speak the Chat Completions API (not the Responses API) and ignore the API key.
+    async fn call_local(&self, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error> {
+        use rig::client::CompletionClient;
+        use rig::providers::openai;
+
+        let server_url = self.config.server_url()
+            .ok_or_else(|| anyhow::anyhow!("No server URL for {:?}", self.config.provider))?;
+        let api_key = self.config.api_key.as_deref().unwrap_or("local");
+
+        let client: openai::Client = openai::Client::builder()
+            .api_key(api_key)
+            .base_url(format!("{}/v1", server_url))
+            .build()?;
+
+        let model = client.completion_model(&self.config.model).completions_api();
+        self.complete_with_rig(model, system, conversation, sink).await
+    }
+
+    /// Request parameters selecting the native JSON mode, or None if it does not apply
+    /// OpenAI goes through the Responses API (`text.format`); OpenRouter and local servers take
+    /// Chat Completions' `response_format`. Structured outputs need an object at the root, so
+    /// schemas for arrays (e.g. Planner steps) are left to the prompt.
+    fn response_format_params(&self) -> Option<serde_json::Value> {
+        let format = match self.response_format.as_ref()? {
+            ResponseFormat::JsonSchema(schema) if schema["type"] == "object" => serde_json::json!({
+                "type": "json_schema",
+                "name": "atom_output",
+                "schema": schema,
+                "strict": false,
+            }),
+            ResponseFormat::JsonSchema(_) => return None,
+            ResponseFormat::Json => serde_json::json!({ "type": "json_object" }),
+        };
+
+        match self.config.provider {
+            ProviderType::OpenAI | ProviderType::OpenAICompatible => {
+                Some(serde_json::json!({ "text": { "format": format } }))
+            }
+            ProviderType::OpenRouter | ProviderType::Ollama | ProviderType::LlamaCpp => {
+                // Chat Completions nests the schema fields under `json_schema`
+                let response_format = match format["type"].as_str() {
+                    Some("json_schema") => serde_json::json!({
+                        "type": "json_schema",
+                        "json_schema": {
+                            "name": format["name"],
+                            "schema": format["schema"],
+                            "strict": false,
+                        },
+                    }),
+                    _ => format,
+                };
+                Some(serde_json::json!({ "response_format": response_format }))
+            }
+            ProviderType::Cerebras | ProviderType::Anthropic | ProviderType::Replay => None,
+        }
+    }
+
+    /// Send a full conversation through a rig-core completion model
+    /// The last turn is the prompt; earlier turns are passed as chat history.
+    async fn complete_with_rig<M>(&self, model: M, system: &str, conversation: &[Message], sink: Option<&ChunkSender>) -> Result<LlmResponse, anyhow::Error>
+    where
+        M: rig::completion::CompletionModel,
+        M::Response: Serialize,
+        M::StreamingResponse: Serialize,
+    {
+        use rig::completion::{AssistantContent, Message as RigMessage};
+        use rig::streaming::StreamedAssistantContent;
+
+        let to_rig = |message: &Message| match message.role {
+            Role::Assistant => RigMessage::assistant(message.content.clone()),
+            _ => RigMessage::user(message.content.clone()),
+        };
+
+        let (prompt, history) = conversation.split_last()
+            .ok_or_else(|| anyhow::anyhow!("Conversation has no user or assistant messages"))?;
+
+        let mut request = model
+            .completion_request(to_rig(prompt))
+            .messages(history.iter().map(to_rig).collect())
+            .temperature(self.config.temperature as f64)
+            .max_tokens(self.config.max_tokens as u64);
+        if !system.is_empty() {
+            request = request.preamble(system.to_string());
+        }
+        if let Some(params) = self.response_format_params() {
+            request = request.additional_params(params);
+        }
+
+        if let Some(tx) = sink {
+            let mut stream = request.stream().await?;
+            let mut content = String::new();
+            let mut raw = serde_json::Value::Null;
+            while let Some(item) = stream.next().await {
+                match item? {
+                    StreamedAssistantContent::Text(text) => {
+                        send_delta(tx, &text.text)?;
+                        content.push_str(&text.text);
+                    }
+                    StreamedAssistantContent::Final(response) => {
+                        raw = serde_json::to_value(&response).unwrap_or_default();
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(LlmResponse::from_raw(content, &self.config.model, &raw));
+        }
+
+        let response = request.send().await?;
+
+        let content: String = response.choice.iter()
+            .filter_map(|item| match item {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("");
+
+        let raw = serde_json::to_value(&response.raw_response).unwrap_or_default();
+        let mut result = LlmResponse::from_raw(content, &self.config.model, &raw);
+
+        // Prefer rig's normalized usage when the provider reported any
+        if response.usage.total_tokens > 0 {
+            result.prompt_tokens = Some(response.usage.input_tokens as u32);
+            result.completion_tokens = Some(response.usage.output_tokens as u32);
+            result.tokens_used = Some(response.usage.total_tokens as u32);
+        }
+
+        Ok(result)
+    }
+}
+
+/// Forward a text delta to a stream consumer
+/// Fails once the consumer has dropped the stream, which aborts the upstream request.
+fn send_delta(tx: &ChunkSender, text: &str) -> Result<(), anyhow::Error> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    tx.unbounded_send(Ok(StreamChunk::Delta(text.to_string())))
+        .map_err(|_| LlmError::Cancelled.into())
+}
+
+/// List the models available on a local provider's server
+/// Ollama reports pulled models via `/api/tags`; llama.cpp reports its loaded model via `/v1/models`.
+pub async fn list_local_models(config: &LlmConfig) -> Result<Vec<String>, anyhow::Error> {
+    let server_url = config.server_url()
+        .ok_or_else(|| anyhow::anyhow!("No server URL for {:?}", config.provider))?;
+
+    let (url, list_key, name_key) = match config.provider {
+        ProviderType::Ollama => (format!("{}/api/tags", server_url), "models", "name"),
+        ProviderType::LlamaCpp => (format!("{}/v1/models", server_url), "data", "id"),
+        _ => anyhow::bail!("Model listing is only supported for local providers, not {:?}", config.provider),
+    };
+
+    let response = reqwest::Client::new()
+        .get(&url)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("Could not reach {:?} server at {}: {}", config.provider, server_url, e))?;
+    if !response.status().is_success() {
+        return Err(LlmError::from_response(response).await.into());
     }
+    let body: serde_json::Value = response.json().await?;
+
+    Ok(parse_model_list(&body, list_key, name_key))
+}
+
+/// Extract model names from a model-listing payload
+fn parse_model_list(body: &serde_json::Value, list_key: &str, name_key: &str) -> Vec<String> {
+    let mut models: Vec<String> = body[list_key]
+        .as_array()
+        .map(|entries| {
+            entries.iter()
+                .filter_map(|entry| entry[name_key].as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    models.sort();
+    models
+}
+
+/// Split messages into a system prompt and the ordered user/assistant turns
+/// Multiple system messages are joined with blank lines.
+pub fn split_messages(messages: &[Message]) -> Result<(String, Vec<Message>), LlmError> {
+    let system_prompt = messages.iter()
+        .filter(|m| matches!(m.role, Role::System))
+        .map(|m| m.content.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let conversation: Vec<Message> = messages.iter()
+        .filter(|m| !matches!(m.role, Role::System))
+        .cloned()
+        .collect();
+
+    if conversation.is_empty() {
+        return Err(LlmError::InvalidRequest {
+            status: None,
+            message: "At least one user message is required".to_string(),
+        });
+    }
+
+    Ok((system_prompt, conversation))
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_messages_keeps_turn_order() {
+        let messages = vec![
+            Message::system("You are terse."),
+            Message::user("Hi"),
+            Message::assistant("Hello."),
+            Message::system("Answer in English."),
+            Message::user("What is 2 + 2?"),
+        ];
+
+        let (system, conversation) = split_messages(&messages).unwrap();
+        assert_eq!(system, "You are terse.\n\nAnswer in English.");
+        let turns: Vec<&str> = conversation.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(turns, vec!["Hi", "Hello.", "What is 2 + 2?"]);
+        assert!(matches!(conversation[1].role, Role::Assistant));
+
+        assert!(split_messages(&[Message::system("only system")]).is_err());
+    }
+
+    #[test]
+    fn test_response_usage_from_openai_payload() {
+        let raw = serde_json::json!({
+            "model": "gpt-4o-2024-08-06",
+            "choices": [{ "message": { "content": "4" }, "finish_reason": "length" }],
+            "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+        });
+
+        let response = LlmResponse::from_raw("4".to_string(), "gpt-4o", &raw);
+        assert_eq!(response.model, "gpt-4o-2024-08-06");
+        assert_eq!(response.prompt_tokens, Some(12));
+        assert_eq!(response.completion_tokens, Some(3));
+        assert_eq!(response.tokens_used, Some(15));
+        assert_eq!(response.finish_reason.as_deref(), Some("length"));
+    }
+
+    #[test]
+    fn test_response_usage_from_anthropic_payload() {
+        let raw = serde_json::json!({
+            "stop_reason": "end_turn",
+            "usage": { "input_tokens": 20, "output_tokens": 7 }
+        });
+
+        let response = LlmResponse::from_raw("ok".to_string(), "claude", &raw);
+        assert_eq!(response.model, "claude");
+        assert_eq!(response.tokens_used, Some(27));
+        assert_eq!(response.finish_reason.as_deref(), Some("end_turn"));
+
+        let empty = LlmResponse::from_raw(String::new(), "m", &serde_json::Value::Null);
+        assert_eq!(empty.tokens_used, None);
+        assert_eq!(empty.finish_reason, None);
+    }
+
+    #[test]
+    fn test_send_delta_fails_once_consumer_drops() {
+        let (tx, mut rx) = futures::channel::mpsc::unbounded();
+        send_delta(&tx, "Hel").unwrap();
+        send_delta(&tx, "").unwrap();
+        assert!(matches!(rx.try_next(), Ok(Some(Ok(StreamChunk::Delta(text)))) if text == "Hel"));
+        assert!(rx.try_next().is_err(), "empty deltas are not forwarded");
+
+        drop(rx);
+        assert!(send_delta(&tx, "lo").is_err());
+    }
+
+    #[test]
+    fn test_local_providers_need_no_api_key() {
+        let ollama = LlmConfig {
+            base_url: None,
+            ..LlmConfig::ollama("llama3.3")
+        };
+        assert!(LlmProvider::new(ollama.clone()).is_ok());
+        assert_eq!(ollama.server_url().as_deref(), Some("http://localhost:11434"));
+
+        let llama_cpp = LlmConfig::llama_cpp(Some("http://127.0.0.1:9000/"), "qwen2.5-coder");
+        assert!(LlmProvider::new(llama_cpp.clone()).is_ok());
+        assert_eq!(llama_cpp.server_url().as_deref(), Some("http://127.0.0.1:9000"));
+
+        let bare_host = LlmConfig {
+            base_url: Some("0.0.0.0:11434".to_string()),
+            ..LlmConfig::ollama("llama3.3")
+        };
+        assert_eq!(bare_host.server_url().as_deref(), Some("http://0.0.0.0:11434"));
+        let https = LlmConfig::llama_cpp(Some("https://models.internal/"), "qwen2.5-coder");
+        assert_eq!(https.server_url().as_deref(), Some("https://models.internal"));
+
+        let hosted = LlmConfig { api_key: None, ..LlmConfig::anthropic() };
+        assert!(LlmProvider::new(hosted).is_err());
+    }
+
+    #[test]
+    fn test_response_format_params_per_provider() {
+        let schema = serde_json::json!({ "type": "object", "required": ["valid"] });
+        let with_key = |config: LlmConfig| LlmConfig { api_key: Some("key".to_string()), ..config };
+
+        let openai = LlmProvider::new(with_key(LlmConfig::default())).unwrap()
+            .with_response_format(ResponseFormat::JsonSchema(schema.clone()));
+        let params = openai.response_format_params().unwrap();
+        assert_eq!(params["text"]["format"]["type"], "json_schema");
+        assert_eq!(params["text"]["format"]["schema"], schema);
+
+        let ollama = LlmProvider::new(LlmConfig::ollama("qwen2.5-coder")).unwrap()
+            .with_response_format(ResponseFormat::JsonSchema(schema.clone()));
+        let params = ollama.response_format_params().unwrap();
+        assert_eq!(params["response_format"]["json_schema"]["schema"], schema);
+
+        let json_mode = LlmProvider::new(with_key(LlmConfig::openrouter())).unwrap()
+            .with_response_format(ResponseFormat::Json);
+        assert_eq!(json_mode.response_format_params().unwrap()["response_format"]["type"], "json_object");
+
+        // Array schemas and providers without a JSON mode fall back to the prompt
+        let array = LlmProvider::new(with_key(LlmConfig::default())).unwrap()
+            .with_response_format(ResponseFormat::JsonSchema(serde_json::json!({ "type": "array" })));
+        assert!(array.response_format_params().is_none());
+        let anthropic = LlmProvider::new(with_key(LlmConfig::anthropic())).unwrap()
+            .with_response_format(ResponseFormat::JsonSchema(schema));
+        assert!(anthropic.response_format_params().is_none());
+    }
+
+    #[test]
+    fn test_parse_model_lists() {
+        let ollama = serde_json::json!({
+            "models": [{ "name": "qwen2.5-coder:7b" }, { "name": "llama3.3:latest" }]
+        });
+        assert_eq!(
+            parse_model_list(&ollama, "models", "name"),
+            vec!["llama3.3:latest", "qwen2.5-coder:7b"]
+        );
+
+        let llama_cpp = serde_json::json!({ "object": "list", "data": [{ "id": "model.gguf" }] });
+        assert_eq!(parse_model_list(&llama_cpp, "data", "id"), vec!["model.gguf"]);
+        assert!(parse_model_list(&serde_json::json!({}), "data", "id").is_empty());
+    }
+}
@@ -0,0 +1,384 @@
+// Cerebras-MAKER: Record/Replay LLM Provider
+// Deterministic LLM responses for offline and CI tests:
+// - Record: forward to an upstream provider and save each request/response pair to a fixture
+// - Replay: serve saved pairs back, keyed by a hash of the messages and config
+// - Mock: answer from a registered responder (canned or programmatic)
+
+use super::provider::{LlmConfig, LlmResponse, Message, Role};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// How a `ProviderType::Replay` config answers requests
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ReplayMode {
+    /// Call the upstream provider and append the pair to the fixture
+    Record,
+    /// Serve pairs from the fixture; unknown requests are errors
+    Replay,
+    /// Answer from the mock responder registered under `mock`
+    Mock,
+}
+
+/// Settings for `ProviderType::Replay`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    pub mode: ReplayMode,
+    /// Fixture file read in Replay mode and written in Record mode
+    pub fixture_path: Option<PathBuf>,
+    /// Provider that answers requests in Record mode
+    pub upstream: Option<Box<LlmConfig>>,
+    /// Name of the responder registered with `register_mock`
+    pub mock: Option<String>,
+}
+
+/// One recorded exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedExchange {
+    pub system: String,
+    pub conversation: Vec<Message>,
+    pub response: LlmResponse,
+}
+
+/// On-disk fixture: request key -> exchanges in the order they were recorded
+/// Identical requests (e.g. consensus candidates) keep every recorded response and are
+/// replayed in the same order, cycling once the recording runs out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Fixture {
+    pub version: u32,
+    pub exchanges: BTreeMap<String, Vec<RecordedExchange>>,
+}
+
+/// Fixture plus per-key replay cursors
+#[derive(Default)]
+struct LoadedFixture {
+    fixture: Fixture,
+    cursors: HashMap<String, usize>,
+}
+
+/// Fixtures are shared across providers so concurrent atoms see one recording
+static FIXTURES: once_cell::sync::Lazy<Mutex<HashMap<PathBuf, LoadedFixture>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Responder for Mock mode: (system prompt, conversation) -> response content
+pub type MockResponder = Arc<dyn Fn(&str, &[Message]) -> Result<String, String> + Send + Sync>;
+
+static MOCKS: once_cell::sync::Lazy<RwLock<HashMap<String, MockResponder>>> =
+    once_cell::sync::Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Register (or replace) a mock responder
+pub fn register_mock(name: &str, responder: MockResponder) {
+    if let Ok(mut mocks) = MOCKS.write() {
+        mocks.insert(name.to_string(), responder);
+    }
+}
+
+/// Remove a mock responder
+pub fn unregister_mock(name: &str) {
+    if let Ok(mut mocks) = MOCKS.write() {
+        mocks.remove(name);
+    }
+}
+
+/// Responder that returns the response of the first rule whose pattern appears in the
+/// last user message, or `fallback` if none match
+pub fn canned_responder(rules: Vec<(String, String)>, fallback: Option<String>) -> MockResponder {
+    Arc::new(move |_system: &str, conversation: &[Message]| {
+        let prompt = conversation
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, Role::User))
+            .map(|m| m.content.as_str())
+            .unwrap_or_default();
+        rules
+            .iter()
+            .find(|(pattern, _)| prompt.contains(pattern.as_str()))
+            .map(|(_, response)| response.clone())
+            .or_else(|| fallback.clone())
+            .ok_or_else(|| format!("No canned response matches prompt: {}", preview(prompt)))
+    })
+}
+
+/// Answer a request in Mock mode
+pub fn mock_response(
+    config: &LlmConfig,
+    replay: &ReplayConfig,
+    system: &str,
+    conversation: &[Message],
+) -> Result<LlmResponse, anyhow::Error> {
+    let name = replay
+        .mock
+        .as_deref()
+        .ok_or_else(|| anyhow::anyhow!("Mock replay config has no responder name"))?;
+    let responder = MOCKS
+        .read()
+        .map_err(|_| anyhow::anyhow!("Mock registry lock poisoned"))?
+        .get(name)
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("No mock responder registered as '{}'", name))?;
+
+    let content = responder(system, conversation).map_err(|e| anyhow::anyhow!(e))?;
+    Ok(LlmResponse {
+        content,
+        model: config.model.clone(),
+        tokens_used: None,
+        prompt_tokens: None,
+        completion_tokens: None,
+        finish_reason: Some("stop".to_string()),
+    })
+}
+
+/// Serve the next recorded response for a request in Replay mode
+pub fn replay_response(
+    config: &LlmConfig,
+    fixture_path: &Path,
+    system: &str,
+    conversation: &[Message],
+) -> Result<LlmResponse, anyhow::Error> {
+    let key = request_key(config, system, conversation);
+    let mut fixtures = FIXTURES
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Fixture cache lock poisoned"))?;
+    let loaded = load_fixture(&mut fixtures, fixture_path)?;
+
+    let exchanges = loaded
+        .fixture
+        .exchanges
+        .get(&key)
+        .filter(|exchanges| !exchanges.is_empty())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No recorded response for request {} in {:?} (prompt: {})",
+                key,
+                fixture_path,
+                preview(conversation.last().map(|m| m.content.as_str()).unwrap_or_default())
+            )
+        })?;
+
+    let cursor = loaded.cursors.entry(key).or_insert(0);
+    let exchange = &exchanges[*cursor % exchanges.len()];
+    *cursor += 1;
+    Ok(exchange.response.clone())
+}
+
+/// Append a recorded exchange to the fixture and write it to disk
+pub fn record_response(
+    config: &LlmConfig,
+    fixture_path: &Path,
+    system: &str,
+    conversation: &[Message],
+    response: &LlmResponse,
+) -> Result<(), anyhow::Error> {
+    let key = request_key(config, system, conversation);
+    let mut fixtures = FIXTURES
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Fixture cache lock poisoned"))?;
+    let loaded = load_fixture(&mut fixtures, fixture_path)?;
+
+    loaded
+        .fixture
+        .exchanges
+        .entry(key)
+        .or_default()
+        .push(RecordedExchange {
+            system: system.to_string(),
+            conversation: conversation.to_vec(),
+            response: response.clone(),
+        });
+
+    save_fixture(fixture_path, &loaded.fixture)
+}
+
+/// Stable key for a request: FNV-1a over the canonical JSON of the messages and sampling config
+/// The provider type and API key are deliberately left out so a recording made against one
+/// provider replays under a `Replay` config with the same model settings.
+pub fn request_key(config: &LlmConfig, system: &str, conversation: &[Message]) -> String {
+    let canonical = serde_json::json!({
+        "model": config.model,
+        "temperature": config.temperature,
+        "max_tokens": config.max_tokens,
+        "system": system,
+        "conversation": conversation,
+    });
+    content_hash(&canonical.to_string())
+}
+
+/// Stable 64-bit FNV-1a hash of `text` as 16 hex digits
+pub fn content_hash(text: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+fn load_fixture<'a>(
+    fixtures: &'a mut HashMap<PathBuf, LoadedFixture>,
+    path: &Path,
+) -> Result<&'a mut LoadedFixture, anyhow::Error> {
+    if !fixtures.contains_key(path) {
+        let fixture = if path.exists() {
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow::anyhow!("Failed to read fixture {:?}: {}", path, e))?;
+            serde_json::from_str(&content)
+                .map_err(|e| anyhow::anyhow!("Failed to parse fixture {:?}: {}", path, e))?
+        } else {
+            Fixture {
+                version: 1,
+                ..Default::default()
+            }
+        };
+        fixtures.insert(
+            path.to_path_buf(),
+            LoadedFixture {
+                fixture,
+                cursors: HashMap::new(),
+            },
+        );
+    }
+    Ok(fixtures.get_mut(path).expect("fixture inserted above"))
+}
+
+fn save_fixture(path: &Path, fixture: &Fixture) -> Result<(), anyhow::Error> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let content = serde_json::to_string_pretty(fixture)?;
+    // Write via a temp file so an interrupted recording never corrupts the fixture
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, content)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn preview(text: &str) -> String {
+    let short: String = text.chars().take(80).collect();
+    if short.len() < text.len() {
+        format!("{}...", short)
+    } else {
+        short
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{AgentContext, Architect, Interrogator, Orchestrator};
+    use crate::llm::LlmProvider;
+    use crate::maker_core::{run_consensus, AtomType, ConsensusConfig};
+
+    #[tokio::test]
+    async fn test_record_then_replay_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("fixtures").join("echo.json");
+        register_mock(
+            "replay_round_trip",
+            Arc::new(|_system: &str, conversation: &[Message]| {
+                Ok(format!("echo: {}", conversation.last().unwrap().content))
+            }),
+        );
+
+        let upstream = LlmConfig::mock("replay_round_trip");
+        let recorder = LlmProvider::new(upstream.clone().recording(&fixture)).unwrap();
+        let recorded = recorder
+            .complete(vec![Message::system("sys"), Message::user("hello")])
+            .await
+            .unwrap();
+        assert_eq!(recorded.content, "echo: hello");
+
+        // The upstream is gone; replay must serve from the fixture alone
+        unregister_mock("replay_round_trip");
+        FIXTURES.lock().unwrap().remove(&fixture);
+
+        let player = LlmProvider::new(upstream.replaying(&fixture)).unwrap();
+        let replayed = player
+            .complete(vec![Message::system("sys"), Message::user("hello")])
+            .await
+            .unwrap();
+        assert_eq!(replayed.content, "echo: hello");
+
+        let missing = player.complete(vec![Message::user("unrecorded")]).await;
+        assert!(missing.unwrap_err().to_string().contains("No recorded response"));
+    }
+
+    #[test]
+    fn test_request_key_depends_on_messages_and_model() {
+        let config = LlmConfig::mock("any");
+        let hello = [Message::user("hello")];
+        let key = request_key(&config, "sys", &hello);
+        assert_eq!(key, request_key(&config, "sys", &hello));
+        assert_ne!(key, request_key(&config, "other", &hello));
+        assert_ne!(key, request_key(&config, "sys", &[Message::user("bye")]));
+
+        let other_model = LlmConfig {
+            model: "other".to_string(),
+            ..config.clone()
+        };
+        assert_ne!(key, request_key(&other_model, "sys", &hello));
+    }
+
+    /// PRD → PLAN → script → consensus with no network access
+    #[tokio::test]
+    async fn test_pipeline_runs_offline_against_canned_mock() {
+        let workspace = tempfile::tempdir().unwrap();
+        let context = AgentContext::new(workspace.path().to_str().unwrap());
+        register_mock(
+            "offline_pipeline",
+            canned_responder(
+                vec![
+                    (
+                        "Analyze this request".to_string(),
+                        r#"{"ambiguity_score": 0.1, "proceed": true, "unknowns": []}"#.to_string(),
+                    ),
+                    (
+                        "decompose these requirements".to_string(),
+                        "# Plan: Greeting\n1. [TASK_001] [AtomType: Coder] - Add a greet function\n"
+                            .to_string(),
+                    ),
+                    (
+                        "## Task".to_string(),
+                        "FILE: src/lib.rs\n```rust\npub fn greet() -> &'static str { \"hi\" }\n```"
+                            .to_string(),
+                    ),
+                ],
+                None,
+            ),
+        );
+        let llm = LlmConfig::mock("offline_pipeline");
+
+        let analysis = Interrogator::new()
+            .with_config(llm.clone())
+            .analyze("Add a greeting", &context)
+            .await
+            .unwrap();
+        assert!(analysis.proceed);
+
+        let decomposition = Architect::new()
+            .with_config(llm.clone())
+            .decompose("Add a greeting", &context)
+            .await
+            .unwrap();
+        let plan = decomposition.plan;
+        assert_eq!(plan.micro_tasks.len(), 1);
+        assert_eq!(plan.micro_tasks[0].id, "TASK_001");
+
+        let script = Orchestrator::new()
+            .generate_master_script(&plan, &context)
+            .await
+            .unwrap();
+        assert!(script.contains("spawn_atom(AtomType::Coder"));
+
+        let consensus = run_consensus(
+            AtomType::Coder,
+            &plan.micro_tasks[0].description,
+            ConsensusConfig::default(),
+            &llm,
+            &context.workspace_path,
+        )
+        .await;
+        assert!(consensus.reached, "{:?}", consensus.error);
+        assert!(consensus.winner.unwrap().contains("pub fn greet"));
+    }
+}
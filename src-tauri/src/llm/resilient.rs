@@ -2,8 +2,12 @@
 // Implements retry logic with exponential backoff and fallback chain
 // for robust LLM operations when primary providers fail.
 
-use super::{LlmConfig, LlmProvider, LlmResponse, Message};
+use super::limiter::{estimate_tokens, BreakerStatus, Permit, ProviderLimiter, ProviderLimits};
+use super::{LlmConfig, LlmError, LlmProvider, LlmResponse, LlmStream, Message, StreamChunk};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Configuration for retry behavior with exponential backoff
@@ -66,91 +70,19 @@ impl RetryConfig {
 
         Duration::from_millis(capped_delay + jitter)
     }
-}
-
-/// Error classification for determining retry behavior
-#[derive(Debug, Clone, PartialEq)]
-pub enum ErrorKind {
-    /// Transient errors that may succeed on retry (network issues, rate limits)
-    Transient,
-    /// Permanent errors that won't succeed on retry (auth failures, invalid requests)
-    Permanent,
-    /// Unknown error type - treat as transient with limited retries
-    Unknown,
-}
-
-impl ErrorKind {
-    /// Classify an error based on its string representation
-    /// This heuristic approach works with anyhow::Error's Display
-    pub fn classify(error: &anyhow::Error) -> Self {
-        let error_str = error.to_string().to_lowercase();
-
-        // Network and timeout errors are transient
-        if error_str.contains("timeout")
-            || error_str.contains("connection")
-            || error_str.contains("network")
-            || error_str.contains("timed out")
-            || error_str.contains("temporarily")
-            || error_str.contains("service unavailable")
-            || error_str.contains("503")
-            || error_str.contains("502")
-            || error_str.contains("504")
-        {
-            return Self::Transient;
-        }
-
-        // Rate limiting is transient
-        if error_str.contains("rate limit")
-            || error_str.contains("too many requests")
-            || error_str.contains("429")
-            || error_str.contains("quota")
-        {
-            return Self::Transient;
-        }
-
-        // Server errors (5xx) are generally transient
-        if error_str.contains("500")
-            || error_str.contains("internal server error")
-        {
-            return Self::Transient;
-        }
 
-        // Authentication errors are permanent
-        if error_str.contains("unauthorized")
-            || error_str.contains("401")
-            || error_str.contains("403")
-            || error_str.contains("forbidden")
-            || error_str.contains("invalid api key")
-            || error_str.contains("authentication")
-        {
-            return Self::Permanent;
+    /// Delay before retrying after `error` on attempt `attempt` (0-indexed)
+    /// None means give up on this provider: the error is not retryable, retries are used up,
+    /// or the server asked us to wait longer than `max_delay_ms`.
+    pub fn delay_for(&self, attempt: u32, error: &LlmError) -> Option<Duration> {
+        if !error.is_retryable() || attempt >= self.max_retries {
+            return None;
         }
-
-        // Invalid request errors are permanent
-        if error_str.contains("bad request")
-            || error_str.contains("400")
-            || error_str.contains("invalid")
-            || error_str.contains("not found")
-            || error_str.contains("404")
-        {
-            return Self::Permanent;
-        }
-
-        // Model-specific errors are often permanent
-        if error_str.contains("model not found")
-            || error_str.contains("unsupported model")
-            || error_str.contains("context length")
-            || error_str.contains("token limit")
-        {
-            return Self::Permanent;
+        match error.retry_after() {
+            Some(wait) if wait > Duration::from_millis(self.max_delay_ms) => None,
+            Some(wait) => Some(wait),
+            None => Some(self.calculate_delay(attempt)),
         }
-
-        Self::Unknown
-    }
-
-    /// Whether this error type should trigger a retry
-    pub fn should_retry(&self) -> bool {
-        matches!(self, Self::Transient | Self::Unknown)
     }
 }
 
@@ -167,6 +99,12 @@ pub struct ResilienceStats {
     pub fallback_count: u64,
     /// Number of permanent failures
     pub permanent_failures: u64,
+    /// Number of times a provider was skipped because its circuit was open
+    #[serde(default)]
+    pub circuit_open_skips: u64,
+    /// Circuit breaker state of each provider, primary first
+    #[serde(default)]
+    pub circuit_breakers: Vec<BreakerStatus>,
 }
 
 /// A resilient LLM provider that wraps multiple providers with retry and fallback logic
@@ -175,6 +113,10 @@ pub struct ResilientLlmProvider {
     provider_configs: Vec<LlmConfig>,
     /// Retry configuration
     retry_config: RetryConfig,
+    /// Concurrency, rate limits and circuit breaker applied to every provider
+    limits: ProviderLimits,
+    /// Shared limiter of each provider, aligned with `provider_configs`
+    limiters: Vec<Arc<ProviderLimiter>>,
     /// Statistics tracking (protected by mutex for interior mutability)
     stats: std::sync::Mutex<ResilienceStats>,
 }
@@ -188,8 +130,26 @@ impl ResilientLlmProvider {
         Self {
             provider_configs,
             retry_config,
+            limits: ProviderLimits::default(),
+            limiters: Vec::new(),
             stats: std::sync::Mutex::new(ResilienceStats::default()),
         }
+        .with_limits(ProviderLimits::default())
+    }
+
+    /// Apply concurrency, rate limits and circuit breaker settings to every provider
+    /// Limits are shared with other resilient providers using the same endpoint.
+    pub fn with_limits(mut self, limits: ProviderLimits) -> Self {
+        self.limiters = self.provider_configs.iter()
+            .map(|config| ProviderLimiter::shared(config, &limits))
+            .collect();
+        self.limits = limits;
+        self
+    }
+
+    /// Get the configured limits
+    pub fn limits(&self) -> &ProviderLimits {
+        &self.limits
     }
 
     /// Create a resilient provider with just a primary (no fallbacks)
@@ -211,7 +171,9 @@ impl ResilientLlmProvider {
 
     /// Get current resilience statistics
     pub fn stats(&self) -> ResilienceStats {
-        self.stats.lock().unwrap().clone()
+        let mut stats = self.stats.lock().unwrap().clone();
+        stats.circuit_breakers = self.limiters.iter().map(|l| l.status()).collect();
+        stats
     }
 
     /// Reset statistics
@@ -221,13 +183,50 @@ impl ResilientLlmProvider {
     }
 
     /// Complete a request with retry and fallback logic
-    pub async fn complete(&self, messages: Vec<Message>) -> Result<LlmResponse, anyhow::Error> {
+    pub async fn complete(&self, messages: Vec<Message>) -> Result<LlmResponse, LlmError> {
+        self.with_fallback(&messages, |provider, messages, permit| async move {
+            let response = provider.complete(messages).await?;
+            permit.settle(response.tokens_used);
+            Ok(response)
+        })
+        .await
+    }
+
+    /// Stream a request with retry and fallback logic
+    /// Retries and fallbacks only apply until the first chunk arrives; once output has
+    /// started flowing, errors are passed through to the consumer. The stream holds its
+    /// concurrency slot until it is dropped.
+    pub async fn complete_stream(&self, messages: Vec<Message>) -> Result<LlmStream, LlmError> {
+        self.with_fallback(&messages, |provider, messages, permit| async move {
+            let mut stream = provider.complete_stream(messages).await?;
+            match stream.next().await {
+                Some(Ok(first)) => Ok(futures::stream::once(async move { Ok(first) })
+                    .chain(stream)
+                    .inspect(move |chunk| {
+                        if let Ok(StreamChunk::Done(response)) = chunk {
+                            permit.settle(response.tokens_used);
+                        }
+                    })
+                    .boxed()),
+                Some(Err(e)) => Err(e),
+                None => Err(LlmError::Malformed("Stream ended before producing any output".to_string())),
+            }
+        })
+        .await
+    }
+
+    /// Run an operation against each provider in order, retrying each before falling back
+    async fn with_fallback<T, F, Fut>(&self, messages: &[Message], op: F) -> Result<T, LlmError>
+    where
+        F: Fn(LlmProvider, Vec<Message>, Permit) -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
         self.increment_total_requests();
 
-        let mut last_error: Option<anyhow::Error> = None;
+        let mut last_error: Option<LlmError> = None;
 
         // Try each provider in order
-        for (provider_idx, config) in self.provider_configs.iter().enumerate() {
+        for (provider_idx, (config, limiter)) in self.provider_configs.iter().zip(&self.limiters).enumerate() {
             let provider_name = format!("{:?}", config.provider);
 
             if provider_idx > 0 {
@@ -241,7 +240,7 @@ impl ResilientLlmProvider {
             }
 
             // Try this provider with retries
-            match self.try_provider_with_retries(config, &messages, &provider_name).await {
+            match self.try_provider_with_retries(config, limiter, messages, &provider_name, &op).await {
                 Ok(response) => {
                     self.increment_successful_requests();
                     if provider_idx > 0 {
@@ -254,18 +253,17 @@ impl ResilientLlmProvider {
                     return Ok(response);
                 }
                 Err(e) => {
-                    let error_kind = ErrorKind::classify(&e);
-                    log::warn!(
-                        "Provider {} failed with {:?} error: {}",
-                        provider_name,
-                        error_kind,
-                        e
-                    );
+                    log::warn!("Provider {} failed: {:?}", provider_name, e);
 
-                    // If it's a permanent error, don't try fallbacks for same issue
-                    if error_kind == ErrorKind::Permanent {
+                    if matches!(e, LlmError::CircuitOpen(_)) {
+                        self.increment_circuit_open_skips();
+                    } else if !e.is_retryable() {
                         self.increment_permanent_failures();
-                        // Continue to next provider anyway - might be config-specific
+                    }
+                    // Auth, context length and the like are provider-specific, so fall back;
+                    // a cancelled request is not worth sending anywhere else
+                    if !e.should_fall_back() {
+                        return Err(e);
                     }
 
                     last_error = Some(e);
@@ -274,38 +272,44 @@ impl ResilientLlmProvider {
         }
 
         // All providers failed
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No providers configured")))
+        Err(last_error.unwrap_or_else(|| LlmError::Other("No providers configured".to_string())))
     }
 }
 
 
 impl ResilientLlmProvider {
     /// Try a single provider with retry logic
-    async fn try_provider_with_retries(
+    /// Backoff follows `RetryConfig::delay_for`, which honours the server's Retry-After hint.
+    /// Each attempt waits for the provider's concurrency and rate limits; an open circuit
+    /// refuses the provider so the caller falls back.
+    async fn try_provider_with_retries<T, F, Fut>(
         &self,
         config: &LlmConfig,
+        limiter: &Arc<ProviderLimiter>,
         messages: &[Message],
         provider_name: &str,
-    ) -> Result<LlmResponse, anyhow::Error> {
+        op: &F,
+    ) -> Result<T, LlmError>
+    where
+        F: Fn(LlmProvider, Vec<Message>, Permit) -> Fut,
+        Fut: Future<Output = Result<T, LlmError>>,
+    {
         let provider = LlmProvider::new(config.clone())?;
-        let mut last_error: Option<anyhow::Error> = None;
-
-        for attempt in 0..=self.retry_config.max_retries {
-            if attempt > 0 {
-                let delay = self.retry_config.calculate_delay(attempt - 1);
-                log::debug!(
-                    "Retry attempt {}/{} for {} after {:?} delay",
-                    attempt,
-                    self.retry_config.max_retries,
-                    provider_name,
-                    delay
-                );
-                self.increment_retry_count();
-                tokio::time::sleep(delay).await;
-            }
+        if !limiter.admit() {
+            return Err(LlmError::CircuitOpen(format!(
+                "{} failed repeatedly; waiting for a probe to succeed",
+                provider_name
+            )));
+        }
 
-            match provider.complete(messages.to_vec()).await {
+        let estimated_tokens = estimate_tokens(messages.iter().map(|m| m.content.len()).sum());
+        let mut attempt = 0;
+
+        loop {
+            let permit = limiter.acquire(estimated_tokens).await;
+            match op(provider.clone(), messages.to_vec(), permit).await {
                 Ok(response) => {
+                    limiter.record_success();
                     if attempt > 0 {
                         log::info!(
                             "Provider {} succeeded after {} retries",
@@ -316,35 +320,48 @@ impl ResilientLlmProvider {
                     return Ok(response);
                 }
                 Err(e) => {
-                    let error_kind = ErrorKind::classify(&e);
-
+                    if e.is_provider_failure() {
+                        limiter.record_failure();
+                    } else {
+                        limiter.release_probe();
+                    }
                     log::debug!(
-                        "Provider {} attempt {}/{} failed ({:?}): {}",
+                        "Provider {} attempt {}/{} failed: {:?}",
                         provider_name,
                         attempt + 1,
                         self.retry_config.max_retries + 1,
-                        error_kind,
                         e
                     );
 
-                    // Don't retry permanent errors
-                    if !error_kind.should_retry() {
-                        log::warn!(
-                            "Provider {} encountered permanent error, not retrying: {}",
-                            provider_name,
-                            e
-                        );
+                    let Some(delay) = self.retry_config.delay_for(attempt, &e) else {
+                        if !e.is_retryable() {
+                            log::warn!(
+                                "Provider {} encountered permanent error, not retrying: {}",
+                                provider_name,
+                                e
+                            );
+                        }
                         return Err(e);
-                    }
+                    };
 
-                    last_error = Some(e);
+                    attempt += 1;
+                    log::debug!(
+                        "Retry attempt {}/{} for {} after {:?} delay",
+                        attempt,
+                        self.retry_config.max_retries,
+                        provider_name,
+                        delay
+                    );
+                    self.increment_retry_count();
+                    tokio::time::sleep(delay).await;
+
+                    // The circuit may have opened meanwhile; leave the request to the fallbacks
+                    if !limiter.admit() {
+                        return Err(e);
+                    }
                 }
             }
         }
-
-        Err(last_error.unwrap_or_else(|| {
-            anyhow::anyhow!("Provider {} exhausted all retries", provider_name)
-        }))
     }
 
     // Statistics helper methods
@@ -377,6 +394,12 @@ impl ResilientLlmProvider {
             stats.permanent_failures += 1;
         }
     }
+
+    fn increment_circuit_open_skips(&self) {
+        if let Ok(mut stats) = self.stats.lock() {
+            stats.circuit_open_skips += 1;
+        }
+    }
 }
 
 /// Builder pattern for creating ResilientLlmProvider
@@ -384,6 +407,7 @@ pub struct ResilientLlmProviderBuilder {
     primary: Option<LlmConfig>,
     fallbacks: Vec<LlmConfig>,
     retry_config: RetryConfig,
+    limits: ProviderLimits,
 }
 
 impl Default for ResilientLlmProviderBuilder {
@@ -398,6 +422,7 @@ impl ResilientLlmProviderBuilder {
             primary: None,
             fallbacks: Vec::new(),
             retry_config: RetryConfig::default(),
+            limits: ProviderLimits::default(),
         }
     }
 
@@ -425,18 +450,26 @@ impl ResilientLlmProviderBuilder {
         self
     }
 
+    /// Set concurrency, rate limit and circuit breaker settings
+    pub fn limits(mut self, limits: ProviderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     /// Build the resilient provider
     pub fn build(self) -> Result<ResilientLlmProvider, anyhow::Error> {
         let primary = self.primary
             .ok_or_else(|| anyhow::anyhow!("Primary provider configuration is required"))?;
 
-        Ok(ResilientLlmProvider::new(primary, self.fallbacks, self.retry_config))
+        Ok(ResilientLlmProvider::new(primary, self.fallbacks, self.retry_config).with_limits(self.limits))
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm::replay::{register_mock, unregister_mock};
+    use crate::llm::{CircuitBreakerConfig, CircuitState};
 
     #[test]
     fn test_retry_config_delay_calculation() {
@@ -469,43 +502,80 @@ mod tests {
     }
 
     #[test]
-    fn test_error_classification() {
-        // Transient errors
-        assert_eq!(
-            ErrorKind::classify(&anyhow::anyhow!("Connection timeout")),
-            ErrorKind::Transient
-        );
-        assert_eq!(
-            ErrorKind::classify(&anyhow::anyhow!("Rate limit exceeded (429)")),
-            ErrorKind::Transient
-        );
-        assert_eq!(
-            ErrorKind::classify(&anyhow::anyhow!("Service temporarily unavailable")),
-            ErrorKind::Transient
-        );
+    fn test_delay_for_follows_error_type() {
+        let config = RetryConfig {
+            max_retries: 3,
+            base_delay_ms: 1000,
+            max_delay_ms: 10000,
+            jitter_factor: 0.0,
+        };
+        let server = LlmError::Server {
+            status: 503,
+            message: "unavailable".to_string(),
+        };
+        assert_eq!(config.delay_for(1, &server), Some(Duration::from_millis(2000)));
+        assert_eq!(config.delay_for(3, &server), None, "retries used up");
 
-        // Permanent errors
-        assert_eq!(
-            ErrorKind::classify(&anyhow::anyhow!("Unauthorized: Invalid API key")),
-            ErrorKind::Permanent
-        );
-        assert_eq!(
-            ErrorKind::classify(&anyhow::anyhow!("Bad request: invalid parameter")),
-            ErrorKind::Permanent
-        );
+        // The server's Retry-After wins over exponential backoff...
+        let rate_limited = |secs| LlmError::RateLimited {
+            retry_after: Some(Duration::from_secs(secs)),
+            message: "slow down".to_string(),
+        };
+        assert_eq!(config.delay_for(0, &rate_limited(5)), Some(Duration::from_secs(5)));
+        // ...unless it is longer than we would ever wait, then we fall back instead
+        assert_eq!(config.delay_for(0, &rate_limited(60)), None);
+
+        assert_eq!(config.delay_for(0, &LlmError::Auth("bad key".to_string())), None);
+        assert!(config
+            .delay_for(0, &LlmError::Malformed("invalid JSON in model output".to_string()))
+            .is_some());
+    }
 
-        // Unknown errors
-        assert_eq!(
-            ErrorKind::classify(&anyhow::anyhow!("Something went wrong")),
-            ErrorKind::Unknown
+    #[tokio::test]
+    async fn test_open_circuit_routes_to_fallback() {
+        register_mock(
+            "breaker_primary",
+            Arc::new(|_: &str, _: &[Message]| Err("status code 503: down".to_string())),
+        );
+        register_mock(
+            "breaker_fallback",
+            Arc::new(|_: &str, _: &[Message]| Ok("from fallback".to_string())),
         );
-    }
 
-    #[test]
-    fn test_should_retry() {
-        assert!(ErrorKind::Transient.should_retry());
-        assert!(ErrorKind::Unknown.should_retry());
-        assert!(!ErrorKind::Permanent.should_retry());
+        let retry = RetryConfig {
+            max_retries: 1,
+            base_delay_ms: 1,
+            max_delay_ms: 1,
+            jitter_factor: 0.0,
+        };
+        let provider = ResilientLlmProvider::new(
+            LlmConfig::mock("breaker_primary"),
+            vec![LlmConfig::mock("breaker_fallback")],
+            retry,
+        )
+        .with_limits(ProviderLimits {
+            breaker: CircuitBreakerConfig {
+                failure_threshold: 2,
+                open_secs: 60,
+            },
+            ..Default::default()
+        });
+
+        // Both attempts on the primary fail and open its circuit
+        let first = provider.complete(vec![Message::user("hi")]).await.unwrap();
+        assert_eq!(first.content, "from fallback");
+
+        // The next request skips the primary without calling it
+        provider.complete(vec![Message::user("hi")]).await.unwrap();
+
+        let stats = provider.stats();
+        assert_eq!(stats.retry_count, 1);
+        assert_eq!(stats.circuit_open_skips, 1);
+        assert_eq!(stats.circuit_breakers[0].state, CircuitState::Open);
+        assert_eq!(stats.circuit_breakers[1].state, CircuitState::Closed);
+
+        unregister_mock("breaker_primary");
+        unregister_mock("breaker_fallback");
     }
 }
 
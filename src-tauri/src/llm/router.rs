@@ -0,0 +1,250 @@
+// Cerebras-MAKER: Per-Agent LLM Routing
+// Resolves the LlmConfig for each agent role and atom type from AppSettings.agent_config,
+// so e.g. Coder voting can run on a cheap fast model while the Architect uses a strong one.
+
+use super::provider::LlmConfig;
+use crate::maker_core::AtomType;
+use crate::{ApiKeys, AppSettings, ProviderConfig};
+use serde::{Deserialize, Serialize};
+
+/// Agent roles with their own entry in `AgentConfig`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AgentRole {
+    Interrogator,
+    Architect,
+    Orchestrator,
+    Coder,
+    Reviewer,
+    Tester,
+}
+
+impl AgentRole {
+    /// Role whose settings drive an atom type
+    pub fn for_atom(atom_type: AtomType) -> Self {
+        match atom_type {
+            AtomType::Coder => Self::Coder,
+            AtomType::Reviewer | AtomType::Validator => Self::Reviewer,
+            AtomType::Tester => Self::Tester,
+            AtomType::Architect => Self::Architect,
+            // Planning, search and analysis atoms serve the L2 orchestration loop
+            AtomType::Planner
+            | AtomType::Search
+            | AtomType::GritsAnalyzer
+            | AtomType::RLMProcessor
+            | AtomType::WebResearcher => Self::Orchestrator,
+        }
+    }
+}
+
+/// Resolves LLM configs per agent role
+#[derive(Debug, Clone)]
+pub struct LlmRouter {
+    settings: Option<AppSettings>,
+    /// Used for every role when there are no settings
+    fallback: LlmConfig,
+}
+
+impl Default for LlmRouter {
+    fn default() -> Self {
+        Self::from_settings(None)
+    }
+}
+
+impl LlmRouter {
+    /// Route from the given settings; without settings every role uses Cerebras
+    pub fn from_settings(settings: Option<AppSettings>) -> Self {
+        Self {
+            settings,
+            fallback: LlmConfig::cerebras(),
+        }
+    }
+
+    /// Route from the saved app settings
+    pub fn load() -> Self {
+        Self::from_settings(crate::load_cached_settings())
+    }
+
+    /// Use one config for every role (tests, explicit overrides)
+    pub fn fixed(config: LlmConfig) -> Self {
+        Self {
+            settings: None,
+            fallback: config,
+        }
+    }
+
+    /// Config for an agent role
+    pub fn config_for(&self, role: AgentRole) -> LlmConfig {
+        let Some(settings) = &self.settings else {
+            return self.fallback.clone();
+        };
+
+        let agents = &settings.agent_config;
+        let provider_config = match role {
+            AgentRole::Interrogator => &agents.interrogator,
+            AgentRole::Architect => &agents.architect,
+            AgentRole::Orchestrator => &agents.orchestrator,
+            AgentRole::Coder => &agents.coder,
+            AgentRole::Reviewer => &agents.reviewer,
+            AgentRole::Tester => &agents.tester,
+        };
+
+        Self::resolve(provider_config, settings).unwrap_or_else(|| {
+            eprintln!(
+                "Warning: Unknown provider '{}' for {:?}, using {:?}",
+                provider_config.provider, role, self.fallback.provider
+            );
+            self.fallback.clone()
+        })
+    }
+
+    /// Config for the agent role that runs an atom type
+    pub fn config_for_atom(&self, atom_type: AtomType) -> LlmConfig {
+        self.config_for(AgentRole::for_atom(atom_type))
+    }
+
+    /// Config for a settings provider ID and model, e.g. ("openrouter", "qwen/qwen3-coder")
+    /// Uses the saved API keys; None if the provider ID is unknown.
+    pub fn config_for_model(&self, provider: &str, model: &str) -> Option<LlmConfig> {
+        Self::preset(
+            provider,
+            model,
+            self.settings.as_ref().map(|settings| &settings.api_keys),
+        )
+    }
+
+    /// Build a config from a settings entry, or None if the provider ID is unknown
+    fn resolve(provider_config: &ProviderConfig, settings: &AppSettings) -> Option<LlmConfig> {
+        let mut config = Self::preset(
+            &provider_config.provider,
+            &provider_config.model,
+            Some(&settings.api_keys),
+        )?;
+        config.temperature = provider_config.temperature;
+        Some(config)
+    }
+
+    /// Provider preset for a provider ID, with the model and any keys and URLs from settings
+    /// Keys and URLs left empty in settings fall back to the provider preset (environment variables).
+    fn preset(provider: &str, model: &str, keys: Option<&ApiKeys>) -> Option<LlmConfig> {
+        let (mut config, api_key, base_url) = match provider {
+            "openai" => (LlmConfig::default(), keys.map(|k| &k.openai), None),
+            "anthropic" => (LlmConfig::anthropic(), keys.map(|k| &k.anthropic), None),
+            "cerebras" => (LlmConfig::cerebras(), keys.map(|k| &k.cerebras), None),
+            "openrouter" => (LlmConfig::openrouter(), keys.map(|k| &k.openrouter), None),
+            "ollama" => (LlmConfig::ollama(model), None, keys.map(|k| &k.ollama_url)),
+            "llamacpp" => (
+                LlmConfig::llama_cpp(None, model),
+                None,
+                keys.map(|k| &k.llamacpp_url),
+            ),
+            _ => return None,
+        };
+
+        config.model = model.to_string();
+        if let Some(key) = api_key.filter(|key| !key.trim().is_empty()) {
+            config.api_key = Some(key.clone());
+        }
+        if let Some(url) = base_url.filter(|url| !url.trim().is_empty()) {
+            config.base_url = Some(url.clone());
+        }
+        Some(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ProviderType;
+    use crate::{AgentConfig, ApiKeys};
+
+    fn provider(provider: &str, model: &str) -> ProviderConfig {
+        ProviderConfig {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            temperature: 0.2,
+        }
+    }
+
+    fn settings() -> AppSettings {
+        AppSettings {
+            agent_config: AgentConfig {
+                interrogator: provider("anthropic", "claude-sonnet-4"),
+                architect: provider("openai", "gpt-5.2"),
+                orchestrator: provider("cerebras", "zai-glm-4.7"),
+                coder: provider("ollama", "qwen2.5-coder"),
+                reviewer: provider("openrouter", "openai/o4-mini"),
+                tester: provider("google", "gemini-2.5-pro"),
+            },
+            api_keys: ApiKeys {
+                openai: "sk-openai".to_string(),
+                anthropic: "sk-ant".to_string(),
+                cerebras: String::new(),
+                ollama_url: "http://gpu-box:11434".to_string(),
+                llamacpp_url: String::new(),
+                openrouter: "sk-or".to_string(),
+            },
+            budget: Default::default(),
+            provider_limits: Default::default(),
+            response_cache: None,
+        }
+    }
+
+    #[test]
+    fn test_routes_each_role_to_its_provider() {
+        let router = LlmRouter::from_settings(Some(settings()));
+
+        let architect = router.config_for(AgentRole::Architect);
+        assert_eq!(architect.provider, ProviderType::OpenAI);
+        assert_eq!(architect.model, "gpt-5.2");
+        assert_eq!(architect.api_key.as_deref(), Some("sk-openai"));
+        assert_eq!(architect.temperature, 0.2);
+
+        let coder = router.config_for_atom(AtomType::Coder);
+        assert_eq!(coder.provider, ProviderType::Ollama);
+        assert_eq!(coder.model, "qwen2.5-coder");
+        assert_eq!(coder.base_url.as_deref(), Some("http://gpu-box:11434"));
+
+        let validator = router.config_for_atom(AtomType::Validator);
+        assert_eq!(validator.provider, ProviderType::OpenRouter);
+        assert_eq!(validator.api_key.as_deref(), Some("sk-or"));
+
+        assert_eq!(
+            router.config_for_atom(AtomType::Planner).provider,
+            ProviderType::Cerebras
+        );
+    }
+
+    #[test]
+    fn test_unknown_provider_and_missing_settings_use_fallback() {
+        let router = LlmRouter::from_settings(Some(settings()));
+        assert_eq!(
+            router.config_for(AgentRole::Tester).provider,
+            ProviderType::Cerebras
+        );
+
+        let fixed = LlmRouter::fixed(LlmConfig::mock("router"));
+        assert_eq!(
+            fixed.config_for_atom(AtomType::Coder).provider,
+            ProviderType::Replay
+        );
+    }
+
+    #[test]
+    fn test_config_for_model_uses_saved_keys() {
+        let router = LlmRouter::from_settings(Some(settings()));
+        let openrouter = router
+            .config_for_model("openrouter", "qwen/qwen3-coder")
+            .unwrap();
+        assert_eq!(openrouter.model, "qwen/qwen3-coder");
+        assert_eq!(openrouter.api_key.as_deref(), Some("sk-or"));
+        assert!(router
+            .config_for_model("google", "gemini-2.5-pro")
+            .is_none());
+
+        let ollama = LlmRouter::fixed(LlmConfig::mock("router"))
+            .config_for_model("ollama", "llama3.2")
+            .unwrap();
+        assert_eq!(ollama.provider, ProviderType::Ollama);
+        assert_eq!(ollama.model, "llama3.2");
+    }
+}
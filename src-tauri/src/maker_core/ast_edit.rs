@@ -4,12 +4,16 @@
 //! before applying code changes to disk.
 //!
 //! This module provides:
-//! - Syntax validation for multiple languages (pattern-based)
+//! - Syntax validation for multiple languages (tree-sitter parse trees)
 //! - Code structure analysis
-//! - Safe code transformations
+//! - Safe code transformations (structural edits and ast-grep rewrite rules)
 
+use ast_grep_core::matcher::{Pattern, PatternBuilder, PatternError};
+use ast_grep_core::tree_sitter::{LanguageExt, StrDoc, TSLanguage};
+use ast_grep_core::Language;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tree_sitter::{Node, Parser, Tree};
 
 /// Maximum cache size to prevent memory issues
 #[allow(dead_code)]
@@ -20,6 +24,7 @@ const MAX_CACHE_SIZE: usize = 100;
 pub enum SupportedLanguage {
     Rust,
     TypeScript,
+    Tsx,
     JavaScript,
     Python,
     Go,
@@ -30,7 +35,8 @@ impl SupportedLanguage {
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext.to_lowercase().as_str() {
             "rs" => Some(SupportedLanguage::Rust),
-            "ts" | "tsx" => Some(SupportedLanguage::TypeScript),
+            "ts" | "mts" | "cts" => Some(SupportedLanguage::TypeScript),
+            "tsx" => Some(SupportedLanguage::Tsx),
             "js" | "jsx" | "mjs" | "cjs" => Some(SupportedLanguage::JavaScript),
             "py" | "pyi" => Some(SupportedLanguage::Python),
             "go" => Some(SupportedLanguage::Go),
@@ -42,6 +48,50 @@ impl SupportedLanguage {
     pub fn from_path(path: &str) -> Option<Self> {
         path.rsplit('.').next().and_then(Self::from_extension)
     }
+
+    /// Indentation used when generating a new block
+    fn indent_unit(&self) -> &'static str {
+        match self {
+            SupportedLanguage::Go => "\t",
+            SupportedLanguage::Rust | SupportedLanguage::Python => "    ",
+            _ => "  ",
+        }
+    }
+
+    /// Get the tree-sitter grammar for this language
+    pub fn tree_sitter_language(&self) -> tree_sitter::Language {
+        match self {
+            SupportedLanguage::Rust => tree_sitter_rust::LANGUAGE.into(),
+            SupportedLanguage::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            SupportedLanguage::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            SupportedLanguage::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            SupportedLanguage::Python => tree_sitter_python::LANGUAGE.into(),
+            SupportedLanguage::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+}
+
+/// ast-grep binding so rewrite rules (`pattern` + `fix`) can run on our grammars
+impl Language for SupportedLanguage {
+    fn kind_to_id(&self, kind: &str) -> u16 {
+        self.get_ts_language().id_for_node_kind(kind, true)
+    }
+
+    fn field_to_id(&self, field: &str) -> Option<u16> {
+        self.get_ts_language()
+            .field_id_for_name(field)
+            .map(|id| id.get())
+    }
+
+    fn build_pattern(&self, builder: &PatternBuilder) -> Result<Pattern, PatternError> {
+        builder.build(|src| StrDoc::try_new(src, *self))
+    }
+}
+
+impl LanguageExt for SupportedLanguage {
+    fn get_ts_language(&self) -> TSLanguage {
+        self.tree_sitter_language()
+    }
 }
 
 /// Result of syntax validation
@@ -70,6 +120,28 @@ pub struct SyntaxError {
     pub snippet: Option<String>,
 }
 
+/// A named code structure (function, method, type) found in the parse tree
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CodeSymbol {
+    /// Symbol name as written in the source
+    pub name: String,
+    /// Symbol kind: "function", "method", "struct", "enum", "trait", "class", "interface", "type"
+    pub kind: String,
+    /// Start line (1-based)
+    pub start_line: usize,
+    /// End line (1-based)
+    pub end_line: usize,
+    /// Byte range of the whole definition
+    pub byte_range: (usize, usize),
+}
+
+impl CodeSymbol {
+    /// Whether this symbol is a function or method
+    pub fn is_function(&self) -> bool {
+        self.kind == "function" || self.kind == "method"
+    }
+}
+
 /// AST editor for code analysis and transformation
 pub struct AstEditor {
     /// Cache of parsed ASTs (reserved for future use)
@@ -100,21 +172,49 @@ impl AstEditor {
         }
     }
 
-    /// Validate syntax of code content using pattern-based heuristics
-    /// This is a lightweight validation that catches common syntax errors
+    /// Parse code into a tree-sitter syntax tree
+    pub fn parse(&self, code: &str, language: SupportedLanguage) -> Result<Tree, String> {
+        let mut parser = Parser::new();
+        parser
+            .set_language(&language.tree_sitter_language())
+            .map_err(|e| format!("Failed to load {:?} grammar: {}", language, e))?;
+        parser
+            .parse(code, None)
+            .ok_or_else(|| format!("Failed to parse {:?} source", language))
+    }
+
+    /// Validate syntax of code content by parsing it.
+    /// Every ERROR and MISSING node in the parse tree is reported with its position.
     pub fn validate_syntax(
         &self,
         code: &str,
         language: SupportedLanguage,
     ) -> SyntaxValidationResult {
-        let errors = self.check_syntax_patterns(code, language);
-        let structure_count = self.count_structures(code, language);
+        let (errors, node_count) = match self.parse(code, language) {
+            Ok(tree) => {
+                let root = tree.root_node();
+                let mut errors = Vec::new();
+                if root.has_error() {
+                    collect_syntax_errors(root, code, &mut errors);
+                }
+                (errors, root.descendant_count())
+            }
+            Err(e) => (
+                vec![SyntaxError {
+                    message: e,
+                    line: 1,
+                    column: 1,
+                    snippet: None,
+                }],
+                0,
+            ),
+        };
 
         SyntaxValidationResult {
             is_valid: errors.is_empty(),
             errors,
             language: Some(format!("{:?}", language)),
-            node_count: structure_count,
+            node_count,
         }
     }
 
@@ -131,309 +231,559 @@ impl AstEditor {
         }
     }
 
-    /// Check for common syntax errors using pattern matching
-    fn check_syntax_patterns(&self, code: &str, language: SupportedLanguage) -> Vec<SyntaxError> {
-        let mut errors = Vec::new();
+    /// Extract named functions, methods and types from the parse tree
+    pub fn extract_symbols(&self, code: &str, language: SupportedLanguage) -> Vec<CodeSymbol> {
+        let tree = match self.parse(code, language) {
+            Ok(tree) => tree,
+            Err(_) => return Vec::new(),
+        };
 
-        // Check bracket balance
-        errors.extend(self.check_bracket_balance(code));
+        let mut symbols = Vec::new();
+        collect_symbols(tree.root_node(), code, language, &mut symbols);
+        symbols
+    }
 
-        // Language-specific checks
-        match language {
-            SupportedLanguage::Rust => errors.extend(self.check_rust_syntax(code)),
-            SupportedLanguage::TypeScript | SupportedLanguage::JavaScript => {
-                errors.extend(self.check_js_ts_syntax(code))
-            }
-            SupportedLanguage::Python => errors.extend(self.check_python_syntax(code)),
-            SupportedLanguage::Go => errors.extend(self.check_go_syntax(code)),
+    /// Extract function/method names from code
+    pub fn extract_functions(&self, code: &str, language: SupportedLanguage) -> Vec<String> {
+        self.extract_symbols(code, language)
+            .into_iter()
+            .filter(|s| s.is_function())
+            .map(|s| s.name)
+            .collect()
+    }
+
+    /// Extract struct/class/enum/trait/interface names from code
+    pub fn extract_types(&self, code: &str, language: SupportedLanguage) -> Vec<String> {
+        self.extract_symbols(code, language)
+            .into_iter()
+            .filter(|s| !s.is_function())
+            .map(|s| s.name)
+            .collect()
+    }
+
+    // =======================================================================
+    // Structural edits
+    // Every edit returns the new source. An edit that would turn code that
+    // parses into code that does not is rejected instead of returned.
+    // =======================================================================
+
+    /// Find a named function/method/type definition
+    pub fn find_symbol(
+        &self,
+        code: &str,
+        language: SupportedLanguage,
+        name: &str,
+    ) -> Option<CodeSymbol> {
+        self.extract_symbols(code, language)
+            .into_iter()
+            .find(|s| s.name == name)
+    }
+
+    /// Replace a byte range (e.g. a grits `Symbol::byte_range`) with new code
+    pub fn replace_range(
+        &self,
+        code: &str,
+        language: SupportedLanguage,
+        range: (usize, usize),
+        new_code: &str,
+    ) -> Result<String, String> {
+        let (start, end) = range;
+        if start > end || end > code.len() {
+            return Err(format!(
+                "Byte range {}..{} is out of bounds ({} bytes)",
+                start,
+                end,
+                code.len()
+            ));
+        }
+        if !code.is_char_boundary(start) || !code.is_char_boundary(end) {
+            return Err(format!(
+                "Byte range {}..{} does not fall on character boundaries",
+                start, end
+            ));
         }
 
-        errors
-    }
-
-    /// Check bracket balance (common across all languages)
-    fn check_bracket_balance(&self, code: &str) -> Vec<SyntaxError> {
-        let mut errors = Vec::new();
-        let mut stack: Vec<(char, usize, usize)> = Vec::new();
-
-        for (line_num, line) in code.lines().enumerate() {
-            for (col, ch) in line.chars().enumerate() {
-                match ch {
-                    '(' | '[' | '{' => stack.push((ch, line_num + 1, col + 1)),
-                    ')' => {
-                        if let Some((open, _, _)) = stack.pop() {
-                            if open != '(' {
-                                errors.push(SyntaxError {
-                                    message: format!(
-                                        "Mismatched bracket: expected ')', found '{}'",
-                                        open
-                                    ),
-                                    line: line_num + 1,
-                                    column: col + 1,
-                                    snippet: Some(line.to_string()),
-                                });
-                            }
-                        } else {
-                            errors.push(SyntaxError {
-                                message: "Unmatched closing parenthesis ')'".to_string(),
-                                line: line_num + 1,
-                                column: col + 1,
-                                snippet: Some(line.to_string()),
-                            });
-                        }
-                    }
-                    ']' => {
-                        if let Some((open, _, _)) = stack.pop() {
-                            if open != '[' {
-                                errors.push(SyntaxError {
-                                    message: format!(
-                                        "Mismatched bracket: expected ']', found '{}'",
-                                        open
-                                    ),
-                                    line: line_num + 1,
-                                    column: col + 1,
-                                    snippet: Some(line.to_string()),
-                                });
-                            }
-                        } else {
-                            errors.push(SyntaxError {
-                                message: "Unmatched closing bracket ']'".to_string(),
-                                line: line_num + 1,
-                                column: col + 1,
-                                snippet: Some(line.to_string()),
-                            });
-                        }
-                    }
-                    '}' => {
-                        if let Some((open, _, _)) = stack.pop() {
-                            if open != '{' {
-                                errors.push(SyntaxError {
-                                    message: format!(
-                                        "Mismatched bracket: expected '}}', found '{}'",
-                                        open
-                                    ),
-                                    line: line_num + 1,
-                                    column: col + 1,
-                                    snippet: Some(line.to_string()),
-                                });
-                            }
-                        } else {
-                            errors.push(SyntaxError {
-                                message: "Unmatched closing brace '}'".to_string(),
-                                line: line_num + 1,
-                                column: col + 1,
-                                snippet: Some(line.to_string()),
-                            });
-                        }
-                    }
-                    _ => {}
+        let edited = splice(code, start, end, new_code);
+        self.ensure_parses(code, edited, language)
+    }
+
+    /// Replace the body of function `name`.
+    /// `new_body` may be a full block (`{ ... }`) or just the statements inside it.
+    pub fn replace_function_body(
+        &self,
+        code: &str,
+        language: SupportedLanguage,
+        name: &str,
+        new_body: &str,
+    ) -> Result<String, String> {
+        let tree = self.parse(code, language)?;
+        let definition = find_definition(tree.root_node(), code, language, name, |kind| {
+            kind == "function" || kind == "method"
+        })
+        .ok_or_else(|| format!("Function '{}' not found", name))?;
+
+        // Arrow functions assigned to a variable keep their body on the value node
+        let function = if definition.kind() == "variable_declarator" {
+            definition
+                .child_by_field_name("value")
+                .unwrap_or(definition)
+        } else {
+            definition
+        };
+        let body = function
+            .child_by_field_name("body")
+            .ok_or_else(|| format!("Function '{}' has no body", name))?;
+
+        let replacement = if language == SupportedLanguage::Python {
+            // Python bodies start after the colon; indentation is significant
+            let indent = " ".repeat(body.start_position().column);
+            reindent(new_body, &indent).trim_start().to_string()
+        } else if new_body.trim_start().starts_with('{') {
+            new_body.trim().to_string()
+        } else {
+            let outer = line_indent(code, definition.start_byte());
+            let inner = format!("{}{}", outer, language.indent_unit());
+            format!("{{\n{}\n{}}}", reindent(new_body, &inner), outer)
+        };
+
+        let edited = splice(code, body.start_byte(), body.end_byte(), &replacement);
+        self.ensure_parses(code, edited, language)
+    }
+
+    /// Insert a method into the body of type `type_name`:
+    /// the inherent `impl` block in Rust, the class body in TS/JS/Python.
+    /// Go methods are top-level, so they are appended after the file's last declaration.
+    pub fn insert_method(
+        &self,
+        code: &str,
+        language: SupportedLanguage,
+        type_name: &str,
+        method_code: &str,
+    ) -> Result<String, String> {
+        let tree = self.parse(code, language)?;
+        let root = tree.root_node();
+
+        let edited = match language {
+            SupportedLanguage::Rust => match find_inherent_impl(root, code, type_name) {
+                Some(impl_node) => {
+                    let body = impl_node
+                        .child_by_field_name("body")
+                        .ok_or_else(|| format!("impl {} has no body", type_name))?;
+                    insert_into_braces(
+                        code,
+                        body,
+                        line_indent(code, impl_node.start_byte()),
+                        method_code,
+                        language,
+                    )
                 }
+                None => {
+                    let type_def = find_definition(root, code, language, type_name, |kind| {
+                        !matches!(kind, "function" | "method")
+                    })
+                    .ok_or_else(|| format!("Type '{}' not found", type_name))?;
+                    let unit = language.indent_unit();
+                    let block = format!(
+                        "\n\nimpl {} {{\n{}\n}}",
+                        type_name,
+                        reindent(method_code, unit)
+                    );
+                    splice(code, type_def.end_byte(), type_def.end_byte(), &block)
+                }
+            },
+            SupportedLanguage::TypeScript
+            | SupportedLanguage::Tsx
+            | SupportedLanguage::JavaScript => {
+                let class =
+                    find_definition(root, code, language, type_name, |kind| kind == "class")
+                        .ok_or_else(|| format!("Class '{}' not found", type_name))?;
+                let body = class
+                    .child_by_field_name("body")
+                    .ok_or_else(|| format!("Class '{}' has no body", type_name))?;
+                insert_into_braces(
+                    code,
+                    body,
+                    line_indent(code, class.start_byte()),
+                    method_code,
+                    language,
+                )
             }
+            SupportedLanguage::Python => {
+                let class =
+                    find_definition(root, code, language, type_name, |kind| kind == "class")
+                        .ok_or_else(|| format!("Class '{}' not found", type_name))?;
+                let body = class
+                    .child_by_field_name("body")
+                    .ok_or_else(|| format!("Class '{}' has no body", type_name))?;
+                let indent = " ".repeat(body.start_position().column);
+                let block = format!("\n\n{}", reindent(method_code, &indent));
+                splice(code, body.end_byte(), body.end_byte(), &block)
+            }
+            SupportedLanguage::Go => {
+                find_definition(root, code, language, type_name, |kind| {
+                    !matches!(kind, "function" | "method")
+                })
+                .ok_or_else(|| format!("Type '{}' not found", type_name))?;
+                format!("{}\n\n{}\n", code.trim_end(), reindent(method_code, ""))
+            }
+        };
+
+        self.ensure_parses(code, edited, language)
+    }
+
+    /// Rename every identifier named `old_name` in the file.
+    /// Strings and comments are left untouched. Returns the new source and the number of renames.
+    pub fn rename_symbol(
+        &self,
+        code: &str,
+        language: SupportedLanguage,
+        old_name: &str,
+        new_name: &str,
+    ) -> Result<(String, usize), String> {
+        let is_identifier = !new_name.is_empty()
+            && !new_name.starts_with(|c: char| c.is_ascii_digit())
+            && new_name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+        if !is_identifier {
+            return Err(format!("'{}' is not a valid identifier", new_name));
         }
 
-        // Report unclosed brackets
-        for (ch, line, col) in stack {
-            errors.push(SyntaxError {
-                message: format!("Unclosed bracket '{}'", ch),
-                line,
-                column: col,
-                snippet: None,
-            });
+        let tree = self.parse(code, language)?;
+        let mut ranges = Vec::new();
+        collect_identifiers(tree.root_node(), code, old_name, &mut ranges);
+
+        let mut edited = code.to_string();
+        for (start, end) in ranges.iter().rev() {
+            edited.replace_range(*start..*end, new_name);
         }
 
-        errors
+        let edited = self.ensure_parses(code, edited, language)?;
+        Ok((edited, ranges.len()))
     }
 
-    /// Rust-specific syntax checks
-    fn check_rust_syntax(&self, code: &str) -> Vec<SyntaxError> {
-        let mut errors = Vec::new();
-
-        for (line_num, line) in code.lines().enumerate() {
-            let trimmed = line.trim();
+    /// Apply an ast-grep rewrite rule: every match of `pattern` is replaced by `fix`.
+    /// Meta-variables (`$NAME`, `$$$ARGS`) captured by the pattern can be used in the fix.
+    /// Returns the new source and the number of replacements.
+    pub fn apply_rewrite(
+        &self,
+        code: &str,
+        language: SupportedLanguage,
+        pattern: &str,
+        fix: &str,
+    ) -> Result<(String, usize), String> {
+        let pattern = Pattern::try_new(pattern, language)
+            .map_err(|e| format!("Invalid {:?} pattern '{}': {}", language, pattern, e))?;
+
+        let grep = language.ast_grep(code);
+        let mut edits = grep.root().replace_all(pattern, fix);
+        if edits.is_empty() {
+            return Ok((code.to_string(), 0));
+        }
 
-            // Check for missing semicolons on statements (heuristic)
-            if (trimmed.starts_with("let ") || trimmed.starts_with("use "))
-                && !trimmed.ends_with(';')
-                && !trimmed.ends_with('{')
-                && !trimmed.contains("//")
-            {
-                errors.push(SyntaxError {
-                    message: "Possible missing semicolon".to_string(),
-                    line: line_num + 1,
-                    column: line.len(),
-                    snippet: Some(line.to_string()),
-                });
-            }
+        // Apply back to front so earlier byte positions stay valid
+        edits.sort_by_key(|edit| edit.position);
+        let count = edits.len();
+        let mut edited = code.to_string();
+        for edit in edits.iter().rev() {
+            let inserted = String::from_utf8_lossy(&edit.inserted_text);
+            edited.replace_range(
+                edit.position..edit.position + edit.deleted_length,
+                &inserted,
+            );
         }
 
-        errors
+        let edited = self.ensure_parses(code, edited, language)?;
+        Ok((edited, count))
     }
 
-    /// JavaScript/TypeScript-specific syntax checks
-    fn check_js_ts_syntax(&self, code: &str) -> Vec<SyntaxError> {
-        let errors = Vec::new();
+    /// Reject an edit that breaks parsing of code that parsed before the edit
+    fn ensure_parses(
+        &self,
+        original: &str,
+        edited: String,
+        language: SupportedLanguage,
+    ) -> Result<String, String> {
+        let result = self.validate_syntax(&edited, language);
+        if result.is_valid || !self.validate_syntax(original, language).is_valid {
+            return Ok(edited);
+        }
+
+        let details: Vec<String> = result
+            .errors
+            .iter()
+            .map(|e| format!("{}:{} {}", e.line, e.column, e.message))
+            .collect();
+        Err(format!(
+            "Edit would introduce syntax errors: {}",
+            details.join("; ")
+        ))
+    }
 
-        for (_line_num, line) in code.lines().enumerate() {
-            let trimmed = line.trim();
+    /// Check if code contains a specific text pattern
+    pub fn contains_pattern(
+        &self,
+        code: &str,
+        pattern: &str,
+        _language: SupportedLanguage,
+    ) -> bool {
+        code.contains(pattern)
+    }
+}
 
-            // Check for common JS/TS issues
-            if trimmed.contains("===") && trimmed.contains("==") && !trimmed.contains("!==") {
-                // Mixed equality operators might be intentional, just a warning
+/// Walk the subtrees that contain errors and record ERROR/MISSING nodes.
+/// Children of an ERROR node are not reported separately.
+fn collect_syntax_errors(node: Node, code: &str, errors: &mut Vec<SyntaxError>) {
+    if node.is_missing() || node.is_error() {
+        let pos = node.start_position();
+        let line_text = code.lines().nth(pos.row).unwrap_or("");
+        let message = if node.is_missing() {
+            format!("Missing '{}'", node.kind())
+        } else {
+            let text = node.utf8_text(code.as_bytes()).unwrap_or("");
+            let first_line = text.lines().next().unwrap_or("").trim();
+            if first_line.is_empty() {
+                "Unexpected syntax".to_string()
+            } else {
+                format!("Unexpected syntax near '{}'", truncate(first_line, 40))
             }
-        }
+        };
+
+        errors.push(SyntaxError {
+            message,
+            line: pos.row + 1,
+            column: pos.column + 1,
+            snippet: Some(line_text.to_string()),
+        });
+        return;
+    }
 
-        errors
-    }
-
-    /// Python-specific syntax checks
-    fn check_python_syntax(&self, code: &str) -> Vec<SyntaxError> {
-        let mut errors = Vec::new();
-
-        for (line_num, line) in code.lines().enumerate() {
-            let trimmed = line.trim();
-
-            // Check for missing colons after def/class/if/for/while
-            let keywords = [
-                "def ", "class ", "if ", "elif ", "else", "for ", "while ", "try", "except",
-                "finally", "with ",
-            ];
-            for kw in keywords {
-                if trimmed.starts_with(kw) && !trimmed.ends_with(':') && !trimmed.contains('#') {
-                    errors.push(SyntaxError {
-                        message: format!("Possible missing colon after '{}'", kw.trim()),
-                        line: line_num + 1,
-                        column: line.len(),
-                        snippet: Some(line.to_string()),
-                    });
-                }
-            }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.has_error() {
+            collect_syntax_errors(child, code, errors);
         }
-
-        errors
     }
+}
 
-    /// Go-specific syntax checks
-    fn check_go_syntax(&self, _code: &str) -> Vec<SyntaxError> {
-        // Go has fewer common syntax issues due to gofmt
-        Vec::new()
+/// Recursively collect named definitions from the tree
+fn collect_symbols(
+    node: Node,
+    code: &str,
+    language: SupportedLanguage,
+    symbols: &mut Vec<CodeSymbol>,
+) {
+    if let Some(kind) = symbol_kind(node, language) {
+        // For `const add = (a, b) => a + b` the name lives on the variable_declarator
+        let name_node = node.child_by_field_name("name");
+        if let Some(name) = name_node.and_then(|n| n.utf8_text(code.as_bytes()).ok()) {
+            symbols.push(CodeSymbol {
+                name: name.to_string(),
+                kind: kind.to_string(),
+                start_line: node.start_position().row + 1,
+                end_line: node.end_position().row + 1,
+                byte_range: (node.start_byte(), node.end_byte()),
+            });
+        }
     }
 
-    /// Count code structures (functions, classes, etc.)
-    fn count_structures(&self, code: &str, language: SupportedLanguage) -> usize {
-        let mut count = 0;
-
-        for line in code.lines() {
-            let trimmed = line.trim();
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_symbols(child, code, language, symbols);
+    }
+}
 
-            match language {
-                SupportedLanguage::Rust => {
-                    if trimmed.starts_with("fn ")
-                        || trimmed.starts_with("pub fn ")
-                        || trimmed.starts_with("struct ")
-                        || trimmed.starts_with("pub struct ")
-                        || trimmed.starts_with("enum ")
-                        || trimmed.starts_with("pub enum ")
-                        || trimmed.starts_with("impl ")
-                        || trimmed.starts_with("trait ")
-                    {
-                        count += 1;
-                    }
-                }
-                SupportedLanguage::TypeScript | SupportedLanguage::JavaScript => {
-                    if trimmed.starts_with("function ")
-                        || trimmed.contains("=> {")
-                        || trimmed.starts_with("class ")
-                        || trimmed.starts_with("interface ")
-                    {
-                        count += 1;
-                    }
-                }
-                SupportedLanguage::Python => {
-                    if trimmed.starts_with("def ") || trimmed.starts_with("class ") {
-                        count += 1;
-                    }
-                }
-                SupportedLanguage::Go => {
-                    if trimmed.starts_with("func ") || trimmed.starts_with("type ") {
-                        count += 1;
+/// Map a tree-sitter node kind to a symbol kind for the given language
+fn symbol_kind(node: Node, language: SupportedLanguage) -> Option<&'static str> {
+    match language {
+        SupportedLanguage::Rust => match node.kind() {
+            "function_item" | "function_signature_item" => {
+                let in_impl = matches!(enclosing_kind(node), Some("impl_item" | "trait_item"));
+                Some(if in_impl { "method" } else { "function" })
+            }
+            "struct_item" => Some("struct"),
+            "enum_item" => Some("enum"),
+            "trait_item" => Some("trait"),
+            "type_item" => Some("type"),
+            _ => None,
+        },
+        SupportedLanguage::TypeScript | SupportedLanguage::Tsx | SupportedLanguage::JavaScript => {
+            match node.kind() {
+                "function_declaration" | "generator_function_declaration" => Some("function"),
+                "method_definition" => Some("method"),
+                "variable_declarator" => {
+                    let value = node.child_by_field_name("value")?;
+                    match value.kind() {
+                        "arrow_function" | "function_expression" | "function" => Some("function"),
+                        _ => None,
                     }
                 }
+                "class_declaration" | "abstract_class_declaration" => Some("class"),
+                "interface_declaration" => Some("interface"),
+                "type_alias_declaration" => Some("type"),
+                "enum_declaration" => Some("enum"),
+                _ => None,
             }
         }
+        SupportedLanguage::Python => match node.kind() {
+            "function_definition" => {
+                let in_class = enclosing_kind(node) == Some("class_definition");
+                Some(if in_class { "method" } else { "function" })
+            }
+            "class_definition" => Some("class"),
+            _ => None,
+        },
+        SupportedLanguage::Go => match node.kind() {
+            "function_declaration" => Some("function"),
+            "method_declaration" => Some("method"),
+            "type_spec" => match node.child_by_field_name("type").map(|t| t.kind()) {
+                Some("struct_type") => Some("struct"),
+                Some("interface_type") => Some("interface"),
+                _ => Some("type"),
+            },
+            _ => None,
+        },
+    }
+}
 
-        count
+/// Kind of the nearest enclosing definition, skipping body and decorator wrappers
+fn enclosing_kind(node: Node) -> Option<&'static str> {
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        match parent.kind() {
+            "block" | "declaration_list" | "decorated_definition" => current = parent.parent(),
+            kind => return Some(kind),
+        }
     }
+    None
+}
 
-    /// Extract function/method names from code using pattern matching
-    pub fn extract_functions(&self, code: &str, language: SupportedLanguage) -> Vec<String> {
-        let mut functions = Vec::new();
+/// Find the first definition named `name` whose symbol kind passes `accept`
+fn find_definition<'t>(
+    node: Node<'t>,
+    code: &str,
+    language: SupportedLanguage,
+    name: &str,
+    accept: impl Fn(&str) -> bool + Copy,
+) -> Option<Node<'t>> {
+    if let Some(kind) = symbol_kind(node, language) {
+        let matches_name = node
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(code.as_bytes()).ok())
+            == Some(name);
+        if matches_name && accept(kind) {
+            return Some(node);
+        }
+    }
 
-        for line in code.lines() {
-            let trimmed = line.trim();
+    let mut cursor = node.walk();
+    let children: Vec<Node<'t>> = node.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| find_definition(child, code, language, name, accept))
+}
 
-            if let Some(name) = self.extract_function_name(trimmed, language) {
-                functions.push(name);
-            }
+/// Find the `impl Type { ... }` block (not a trait impl) for a Rust type
+fn find_inherent_impl<'t>(node: Node<'t>, code: &str, type_name: &str) -> Option<Node<'t>> {
+    if node.kind() == "impl_item" && node.child_by_field_name("trait").is_none() {
+        let impl_type = node
+            .child_by_field_name("type")
+            .and_then(|t| t.utf8_text(code.as_bytes()).ok())
+            .map(|t| t.split('<').next().unwrap_or(t).trim());
+        if impl_type == Some(type_name) {
+            return Some(node);
         }
-
-        functions
     }
 
-    /// Extract function name from a line based on language
-    fn extract_function_name(&self, line: &str, language: SupportedLanguage) -> Option<String> {
-        match language {
-            SupportedLanguage::Rust => {
-                if line.starts_with("fn ") || line.starts_with("pub fn ") {
-                    let after_fn = line.split("fn ").nth(1)?;
-                    let name = after_fn.split('(').next()?.trim();
-                    return Some(name.to_string());
-                }
-            }
-            SupportedLanguage::TypeScript | SupportedLanguage::JavaScript => {
-                if line.starts_with("function ") || line.contains("function ") {
-                    let after_fn = line.split("function ").nth(1)?;
-                    let name = after_fn.split('(').next()?.trim();
-                    return Some(name.to_string());
-                }
-                // Arrow functions with const
-                if line.contains("const ") && line.contains(" = ") && line.contains("=>") {
-                    let after_const = line.split("const ").nth(1)?;
-                    let name = after_const.split(&[' ', ':', '='][..]).next()?.trim();
-                    return Some(name.to_string());
-                }
-            }
-            SupportedLanguage::Python => {
-                if line.starts_with("def ") {
-                    let after_def = line.split("def ").nth(1)?;
-                    let name = after_def.split('(').next()?.trim();
-                    return Some(name.to_string());
-                }
-            }
-            SupportedLanguage::Go => {
-                if line.starts_with("func ") {
-                    let after_func = line.split("func ").nth(1)?;
-                    // Handle method receivers: func (r *Receiver) Name()
-                    let name_part = if after_func.starts_with('(') {
-                        after_func.split(')').nth(1)?.trim()
-                    } else {
-                        after_func
-                    };
-                    let name = name_part.split('(').next()?.trim();
-                    return Some(name.to_string());
-                }
-            }
+    let mut cursor = node.walk();
+    let children: Vec<Node<'t>> = node.named_children(&mut cursor).collect();
+    children
+        .into_iter()
+        .find_map(|child| find_inherent_impl(child, code, type_name))
+}
+
+/// Collect byte ranges of identifier leaves whose text is `name`
+fn collect_identifiers(node: Node, code: &str, name: &str, ranges: &mut Vec<(usize, usize)>) {
+    if node.child_count() == 0 {
+        if node.kind().ends_with("identifier") && node.utf8_text(code.as_bytes()).ok() == Some(name)
+        {
+            ranges.push((node.start_byte(), node.end_byte()));
         }
-        None
+        return;
     }
 
-    /// Check if code contains a specific text pattern
-    pub fn contains_pattern(
-        &self,
-        code: &str,
-        pattern: &str,
-        _language: SupportedLanguage,
-    ) -> bool {
-        code.contains(pattern)
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_identifiers(child, code, name, ranges);
+    }
+}
+
+/// Insert `item` as the last entry of a `{ ... }` body node
+fn insert_into_braces(
+    code: &str,
+    body: Node,
+    outer_indent: &str,
+    item: &str,
+    language: SupportedLanguage,
+) -> String {
+    let close = body.end_byte().saturating_sub(1);
+    let content_end = code[body.start_byte()..close].trim_end().len() + body.start_byte();
+    let separator = if content_end == body.start_byte() + 1 {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    let inner = format!("{}{}", outer_indent, language.indent_unit());
+    let insertion = format!("{}{}\n{}", separator, reindent(item, &inner), outer_indent);
+    splice(code, content_end, close, &insertion)
+}
+
+/// Replace `code[start..end]` with `replacement`
+fn splice(code: &str, start: usize, end: usize, replacement: &str) -> String {
+    let mut out = String::with_capacity(code.len() + replacement.len());
+    out.push_str(&code[..start]);
+    out.push_str(replacement);
+    out.push_str(&code[end..]);
+    out
+}
+
+/// Leading whitespace of the line containing byte `pos`
+fn line_indent(code: &str, pos: usize) -> &str {
+    let line_start = code[..pos].rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let line = &code[line_start..];
+    let indent_len = line.len() - line.trim_start_matches([' ', '\t']).len();
+    &line[..indent_len]
+}
+
+/// Strip the common indentation of `text` and re-indent every non-empty line with `indent`
+fn reindent(text: &str, indent: &str) -> String {
+    let lines: Vec<&str> = text.trim_matches('\n').lines().collect();
+    let common = lines
+        .iter()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|l| {
+            if l.trim().is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", indent, l[common..].trim_end())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Truncate a string to at most `max` characters for error messages
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        s.to_string()
+    } else {
+        format!("{}...", s.chars().take(max).collect::<String>())
     }
 }
 
@@ -498,4 +848,159 @@ fn bar(x: i32) -> i32 { x }
         assert!(functions.contains(&"foo".to_string()));
         assert!(functions.contains(&"bar".to_string()));
     }
+
+    #[test]
+    fn test_validate_rust_syntax_reports_position() {
+        let editor = AstEditor::new();
+        let code = "fn main() {\n    let x = 1\n    let y = 2;\n}\n";
+        let result = editor.validate_syntax(code, SupportedLanguage::Rust);
+        assert!(!result.is_valid);
+        let error = &result.errors[0];
+        assert_eq!(error.line, 2);
+        assert!(error.column > 1);
+    }
+
+    #[test]
+    fn test_validate_python_missing_colon() {
+        let editor = AstEditor::new();
+        let result = editor.validate_syntax("def foo()\n    return 1\n", SupportedLanguage::Python);
+        assert!(!result.is_valid);
+        assert_eq!(result.errors[0].line, 1);
+
+        let result =
+            editor.validate_syntax("def foo():\n    return 1\n", SupportedLanguage::Python);
+        assert!(result.is_valid);
+        assert!(result.node_count > 0);
+    }
+
+    #[test]
+    fn test_extract_symbols_from_parse_tree() {
+        let editor = AstEditor::new();
+        let code = r#"
+pub struct Counter { n: u32 }
+
+impl Counter {
+    pub fn increment(&mut self) { self.n += 1; }
+}
+
+// fn commented_out() {}
+"#;
+        let symbols = editor.extract_symbols(code, SupportedLanguage::Rust);
+        let names: Vec<_> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind.as_str()))
+            .collect();
+        assert_eq!(names, vec![("Counter", "struct"), ("increment", "method")]);
+        assert_eq!(
+            editor.extract_types(code, SupportedLanguage::Rust),
+            vec!["Counter"]
+        );
+
+        let ts =
+            "export class Greeter { greet() {} }\nconst add = (a: number, b: number) => a + b;\n";
+        let functions = editor.extract_functions(ts, SupportedLanguage::TypeScript);
+        assert_eq!(functions, vec!["greet", "add"]);
+    }
+
+    #[test]
+    fn test_replace_function_body() {
+        let editor = AstEditor::new();
+        let code = "impl Calc {\n    fn double(&self, x: i32) -> i32 {\n        x + x\n    }\n}\n";
+        let edited = editor
+            .replace_function_body(code, SupportedLanguage::Rust, "double", "x * 2")
+            .unwrap();
+        assert_eq!(
+            edited,
+            "impl Calc {\n    fn double(&self, x: i32) -> i32 {\n        x * 2\n    }\n}\n"
+        );
+
+        let py = "class A:\n    def f(self):\n        return 1\n";
+        let edited = editor
+            .replace_function_body(py, SupportedLanguage::Python, "f", "y = 2\nreturn y")
+            .unwrap();
+        assert_eq!(
+            edited,
+            "class A:\n    def f(self):\n        y = 2\n        return y\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_method() {
+        let editor = AstEditor::new();
+        let code =
+            "struct Point { x: i32 }\n\nimpl Point {\n    fn x(&self) -> i32 { self.x }\n}\n";
+        let edited = editor
+            .insert_method(
+                code,
+                SupportedLanguage::Rust,
+                "Point",
+                "fn reset(&mut self) {\n    self.x = 0;\n}",
+            )
+            .unwrap();
+        assert_eq!(
+            edited,
+            "struct Point { x: i32 }\n\nimpl Point {\n    fn x(&self) -> i32 { self.x }\n\n    fn reset(&mut self) {\n        self.x = 0;\n    }\n}\n"
+        );
+
+        // No inherent impl yet: one is created after the type
+        let edited = editor
+            .insert_method(
+                "struct Unit;\n",
+                SupportedLanguage::Rust,
+                "Unit",
+                "fn new() -> Self { Unit }",
+            )
+            .unwrap();
+        assert_eq!(
+            edited,
+            "struct Unit;\n\nimpl Unit {\n    fn new() -> Self { Unit }\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_rename_symbol_skips_strings_and_comments() {
+        let editor = AstEditor::new();
+        let code = "fn total(v: &[i32]) -> i32 { v.iter().sum() }\n// total is documented\nfn main() { let t = total(&[1]); println!(\"total\"); }\n";
+        let (edited, count) = editor
+            .rename_symbol(code, SupportedLanguage::Rust, "total", "sum_all")
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(edited.contains("fn sum_all(v"));
+        assert!(edited.contains("let t = sum_all(&[1])"));
+        assert!(edited.contains("// total is documented"));
+        assert!(edited.contains("\"total\""));
+    }
+
+    #[test]
+    fn test_edit_rejected_when_it_breaks_parsing() {
+        let editor = AstEditor::new();
+        let code = "fn a() {}\n";
+        let range = editor
+            .find_symbol(code, SupportedLanguage::Rust, "a")
+            .unwrap()
+            .byte_range;
+        assert!(editor
+            .replace_range(code, SupportedLanguage::Rust, range, "fn a( {")
+            .is_err());
+        let edited = editor
+            .replace_range(code, SupportedLanguage::Rust, range, "fn a() -> u8 { 1 }")
+            .unwrap();
+        assert_eq!(edited, "fn a() -> u8 { 1 }\n");
+    }
+
+    #[test]
+    fn test_apply_rewrite_with_meta_variables() {
+        let editor = AstEditor::new();
+        let code = "var a = 1;\nvar b = a + 1;\n";
+        let (edited, count) = editor
+            .apply_rewrite(
+                code,
+                SupportedLanguage::JavaScript,
+                "var $A = $B",
+                "let $A = $B",
+            )
+            .unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(edited, "let a = 1;\nlet b = a + 1;\n");
+    }
 }
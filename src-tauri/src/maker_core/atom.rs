@@ -2,6 +2,7 @@
 // PRD Section 4.2: AtomType - Strictly typed worker definitions
 
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use std::collections::HashMap;
 
 /// Atom types for the MAKER framework
@@ -82,6 +83,92 @@ impl AtomType {
         }
     }
 
+    /// JSON Schema for this atom type's output, or None for atoms that return code
+    /// These mirror the shapes requested in `system_prompt`; outputs are validated against
+    /// them when JSON is required (and always for Reviewer and Validator JSON).
+    pub fn output_schema(&self) -> Option<Value> {
+        let strings = json!({ "type": "array", "items": { "type": "string" } });
+        let schema = match self {
+            AtomType::Coder | AtomType::Tester => return None,
+            AtomType::Search => json!({
+                "type": "object",
+                "properties": {
+                    "files": strings,
+                    "snippets": { "type": "array" }
+                },
+                "required": ["files", "snippets"]
+            }),
+            AtomType::Reviewer => json!({
+                "type": "object",
+                "properties": {
+                    "approved": { "type": "boolean" },
+                    "issues": strings,
+                    "suggestions": strings
+                },
+                "required": ["approved", "issues"]
+            }),
+            AtomType::Planner => json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": ["string", "integer"] },
+                        "description": { "type": "string" },
+                        "dependencies": { "type": "array" }
+                    },
+                    "required": ["id", "description", "dependencies"]
+                }
+            }),
+            AtomType::Validator => json!({
+                "type": "object",
+                "properties": {
+                    "valid": { "type": "boolean" },
+                    "violations": strings,
+                    "score": { "type": "number" }
+                },
+                "required": ["valid", "violations"]
+            }),
+            AtomType::Architect => json!({
+                "type": "object",
+                "properties": {
+                    "interfaces": { "type": "array" },
+                    "structs": { "type": "array" },
+                    "relationships": { "type": "array" }
+                },
+                "required": ["interfaces", "structs", "relationships"]
+            }),
+            AtomType::GritsAnalyzer => json!({
+                "type": "object",
+                "properties": {
+                    "cycles": { "type": "array" },
+                    "layers": { "type": "array" },
+                    "violations": { "type": "array" },
+                    "red_flags": { "type": "array" }
+                },
+                "required": ["cycles", "layers", "violations", "red_flags"]
+            }),
+            AtomType::RLMProcessor => json!({
+                "type": "object",
+                "properties": {
+                    "answer": {},
+                    "iterations": { "type": "integer" },
+                    "sub_calls": { "type": "integer" }
+                },
+                "required": ["answer"]
+            }),
+            AtomType::WebResearcher => json!({
+                "type": "object",
+                "properties": {
+                    "sources": { "type": "array" },
+                    "findings": { "type": "array" },
+                    "summary": { "type": "string" }
+                },
+                "required": ["sources", "findings", "summary"]
+            }),
+        };
+        Some(schema)
+    }
+
     /// Get the max output tokens for this atom type
     pub fn max_tokens(&self) -> usize {
         match self {
@@ -145,8 +232,17 @@ pub struct AtomResult {
     pub errors: Vec<String>,
     /// Execution time in milliseconds
     pub execution_time_ms: u64,
-    /// Token usage
+    /// Token usage (prompt + completion)
     pub tokens_used: usize,
+    /// Prompt tokens reported by the provider
+    #[serde(default)]
+    pub prompt_tokens: usize,
+    /// Completion tokens reported by the provider
+    #[serde(default)]
+    pub completion_tokens: usize,
+    /// Cost in US dollars, from the pricing table
+    #[serde(default)]
+    pub cost_usd: f64,
     /// Metadata from execution
     pub metadata: HashMap<String, String>,
 }
@@ -161,6 +257,9 @@ impl AtomResult {
             errors: Vec::new(),
             execution_time_ms,
             tokens_used,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost_usd: 0.0,
             metadata: HashMap::new(),
         }
     }
@@ -174,6 +273,9 @@ impl AtomResult {
             errors,
             execution_time_ms: 0,
             tokens_used: 0,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            cost_usd: 0.0,
             metadata: HashMap::new(),
         }
     }
@@ -191,7 +293,9 @@ impl AtomResult {
 }
 
 /// Spawn flags for atom execution
+/// Fields missing when deserializing (e.g. from a script map) keep their defaults.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SpawnFlags {
     /// Require JSON output
     pub require_json: bool,
@@ -201,6 +305,16 @@ pub struct SpawnFlags {
     pub temperature: f32,
     /// Enable red-flag checking
     pub red_flag_check: bool,
+    /// JSON Schema for a custom atom's output; replaces the atom type's schema and implies JSON
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+    /// Round-trips allowed to fix output that is not valid JSON or fails its schema
+    #[serde(default = "default_max_repairs")]
+    pub max_repairs: u32,
+}
+
+fn default_max_repairs() -> u32 {
+    1
 }
 
 impl Default for SpawnFlags {
@@ -210,11 +324,25 @@ impl Default for SpawnFlags {
             max_tokens: None,
             temperature: 0.1,
             red_flag_check: true,
+            output_schema: None,
+            max_repairs: default_max_repairs(),
         }
     }
 }
 
 impl SpawnFlags {
+    /// Whether the output must be JSON
+    pub fn expects_json(&self) -> bool {
+        self.require_json || self.output_schema.is_some()
+    }
+
+    /// Schema the output is validated against: the custom one, else the atom type's
+    pub fn schema_for(&self, atom_type: AtomType) -> Option<Value> {
+        self.output_schema
+            .clone()
+            .or_else(|| atom_type.output_schema())
+    }
+
     /// HIGH-11: Validate spawn flags before execution
     pub fn validate(&self) -> Result<(), String> {
         // Temperature must be between 0.0 and 2.0
@@ -238,6 +366,11 @@ impl SpawnFlags {
             }
         }
 
+        if let Some(schema) = &self.output_schema {
+            jsonschema::validator_for(schema)
+                .map_err(|e| format!("Invalid output_schema: {}", e))?;
+        }
+
         Ok(())
     }
 
@@ -253,6 +386,7 @@ impl SpawnFlags {
             max_tokens,
             temperature,
             red_flag_check,
+            ..Default::default()
         };
         flags.validate()?;
         Ok(flags)
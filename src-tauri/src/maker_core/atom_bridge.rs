@@ -17,13 +17,20 @@ use std::sync::{Arc, Mutex};
 use tokio::sync::{mpsc, oneshot};
 use once_cell::sync::Lazy;
 
-use crate::agents::atom_executor::{AtomExecutor, AtomInput};
+use crate::agents::atom_executor::{AtomExecutor, AtomInput, StreamOptions};
+use crate::agents::response_cache::ResponseCache;
 use crate::llm::LlmConfig;
 use crate::maker_core::AtomResult;
 
 /// Atom-specific request for executing atoms
 pub struct AtomRequest {
     pub input: AtomInput,
+    /// Run with this config instead of the pool's default (per-agent routing)
+    pub llm_config: Option<LlmConfig>,
+    /// Serve the atom from this response cache when it has a fresh entry
+    pub cache: Option<ResponseCache>,
+    /// Stream the atom's output when set
+    pub stream: Option<StreamOptions>,
     pub response_tx: oneshot::Sender<Result<AtomResult, String>>,
 }
 
@@ -41,6 +48,7 @@ impl AtomWorkerPool {
 
         let executor = Arc::new(AtomExecutor::new((*llm_config).clone()));
         let worker_executor = executor.clone();
+        let default_config = (*llm_config).clone();
 
         // Spawn dedicated runtime thread that lives for application lifetime
         std::thread::Builder::new()
@@ -61,9 +69,19 @@ impl AtomWorkerPool {
 
                 rt.block_on(async move {
                     while let Some(request) = atom_rx.recv().await {
-                        let exec = worker_executor.clone();
+                        let exec = match (request.llm_config, request.cache) {
+                            (Some(config), None) => Arc::new(AtomExecutor::new(config)),
+                            (config, Some(cache)) => Arc::new(
+                                AtomExecutor::new(config.unwrap_or_else(|| default_config.clone()))
+                                    .with_cache(cache),
+                            ),
+                            (None, None) => worker_executor.clone(),
+                        };
                         tokio::spawn(async move {
-                            let result = exec.execute(request.input).await;
+                            let result = match request.stream {
+                                Some(options) => exec.execute_stream(request.input, options).await,
+                                None => exec.execute(request.input).await,
+                            };
                             let _ = request.response_tx.send(result);
                         });
                     }
@@ -79,10 +97,33 @@ impl AtomWorkerPool {
     
     /// Execute an atom synchronously (safe to call from Rhai)
     pub fn execute_atom_sync(&self, input: AtomInput) -> Result<AtomResult, String> {
+        self.send_request(input, None, None, None)
+    }
+
+    /// Execute an atom synchronously, streaming its output through `options`
+    /// `llm_config` overrides the pool's default config for this atom only; `cache` serves it
+    /// from a response cache.
+    pub fn execute_atom_stream_sync(
+        &self,
+        input: AtomInput,
+        llm_config: Option<LlmConfig>,
+        cache: Option<ResponseCache>,
+        options: StreamOptions,
+    ) -> Result<AtomResult, String> {
+        self.send_request(input, llm_config, cache, Some(options))
+    }
+
+    fn send_request(
+        &self,
+        input: AtomInput,
+        llm_config: Option<LlmConfig>,
+        cache: Option<ResponseCache>,
+        stream: Option<StreamOptions>,
+    ) -> Result<AtomResult, String> {
         let (response_tx, response_rx) = oneshot::channel();
         
         // Send to worker pool (non-blocking send, but we block on receive)
-        self.atom_tx.blocking_send(AtomRequest { input, response_tx })
+        self.atom_tx.blocking_send(AtomRequest { input, llm_config, cache, stream, response_tx })
             .map_err(|e| format!("Failed to send to worker pool: {}", e))?;
         
         // Wait for response (blocking, but on the worker pool's dedicated thread)
@@ -125,6 +166,21 @@ pub fn execute_atom_sync(input: AtomInput) -> Result<AtomResult, String> {
         .execute_atom_sync(input)
 }
 
+/// Execute an atom synchronously using the global pool, streaming its output through `options`
+pub fn execute_atom_stream_sync(
+    input: AtomInput,
+    llm_config: Option<LlmConfig>,
+    cache: Option<ResponseCache>,
+    options: StreamOptions,
+) -> Result<AtomResult, String> {
+    let pool = ATOM_POOL.lock()
+        .map_err(|_| "Failed to acquire atom pool lock")?;
+    
+    pool.as_ref()
+        .ok_or_else(|| "Atom pool not initialized. Call init_atom_pool first.".to_string())?
+        .execute_atom_stream_sync(input, llm_config, cache, options)
+}
+
 /// Get the executor for direct async operations
 pub fn get_executor() -> Result<Arc<AtomExecutor>, String> {
     let pool = ATOM_POOL.lock()
@@ -0,0 +1,276 @@
+// Cerebras-MAKER: Cost and Budget Accounting
+// Tracks token and dollar spend for a run and enforces hard limits on tokens, dollars and
+// atoms spawned. Consensus voting multiplies cost by the number of candidates, so the atom
+// limit is the guard against runaway `max_atoms` loops.
+
+use super::atom::AtomType;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+
+/// Hard limits for a run; `None` means unlimited
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub max_cost_usd: Option<f64>,
+    #[serde(default)]
+    pub max_atoms: Option<usize>,
+}
+
+impl Budget {
+    /// True if no limit is set
+    pub fn is_unlimited(&self) -> bool {
+        self.max_tokens.is_none() && self.max_cost_usd.is_none() && self.max_atoms.is_none()
+    }
+}
+
+/// Spend of one LLM operation (an atom, a consensus vote, an RLM call...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostEntry {
+    /// What spent it, e.g. "spawn_atom", "run_consensus" or a task ID
+    pub source: String,
+    pub atom_type: AtomType,
+    pub atoms: usize,
+    pub tokens: u64,
+    pub cost_usd: f64,
+}
+
+/// Running totals for a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Spend {
+    pub tokens: u64,
+    pub cost_usd: f64,
+    pub atoms: usize,
+    /// Every charged operation, in order
+    pub entries: Vec<CostEntry>,
+}
+
+/// Accumulates spend against a budget
+#[derive(Debug, Clone, Default)]
+pub struct BudgetTracker {
+    budget: Budget,
+    /// Limits `narrow` may not exceed (the budget the tracker was created with)
+    ceiling: Budget,
+    spend: Spend,
+    /// Set when an operation was refused; sticky until the budget is raised
+    refused: Option<String>,
+}
+
+/// Tracker shared between the runtime and its registered functions
+pub type SharedBudget = Arc<Mutex<BudgetTracker>>;
+
+impl BudgetTracker {
+    pub fn new(budget: Budget) -> Self {
+        Self {
+            ceiling: budget.clone(),
+            budget,
+            ..Default::default()
+        }
+    }
+
+    pub fn shared(budget: Budget) -> SharedBudget {
+        Arc::new(Mutex::new(Self::new(budget)))
+    }
+
+    pub fn budget(&self) -> &Budget {
+        &self.budget
+    }
+
+    /// Replace the limits and the ceiling; spend so far still counts against them
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.ceiling = budget.clone();
+        self.budget = budget;
+        self.refused = None;
+    }
+
+    /// Apply limits requested by untrusted code (a script): each limit is the tighter
+    /// of the request and the ceiling, and an unset request keeps the ceiling's limit
+    pub fn narrow(&mut self, requested: Budget) {
+        fn tighter<T: PartialOrd>(ceiling: Option<T>, requested: Option<T>) -> Option<T> {
+            match (ceiling, requested) {
+                (Some(c), Some(r)) => Some(if r < c { r } else { c }),
+                (c, r) => c.or(r),
+            }
+        }
+        let ceiling = &self.ceiling;
+        self.budget = Budget {
+            max_tokens: tighter(ceiling.max_tokens, requested.max_tokens),
+            max_cost_usd: tighter(ceiling.max_cost_usd, requested.max_cost_usd),
+            max_atoms: tighter(ceiling.max_atoms, requested.max_atoms),
+        };
+        self.refused = None;
+    }
+
+    pub fn spend(&self) -> &Spend {
+        &self.spend
+    }
+
+    /// Atoms that may still be spawned, or None if unlimited
+    pub fn remaining_atoms(&self) -> Option<usize> {
+        self.budget
+            .max_atoms
+            .map(|max| max.saturating_sub(self.spend.atoms))
+    }
+
+    /// Dollars that may still be spent, or None if unlimited
+    pub fn remaining_cost_usd(&self) -> Option<f64> {
+        self.budget
+            .max_cost_usd
+            .map(|max| (max - self.spend.cost_usd).max(0.0))
+    }
+
+    /// Check that at least one more atom may run
+    /// A refusal is remembered, so `exceeded` reports it until the budget changes.
+    pub fn admit(&mut self) -> Result<(), String> {
+        let reason = self.exceeded().or_else(|| {
+            (self.remaining_atoms() == Some(0)).then(|| {
+                format!(
+                    "Atom budget exhausted ({} of {} atoms spawned)",
+                    self.spend.atoms,
+                    self.budget.max_atoms.unwrap_or_default()
+                )
+            })
+        });
+        match reason {
+            Some(reason) => {
+                self.refused = Some(reason.clone());
+                Err(reason)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record the spend of an operation
+    pub fn charge(&mut self, entry: CostEntry) {
+        self.spend.tokens += entry.tokens;
+        self.spend.cost_usd += entry.cost_usd;
+        self.spend.atoms += entry.atoms;
+        self.spend.entries.push(entry);
+    }
+
+    /// Why the run must stop, or None while it is within budget
+    pub fn exceeded(&self) -> Option<String> {
+        if let Some(reason) = &self.refused {
+            return Some(reason.clone());
+        }
+        if let Some(max) = self.budget.max_tokens {
+            if self.spend.tokens > max {
+                return Some(format!(
+                    "Token budget exceeded ({} > {} tokens)",
+                    self.spend.tokens, max
+                ));
+            }
+        }
+        if let Some(max) = self.budget.max_cost_usd {
+            if self.spend.cost_usd > max {
+                return Some(format!(
+                    "Cost budget exceeded (${:.4} > ${:.4})",
+                    self.spend.cost_usd, max
+                ));
+            }
+        }
+        if let Some(max) = self.budget.max_atoms {
+            if self.spend.atoms > max {
+                return Some(format!(
+                    "Atom budget exceeded ({} > {} atoms)",
+                    self.spend.atoms, max
+                ));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(atoms: usize, tokens: u64, cost_usd: f64) -> CostEntry {
+        CostEntry {
+            source: "spawn_atom".to_string(),
+            atom_type: AtomType::Coder,
+            atoms,
+            tokens,
+            cost_usd,
+        }
+    }
+
+    #[test]
+    fn test_unlimited_budget_never_trips() {
+        let mut tracker = BudgetTracker::new(Budget::default());
+        tracker.charge(entry(100, 1_000_000, 50.0));
+        assert!(tracker.admit().is_ok());
+        assert!(tracker.exceeded().is_none());
+        assert_eq!(tracker.spend().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_limits_trip_on_tokens_and_dollars() {
+        let mut tracker = BudgetTracker::new(Budget {
+            max_tokens: Some(1_000),
+            max_cost_usd: Some(0.5),
+            max_atoms: None,
+        });
+        tracker.charge(entry(1, 900, 0.1));
+        assert!(tracker.exceeded().is_none());
+        tracker.charge(entry(1, 200, 0.1));
+        assert!(tracker.exceeded().unwrap().contains("Token budget"));
+
+        tracker.set_budget(Budget {
+            max_cost_usd: Some(0.15),
+            ..Default::default()
+        });
+        assert!(tracker.exceeded().unwrap().contains("Cost budget"));
+        assert_eq!(tracker.remaining_cost_usd(), Some(0.0));
+    }
+
+    #[test]
+    fn test_atom_limit_refuses_and_sticks() {
+        let mut tracker = BudgetTracker::new(Budget {
+            max_atoms: Some(3),
+            ..Default::default()
+        });
+        tracker.charge(entry(3, 10, 0.0));
+        assert_eq!(tracker.remaining_atoms(), Some(0));
+        assert!(tracker.exceeded().is_none());
+
+        assert!(tracker.admit().is_err());
+        assert!(tracker
+            .exceeded()
+            .unwrap()
+            .contains("Atom budget exhausted"));
+
+        // Raising the limit clears the refusal
+        tracker.set_budget(Budget {
+            max_atoms: Some(10),
+            ..Default::default()
+        });
+        assert!(tracker.admit().is_ok());
+        assert_eq!(tracker.remaining_atoms(), Some(7));
+    }
+
+    #[test]
+    fn test_narrow_never_exceeds_ceiling() {
+        let mut tracker = BudgetTracker::new(Budget {
+            max_tokens: Some(1_000),
+            max_cost_usd: None,
+            max_atoms: Some(5),
+        });
+
+        tracker.narrow(Budget {
+            max_tokens: Some(5_000),
+            max_cost_usd: Some(0.5),
+            max_atoms: Some(2),
+        });
+        assert_eq!(tracker.budget().max_tokens, Some(1_000));
+        assert_eq!(tracker.budget().max_cost_usd, Some(0.5));
+        assert_eq!(tracker.budget().max_atoms, Some(2));
+
+        // Unset limits fall back to the ceiling, and can be raised back up to it
+        tracker.narrow(Budget::default());
+        assert_eq!(tracker.budget().max_tokens, Some(1_000));
+        assert_eq!(tracker.budget().max_cost_usd, None);
+        assert_eq!(tracker.budget().max_atoms, Some(5));
+    }
+}
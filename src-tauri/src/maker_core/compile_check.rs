@@ -0,0 +1,400 @@
+// Cerebras-MAKER: Compile-Check Red Flags for Coder Candidates
+// Code that does not build cannot be the right answer, however many candidates agree on it.
+// With `ConsensusConfig::compile_check` set, each Coder candidate's files are written into a
+// scratch copy of the workspace and the stack's check command (see `detect_framework`) runs
+// there under a timeout. Rust checks share a target directory in the temp dir and run one
+// at a time, since cargo locks it:
+//   rust   - cargo check
+//   node   - tsc --noEmit, when the workspace has a tsconfig.json
+//   python - python -m py_compile on the changed files
+//   go     - go vet ./...
+// A candidate that fails is red-flagged with the diagnostics and never votes. A check that
+// cannot run (no command for the stack, tool not installed, timeout) leaves the candidate in.
+
+use super::ast_edit::SupportedLanguage;
+use super::atom::AtomResult;
+use crate::agents::CodeChange;
+use crate::handlers::testing::detect_framework;
+use crate::llm::replay::content_hash;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Stands for the candidate's changed source files in a check command
+pub const FILES_PLACEHOLDER: &str = "{files}";
+
+/// Directories not copied into the scratch workspace
+const SKIPPED_DIRS: &[&str] = &[".git", ".maker", "target", "node_modules"];
+
+/// Characters of diagnostics kept in the red-flag reason
+const DIAGNOSTIC_CHARS: usize = 2000;
+
+/// Cargo progress lines that say nothing about the candidate's code
+const CARGO_PROGRESS: &[&str] = &[
+    "Adding",
+    "Blocking",
+    "Checking",
+    "Compiling",
+    "Downloaded",
+    "Downloading",
+    "Finished",
+    "Locking",
+    "Updating",
+];
+
+static SCRATCH_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Held while a cargo check runs against the shared target directory
+static CARGO_CHECKS: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Settings for compile-checking Coder candidates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompileCheck {
+    /// Time allowed for the check command
+    pub timeout_ms: u64,
+    /// Command to run instead of the detected one; may contain `{files}`
+    /// Only settable from Rust: scripts must not choose which program runs.
+    #[serde(skip)]
+    pub command: Option<String>,
+}
+
+impl Default for CompileCheck {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 120_000,
+            command: None,
+        }
+    }
+}
+
+/// What a compile check found
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckOutcome {
+    Passed,
+    /// The candidate does not build; carries the diagnostics
+    Failed(String),
+    /// The check could not be run; carries the reason
+    Skipped(String),
+}
+
+impl CompileCheck {
+    /// Check a finished candidate, red-flagging it if its code does not build
+    /// Invalid or already red-flagged candidates, and output without files, are left alone.
+    pub async fn check_candidate(
+        &self,
+        workspace_path: &str,
+        changes: &[CodeChange],
+        result: &mut AtomResult,
+    ) {
+        if !result.valid || result.is_red_flagged() || changes.is_empty() {
+            return;
+        }
+        match self.run(workspace_path, changes).await {
+            CheckOutcome::Passed => {
+                result
+                    .metadata
+                    .insert("compile_check".to_string(), "passed".to_string());
+            }
+            CheckOutcome::Failed(diagnostics) => {
+                result
+                    .metadata
+                    .insert("compile_check".to_string(), "failed".to_string());
+                result.set_red_flagged(&format!("Compile check failed: {}", diagnostics));
+            }
+            CheckOutcome::Skipped(reason) => {
+                eprintln!("Warning: Compile check skipped: {}", reason);
+                result
+                    .metadata
+                    .insert("compile_check".to_string(), format!("skipped: {}", reason));
+            }
+        }
+    }
+
+    /// Apply `changes` to a scratch copy of the workspace and run the check command in it
+    pub async fn run(&self, workspace_path: &str, changes: &[CodeChange]) -> CheckOutcome {
+        let workspace = Path::new(workspace_path);
+        let Some(command) = self
+            .command
+            .clone()
+            .or_else(|| detect_framework(workspace).check_command)
+        else {
+            return CheckOutcome::Skipped("no check command for this workspace".to_string());
+        };
+
+        let files = match changed_files(changes) {
+            Ok(files) => files,
+            Err(e) => return CheckOutcome::Failed(e),
+        };
+        let mut args: Vec<String> = Vec::new();
+        for arg in command.split_whitespace() {
+            if arg == FILES_PLACEHOLDER {
+                args.extend(
+                    files
+                        .iter()
+                        .filter(|file| SupportedLanguage::from_path(file).is_some())
+                        .cloned(),
+                );
+            } else {
+                args.push(arg.to_string());
+            }
+        }
+        let Some((program, args)) = args.split_first() else {
+            return CheckOutcome::Skipped("empty check command".to_string());
+        };
+
+        // Copying the workspace is blocking I/O; keep it off the async workers
+        let (from, owned) = (workspace.to_path_buf(), changes.to_vec());
+        let scratch = match tokio::task::spawn_blocking(move || {
+            ScratchDir::with_changes(&from, &owned)
+        })
+        .await
+        {
+            Ok(Ok(scratch)) => scratch,
+            Ok(Err(e)) => return CheckOutcome::Skipped(e),
+            Err(e) => return CheckOutcome::Skipped(format!("Scratch copy panicked: {}", e)),
+        };
+
+        let mut process = tokio::process::Command::new(program);
+        process
+            .args(args)
+            .current_dir(&scratch.path)
+            // Share build artifacts between candidates instead of rebuilding from scratch
+            .env("CARGO_TARGET_DIR", check_target_dir(workspace_path))
+            .kill_on_drop(true);
+        // Cargo locks the shared target directory, so Rust checks take turns; waiting for
+        // the lock does not count against the timeout
+        let _turn = if program == "cargo" {
+            Some(CARGO_CHECKS.lock().await)
+        } else {
+            None
+        };
+        let timeout = Duration::from_millis(self.timeout_ms);
+        let output = match tokio::time::timeout(timeout, process.output()).await {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                return CheckOutcome::Skipped(format!("Failed to run {}: {}", program, e))
+            }
+            Err(_) => {
+                return CheckOutcome::Skipped(format!(
+                    "{} timed out after {}ms",
+                    command, self.timeout_ms
+                ))
+            }
+        };
+
+        if output.status.success() {
+            CheckOutcome::Passed
+        } else {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            CheckOutcome::Failed(format!(
+                "`{}` exited with {}\n{}",
+                command,
+                output.status,
+                diagnostics(&stderr, &stdout)
+            ))
+        }
+    }
+}
+
+/// Build directory shared by a workspace's checks
+/// It lives outside the workspace so snapshots and rollbacks never touch it.
+fn check_target_dir(workspace_path: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join("maker-check-target")
+        .join(content_hash(workspace_path))
+}
+
+/// Paths the candidate writes, relative to the workspace
+fn changed_files(changes: &[CodeChange]) -> Result<Vec<String>, String> {
+    changes
+        .iter()
+        .map(|change| {
+            let escapes = Path::new(&change.file_path)
+                .components()
+                .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+            if escapes {
+                Err(format!("{} is outside the workspace", change.file_path))
+            } else {
+                Ok(change.file_path.clone())
+            }
+        })
+        .collect()
+}
+
+/// Compiler output without build progress, truncated to `DIAGNOSTIC_CHARS`
+fn diagnostics(stderr: &str, stdout: &str) -> String {
+    let text: Vec<&str> = stderr
+        .lines()
+        .chain(stdout.lines())
+        .filter(|line| {
+            let first = line.split_whitespace().next().unwrap_or("");
+            !line.trim().is_empty() && !CARGO_PROGRESS.contains(&first)
+        })
+        .collect();
+    text.join("\n").chars().take(DIAGNOSTIC_CHARS).collect()
+}
+
+/// A copy of the workspace in the system temp directory, removed on drop
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Copy the workspace and write `changes` over the copy
+    fn with_changes(workspace: &Path, changes: &[CodeChange]) -> Result<Self, String> {
+        let scratch = Self::copy_of(workspace)?;
+        for change in changes {
+            let path = scratch.path.join(&change.file_path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+            }
+            std::fs::write(&path, format!("{}\n", change.content))
+                .map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+        }
+        Ok(scratch)
+    }
+
+    fn copy_of(workspace: &Path) -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!(
+            "maker-compile-check-{}-{}",
+            std::process::id(),
+            SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let scratch = Self { path };
+        copy_dir(workspace, &scratch.path)
+            .map_err(|e| format!("Failed to copy workspace for compile check: {}", e))?;
+
+        link_node_modules(workspace, &scratch.path);
+        Ok(scratch)
+    }
+}
+
+/// Installed packages are needed to type-check but too large to copy, so link them
+#[cfg(unix)]
+fn link_node_modules(workspace: &Path, scratch: &Path) {
+    let node_modules = workspace.join("node_modules");
+    if node_modules.is_dir() {
+        let _ = std::os::unix::fs::symlink(node_modules, scratch.join("node_modules"));
+    }
+}
+
+#[cfg(not(unix))]
+fn link_node_modules(_workspace: &Path, _scratch: &Path) {}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name = entry.file_name();
+        if file_type.is_dir() {
+            if SKIPPED_DIRS.iter().any(|skipped| name == *skipped) {
+                continue;
+            }
+            copy_dir(&entry.path(), &to.join(&name))?;
+        } else if file_type.is_file() {
+            std::fs::copy(entry.path(), to.join(&name))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maker_core::AtomType;
+
+    fn change(path: &str, content: &str) -> CodeChange {
+        CodeChange {
+            file_path: path.to_string(),
+            content: content.to_string(),
+            language: None,
+        }
+    }
+
+    fn check(command: &str) -> CompileCheck {
+        CompileCheck {
+            timeout_ms: 5_000,
+            command: Some(command.to_string()),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_check_runs_against_candidate_files_in_a_scratch_copy() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ws = workspace.path().to_str().unwrap();
+        std::fs::write(workspace.path().join("lib.py"), "x = 1\n").unwrap();
+        let changes = [change("pkg/new.py", "y = 2"), change("NOTES.md", "notes")];
+
+        // Both the copied workspace and the candidate's source files are there
+        assert_eq!(
+            check("ls lib.py {files}").run(ws, &changes).await,
+            CheckOutcome::Passed
+        );
+        assert!(!workspace.path().join("pkg").exists());
+
+        let CheckOutcome::Failed(diagnostics) =
+            check("ls NOTES.md missing.py").run(ws, &changes).await
+        else {
+            panic!("expected the check to fail");
+        };
+        assert!(diagnostics.contains("missing.py"));
+
+        let escaping = [change("../outside.rs", "fn main() {}")];
+        assert!(matches!(
+            check("true").run(ws, &escaping).await,
+            CheckOutcome::Failed(_)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_failed_check_red_flags_and_unrunnable_check_does_not() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ws = workspace.path().to_str().unwrap();
+        let changes = [change("src/lib.rs", "fn broken( {")];
+        let output = "FILE: src/lib.rs\n```rust\nfn broken( {\n```".to_string();
+
+        let mut failed = AtomResult::success(AtomType::Coder, output.clone(), 0, 10);
+        check("false")
+            .check_candidate(ws, &changes, &mut failed)
+            .await;
+        assert!(failed.is_red_flagged());
+        assert_eq!(failed.metadata["compile_check"], "failed");
+
+        let mut timed_out = AtomResult::success(AtomType::Coder, output.clone(), 0, 10);
+        let slow = CompileCheck {
+            timeout_ms: 50,
+            ..check("sleep 5")
+        };
+        slow.check_candidate(ws, &changes, &mut timed_out).await;
+        assert!(!timed_out.is_red_flagged());
+        assert!(timed_out.metadata["compile_check"].starts_with("skipped"));
+
+        // No manifest, no detected command
+        assert!(matches!(
+            CompileCheck::default().run(ws, &changes).await,
+            CheckOutcome::Skipped(_)
+        ));
+    }
+
+    #[test]
+    fn test_diagnostics_drop_cargo_progress() {
+        let stderr = "    Checking demo v0.1.0\nsrc/lib.rs:1:11: error: mismatched closing delimiter\n    Finished dev\n";
+        assert_eq!(
+            diagnostics(stderr, ""),
+            "src/lib.rs:1:11: error: mismatched closing delimiter"
+        );
+    }
+}
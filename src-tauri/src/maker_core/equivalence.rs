@@ -0,0 +1,419 @@
+// Cerebras-MAKER: Candidate Equivalence for Voting
+// First-to-ahead-by-k only converges if candidates that mean the same thing count as the same
+// vote. Each strategy maps an output to the key of its equivalence class:
+//   Text      - whitespace-normalized text
+//   Code      - tree-sitter token stream: comments, formatting, trailing commas and the order
+//               of adjacent imports are ignored (imports that run code keep their order)
+//   Json      - canonical JSON (sorted keys, no formatting)
+//   KeyFields - canonical JSON of selected top-level fields, e.g. only Reviewer `approved`
+// Outputs a strategy cannot read (unparseable code, no JSON) fall back to Text.
+
+use super::ast_edit::SupportedLanguage;
+use super::atom::AtomType;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tree_sitter::{Node, Parser};
+
+/// How candidate outputs are grouped into votes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Equivalence {
+    Text,
+    Code,
+    Json,
+    KeyFields(Vec<String>),
+}
+
+impl Equivalence {
+    /// Default strategy for an atom type
+    pub fn for_atom(atom_type: AtomType) -> Self {
+        match atom_type {
+            AtomType::Coder | AtomType::Tester => Self::Code,
+            AtomType::Reviewer => Self::KeyFields(vec!["approved".to_string()]),
+            AtomType::Validator => Self::KeyFields(vec!["valid".to_string()]),
+            AtomType::Search
+            | AtomType::Planner
+            | AtomType::Architect
+            | AtomType::GritsAnalyzer
+            | AtomType::RLMProcessor
+            | AtomType::WebResearcher => Self::Json,
+        }
+    }
+
+    /// Key of the equivalence class an output belongs to
+    pub fn key(&self, output: &str) -> String {
+        let key = match self {
+            Self::Text => None,
+            Self::Code => code_key(output),
+            Self::Json => extract_json(output).map(|json| canonical_json(&json).to_string()),
+            Self::KeyFields(fields) => extract_json(output).map(|json| {
+                let projected = match json {
+                    Value::Object(object) => Value::Object(
+                        fields
+                            .iter()
+                            .map(|field| {
+                                let value = object.get(field).cloned().unwrap_or(Value::Null);
+                                (field.clone(), value)
+                            })
+                            .collect(),
+                    ),
+                    other => other,
+                };
+                canonical_json(&projected).to_string()
+            }),
+        };
+        key.unwrap_or_else(|| normalize_text(output))
+    }
+}
+
+/// Collapse whitespace runs
+fn normalize_text(output: &str) -> String {
+    output.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Key for code output: the canonical form of each fenced block, with its `FILE:` path
+/// None if there are no code blocks.
+fn code_key(output: &str) -> Option<String> {
+    let blocks = code_blocks(output);
+    if blocks.is_empty() {
+        return None;
+    }
+
+    let keys: Vec<String> = blocks
+        .iter()
+        .map(|block| {
+            let language = block
+                .file
+                .as_deref()
+                .and_then(SupportedLanguage::from_path)
+                .or_else(|| block.tag.as_deref().and_then(fence_language));
+            let code = language
+                .and_then(|language| canonical_code(&block.code, language))
+                .unwrap_or_else(|| normalize_text(&block.code));
+            match &block.file {
+                Some(file) => format!("FILE: {}\n{}", file, code),
+                None => code,
+            }
+        })
+        .collect();
+    Some(keys.join("\n"))
+}
+
+/// A fenced code block and the `FILE:` line before it
+struct CodeBlock {
+    file: Option<String>,
+    tag: Option<String>,
+    code: String,
+}
+
+fn code_blocks(output: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut file = None;
+    let mut open: Option<CodeBlock> = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim_start();
+        if let Some(rest) = trimmed.strip_prefix("```") {
+            match open.take() {
+                Some(block) => blocks.push(block),
+                None => {
+                    let tag = rest.trim();
+                    open = Some(CodeBlock {
+                        file: file.take(),
+                        tag: (!tag.is_empty()).then(|| tag.to_string()),
+                        code: String::new(),
+                    });
+                }
+            }
+        } else if let Some(block) = open.as_mut() {
+            block.code.push_str(line);
+            block.code.push('\n');
+        } else if let Some(path) = trimmed.strip_prefix("FILE:") {
+            file = Some(path.trim().to_string());
+        }
+    }
+    // An unterminated block still counts
+    blocks.extend(open);
+    blocks
+}
+
+/// Language of a fence tag such as "rust" or "ts"
+fn fence_language(tag: &str) -> Option<SupportedLanguage> {
+    match tag.to_lowercase().as_str() {
+        "rust" => Some(SupportedLanguage::Rust),
+        "typescript" => Some(SupportedLanguage::TypeScript),
+        "javascript" => Some(SupportedLanguage::JavaScript),
+        "python" => Some(SupportedLanguage::Python),
+        "golang" => Some(SupportedLanguage::Go),
+        other => SupportedLanguage::from_extension(other),
+    }
+}
+
+/// Canonical form of source code, or None if it does not parse
+/// Top-level items become one line of tokens each; consecutive reorderable imports are sorted.
+fn canonical_code(code: &str, language: SupportedLanguage) -> Option<String> {
+    let mut parser = Parser::new();
+    parser.set_language(&language.tree_sitter_language()).ok()?;
+    let tree = parser.parse(code, None)?;
+    let root = tree.root_node();
+    if root.has_error() {
+        return None;
+    }
+
+    let mut items = Vec::new();
+    let mut imports = Vec::new();
+    let mut cursor = root.walk();
+    for child in root.children(&mut cursor) {
+        if is_comment(child) {
+            continue;
+        }
+        let mut tokens = Vec::new();
+        collect_tokens(child, code, language, &mut tokens);
+        let line = drop_trailing_commas(tokens, language).join(" ");
+        if is_reorderable_import(child, language) {
+            imports.push(line);
+        } else {
+            imports.sort();
+            items.append(&mut imports);
+            items.push(line);
+        }
+    }
+    imports.sort();
+    items.append(&mut imports);
+    Some(items.join("\n"))
+}
+
+fn is_comment(node: Node) -> bool {
+    node.kind().contains("comment")
+}
+
+/// Imports whose order does not change behaviour: Rust `use`, Go imports, and JS/TS imports
+/// that bind names. Python imports and side-effect imports (`import "./setup"`) run code on
+/// load, so they stay where they are.
+fn is_reorderable_import(node: Node, language: SupportedLanguage) -> bool {
+    match language {
+        SupportedLanguage::Rust => node.kind() == "use_declaration",
+        SupportedLanguage::Go => node.kind() == "import_declaration",
+        SupportedLanguage::TypeScript | SupportedLanguage::Tsx | SupportedLanguage::JavaScript => {
+            let mut cursor = node.walk();
+            node.kind() == "import_statement"
+                && node
+                    .children(&mut cursor)
+                    .any(|child| child.kind() == "import_clause")
+        }
+        SupportedLanguage::Python => false,
+    }
+}
+
+/// Leaf tokens of a node, skipping comments
+/// Python blocks are delimited by indentation, so they are bracketed to keep nesting in the key.
+fn collect_tokens<'a>(
+    node: Node,
+    code: &'a str,
+    language: SupportedLanguage,
+    tokens: &mut Vec<&'a str>,
+) {
+    if is_comment(node) {
+        return;
+    }
+    if node.child_count() == 0 {
+        if let Some(text) = code.get(node.byte_range()) {
+            if !text.trim().is_empty() {
+                tokens.push(text);
+            }
+        }
+        return;
+    }
+
+    let python_block = language == SupportedLanguage::Python && node.kind() == "block";
+    if python_block {
+        tokens.push("{");
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_tokens(child, code, language, tokens);
+    }
+    if python_block {
+        tokens.push("}");
+    }
+}
+
+/// Drop commas directly before a closing bracket
+/// In Rust and Python a comma that is the only separator inside parentheses is kept: `(x,)` is
+/// a one-element tuple there, not `(x)`. In JS, TS and Go it is only a trailing comma.
+fn drop_trailing_commas(tokens: Vec<&str>, language: SupportedLanguage) -> Vec<&str> {
+    let has_tuples = matches!(
+        language,
+        SupportedLanguage::Rust | SupportedLanguage::Python
+    );
+    let mut kept: Vec<&str> = Vec::with_capacity(tokens.len());
+    // Open brackets with the number of commas seen directly inside each
+    let mut open: Vec<(&str, usize)> = Vec::new();
+    for token in tokens {
+        match token {
+            "(" | "[" | "{" => open.push((token, 0)),
+            "," => {
+                if let Some((_, commas)) = open.last_mut() {
+                    *commas += 1;
+                }
+            }
+            ")" | "]" | "}" => {
+                let (opener, commas) = open.pop().unwrap_or(("", 0));
+                let one_tuple = has_tuples && opener == "(" && commas == 1;
+                if kept.last() == Some(&",") && !one_tuple {
+                    kept.pop();
+                }
+            }
+            _ => {}
+        }
+        kept.push(token);
+    }
+    kept
+}
+
+/// JSON in an output: the whole output, its first code block, or the outermost braces
+fn extract_json(output: &str) -> Option<Value> {
+    if let Ok(json) = serde_json::from_str(output.trim()) {
+        return Some(json);
+    }
+    if let Some(json) = code_blocks(output)
+        .first()
+        .and_then(|block| serde_json::from_str(block.code.trim()).ok())
+    {
+        return Some(json);
+    }
+    let start = output.find(['{', '['])?;
+    let end = output.rfind(['}', ']'])?;
+    serde_json::from_str(output.get(start..=end)?).ok()
+}
+
+/// JSON with object keys sorted at every level
+fn canonical_json(value: &Value) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut entries: Vec<_> = object.iter().collect();
+            entries.sort_by(|a, b| a.0.cmp(b.0));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key.clone(), canonical_json(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonical_json).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_ignores_comments_formatting_and_import_order() {
+        let a = "FILE: src/lib.rs\n```rust\nuse std::fmt;\nuse std::collections::HashMap;\n\n// Build the map\nfn build() -> HashMap<String, u32> {\n    let m = HashMap::from([\n        (\"a\".to_string(), 1),\n    ]);\n    m\n}\n```";
+        let b = "Here is the code:\nFILE: src/lib.rs\n```rust\nuse std::collections::HashMap;\nuse std::fmt;\n/// Builds a map\nfn build()->HashMap<String,u32>{ let m = HashMap::from([(\"a\".to_string(), 1)]); m }\n```";
+        let c = "FILE: src/lib.rs\n```rust\nuse std::collections::HashMap;\nuse std::fmt;\nfn build() -> HashMap<String, u32> {\n    let m = HashMap::from([(\"a\".to_string(), 2)]);\n    m\n}\n```";
+
+        let code = Equivalence::Code;
+        assert_eq!(code.key(a), code.key(b));
+        assert_ne!(code.key(a), code.key(c));
+        // String contents are tokens, not formatting
+        assert_ne!(
+            code.key("```rust\nlet s = \"a  b\";\n```"),
+            code.key("```rust\nlet s = \"a b\";\n```")
+        );
+    }
+
+    #[test]
+    fn test_one_element_tuples_and_side_effect_imports_are_kept() {
+        let code = Equivalence::Code;
+        assert_ne!(
+            code.key("```rust\nlet t = (x,);\n```"),
+            code.key("```rust\nlet t = (x);\n```")
+        );
+        assert_eq!(
+            code.key("```rust\nlet t = (x, y,);\n```"),
+            code.key("```rust\nlet t = (x, y);\n```")
+        );
+        assert_ne!(
+            code.key("```python\nt = (x,)\n```"),
+            code.key("```python\nt = (x)\n```")
+        );
+        // Other languages have no tuples, so a lone trailing comma is formatting
+        assert_eq!(
+            code.key("```ts\nfoo(\n  arg,\n);\n```"),
+            code.key("```ts\nfoo(arg);\n```")
+        );
+        assert_eq!(
+            code.key("```js\nfoo(\n  arg,\n);\n```"),
+            code.key("```js\nfoo(arg);\n```")
+        );
+        assert_eq!(
+            code.key("```go\nfunc f() {\n\tfoo(\n\t\targ,\n\t)\n}\n```"),
+            code.key("```go\nfunc f() {\n\tfoo(arg)\n}\n```")
+        );
+
+        // Python and side-effect imports run code, so their order is part of the program
+        assert_ne!(
+            code.key("```python\nimport setup\nimport app\n```"),
+            code.key("```python\nimport app\nimport setup\n```")
+        );
+        assert_ne!(
+            code.key("```ts\nimport \"./polyfill\";\nimport \"./app\";\n```"),
+            code.key("```ts\nimport \"./app\";\nimport \"./polyfill\";\n```")
+        );
+        assert_eq!(
+            code.key("```ts\nimport { a } from \"./a\";\nimport { b } from \"./b\";\n```"),
+            code.key("```ts\nimport { b } from \"./b\";\nimport { a } from \"./a\";\n```")
+        );
+    }
+
+    #[test]
+    fn test_python_indentation_is_structure() {
+        let inside = "```python\nif ready:\n    start()\n    stop()\n```";
+        let outside = "```python\nif ready:\n    start()\nstop()\n```";
+        assert_ne!(
+            Equivalence::Code.key(inside),
+            Equivalence::Code.key(outside)
+        );
+
+        let commented = "```py\n# Start when ready\nif ready:\n  start()  # go\n  stop()\n```";
+        assert_eq!(
+            Equivalence::Code.key(inside),
+            Equivalence::Code.key(commented)
+        );
+    }
+
+    #[test]
+    fn test_json_and_key_fields() {
+        let json = Equivalence::Json;
+        assert_eq!(
+            json.key(r#"{"b": [1, 2], "a": {"y": 1, "x": 2}}"#),
+            json.key("```json\n{\"a\": {\"x\": 2, \"y\": 1},\n \"b\": [1,2]}\n```")
+        );
+        assert_ne!(json.key("[1, 2]"), json.key("[2, 1]"));
+
+        let reviewer = Equivalence::for_atom(AtomType::Reviewer);
+        let approve_a = r#"{"approved": true, "issues": [], "suggestions": ["rename x"]}"#;
+        let approve_b = r#"Looks fine. {"issues": ["nit"], "approved": true}"#;
+        let reject = r#"{"approved": false, "issues": ["panics on empty input"]}"#;
+        assert_eq!(reviewer.key(approve_a), reviewer.key(approve_b));
+        assert_ne!(reviewer.key(approve_a), reviewer.key(reject));
+        assert_eq!(reviewer.key(approve_a), r#"{"approved":true}"#);
+    }
+
+    #[test]
+    fn test_unreadable_outputs_fall_back_to_text() {
+        assert_eq!(
+            Equivalence::Json.key("not  json\n at all"),
+            "not json at all"
+        );
+        assert_eq!(Equivalence::Code.key("no code here"), "no code here");
+        // Code that does not parse is compared as text
+        assert_eq!(
+            Equivalence::Code.key("```rust\nfn broken( {\n```"),
+            Equivalence::Code.key("```rust\nfn  broken(  {\n```")
+        );
+    }
+}
@@ -0,0 +1,358 @@
+// Cerebras-MAKER: Voting Ledger
+// Appends every consensus run to .maker/voting_ledger.jsonl, one LedgerEntry per line, so
+// k_threshold and sampling schedules can be tuned from evidence. LedgerStats summarizes the
+// ledger: convergence rate and atoms-to-consensus per atom type, red-flag rate per model.
+
+use super::atom::AtomType;
+use super::sampling::SampleConfig;
+use super::voting::{CandidateVote, ConsensusConfig, ConsensusResult};
+use crate::llm::replay::content_hash;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// One candidate of a recorded run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateRecord {
+    pub model: String,
+    /// Class hash the candidate voted for; None if it was discarded or failed
+    pub hash: Option<String>,
+    pub red_flag: Option<String>,
+    pub failed: bool,
+}
+
+/// A recorded consensus run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub timestamp: String,
+    /// `content_hash` of the task, so runs of the same task can be grouped
+    pub task_hash: String,
+    pub atom_type: AtomType,
+    pub k_threshold: usize,
+    /// Candidates sampled from each model
+    pub model_mix: HashMap<String, usize>,
+    /// Every counted candidate, in the order it finished
+    pub candidates: Vec<CandidateRecord>,
+    /// Votes per class hash
+    pub class_votes: HashMap<String, usize>,
+    /// Candidates left out of the vote for a red flag
+    pub red_flag_discards: usize,
+    pub atoms_spawned: usize,
+    pub elapsed_ms: u64,
+    pub total_tokens: usize,
+    pub cost_usd: f64,
+    pub reached: bool,
+    pub winner_hash: Option<String>,
+    pub winner_sample: Option<SampleConfig>,
+}
+
+impl LedgerEntry {
+    /// Record a finished run from its result and the votes reported while it ran
+    pub fn new(
+        task: &str,
+        atom_type: AtomType,
+        config: &ConsensusConfig,
+        result: &ConsensusResult,
+        votes: &[CandidateVote],
+    ) -> Self {
+        let candidates: Vec<CandidateRecord> = votes
+            .iter()
+            .map(|vote| CandidateRecord {
+                model: vote.sample.model.clone(),
+                hash: vote.hash.clone(),
+                red_flag: vote.red_flag.clone(),
+                failed: vote.error.is_some(),
+            })
+            .collect();
+
+        let mut model_mix = HashMap::new();
+        for candidate in &candidates {
+            *model_mix.entry(candidate.model.clone()).or_insert(0) += 1;
+        }
+
+        Self {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            task_hash: content_hash(task),
+            atom_type,
+            k_threshold: config.k_threshold,
+            model_mix,
+            red_flag_discards: candidates
+                .iter()
+                .filter(|c| c.hash.is_none() && c.red_flag.is_some())
+                .count(),
+            candidates,
+            class_votes: result
+                .classes
+                .iter()
+                .map(|class| (class.hash.clone(), class.votes))
+                .collect(),
+            atoms_spawned: result.atoms_spawned,
+            elapsed_ms: result.elapsed_ms,
+            total_tokens: result.total_tokens,
+            cost_usd: result.cost_usd,
+            reached: result.reached,
+            winner_hash: result.winner_class().map(|class| class.hash.clone()),
+            winner_sample: result.winner_sample.clone(),
+        }
+    }
+}
+
+/// Append-only ledger of consensus runs for a workspace
+pub struct VotingLedger {
+    path: PathBuf,
+}
+
+impl VotingLedger {
+    pub fn path(workspace_path: &str) -> PathBuf {
+        Path::new(workspace_path)
+            .join(".maker")
+            .join("voting_ledger.jsonl")
+    }
+
+    pub fn open(workspace_path: &str) -> Self {
+        Self {
+            path: Self::path(workspace_path),
+        }
+    }
+
+    pub fn append(&self, entry: &LedgerEntry) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        let line = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize ledger entry: {}", e))?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|e| format!("Failed to open voting ledger: {}", e))?;
+        writeln!(file, "{}", line).map_err(|e| format!("Failed to append to voting ledger: {}", e))
+    }
+
+    /// Every recorded run, oldest first
+    pub fn entries(&self) -> Result<Vec<LedgerEntry>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = std::fs::read_to_string(&self.path)
+            .map_err(|e| format!("Failed to read voting ledger: {}", e))?;
+        // A crash can leave a truncated last line; skip anything that does not parse
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn stats(&self) -> Result<LedgerStats, String> {
+        Ok(LedgerStats::from_entries(&self.entries()?))
+    }
+}
+
+/// Consensus outcomes for one atom type
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AtomTypeStats {
+    pub runs: usize,
+    pub converged: usize,
+    pub convergence_rate: f64,
+    /// Mean atoms spawned by runs that reached consensus; None if none did
+    pub avg_atoms_to_consensus: Option<f64>,
+    pub avg_tokens: f64,
+}
+
+/// Candidate outcomes for one model
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelStats {
+    pub candidates: usize,
+    pub red_flagged: usize,
+    pub failed: usize,
+    /// Red-flagged share of the candidates that ran
+    pub red_flag_rate: f64,
+    /// Runs won by one of this model's outputs
+    pub wins: usize,
+}
+
+/// Summary of a voting ledger
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LedgerStats {
+    pub runs: usize,
+    pub atom_types: HashMap<AtomType, AtomTypeStats>,
+    pub models: HashMap<String, ModelStats>,
+}
+
+impl LedgerStats {
+    pub fn from_entries(entries: &[LedgerEntry]) -> Self {
+        let mut stats = Self {
+            runs: entries.len(),
+            ..Default::default()
+        };
+        let mut atoms_to_consensus: HashMap<AtomType, usize> = HashMap::new();
+        let mut tokens: HashMap<AtomType, usize> = HashMap::new();
+
+        for entry in entries {
+            let atom = stats.atom_types.entry(entry.atom_type).or_default();
+            atom.runs += 1;
+            *tokens.entry(entry.atom_type).or_insert(0) += entry.total_tokens;
+            if entry.reached {
+                atom.converged += 1;
+                *atoms_to_consensus.entry(entry.atom_type).or_insert(0) += entry.atoms_spawned;
+            }
+
+            for candidate in &entry.candidates {
+                let model = stats.models.entry(candidate.model.clone()).or_default();
+                model.candidates += 1;
+                if candidate.failed {
+                    model.failed += 1;
+                } else if candidate.red_flag.is_some() {
+                    model.red_flagged += 1;
+                }
+            }
+            if let Some(sample) = &entry.winner_sample {
+                stats.models.entry(sample.model.clone()).or_default().wins += 1;
+            }
+        }
+
+        for (atom_type, atom) in stats.atom_types.iter_mut() {
+            atom.convergence_rate = atom.converged as f64 / atom.runs as f64;
+            atom.avg_tokens = tokens.get(atom_type).copied().unwrap_or(0) as f64 / atom.runs as f64;
+            atom.avg_atoms_to_consensus = (atom.converged > 0).then(|| {
+                atoms_to_consensus.get(atom_type).copied().unwrap_or(0) as f64
+                    / atom.converged as f64
+            });
+        }
+        for model in stats.models.values_mut() {
+            let ran = model.candidates - model.failed;
+            if ran > 0 {
+                model.red_flag_rate = model.red_flagged as f64 / ran as f64;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ProviderType;
+    use crate::maker_core::ShadowGit;
+
+    fn vote(
+        candidate: usize,
+        model: &str,
+        hash: Option<&str>,
+        red_flag: Option<&str>,
+    ) -> CandidateVote {
+        CandidateVote {
+            candidate,
+            sample: SampleConfig {
+                provider: ProviderType::Replay,
+                model: model.to_string(),
+                temperature: 0.1,
+                paraphrase: 0,
+            },
+            hash: hash.map(str::to_string),
+            snippet: String::new(),
+            red_flag: red_flag.map(str::to_string),
+            error: None,
+            tally: HashMap::new(),
+        }
+    }
+
+    fn entry(
+        atom_type: AtomType,
+        reached: bool,
+        atoms: usize,
+        votes: &[CandidateVote],
+    ) -> LedgerEntry {
+        let mut result = if reached {
+            ConsensusResult::success("out".to_string(), 2, HashMap::new(), atoms, 0, 100)
+        } else {
+            ConsensusResult::failure("no consensus".to_string(), HashMap::new(), atoms, 0, 100)
+        };
+        result.winner_sample = votes.first().filter(|_| reached).map(|v| v.sample.clone());
+        LedgerEntry::new(
+            "task",
+            atom_type,
+            &ConsensusConfig::default(),
+            &result,
+            votes,
+        )
+    }
+
+    #[test]
+    fn test_ledger_round_trip_and_stats() {
+        let workspace = tempfile::tempdir().unwrap();
+        let ledger = VotingLedger::open(workspace.path().to_str().unwrap());
+        assert_eq!(ledger.stats().unwrap(), LedgerStats::default());
+
+        let coder_votes = [
+            vote(0, "fast", Some("a"), None),
+            vote(1, "strong", None, Some("Red flag: eval")),
+            vote(2, "fast", Some("a"), None),
+        ];
+        let first = entry(AtomType::Coder, true, 3, &coder_votes);
+        assert_eq!(first.red_flag_discards, 1);
+        assert_eq!(first.model_mix.get("fast"), Some(&2));
+        ledger.append(&first).unwrap();
+        ledger
+            .append(&entry(
+                AtomType::Coder,
+                false,
+                5,
+                &[vote(0, "strong", Some("b"), None)],
+            ))
+            .unwrap();
+        ledger
+            .append(&entry(
+                AtomType::Reviewer,
+                true,
+                2,
+                &[vote(0, "fast", Some("c"), None)],
+            ))
+            .unwrap();
+
+        let stats = ledger.stats().unwrap();
+        assert_eq!(stats.runs, 3);
+        let coder = &stats.atom_types[&AtomType::Coder];
+        assert_eq!((coder.runs, coder.converged), (2, 1));
+        assert_eq!(coder.convergence_rate, 0.5);
+        assert_eq!(coder.avg_atoms_to_consensus, Some(3.0));
+
+        let strong = &stats.models["strong"];
+        assert_eq!((strong.candidates, strong.red_flagged), (2, 1));
+        assert_eq!(strong.red_flag_rate, 0.5);
+        assert_eq!(stats.models["fast"].wins, 2);
+    }
+
+    #[test]
+    fn test_ledger_survives_rollback() {
+        let workspace = tempfile::tempdir().unwrap();
+        let path = workspace.path().to_str().unwrap();
+        let mut shadow = ShadowGit::new(path);
+        shadow.init().unwrap();
+        let ledger = VotingLedger::open(path);
+        let votes = [vote(0, "fast", Some("a"), None)];
+
+        std::fs::write(workspace.path().join("main.rs"), "fn main() {}").unwrap();
+        ledger
+            .append(&entry(AtomType::Coder, true, 1, &votes))
+            .unwrap();
+        let first = shadow.snapshot("first").unwrap();
+        assert!(first.commit_hash.is_some());
+
+        std::fs::write(workspace.path().join("main.rs"), "broken").unwrap();
+        ledger
+            .append(&entry(AtomType::Coder, false, 3, &votes))
+            .unwrap();
+        shadow.snapshot("second").unwrap();
+        ledger
+            .append(&entry(AtomType::Reviewer, true, 2, &votes))
+            .unwrap();
+
+        shadow.rollback_to(&first.id).unwrap();
+        let main = std::fs::read_to_string(workspace.path().join("main.rs")).unwrap();
+        assert_eq!(main, "fn main() {}");
+        assert_eq!(ledger.entries().unwrap().len(), 3);
+    }
+}
@@ -4,18 +4,37 @@
 pub mod ast_edit;
 pub mod atom;
 pub mod atom_bridge;
+pub mod budget;
+pub mod compile_check;
+pub mod equivalence;
+pub mod ledger;
+pub mod reliability;
 pub mod rlm;
 pub mod runtime;
+pub mod sampling;
 pub mod shadow_git;
+pub mod tdd;
 pub mod voting;
 pub mod web_research_bridge;
+pub mod workspace_fs;
 
 // Re-exports for convenience
-pub use ast_edit::{AstEditor, SupportedLanguage, SyntaxValidationResult, SyntaxError};
+pub use ast_edit::{AstEditor, CodeSymbol, SupportedLanguage, SyntaxValidationResult, SyntaxError};
 pub use atom::{AtomType, AtomResult, SpawnFlags};
+pub use budget::{Budget, BudgetTracker, CostEntry, SharedBudget, Spend};
+pub use compile_check::{CheckOutcome, CompileCheck};
+pub use equivalence::Equivalence;
+pub use ledger::{LedgerEntry, LedgerStats, VotingLedger};
+pub use reliability::{AdaptiveK, KSelection};
 pub use rlm::{RLMConfig, RLMContextStore, RLMResult, RLMTrajectoryStep, RLMOperation, ContextType, ContextMetadata, SharedRLMContextStore, create_shared_store, RLMAction, RLMExecutionState};
 pub use runtime::CodeModeRuntime;
+pub use sampling::{ModelChoice, SampleConfig, SamplingSchedule, TemperatureRamp};
 pub use shadow_git::ShadowGit;
-pub use voting::{run_consensus, ConsensusConfig, ConsensusResult};
+pub use tdd::{TddIteration, TddReport, TddRun, TddStatus, TddSteps};
+pub use voting::{
+    run_consensus, run_consensus_observed, CandidateClass, CandidateVote, ConsensusConfig,
+    ConsensusObserver, ConsensusOptions, ConsensusResult,
+};
 pub use web_research_bridge::{init_web_research_worker, crawl_url_sync, research_docs_sync, extract_content_sync};
+pub use workspace_fs::{WorkspaceFs, FileAccess, FileOperation, DirEntryInfo};
 
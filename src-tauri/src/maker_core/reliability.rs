@@ -0,0 +1,230 @@
+// Cerebras-MAKER: Adaptive k for Consensus Voting
+// MAKER's first-to-ahead-by-k vote picks the correct answer of a step with probability
+//   P(step) = 1 / (1 + ((1 - p) / p)^k)
+// where p is the chance a single candidate is correct, and a plan of s steps succeeds with
+// P(step)^s. AdaptiveK estimates p per atom type from recent voting ledger entries (the share
+// of voting candidates that agreed with the winning class) and picks the smallest k whose
+// plan reliability meets the target.
+
+use super::atom::AtomType;
+use super::ledger::LedgerEntry;
+use serde::{Deserialize, Serialize};
+
+/// Settings for choosing k from voting history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdaptiveK {
+    /// Required probability that every step of the plan is right
+    pub target_reliability: f64,
+    /// Steps the target covers, e.g. the micro-tasks of the current PlanOutput
+    pub plan_steps: usize,
+    /// Most recent ledger runs of the atom type to estimate from
+    pub window: usize,
+    pub max_k: usize,
+}
+
+impl Default for AdaptiveK {
+    fn default() -> Self {
+        Self {
+            target_reliability: 0.99,
+            plan_steps: 1,
+            window: 50,
+            max_k: 10,
+        }
+    }
+}
+
+/// The k chosen for an atom type and the reliability it is expected to give
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KSelection {
+    pub atom_type: AtomType,
+    pub k: usize,
+    /// Estimated chance a single candidate is correct; None without voting history
+    pub success_rate: Option<f64>,
+    /// Voting candidates the estimate is based on
+    pub samples: usize,
+    pub step_reliability: Option<f64>,
+    pub plan_reliability: Option<f64>,
+    pub plan_steps: usize,
+    pub target_reliability: f64,
+    /// False when even `max_k` cannot reach the target, or there was no history to judge by
+    pub meets_target: bool,
+}
+
+impl AdaptiveK {
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.target_reliability > 0.0 && self.target_reliability < 1.0) {
+            return Err(format!(
+                "target_reliability must be between 0 and 1, got {}",
+                self.target_reliability
+            ));
+        }
+        if self.plan_steps == 0 || self.window == 0 || self.max_k == 0 {
+            return Err("plan_steps, window and max_k must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// Choose k for an atom type from ledger entries (oldest first)
+    /// Without history for the atom type, `fallback_k` is kept.
+    pub fn select(
+        &self,
+        entries: &[LedgerEntry],
+        atom_type: AtomType,
+        fallback_k: usize,
+    ) -> KSelection {
+        let mut selection = KSelection {
+            atom_type,
+            k: fallback_k,
+            success_rate: None,
+            samples: 0,
+            step_reliability: None,
+            plan_reliability: None,
+            plan_steps: self.plan_steps,
+            target_reliability: self.target_reliability,
+            meets_target: false,
+        };
+        let Some((success_rate, samples)) = success_rate(entries, atom_type, self.window) else {
+            return selection;
+        };
+
+        let k = (1..=self.max_k)
+            .find(|k| {
+                plan_reliability(success_rate, *k, self.plan_steps) >= self.target_reliability
+            })
+            .unwrap_or(self.max_k);
+        let plan = plan_reliability(success_rate, k, self.plan_steps);
+        selection.k = k;
+        selection.success_rate = Some(success_rate);
+        selection.samples = samples;
+        selection.step_reliability = Some(step_reliability(success_rate, k));
+        selection.plan_reliability = Some(plan);
+        selection.meets_target = plan >= self.target_reliability;
+        selection
+    }
+}
+
+/// Chance an ahead-by-k vote picks the correct answer when each candidate is right with p
+pub fn step_reliability(p: f64, k: usize) -> f64 {
+    if p <= 0.0 {
+        return 0.0;
+    }
+    1.0 / (1.0 + ((1.0 - p) / p).powi(k as i32))
+}
+
+/// Chance every one of `steps` votes picks the correct answer
+pub fn plan_reliability(p: f64, k: usize, steps: usize) -> f64 {
+    step_reliability(p, k).powi(steps as i32)
+}
+
+/// Estimated per-candidate success rate and the number of voting candidates behind it
+/// A candidate counts as correct if it joined the winning class, or the largest class of a
+/// run that did not converge. Laplace smoothing keeps the estimate away from 0 and 1.
+pub fn success_rate(
+    entries: &[LedgerEntry],
+    atom_type: AtomType,
+    window: usize,
+) -> Option<(f64, usize)> {
+    let mut agreed = 0;
+    let mut voted = 0;
+    for entry in entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.atom_type == atom_type)
+        .take(window)
+    {
+        voted += entry.class_votes.values().sum::<usize>();
+        agreed += match &entry.winner_hash {
+            Some(hash) => entry.class_votes.get(hash).copied().unwrap_or(0),
+            None => entry.class_votes.values().copied().max().unwrap_or(0),
+        };
+    }
+    (voted > 0).then(|| ((agreed + 1) as f64 / (voted + 2) as f64, voted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn entry(atom_type: AtomType, winner: Option<&str>, votes: &[(&str, usize)]) -> LedgerEntry {
+        LedgerEntry {
+            timestamp: String::new(),
+            task_hash: String::new(),
+            atom_type,
+            k_threshold: 3,
+            model_mix: HashMap::new(),
+            candidates: Vec::new(),
+            class_votes: votes
+                .iter()
+                .map(|(hash, n)| (hash.to_string(), *n))
+                .collect(),
+            red_flag_discards: 0,
+            atoms_spawned: votes.iter().map(|(_, n)| n).sum(),
+            elapsed_ms: 0,
+            total_tokens: 0,
+            cost_usd: 0.0,
+            reached: winner.is_some(),
+            winner_hash: winner.map(str::to_string),
+            winner_sample: None,
+        }
+    }
+
+    #[test]
+    fn test_minimal_k_for_plan_reliability() {
+        // p = 0.9 over 100 steps: k = 4 gives ~0.985, k = 5 gives ~0.998
+        assert!(plan_reliability(0.9, 4, 100) < 0.99);
+        assert!(plan_reliability(0.9, 5, 100) >= 0.99);
+
+        let adaptive = AdaptiveK {
+            plan_steps: 100,
+            ..Default::default()
+        };
+        // 89 of 98 votes agree: (89 + 1) / (98 + 2) = 0.9
+        let entries = vec![
+            entry(AtomType::Coder, Some("a"), &[("a", 45), ("b", 4)]),
+            entry(AtomType::Reviewer, Some("r"), &[("r", 2)]),
+            entry(AtomType::Coder, None, &[("c", 44), ("d", 5)]),
+        ];
+        let selection = adaptive.select(&entries, AtomType::Coder, 3);
+        assert_eq!(selection.samples, 98);
+        assert!((selection.success_rate.unwrap() - 0.9).abs() < 1e-9);
+        assert_eq!(selection.k, 5);
+        assert!(selection.meets_target);
+        assert!(selection.plan_reliability.unwrap() >= 0.99);
+
+        // A short plan needs a smaller margin
+        let short = AdaptiveK {
+            plan_steps: 1,
+            ..Default::default()
+        };
+        assert_eq!(short.select(&entries, AtomType::Coder, 3).k, 3);
+    }
+
+    #[test]
+    fn test_no_history_or_coin_flip_candidates() {
+        let adaptive = AdaptiveK::default();
+        let none = adaptive.select(&[], AtomType::Tester, 3);
+        assert_eq!(
+            (none.k, none.success_rate, none.meets_target),
+            (3, None, false)
+        );
+
+        // Candidates that agree less than half the time cannot be voted into reliability
+        let entries = vec![entry(
+            AtomType::Tester,
+            None,
+            &[("a", 3), ("b", 3), ("c", 3)],
+        )];
+        let hopeless = adaptive.select(&entries, AtomType::Tester, 3);
+        assert_eq!(hopeless.k, adaptive.max_k);
+        assert!(!hopeless.meets_target);
+
+        assert!(AdaptiveK {
+            target_reliability: 1.0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
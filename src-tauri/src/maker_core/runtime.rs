@@ -2,18 +2,66 @@
 // PRD Section 4: The Logic Layer - Sandboxed Scripting Runtime
 // Enhanced with RLM (Recursive Language Model) capabilities
 
+use super::ast_edit::{AstEditor, SupportedLanguage};
 use super::atom::{AtomResult, AtomType, SpawnFlags};
+use super::budget::{Budget, BudgetTracker, CostEntry, SharedBudget, Spend};
+use super::compile_check::CompileCheck;
+use super::ledger::{LedgerEntry, VotingLedger};
 use super::rlm::{ContextType, RLMConfig, RLMOperation, RLMTrajectoryStep, SharedRLMContextStore};
 use super::shadow_git::ShadowGit;
-use super::voting::{run_consensus as voting_run_consensus, ConsensusConfig, ConsensusResult};
-use crate::agents::AtomInput;
+use super::tdd::{tdd_loop, TddRun, TddSteps};
+use super::voting::{
+    run_consensus_observed, CandidateDeltaCallback, CandidateVote, ConsensusConfig,
+    ConsensusObserver, ConsensusOptions, ConsensusResult, VoteCallback,
+};
+use super::workspace_fs::{FileAccess, WorkspaceFs};
+use crate::agents::{
+    AtomExecutor, AtomInput, CacheConfig, CodeChange, DeltaCallback, ResponseCache, StreamOptions,
+};
+use crate::handlers::governance::GovernanceConfig;
+use crate::handlers::testing::{
+    detect_framework, run_test_suite, TestExecutionResult, TestRunOptions,
+};
 // use crate::grits;
-use crate::llm::LlmConfig;
-use rhai::{Dynamic, Engine, EvalAltResult, Scope, AST};
+use crate::llm::replay::content_hash;
+use crate::llm::{AgentRole, LlmConfig, LlmRouter};
+use rhai::{ASTNode, Dynamic, Engine, EvalAltResult, Expr, Position, Scope, Stmt, AST};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, Mutex};
 
+/// Functions evaluated by the Rhai interpreter itself rather than registered signatures
+const KEYWORD_FUNCTIONS: &[&str] = &[
+    "print",
+    "debug",
+    "type_of",
+    "Fn",
+    "call",
+    "curry",
+    "eval",
+    "is_def_fn",
+    "is_def_var",
+    "is_shared",
+];
+
+/// Quote and escape text for embedding as a Rhai string literal in generated scripts
+pub fn rhai_string_literal(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
 /// The Code Mode Runtime - executes Rhai scripts with MAKER API
 /// Enhanced with RLM (Recursive Language Model) capabilities for handling arbitrarily long contexts
 pub struct CodeModeRuntime {
@@ -23,13 +71,41 @@ pub struct CodeModeRuntime {
     workspace_path: String,
     execution_log: Arc<Mutex<Vec<ExecutionEvent>>>,
     #[allow(dead_code)] // Stored for potential future runtime reconfigurations
-    llm_config: Arc<LlmConfig>,
+    llm_router: Arc<LlmRouter>,
     /// RLM Context Store - holds large contexts as environment variables
     rlm_context_store: SharedRLMContextStore,
     /// RLM Configuration
     rlm_config: RLMConfig,
     /// RLM Trajectory for visualization
     rlm_trajectory: Arc<Mutex<Vec<RLMTrajectoryStep>>>,
+    /// Sandboxed, journaled workspace file access for scripts
+    workspace_fs: Arc<Mutex<WorkspaceFs>>,
+    /// Spend of the current script run, checked against its budget
+    budget: SharedBudget,
+    /// Budget each script run starts with (scripts can change it with set_budget)
+    default_budget: Budget,
+    /// Serves repeated spawn_atom calls from the workspace's .maker/cache when set
+    response_cache: Arc<Mutex<Option<ResponseCache>>>,
+}
+
+/// The runtime's shared handles, cloned into the script API functions
+#[derive(Clone)]
+struct ScriptContext {
+    shadow_git: Arc<Mutex<ShadowGit>>,
+    log: Arc<Mutex<Vec<ExecutionEvent>>>,
+    budget: SharedBudget,
+    response_cache: Arc<Mutex<Option<ResponseCache>>>,
+    workspace_fs: Arc<Mutex<WorkspaceFs>>,
+}
+
+impl ScriptContext {
+    /// The runtime's response cache, if one is set
+    fn cache(&self) -> Option<ResponseCache> {
+        self.response_cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.clone())
+    }
 }
 
 /// Events emitted during execution for the Cockpit
@@ -46,11 +122,17 @@ pub enum ExecutionEventType {
     ScriptStart,
     ScriptEnd,
     AtomSpawned,
+    /// Streamed output from a running atom
+    AtomDelta,
     AtomCompleted,
     ConsensusStart,
     ConsensusVote,
     ConsensusEnd,
     RedFlagDetected,
+    /// A budget limit was hit; the script is terminated and rolled back
+    BudgetExceeded,
+    /// A test suite ran, e.g. in tdd_loop
+    TestRun,
     Snapshot,
     Rollback,
     Error,
@@ -63,15 +145,38 @@ pub enum ExecutionEventType {
     RLMRegexFilter,
     RLMLoadContext,
     RLMFinal,
+    // Workspace file I/O events
+    FileRead,
+    FileWrite,
+    FileMove,
+    FileDelete,
 }
 
 impl CodeModeRuntime {
-    /// Create a new Code Mode Runtime
+    /// Create a new Code Mode Runtime, routing each atom type and budgeting runs per the saved settings
     pub fn new(workspace_path: &str) -> Result<Self, Box<EvalAltResult>> {
-        Self::with_config(workspace_path, LlmConfig::cerebras())
+        let settings = crate::load_cached_settings();
+        let budget = settings
+            .as_ref()
+            .map(|s| s.budget.clone())
+            .unwrap_or_default();
+        let response_cache = settings.as_ref().and_then(|s| s.response_cache.clone());
+        let mut runtime = Self::with_router(workspace_path, LlmRouter::from_settings(settings))?;
+        if let Some(config) = response_cache {
+            runtime = runtime.with_response_cache(config);
+        }
+        Ok(runtime.with_budget(budget))
+    }
+
+    /// Create a new Code Mode Runtime with a custom per-agent router
+    pub fn with_router(
+        workspace_path: &str,
+        llm_router: LlmRouter,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        Self::with_router_and_rlm(workspace_path, llm_router, RLMConfig::default())
     }
 
-    /// Create a new Code Mode Runtime with custom LLM config
+    /// Create a new Code Mode Runtime with one LLM config for every atom type
     pub fn with_config(
         workspace_path: &str,
         llm_config: LlmConfig,
@@ -84,6 +189,15 @@ impl CodeModeRuntime {
         workspace_path: &str,
         llm_config: LlmConfig,
         rlm_config: RLMConfig,
+    ) -> Result<Self, Box<EvalAltResult>> {
+        Self::with_router_and_rlm(workspace_path, LlmRouter::fixed(llm_config), rlm_config)
+    }
+
+    /// Create a new Code Mode Runtime with a custom per-agent router and RLM config
+    pub fn with_router_and_rlm(
+        workspace_path: &str,
+        llm_router: LlmRouter,
+        rlm_config: RLMConfig,
     ) -> Result<Self, Box<EvalAltResult>> {
         let mut engine = Engine::new();
 
@@ -95,13 +209,29 @@ impl CodeModeRuntime {
 
         let shadow_git = Arc::new(Mutex::new(ShadowGit::new(workspace_path)));
         let execution_log = Arc::new(Mutex::new(Vec::new()));
-        let llm_config = Arc::new(llm_config);
+        let llm_router = Arc::new(llm_router);
         let rlm_context_store = super::rlm::create_shared_store();
         let rlm_trajectory = Arc::new(Mutex::new(Vec::new()));
+        let workspace_fs = Arc::new(Mutex::new(WorkspaceFs::new(workspace_path)));
+        let budget = BudgetTracker::shared(Budget::default());
+        let response_cache = Arc::new(Mutex::new(None));
+
+        // Terminate the script at its next operation once the budget is spent.
+        // Termination cannot be caught by the script, and execute_script rolls it back.
+        let budget_progress = budget.clone();
+        engine.on_progress(move |_| {
+            budget_progress
+                .lock()
+                .ok()
+                .and_then(|tracker| tracker.exceeded())
+                .map(Dynamic::from)
+        });
 
         // Initialize the atom worker pool for safe async-to-sync bridging
         // This creates a dedicated runtime thread that handles all atom executions
-        super::atom_bridge::init_atom_pool(llm_config.clone()).map_err(|e| {
+        // Atoms pass their routed config per request; the pool default covers direct executor use
+        let pool_config = Arc::new(llm_router.config_for(AgentRole::Orchestrator));
+        super::atom_bridge::init_atom_pool(pool_config).map_err(|e| {
             Box::new(EvalAltResult::ErrorSystem(
                 "Atom pool initialization failed".into(),
                 Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)),
@@ -111,13 +241,18 @@ impl CodeModeRuntime {
         // Register MAKER API functions (including RLM functions)
         Self::register_api(
             &mut engine,
-            shadow_git.clone(),
-            execution_log.clone(),
             workspace_path.to_string(),
-            llm_config.clone(),
+            llm_router.clone(),
             rlm_context_store.clone(),
             rlm_trajectory.clone(),
             rlm_config.clone(),
+            ScriptContext {
+                shadow_git: shadow_git.clone(),
+                log: execution_log.clone(),
+                budget: budget.clone(),
+                response_cache: response_cache.clone(),
+                workspace_fs: workspace_fs.clone(),
+            },
         );
 
         Ok(Self {
@@ -125,32 +260,66 @@ impl CodeModeRuntime {
             shadow_git,
             workspace_path: workspace_path.to_string(),
             execution_log,
-            llm_config,
+            llm_router,
             rlm_context_store,
             rlm_config,
             rlm_trajectory,
+            workspace_fs,
+            budget,
+            default_budget: Budget::default(),
+            response_cache,
         })
     }
 
+    /// Set the budget every script run starts with
+    pub fn with_budget(mut self, budget: Budget) -> Self {
+        self.default_budget = budget.clone();
+        if let Ok(mut tracker) = self.budget.lock() {
+            tracker.set_budget(budget);
+        }
+        self
+    }
+
+    /// Serve repeated spawn_atom calls from the workspace's response cache
+    /// Consensus candidates always call the LLM so their votes stay independent.
+    pub fn with_response_cache(self, config: CacheConfig) -> Self {
+        match ResponseCache::open(&self.workspace_path, config) {
+            Ok(cache) => {
+                if let Ok(mut slot) = self.response_cache.lock() {
+                    *slot = Some(cache);
+                }
+            }
+            // A broken cache only costs speed, so fall back to uncached atoms
+            Err(e) => eprintln!("Warning: Response cache disabled: {}", e),
+        }
+        self
+    }
+
     /// Register the MAKER API into the Rhai engine
     fn register_api(
         engine: &mut Engine,
-        shadow_git: Arc<Mutex<ShadowGit>>,
-        log: Arc<Mutex<Vec<ExecutionEvent>>>,
         workspace_path: String,
-        llm_config: Arc<LlmConfig>,
+        llm_router: Arc<LlmRouter>,
         rlm_store: SharedRLMContextStore,
         rlm_trajectory: Arc<Mutex<Vec<RLMTrajectoryStep>>>,
         rlm_config: RLMConfig,
+        context: ScriptContext,
     ) {
+        let ScriptContext {
+            shadow_git,
+            log,
+            budget,
+            workspace_fs,
+            ..
+        } = &context;
+
         // Register AtomType enum
         engine.register_type_with_name::<AtomType>("AtomType");
         engine.register_static_module("AtomType", Self::create_atom_type_module().into());
 
         // Register spawn_atom function - bridges to async AtomExecutor
-        let config_spawn = llm_config.clone();
-        let ws_spawn = workspace_path.clone();
-        let log_spawn = log.clone();
+        let config_spawn = llm_router.clone();
+        let context_spawn = context.clone();
         engine.register_fn(
             "spawn_atom",
             move |atom_type: AtomType, prompt: &str| -> Dynamic {
@@ -158,51 +327,170 @@ impl CodeModeRuntime {
                     atom_type.clone(),
                     prompt,
                     SpawnFlags::default(),
-                    &config_spawn,
-                    &ws_spawn,
-                    &log_spawn,
+                    &config_spawn.config_for_atom(atom_type),
+                    context_spawn.cache(),
+                    &context_spawn,
+                    "spawn_atom",
                 )
             },
         );
 
         // Register spawn_atom_with_flags
-        let config_flags = llm_config.clone();
-        let ws_flags = workspace_path.clone();
-        let log_flags = log.clone();
+        let config_flags = llm_router.clone();
+        let context_flags = context.clone();
         engine.register_fn(
             "spawn_atom_with_flags",
-            move |atom_type: AtomType, prompt: &str, flags: Dynamic| -> Dynamic {
-                let spawn_flags: SpawnFlags = rhai::serde::from_dynamic(&flags).unwrap_or_default();
-                Self::execute_spawn_atom(
+            move |atom_type: AtomType,
+                  prompt: &str,
+                  flags: Dynamic|
+                  -> Result<Dynamic, Box<EvalAltResult>> {
+                let spawn_flags = Self::from_script::<SpawnFlags>(&flags)
+                    .and_then(|flags| flags.validate().map(|_| flags))
+                    .map_err(|e| format!("Invalid spawn flags: {}", e))?;
+                Ok(Self::execute_spawn_atom(
                     atom_type.clone(),
                     prompt,
                     spawn_flags,
-                    &config_flags,
-                    &ws_flags,
-                    &log_flags,
-                )
+                    &config_flags.config_for_atom(atom_type),
+                    context_flags.cache(),
+                    &context_flags,
+                    "spawn_atom_with_flags",
+                ))
             },
         );
 
         // Register run_consensus - bridges to async consensus voting
-        let config_consensus = llm_config.clone();
+        let config_consensus = llm_router.clone();
         let ws_consensus = workspace_path.clone();
         let log_consensus = log.clone();
+        let budget_consensus = budget.clone();
         engine.register_fn(
             "run_consensus",
             move |atom_type: AtomType, task: &str, k_threshold: i64| -> Dynamic {
+                let config = ConsensusConfig {
+                    k_threshold: k_threshold as usize,
+                    ..Self::governed_consensus_config()
+                };
                 Self::execute_consensus(
                     atom_type.clone(),
                     task,
-                    k_threshold as usize,
-                    &config_consensus,
+                    config,
+                    &config_consensus.config_for_atom(atom_type),
                     &ws_consensus,
                     &log_consensus,
+                    &budget_consensus,
                 )
             },
         );
 
-        // Register check_red_flags
+        // Register run_consensus_with - consensus with an options map, e.g.
+        // #{k: 2, max_atoms: 8, temperatures: [0.1, 0.7], models: [#{model: "llama3.1-8b"}]}
+        let config_consensus_with = llm_router.clone();
+        let ws_consensus_with = workspace_path.clone();
+        let log_consensus_with = log.clone();
+        let budget_consensus_with = budget.clone();
+        engine.register_fn(
+            "run_consensus_with",
+            move |atom_type: AtomType,
+                  task: &str,
+                  options: Dynamic|
+                  -> Result<Dynamic, Box<EvalAltResult>> {
+                let config = Self::from_script::<ConsensusOptions>(&options)
+                    .and_then(|options| options.apply(Self::governed_consensus_config()))
+                    .map_err(|e| format!("Invalid consensus options: {}", e))?;
+                Ok(Self::execute_consensus(
+                    atom_type.clone(),
+                    task,
+                    config,
+                    &config_consensus_with.config_for_atom(atom_type),
+                    &ws_consensus_with,
+                    &log_consensus_with,
+                    &budget_consensus_with,
+                ))
+            },
+        );
+
+        // Register tdd_loop - a Tester atom writes failing tests, then Coder consensus
+        // iterates on the test failures until they pass or max_iterations is reached
+        let router_tdd = llm_router.clone();
+        let ws_tdd = workspace_path.clone();
+        let context_tdd = context.clone();
+        engine.register_fn(
+            "tdd_loop",
+            move |task: &str, max_iterations: i64| -> Result<Dynamic, Box<EvalAltResult>> {
+                if max_iterations < 1 {
+                    return Err(format!(
+                        "tdd_loop needs at least one iteration, got {}",
+                        max_iterations
+                    )
+                    .into());
+                }
+                let mut steps = RuntimeTddSteps {
+                    llm_router: &router_tdd,
+                    workspace_path: &ws_tdd,
+                    context: &context_tdd,
+                };
+                let framework = detect_framework(std::path::Path::new(&ws_tdd));
+                let report = tdd_loop(task, max_iterations as usize, &framework, &mut steps);
+                Ok(Self::to_dynamic_or_error(
+                    &context_tdd.log,
+                    &report,
+                    "tdd_loop",
+                ))
+            },
+        );
+
+        // Register budget functions - limits apply to the rest of the current script run.
+        // A script can only tighten the budget it was started with; a limit <= 0 keeps it.
+        // set_budget(tokens, dollars): dollars may be an integer or a float
+        let budget_set = budget.clone();
+        engine.register_fn(
+            "set_budget",
+            move |tokens: i64, dollars: Dynamic| -> Result<(), Box<EvalAltResult>> {
+                let dollars = dollars
+                    .as_float()
+                    .or_else(|_| dollars.as_int().map(|d| d as f64))
+                    .map_err(|t| format!("set_budget: dollars must be a number, got {}", t))?;
+                if let Ok(mut tracker) = budget_set.lock() {
+                    let budget = Budget {
+                        max_tokens: (tokens > 0).then_some(tokens as u64),
+                        max_cost_usd: (dollars > 0.0).then_some(dollars),
+                        max_atoms: tracker.budget().max_atoms,
+                    };
+                    tracker.narrow(budget);
+                }
+                Ok(())
+            },
+        );
+
+        // set_atom_budget(max_atoms): caps atoms spawned, counting every consensus candidate
+        let budget_atoms = budget.clone();
+        engine.register_fn("set_atom_budget", move |max_atoms: i64| {
+            if let Ok(mut tracker) = budget_atoms.lock() {
+                let budget = Budget {
+                    max_atoms: (max_atoms > 0).then_some(max_atoms as usize),
+                    ..tracker.budget().clone()
+                };
+                tracker.narrow(budget);
+            }
+        });
+
+        let budget_status = budget.clone();
+        let log_status = log.clone();
+        engine.register_fn("budget_status", move || -> Dynamic {
+            let status = match budget_status.lock() {
+                Ok(tracker) => serde_json::json!({
+                    "tokens": tracker.spend().tokens,
+                    "cost_usd": tracker.spend().cost_usd,
+                    "atoms": tracker.spend().atoms,
+                    "budget": tracker.budget(),
+                    "exceeded": tracker.exceeded(),
+                }),
+                Err(_) => serde_json::Value::Null,
+            };
+            Self::to_dynamic_or_error(&log_status, &status, "budget_status")
+        });
+
         // Register check_red_flags
         engine.register_fn("check_red_flags", move |code: &str| -> bool {
             // Uses new governance check (Paper + Architecture)
@@ -249,6 +537,150 @@ impl CodeModeRuntime {
             }
         });
 
+        // =======================================================================
+        // Workspace File I/O API
+        // Paths are confined to WORKSPACE and every write is journaled so a failed
+        // script is restored together with the pre-execution snapshot
+        // =======================================================================
+
+        // Register read_file - read a UTF-8 file from the workspace
+        let fs_read = workspace_fs.clone();
+        let log_read = log.clone();
+        engine.register_fn(
+            "read_file",
+            move |path: &str| -> Result<String, Box<EvalAltResult>> {
+                Self::with_workspace_fs(&fs_read, &log_read, ExecutionEventType::FileRead, |fs| {
+                    fs.read_file(path)
+                })
+            },
+        );
+
+        // Register write_file - create or overwrite a file in the workspace
+        let fs_write = workspace_fs.clone();
+        let log_write = log.clone();
+        engine.register_fn(
+            "write_file",
+            move |path: &str, content: &str| -> Result<bool, Box<EvalAltResult>> {
+                Self::with_workspace_fs(
+                    &fs_write,
+                    &log_write,
+                    ExecutionEventType::FileWrite,
+                    |fs| fs.write_file(path, content).map(|_| true),
+                )
+            },
+        );
+
+        // Register apply_patch - apply a unified diff, returns the number of hunks applied
+        let fs_patch = workspace_fs.clone();
+        let log_patch = log.clone();
+        engine.register_fn(
+            "apply_patch",
+            move |path: &str, patch: &str| -> Result<i64, Box<EvalAltResult>> {
+                Self::with_workspace_fs(
+                    &fs_patch,
+                    &log_patch,
+                    ExecutionEventType::FileWrite,
+                    |fs| fs.apply_patch(path, patch).map(|hunks| hunks as i64),
+                )
+            },
+        );
+
+        // Register list_dir - list directory entries as maps (name, path, is_dir, size)
+        let fs_list = workspace_fs.clone();
+        let log_list = log.clone();
+        engine.register_fn(
+            "list_dir",
+            move |path: &str| -> Result<rhai::Array, Box<EvalAltResult>> {
+                let entries = Self::with_workspace_fs(
+                    &fs_list,
+                    &log_list,
+                    ExecutionEventType::FileRead,
+                    |fs| fs.list_dir(path),
+                )?;
+                Ok(entries
+                    .iter()
+                    .map(|entry| Self::to_dynamic_or_error(&log_list, entry, "list_dir"))
+                    .collect())
+            },
+        );
+
+        // Register move_file - rename a file within the workspace
+        let fs_move = workspace_fs.clone();
+        let log_move = log.clone();
+        engine.register_fn(
+            "move_file",
+            move |from: &str, to: &str| -> Result<bool, Box<EvalAltResult>> {
+                Self::with_workspace_fs(&fs_move, &log_move, ExecutionEventType::FileMove, |fs| {
+                    fs.move_file(from, to).map(|_| true)
+                })
+            },
+        );
+
+        // Register delete_file - remove a file from the workspace
+        let fs_delete = workspace_fs.clone();
+        let log_delete = log.clone();
+        engine.register_fn(
+            "delete_file",
+            move |path: &str| -> Result<bool, Box<EvalAltResult>> {
+                Self::with_workspace_fs(
+                    &fs_delete,
+                    &log_delete,
+                    ExecutionEventType::FileDelete,
+                    |fs| fs.delete_file(path).map(|_| true),
+                )
+            },
+        );
+
+        // =======================================================================
+        // Structural Edit API
+        // Parse-tree edits on workspace files; an edit whose result no longer
+        // parses is rejected and the file is left untouched
+        // =======================================================================
+
+        // Register ast_replace - apply an ast-grep rewrite rule (pattern + fix), returns the match count
+        let fs_ast = workspace_fs.clone();
+        let log_ast = log.clone();
+        engine.register_fn(
+            "ast_replace",
+            move |path: &str, pattern: &str, rewrite: &str| -> Result<i64, Box<EvalAltResult>> {
+                Self::with_workspace_fs(&fs_ast, &log_ast, ExecutionEventType::FileWrite, |fs| {
+                    let language = Self::edit_language(path)?;
+                    let mut count = 0;
+                    fs.edit_file(path, |code| {
+                        let (edited, replaced) =
+                            AstEditor::new().apply_rewrite(code, language, pattern, rewrite)?;
+                        count = replaced;
+                        Ok(edited)
+                    })?;
+                    Ok(count as i64)
+                })
+            },
+        );
+
+        // Register replace_symbol - replace a symbol's definition using its grits byte range
+        let fs_symbol = workspace_fs.clone();
+        let log_symbol = log.clone();
+        engine.register_fn(
+            "replace_symbol",
+            move |path: &str,
+                  symbol_id: &str,
+                  new_code: &str|
+                  -> Result<bool, Box<EvalAltResult>> {
+                Self::with_workspace_fs(
+                    &fs_symbol,
+                    &log_symbol,
+                    ExecutionEventType::FileWrite,
+                    |fs| {
+                        let language = Self::edit_language(path)?;
+                        fs.edit_file(path, |code| {
+                            let range = Self::locate_symbol(path, symbol_id, code, language)?;
+                            AstEditor::new().replace_range(code, language, range, new_code)
+                        })
+                    },
+                )
+            },
+        );
+
         // CRIT-4: Register rand() function for RLM voting
         // Used by voting.rhai for random selection in consensus
         engine.register_fn("rand", |max: i64| -> i64 {
@@ -496,9 +928,10 @@ impl CodeModeRuntime {
         );
 
         // Register llm_query - recursive sub-LM call (the core RLM capability)
-        let config_query = llm_config.clone();
+        let config_query = llm_router.clone();
         let traj_query = rlm_trajectory.clone();
         let log_query = log.clone();
+        let context_query = context.clone();
         engine.register_fn("llm_query", move |prompt: &str| -> Dynamic {
             let op_start = std::time::Instant::now();
             Self::log_rlm_trajectory(
@@ -526,9 +959,10 @@ impl CodeModeRuntime {
                     temperature: 0.1,
                     ..Default::default()
                 },
-                &config_query,
-                "",
-                &log_query,
+                &config_query.config_for_atom(AtomType::Planner),
+                None,
+                &context_query,
+                "llm_query",
             );
             let duration_ms = op_start.elapsed().as_millis() as u64;
 
@@ -556,10 +990,11 @@ impl CodeModeRuntime {
         });
 
         // Register spawn_rlm - RLM-aware atom spawning with context variable
-        let config_rlm = llm_config.clone();
+        let config_rlm = llm_router.clone();
         let store_rlm = rlm_store.clone();
         let log_rlm = log.clone();
         let traj_rlm = rlm_trajectory.clone();
+        let context_rlm = context.clone();
         engine.register_fn(
             "spawn_rlm",
             move |atom_type: AtomType, task: &str, context_var: &str| -> Dynamic {
@@ -606,9 +1041,10 @@ impl CodeModeRuntime {
                         max_tokens: Some(4000),
                         ..Default::default()
                     },
-                    &config_rlm,
-                    "",
-                    &log_rlm,
+                    &config_rlm.config_for_atom(atom_type),
+                    None,
+                    &context_rlm,
+                    "spawn_rlm",
                 );
                 let duration_ms = op_start.elapsed().as_millis() as u64;
 
@@ -815,6 +1251,175 @@ impl CodeModeRuntime {
         Ok(self.engine.compile(script)?)
     }
 
+    /// Static check for generated scripts: compile, then verify that every function call
+    /// resolves to a registered or script-defined signature with a matching argument count.
+    /// The first unknown call is reported as `ErrorFunctionNotFound` with its position.
+    /// Where a registered function takes an `AtomType`, a literal of another type
+    /// (`spawn_atom("Coder", ..)`) or an unknown `AtomType::` variant is an `ErrorMismatchDataType`.
+    /// Operators, namespaced calls (`AtomType::...`) and interpreter keywords are not checked.
+    pub fn check_script(&self, script: &str) -> Result<AST, Box<EvalAltResult>> {
+        let ast = self.compile(script)?;
+
+        let atom_type_params = self.atom_type_params();
+        let mut known = self.registered_signatures();
+        for function in ast.iter_functions() {
+            known
+                .entry(function.name.to_string())
+                .or_default()
+                .insert(function.params.len());
+        }
+
+        let mut unknown: Option<Box<EvalAltResult>> = None;
+        ast.walk(&mut |path: &[ASTNode]| {
+            let (call, pos, is_method) = match path.last() {
+                Some(ASTNode::Expr(Expr::FnCall(call, pos)))
+                | Some(ASTNode::Stmt(Stmt::FnCall(call, pos))) => (call, *pos, false),
+                Some(ASTNode::Expr(Expr::MethodCall(call, pos))) => (call, *pos, true),
+                _ => return true,
+            };
+            if call.is_operator_call()
+                || call.is_qualified()
+                || KEYWORD_FUNCTIONS.contains(&call.name.as_str())
+            {
+                return true;
+            }
+
+            // Method-call style passes the object as the first argument
+            let arity = call.args.len() + usize::from(is_method);
+            match known.get(call.name.as_str()) {
+                Some(arities) if arities.contains(&arity) => {}
+                arities => {
+                    unknown = Some(Self::unknown_call_error(&call.name, arity, arities, pos));
+                    return false;
+                }
+            }
+
+            let positions = atom_type_params.get(&(call.name.to_string(), arity));
+            for &index in positions.into_iter().flatten() {
+                let arg = if is_method {
+                    index.checked_sub(1).and_then(|index| call.args.get(index))
+                } else {
+                    call.args.get(index)
+                };
+                if let Some(found) = arg.and_then(Self::atom_type_mismatch) {
+                    unknown = Some(Box::new(EvalAltResult::ErrorMismatchDataType(
+                        format!("AtomType for argument {} of {}", index + 1, call.name),
+                        found,
+                        pos,
+                    )));
+                    return false;
+                }
+            }
+            true
+        });
+
+        match unknown {
+            Some(err) => Err(err),
+            None => Ok(ast),
+        }
+    }
+
+    /// Registered function names (including the standard packages) with their argument counts
+    fn registered_signatures(&self) -> HashMap<String, BTreeSet<usize>> {
+        let mut known: HashMap<String, BTreeSet<usize>> = HashMap::new();
+        for signature in self.engine.gen_fn_signatures(true) {
+            if let Some((name, params)) = Self::parse_signature(&signature) {
+                known.entry(name).or_default().insert(params.len());
+            }
+        }
+        known
+    }
+
+    /// Argument positions typed `AtomType`, by registered function name and argument count
+    fn atom_type_params(&self) -> HashMap<(String, usize), Vec<usize>> {
+        let mut params = HashMap::new();
+        for signature in self.engine.gen_fn_signatures(false) {
+            let Some((name, types)) = Self::parse_signature(&signature) else {
+                continue;
+            };
+            let positions: Vec<usize> = types
+                .iter()
+                .enumerate()
+                .filter(|(_, ty)| ty.as_str() == "AtomType")
+                .map(|(index, _)| index)
+                .collect();
+            if !positions.is_empty() {
+                params.insert((name, types.len()), positions);
+            }
+        }
+        params
+    }
+
+    /// What an argument passed for an `AtomType` really is, if it is certainly not one:
+    /// a literal of another type or an `AtomType::` name that is not a variant
+    fn atom_type_mismatch(arg: &Expr) -> Option<String> {
+        if let Expr::Variable(variable, ..) = arg {
+            let (_, name, namespace, _) = variable.as_ref();
+            let known = AtomType::from_str(name).is_some_and(|t| t.as_str() == name.as_str());
+            return (!namespace.is_empty() && namespace.root() == "AtomType" && !known)
+                .then(|| format!("AtomType::{}", name));
+        }
+        let value = arg.get_literal_value(None)?;
+        (!value.is::<AtomType>()).then(|| value.type_name().to_string())
+    }
+
+    /// Parse `name(a: T, b: U) -> R` into the function name and its parameter types
+    fn parse_signature(signature: &str) -> Option<(String, Vec<String>)> {
+        let open = signature.find('(')?;
+        let name = signature[..open].trim();
+
+        let mut depth = 0usize;
+        let mut params = vec![String::new()];
+        for ch in signature[open + 1..].chars() {
+            match ch {
+                ')' if depth == 0 => break,
+                ',' if depth == 0 => {
+                    params.push(String::new());
+                    continue;
+                }
+                '(' | '<' | '[' => depth += 1,
+                ')' | '>' | ']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            if let Some(param) = params.last_mut() {
+                param.push(ch);
+            }
+        }
+
+        // Keep the type of `name: Type`
+        let types: Vec<String> = params
+            .iter()
+            .map(|param| {
+                let ty = param.split_once(':').map_or(param.as_str(), |(_, ty)| ty);
+                ty.trim().to_string()
+            })
+            .collect();
+        if types.len() == 1 && types[0].is_empty() {
+            return Some((name.to_string(), Vec::new()));
+        }
+        Some((name.to_string(), types))
+    }
+
+    /// Build the error reported for a call that matches no registered signature
+    fn unknown_call_error(
+        name: &str,
+        arity: usize,
+        registered: Option<&BTreeSet<usize>>,
+        pos: Position,
+    ) -> Box<EvalAltResult> {
+        let hint = registered
+            .map(|arities| {
+                let counts: Vec<String> = arities.iter().map(|n| n.to_string()).collect();
+                format!(" (registered with {} argument(s))", counts.join(" or "))
+            })
+            .unwrap_or_default();
+
+        Box::new(EvalAltResult::ErrorFunctionNotFound(
+            format!("{} with {} argument(s){}", name, arity, hint),
+            pos,
+        ))
+    }
+
     /// HIGH-7: Validate a Rhai script before execution
     /// Returns Ok(()) if script is syntactically valid, Err with parse errors otherwise
     pub fn validate_script(&self, script: &str) -> Result<(), Box<EvalAltResult>> {
@@ -869,6 +1474,16 @@ impl CodeModeRuntime {
             None
         };
 
+        // Start a fresh file journal so every write made by this script can be undone
+        if let Ok(mut fs) = self.workspace_fs.lock() {
+            fs.begin_transaction();
+        }
+
+        // Each script run is budgeted from zero
+        if let Ok(mut tracker) = self.budget.lock() {
+            *tracker = BudgetTracker::new(self.default_budget.clone());
+        }
+
         // Log script start
         if let Ok(mut log) = self.execution_log.lock() {
             log.push(ExecutionEvent {
@@ -912,6 +1527,35 @@ impl CodeModeRuntime {
                     }
                 }
             }
+
+            // Restore journaled writes - covers untracked files and workspaces without a repo
+            if let Ok(mut fs) = self.workspace_fs.lock() {
+                if fs.has_pending_writes() {
+                    match fs.restore() {
+                        Ok(restored) => {
+                            Self::log_event(
+                                &self.execution_log,
+                                ExecutionEventType::Rollback,
+                                &format!(
+                                    "Restored {} file(s) written by the script",
+                                    restored.len()
+                                ),
+                                Some(
+                                    serde_json::json!({"paths": restored, "reason": "script_failure"}),
+                                ),
+                            );
+                        }
+                        Err(e) => {
+                            Self::log_event(
+                                &self.execution_log,
+                                ExecutionEventType::Error,
+                                &format!("File restore failed: {}", e),
+                                None,
+                            );
+                        }
+                    }
+                }
+            }
         }
 
         // Log script end
@@ -977,16 +1621,46 @@ impl CodeModeRuntime {
         Ok(())
     }
 
+    /// Deserialize a script value (e.g. an options map) via JSON
+    /// rhai's serde will not narrow its f64 floats to the f32 fields used for temperatures.
+    fn from_script<T: serde::de::DeserializeOwned>(value: &Dynamic) -> Result<T, String> {
+        rhai::serde::from_dynamic::<serde_json::Value>(value)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_value(json).map_err(|e| e.to_string()))
+    }
+
     /// Execute spawn_atom by bridging to async AtomExecutor
     /// Uses the dedicated AtomWorkerPool for safe async-to-sync bridging
     fn execute_spawn_atom(
         atom_type: AtomType,
         prompt: &str,
         flags: SpawnFlags,
-        _llm_config: &LlmConfig,
-        _workspace_path: &str,
-        log: &Arc<Mutex<Vec<ExecutionEvent>>>,
+        llm_config: &LlmConfig,
+        cache: Option<ResponseCache>,
+        context: &ScriptContext,
+        source: &str,
     ) -> Dynamic {
+        let result =
+            Self::spawn_atom_result(atom_type, prompt, flags, llm_config, cache, context, source);
+        Self::to_dynamic_or_error(&context.log, &result, "atom")
+    }
+
+    /// Run a single atom, logging and charging it like spawn_atom
+    /// `cache` serves it from the response cache; None always calls the LLM.
+    fn spawn_atom_result(
+        atom_type: AtomType,
+        prompt: &str,
+        flags: SpawnFlags,
+        llm_config: &LlmConfig,
+        cache: Option<ResponseCache>,
+        context: &ScriptContext,
+        source: &str,
+    ) -> AtomResult {
+        let ScriptContext { log, budget, .. } = context;
+        if let Err(reason) = Self::admit_budget(budget, log) {
+            return AtomResult::failure(atom_type, reason.clone(), vec![reason]);
+        }
+
         // Log atom spawned
         Self::log_event(
             log,
@@ -998,12 +1672,35 @@ impl CodeModeRuntime {
         // Build the atom input
         let input = AtomInput::new(atom_type.clone(), prompt).with_flags(flags);
 
+        // Forward output deltas to the execution log so the cockpit shows live output
+        let delta_log = log.clone();
+        let on_delta: DeltaCallback = Arc::new(move |delta: &str| {
+            Self::log_delta(&delta_log, None, delta);
+        });
+
         // Use the worker pool bridge for safe async-to-sync execution
         // This avoids creating a new runtime per call and handles async context properly
-        let result = super::atom_bridge::execute_atom_sync(input);
+        let result = super::atom_bridge::execute_atom_stream_sync(
+            input,
+            Some(llm_config.clone()),
+            cache,
+            StreamOptions::default().with_on_delta(on_delta),
+        );
 
         match result {
             Ok(atom_result) => {
+                Self::charge_budget(
+                    budget,
+                    log,
+                    CostEntry {
+                        source: source.to_string(),
+                        atom_type,
+                        atoms: 1,
+                        tokens: atom_result.tokens_used as u64,
+                        cost_usd: atom_result.cost_usd,
+                    },
+                );
+
                 // Log completion
                 Self::log_event(
                     log,
@@ -1014,19 +1711,7 @@ impl CodeModeRuntime {
                         .ok(),
                 );
 
-                // HIGH-1: Better error handling for serialization
-                match rhai::serde::to_dynamic(&atom_result) {
-                    Ok(dynamic) => dynamic,
-                    Err(e) => {
-                        Self::log_event(
-                            log,
-                            ExecutionEventType::Error,
-                            &format!("Failed to serialize atom result: {}", e),
-                            None,
-                        );
-                        Dynamic::UNIT
-                    }
-                }
+                atom_result
             }
             Err(e) => {
                 Self::log_event(
@@ -1036,91 +1721,142 @@ impl CodeModeRuntime {
                     None,
                 );
 
-                let error_result = AtomResult::failure(atom_type, e.clone(), vec![e]);
-                // HIGH-1: Better error handling for serialization
-                match rhai::serde::to_dynamic(&error_result) {
-                    Ok(dynamic) => dynamic,
-                    Err(ser_err) => {
-                        Self::log_event(
-                            log,
-                            ExecutionEventType::Error,
-                            &format!("Failed to serialize error result: {}", ser_err),
-                            None,
-                        );
-                        Dynamic::UNIT
-                    }
-                }
+                AtomResult::failure(atom_type, e.clone(), vec![e])
             }
         }
     }
 
+    /// Consensus defaults for scripts: streamed candidates, aborted on the verbosity red flag
+    fn governed_consensus_config() -> ConsensusConfig {
+        ConsensusConfig::default().with_governance(&GovernanceConfig::default())
+    }
+
     /// Execute consensus voting by bridging to async voting
+    /// Each counted candidate is logged as a `ConsensusVote` event and shown in the VotingState;
+    /// the finished run is appended to the workspace's voting ledger.
     fn execute_consensus(
         atom_type: AtomType,
         task: &str,
-        k_threshold: usize,
+        config: ConsensusConfig,
         llm_config: &LlmConfig,
         workspace_path: &str,
         log: &Arc<Mutex<Vec<ExecutionEvent>>>,
+        budget: &SharedBudget,
     ) -> Dynamic {
+        let result = Self::consensus_result(
+            atom_type,
+            task,
+            config,
+            llm_config,
+            workspace_path,
+            log,
+            budget,
+        );
+        Self::to_dynamic_or_error(log, &result, "consensus")
+    }
+
+    /// Run consensus voting, logging, recording and charging it like run_consensus
+    fn consensus_result(
+        atom_type: AtomType,
+        task: &str,
+        mut config: ConsensusConfig,
+        llm_config: &LlmConfig,
+        workspace_path: &str,
+        log: &Arc<Mutex<Vec<ExecutionEvent>>>,
+        budget: &SharedBudget,
+    ) -> ConsensusResult {
+        if let Err(reason) = Self::admit_budget(budget, log) {
+            return ConsensusResult::failure(reason, HashMap::new(), 0, 0, 0);
+        }
+
+        // Adaptive mode: pick the smallest k that meets the reliability target given history
+        let k_selection = config.adaptive_k.as_ref().map(|adaptive| {
+            let entries = VotingLedger::open(workspace_path)
+                .entries()
+                .unwrap_or_else(|e| {
+                    eprintln!("Warning: Voting history unavailable: {}", e);
+                    Vec::new()
+                });
+            adaptive.select(&entries, atom_type, config.k_threshold)
+        });
+        if let Some(selection) = &k_selection {
+            config.k_threshold = selection.k;
+        }
+
         // Log consensus start
         Self::log_event(
             log,
             ExecutionEventType::ConsensusStart,
-            &format!("Starting k={} consensus for {:?}", k_threshold, atom_type),
-            None,
+            &format!(
+                "Starting k={} consensus for {:?} (max {} atoms)",
+                config.k_threshold, atom_type, config.max_atoms
+            ),
+            serde_json::to_value(&config).ok(),
         );
+        let task_id = content_hash(task);
+        crate::begin_voting(&task_id, task);
 
-        let config = ConsensusConfig {
-            k_threshold,
-            ..Default::default()
+        // Every candidate is an LLM call, so cap the vote at what is left of the budget
+        if let Ok(tracker) = budget.lock() {
+            if let Some(remaining) = tracker.remaining_atoms() {
+                config.max_atoms = config.max_atoms.min(remaining);
+            }
+            config.max_cost_usd = tracker.remaining_cost_usd();
+        }
+
+        let delta_log = log.clone();
+        let on_delta: CandidateDeltaCallback = Arc::new(move |candidate: usize, delta: &str| {
+            Self::log_delta(&delta_log, Some(candidate), delta);
+        });
+        let vote_log = log.clone();
+        let vote_task_id = task_id.clone();
+        let votes: Arc<Mutex<Vec<CandidateVote>>> = Arc::new(Mutex::new(Vec::new()));
+        let ledger_votes = votes.clone();
+        let on_vote: VoteCallback = Arc::new(move |vote: &CandidateVote| {
+            Self::log_vote(&vote_log, vote);
+            crate::record_vote(&vote_task_id, vote);
+            if let Ok(mut votes) = ledger_votes.lock() {
+                votes.push(vote.clone());
+            }
+        });
+        let observer = ConsensusObserver {
+            on_delta: Some(on_delta),
+            on_vote: Some(on_vote),
         };
 
         // Bridge async to sync
-        let result = match tokio::runtime::Handle::try_current() {
-            Ok(handle) => {
-                let llm = llm_config.clone();
-                let ws = workspace_path.to_string();
-                let task_str = task.to_string();
-                let at = atom_type.clone();
-
-                std::thread::scope(|s| {
-                    s.spawn(move || {
-                        handle.block_on(voting_run_consensus(at, &task_str, config, &llm, &ws))
-                    })
-                    .join()
-                    .unwrap_or_else(|_| {
-                        ConsensusResult::failure(
-                            "Thread panicked".to_string(),
-                            HashMap::new(),
-                            0,
-                            0,
-                            0,
-                        )
-                    })
-                })
-            }
-            Err(_) => match tokio::runtime::Runtime::new() {
-                Ok(rt) => {
-                    let llm = llm_config.clone();
-                    let ws = workspace_path.to_string();
-                    rt.block_on(voting_run_consensus(
-                        atom_type.clone(),
-                        &ws,
-                        config,
-                        &llm,
-                        &ws,
-                    ))
-                }
-                Err(e) => ConsensusResult::failure(
-                    format!("Failed to create runtime: {}", e),
-                    HashMap::new(),
-                    0,
-                    0,
-                    0,
-                ),
+        let llm = llm_config.clone();
+        let task_str = task.to_string();
+        let consensus = run_consensus_observed(
+            atom_type,
+            &task_str,
+            config.clone(),
+            &llm,
+            workspace_path,
+            observer,
+        );
+        let mut result = Self::block_on(consensus)
+            .unwrap_or_else(|e| ConsensusResult::failure(e, HashMap::new(), 0, 0, 0));
+        result.k_selection = k_selection;
+        crate::end_voting(&task_id, &result);
+
+        let votes = votes.lock().map(|votes| votes.clone()).unwrap_or_default();
+        let entry = LedgerEntry::new(task, atom_type, &config, &result, &votes);
+        if let Err(e) = VotingLedger::open(workspace_path).append(&entry) {
+            eprintln!("Warning: Failed to record consensus run: {}", e);
+        }
+
+        Self::charge_budget(
+            budget,
+            log,
+            CostEntry {
+                source: "run_consensus".to_string(),
+                atom_type,
+                atoms: result.atoms_spawned,
+                tokens: result.total_tokens as u64,
+                cost_usd: result.cost_usd,
             },
-        };
+        );
 
         // Log consensus end
         Self::log_event(
@@ -1136,7 +1872,83 @@ impl CodeModeRuntime {
                 .ok(),
         );
 
-        Self::to_dynamic_or_error(log, &result, "consensus")
+        result
+    }
+
+    /// Run a future to completion from synchronous Rhai code
+    /// Inside a Tokio runtime the future runs on a scoped thread, since the worker cannot block.
+    fn block_on<F>(future: F) -> Result<F::Output, String>
+    where
+        F: std::future::Future + Send,
+        F::Output: Send,
+    {
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => std::thread::scope(|s| {
+                s.spawn(move || handle.block_on(future))
+                    .join()
+                    .map_err(|_| "Thread panicked".to_string())
+            }),
+            Err(_) => tokio::runtime::Runtime::new()
+                .map(|rt| rt.block_on(future))
+                .map_err(|e| format!("Failed to create runtime: {}", e)),
+        }
+    }
+
+    /// Log a counted consensus candidate with its class hash and the running tally
+    fn log_vote(log: &Arc<Mutex<Vec<ExecutionEvent>>>, vote: &CandidateVote) {
+        let message = match (&vote.hash, &vote.red_flag, &vote.error) {
+            (Some(hash), _, _) => format!(
+                "Candidate {} voted for {} ({} votes)",
+                vote.candidate,
+                hash,
+                vote.tally.get(hash).copied().unwrap_or(0)
+            ),
+            (None, Some(reason), _) => {
+                format!("Candidate {} discarded: {}", vote.candidate, reason)
+            }
+            (None, None, Some(error)) => format!("Candidate {} failed: {}", vote.candidate, error),
+            (None, None, None) => format!("Candidate {} discarded", vote.candidate),
+        };
+        Self::log_event(
+            log,
+            ExecutionEventType::ConsensusVote,
+            &message,
+            serde_json::to_value(vote).ok(),
+        );
+    }
+
+    /// Refuse an LLM call once the run's budget is spent
+    /// The refusal is sticky, so the progress hook terminates the script right after.
+    fn admit_budget(
+        budget: &SharedBudget,
+        log: &Arc<Mutex<Vec<ExecutionEvent>>>,
+    ) -> Result<(), String> {
+        let admitted = match budget.lock() {
+            Ok(mut tracker) => tracker.admit(),
+            Err(_) => Err("Failed to acquire budget lock".to_string()),
+        };
+        if let Err(reason) = &admitted {
+            Self::log_event(log, ExecutionEventType::BudgetExceeded, reason, None);
+        }
+        admitted
+    }
+
+    /// Record the spend of an LLM operation against the run's budget
+    fn charge_budget(
+        budget: &SharedBudget,
+        log: &Arc<Mutex<Vec<ExecutionEvent>>>,
+        entry: CostEntry,
+    ) {
+        let exceeded = match budget.lock() {
+            Ok(mut tracker) => {
+                tracker.charge(entry);
+                tracker.exceeded()
+            }
+            Err(_) => None,
+        };
+        if let Some(reason) = exceeded {
+            Self::log_event(log, ExecutionEventType::BudgetExceeded, &reason, None);
+        }
     }
 
     /// Helper to log execution events
@@ -1159,6 +1971,146 @@ impl CodeModeRuntime {
         }
     }
 
+    /// Log a streamed output delta, tagged with the consensus candidate index if any
+    /// Consecutive deltas are coalesced: one from the same candidate is appended to that
+    /// candidate's event in the trailing run of `AtomDelta` events, so a streamed atom adds
+    /// one event per candidate rather than one per token.
+    fn log_delta(log: &Arc<Mutex<Vec<ExecutionEvent>>>, candidate: Option<usize>, delta: &str) {
+        let Ok(mut events) = log.lock() else {
+            return;
+        };
+        let previous = events
+            .iter_mut()
+            .rev()
+            .take_while(|e| matches!(e.event_type, ExecutionEventType::AtomDelta))
+            .find(|e| {
+                e.data
+                    .as_ref()
+                    .is_some_and(|d| d["candidate"] == serde_json::json!(candidate))
+            });
+        if let Some(event) = previous {
+            event.message.push_str(delta);
+            event.data =
+                Some(serde_json::json!({ "candidate": candidate, "delta": event.message }));
+            return;
+        }
+        drop(events);
+
+        Self::log_event(
+            log,
+            ExecutionEventType::AtomDelta,
+            delta,
+            Some(serde_json::json!({ "candidate": candidate, "delta": delta })),
+        );
+    }
+
+    /// Helper to run a sandboxed file operation, logging every touched path
+    /// Errors are logged and raised as Rhai runtime errors so the script fails (and rolls back)
+    fn with_workspace_fs<T>(
+        workspace_fs: &Arc<Mutex<WorkspaceFs>>,
+        log: &Arc<Mutex<Vec<ExecutionEvent>>>,
+        event_type: ExecutionEventType,
+        op: impl FnOnce(&mut WorkspaceFs) -> Result<T, String>,
+    ) -> Result<T, Box<EvalAltResult>> {
+        let mut fs = workspace_fs
+            .lock()
+            .map_err(|_| "Failed to acquire workspace fs lock".to_string())?;
+
+        let touched_before = fs.touched_paths().len();
+        match op(&mut fs) {
+            Ok(value) => {
+                let touched: Vec<FileAccess> = fs.touched_paths()[touched_before..].to_vec();
+                let paths: Vec<&str> = touched.iter().map(|t| t.path.as_str()).collect();
+                Self::log_event(
+                    log,
+                    event_type,
+                    &format!(
+                        "{}: {}",
+                        touched
+                            .first()
+                            .map(|t| format!("{:?}", t.operation))
+                            .unwrap_or_default(),
+                        paths.join(" -> ")
+                    ),
+                    serde_json::to_value(&touched)
+                        .map_err(|e| eprintln!("Serialization warning: {}", e))
+                        .ok(),
+                );
+                Ok(value)
+            }
+            Err(e) => {
+                Self::log_event(log, ExecutionEventType::Error, &e, None);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Language used for structural edits, detected from the file extension
+    fn edit_language(path: &str) -> Result<SupportedLanguage, String> {
+        SupportedLanguage::from_path(path)
+            .ok_or_else(|| format!("Structural edits are not supported for '{}'", path))
+    }
+
+    /// Locate a symbol's byte range in the current content of `path`
+    /// The symbol is resolved by parsing the current content; the range recorded in the cached
+    /// grits SymbolGraph only picks between same-named definitions, since it may be stale
+    fn locate_symbol(
+        path: &str,
+        symbol_id: &str,
+        code: &str,
+        language: SupportedLanguage,
+    ) -> Result<(usize, usize), String> {
+        let file = path.trim_start_matches("./").replace('\\', "/");
+        let name = symbol_id.rsplit("::").next().unwrap_or(symbol_id);
+
+        let recorded = crate::grits::get_cached_graph().and_then(|graph| {
+            let qualified = format!("{}::{}", file, symbol_id);
+            graph
+                .nodes
+                .get(symbol_id)
+                .or_else(|| graph.nodes.get(&qualified))
+                .filter(|symbol| symbol.file_path.replace('\\', "/") == file)
+                .and_then(|symbol| symbol.byte_range)
+        });
+
+        Self::resolve_symbol_range(code, language, name, recorded)
+            .ok_or_else(|| format!("Symbol '{}' not found in '{}'", symbol_id, path))
+    }
+
+    /// Byte range of the definition of `name` in `code`
+    /// A recorded range is used only if it is still exactly one of those definitions, or one
+    /// wrapped in `export`/`export default` (grits records the whole export statement).
+    /// Otherwise the first definition wins.
+    fn resolve_symbol_range(
+        code: &str,
+        language: SupportedLanguage,
+        name: &str,
+        recorded: Option<(usize, usize)>,
+    ) -> Option<(usize, usize)> {
+        let definitions: Vec<(usize, usize)> = AstEditor::new()
+            .extract_symbols(code, language)
+            .into_iter()
+            .filter(|symbol| symbol.name == name)
+            .map(|symbol| symbol.byte_range)
+            .collect();
+
+        let still_defines = |(start, end): (usize, usize)| {
+            definitions.iter().any(|&(def_start, def_end)| {
+                def_end == end
+                    && def_start >= start
+                    && code.get(start..def_start).is_some_and(|prefix| {
+                        prefix
+                            .split_whitespace()
+                            .all(|word| matches!(word, "export" | "default"))
+                    })
+            })
+        };
+        match recorded {
+            Some(range) if still_defines(range) => Some(range),
+            _ => definitions.first().copied(),
+        }
+    }
+
     /// Helper to convert a serializable value to Dynamic, logging errors instead of silently discarding them
     fn to_dynamic_or_error<T: serde::Serialize>(
         log: &Arc<Mutex<Vec<ExecutionEvent>>>,
@@ -1286,6 +2238,14 @@ impl CodeModeRuntime {
         }
     }
 
+    /// Get every workspace path touched by the last script execution
+    pub fn get_touched_paths(&self) -> Vec<FileAccess> {
+        self.workspace_fs
+            .lock()
+            .map(|fs| fs.touched_paths().to_vec())
+            .unwrap_or_default()
+    }
+
     /// Get the RLM trajectory for visualization
     pub fn get_rlm_trajectory(&self) -> Vec<RLMTrajectoryStep> {
         self.rlm_trajectory
@@ -1306,12 +2266,128 @@ impl CodeModeRuntime {
         self.rlm_context_store.clone()
     }
 
+    /// Spend of the current (or last) script run
+    pub fn get_spend(&self) -> Spend {
+        self.budget
+            .lock()
+            .map(|tracker| tracker.spend().clone())
+            .unwrap_or_default()
+    }
+
     /// Get RLM configuration
     pub fn get_rlm_config(&self) -> &RLMConfig {
         &self.rlm_config
     }
 }
 
+/// `tdd_loop` steps backed by the runtime's atoms, workspace files and snapshots
+/// Atoms and consensus runs are logged and charged against the script's budget like
+/// spawn_atom and run_consensus.
+struct RuntimeTddSteps<'a> {
+    llm_router: &'a LlmRouter,
+    workspace_path: &'a str,
+    context: &'a ScriptContext,
+}
+
+impl TddSteps for RuntimeTddSteps<'_> {
+    fn write_tests(&mut self, prompt: &str) -> Result<Vec<CodeChange>, String> {
+        let config = self.llm_router.config_for_atom(AtomType::Tester);
+        let result = CodeModeRuntime::spawn_atom_result(
+            AtomType::Tester,
+            prompt,
+            SpawnFlags::default(),
+            &config,
+            None,
+            self.context,
+            "tdd_loop",
+        );
+        if !result.valid {
+            return Err(result.errors.join("; "));
+        }
+        Ok(AtomExecutor::new(config).parse_code_output(&result.output))
+    }
+
+    fn implement(&mut self, prompt: &str) -> Result<Option<Vec<CodeChange>>, String> {
+        let config = self.llm_router.config_for_atom(AtomType::Coder);
+        let consensus = ConsensusConfig {
+            compile_check: Some(CompileCheck::default()),
+            ..CodeModeRuntime::governed_consensus_config()
+        };
+        let result = CodeModeRuntime::consensus_result(
+            AtomType::Coder,
+            prompt,
+            consensus,
+            &config,
+            self.workspace_path,
+            &self.context.log,
+            &self.context.budget,
+        );
+        // Out of budget, every later vote would be refused too
+        if let Some(reason) = self.context.budget.lock().ok().and_then(|t| t.exceeded()) {
+            return Err(reason);
+        }
+        Ok(result
+            .winner
+            .filter(|_| result.reached)
+            .map(|winner| AtomExecutor::new(config).parse_code_output(&winner)))
+    }
+
+    fn apply(&mut self, changes: &[CodeChange]) -> Result<(), String> {
+        CodeModeRuntime::with_workspace_fs(
+            &self.context.workspace_fs,
+            &self.context.log,
+            ExecutionEventType::FileWrite,
+            |fs| {
+                changes.iter().try_for_each(|change| {
+                    fs.write_file(&change.file_path, &format!("{}\n", change.content))
+                })
+            },
+        )
+        .map_err(|e| e.to_string())
+    }
+
+    fn run_tests(
+        &mut self,
+        changed_files: Option<&[String]>,
+    ) -> Result<TestExecutionResult, String> {
+        let options = TestRunOptions {
+            changed_files: changed_files.map(<[String]>::to_vec),
+            ..Default::default()
+        };
+        let result = CodeModeRuntime::block_on(run_test_suite(self.workspace_path, &options))
+            .and_then(|r| r)?;
+        CodeModeRuntime::log_event(
+            &self.context.log,
+            ExecutionEventType::TestRun,
+            &format!(
+                "{} of {} tests passed ({})",
+                result.passed, result.total_tests, result.command
+            ),
+            serde_json::to_value(TddRun::from(&result)).ok(),
+        );
+        Ok(result)
+    }
+
+    fn snapshot(&mut self, message: &str) -> Option<String> {
+        let snapshot = self.context.shadow_git.lock().ok()?.snapshot(message);
+        match snapshot {
+            Ok(snapshot) => {
+                CodeModeRuntime::log_event(
+                    &self.context.log,
+                    ExecutionEventType::Snapshot,
+                    &format!("{}: {}", message, snapshot.id),
+                    Some(serde_json::json!({"snapshot_id": snapshot.id})),
+                );
+                Some(snapshot.id)
+            }
+            Err(e) => {
+                eprintln!("Warning: tdd_loop snapshot failed: {}", e);
+                None
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1536,6 +2612,24 @@ mod tests {
         assert!(trajectory.len() >= 3); // At least load, peek, chunk
     }
 
+    #[test]
+    fn test_log_delta_coalesces_per_candidate() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        CodeModeRuntime::log_delta(&log, None, "a");
+        CodeModeRuntime::log_delta(&log, None, "b");
+        CodeModeRuntime::log_delta(&log, Some(0), "x");
+        CodeModeRuntime::log_delta(&log, Some(1), "y");
+        CodeModeRuntime::log_delta(&log, Some(0), "z");
+        CodeModeRuntime::log_event(&log, ExecutionEventType::AtomCompleted, "done", None);
+        CodeModeRuntime::log_delta(&log, None, "c");
+
+        let events = log.lock().unwrap();
+        let messages: Vec<&str> = events.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, vec!["ab", "xz", "y", "done", "c"]);
+        assert_eq!(events[1].data.as_ref().unwrap()["delta"], "xz");
+        assert_eq!(events[1].data.as_ref().unwrap()["candidate"], 0);
+    }
+
     #[test]
     fn test_execution_log_rlm_events() {
         let runtime = create_test_runtime();
@@ -1581,6 +2675,295 @@ mod tests {
         assert!(config.use_sub_model);
     }
 
+    #[test]
+    fn test_rhai_workspace_file_io() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let runtime = CodeModeRuntime::new(temp_dir.path().to_str().unwrap()).unwrap();
+
+        let script = r#"
+            write_file("src/hello.txt", "one\ntwo\n");
+            apply_patch("src/hello.txt", "@@ -1,2 +1,2 @@\n one\n-two\n+three\n");
+            move_file("src/hello.txt", "src/moved.txt");
+            let entries = list_dir("src");
+            [read_file("src/moved.txt"), entries.len()]
+        "#;
+
+        let result = runtime.execute_script(script).unwrap();
+        let arr = result.into_array().unwrap();
+        assert_eq!(arr[0].clone().into_string().unwrap(), "one\nthree\n");
+        assert_eq!(arr[1].as_int().unwrap(), 1);
+
+        let touched = runtime.get_touched_paths();
+        assert!(touched.iter().any(|t| t.path == "src/moved.txt"));
+        let log = runtime.get_execution_log();
+        assert!(log
+            .iter()
+            .any(|e| matches!(e.event_type, ExecutionEventType::FileWrite)));
+    }
+
+    #[test]
+    fn test_rhai_workspace_rejects_escape() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let runtime = CodeModeRuntime::new(temp_dir.path().to_str().unwrap()).unwrap();
+
+        let result = runtime.execute_script(r#"write_file("../escape.txt", "x")"#);
+        assert!(result.is_err());
+        assert!(!temp_dir
+            .path()
+            .parent()
+            .unwrap()
+            .join("escape.txt")
+            .exists());
+    }
+
+    #[test]
+    fn test_spawn_flags_are_validated() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let runtime = CodeModeRuntime::new(temp_dir.path().to_str().unwrap()).unwrap();
+
+        for flags in [
+            "#{ temperature: 5.0 }",
+            "#{ require_json: \"yes\" }",
+            "#{ output_schema: #{ \"type\": 12 } }",
+        ] {
+            let script = format!("spawn_atom_with_flags(AtomType::Coder, \"x\", {})", flags);
+            let err = runtime.execute_script(&script).unwrap_err();
+            assert!(
+                err.to_string().contains("Invalid spawn flags"),
+                "{}: {}",
+                flags,
+                err
+            );
+        }
+
+        // Well-formed flags (rhai floats included) get as far as the atom budget
+        let limited = CodeModeRuntime::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_budget(Budget {
+                max_atoms: Some(0),
+                ..Default::default()
+            });
+        let script = r#"
+            spawn_atom_with_flags(AtomType::Coder, "x", #{ temperature: 0.5, max_tokens: 100 });
+            let after = 1;
+        "#;
+        let err = limited.execute_script(script).unwrap_err();
+        assert!(
+            matches!(*err, EvalAltResult::ErrorTerminated(..)),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_script_consensus_temperatures_reach_the_sampler() {
+        let engine = Engine::new();
+        let options = engine
+            .eval::<Dynamic>("#{ k: 2, temperatures: [0.2, 0.9] }")
+            .unwrap();
+        let config = CodeModeRuntime::from_script::<ConsensusOptions>(&options)
+            .and_then(|options| options.apply(ConsensusConfig::default()))
+            .unwrap();
+        assert_eq!(config.sampling.temperatures, vec![0.2, 0.9]);
+
+        let sampler = config
+            .sampling
+            .sampler(&LlmConfig::default(), &LlmRouter::default())
+            .unwrap();
+        let temperatures: Vec<f32> = (0..3)
+            .map(|candidate| sampler.sample(candidate, "task").0.temperature)
+            .collect();
+        assert_eq!(temperatures, vec![0.2, 0.9, 0.2]);
+    }
+
+    #[test]
+    fn test_failed_script_restores_writes() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("existing.txt"), "original").unwrap();
+        let runtime = CodeModeRuntime::new(temp_dir.path().to_str().unwrap()).unwrap();
+
+        let script = r#"
+            write_file("existing.txt", "changed");
+            write_file("created.txt", "new");
+            delete_file("existing.txt");
+            throw "boom";
+        "#;
+
+        assert!(runtime.execute_script(script).is_err());
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("existing.txt")).unwrap(),
+            "original"
+        );
+        assert!(!temp_dir.path().join("created.txt").exists());
+    }
+
+    #[test]
+    fn test_budget_terminates_script_and_rolls_back() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("existing.txt"), "original").unwrap();
+        let runtime = CodeModeRuntime::new(temp_dir.path().to_str().unwrap())
+            .unwrap()
+            .with_budget(Budget {
+                max_atoms: Some(0),
+                ..Default::default()
+            });
+
+        // The script cannot lift the settings budget, so the atom is refused
+        // and the script is terminated at its next operation
+        let script = r#"
+            set_atom_budget(5);
+            set_budget(0, 0);
+            set_budget(1000, 2.5);
+            write_file("existing.txt", "changed");
+            spawn_atom(AtomType::Coder, "never runs");
+            write_file("after.txt", "unreachable");
+        "#;
+
+        let err = runtime.execute_script(script).unwrap_err();
+        match *err {
+            EvalAltResult::ErrorTerminated(reason, _) => {
+                assert!(reason.to_string().contains("Atom budget exhausted"))
+            }
+            other => panic!("expected termination, got {}", other),
+        }
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("existing.txt")).unwrap(),
+            "original"
+        );
+        assert!(!temp_dir.path().join("after.txt").exists());
+
+        let budget = runtime.budget.lock().unwrap().budget().clone();
+        assert_eq!(budget.max_atoms, Some(0));
+        assert_eq!(budget.max_tokens, Some(1000));
+        assert_eq!(budget.max_cost_usd, Some(2.5));
+    }
+
+    #[test]
+    fn test_stale_recorded_range_is_re_resolved() {
+        let code =
+            "fn caller() {\n    add(1, 2);\n}\n\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let rust = SupportedLanguage::Rust;
+        let add_start = code.find("fn add").unwrap();
+        let add = (add_start, code.len() - 1);
+        let caller = (0, code.find("}\n").unwrap() + 1);
+
+        // The range now covers `caller`, whose body mentions `add`: not trusted
+        assert_eq!(
+            CodeModeRuntime::resolve_symbol_range(code, rust, "add", Some(caller)),
+            Some(add)
+        );
+        assert_eq!(
+            CodeModeRuntime::resolve_symbol_range(code, rust, "add", Some(add)),
+            Some(add)
+        );
+        assert_eq!(
+            CodeModeRuntime::resolve_symbol_range(code, rust, "missing", Some(add)),
+            None
+        );
+
+        // grits records exported TypeScript functions with their export statement
+        let ts = "export function greet(): string {\n  return \"hi\";\n}\n";
+        let statement = (0, ts.len() - 1);
+        assert_eq!(
+            CodeModeRuntime::resolve_symbol_range(
+                ts,
+                SupportedLanguage::TypeScript,
+                "greet",
+                Some(statement)
+            ),
+            Some(statement)
+        );
+    }
+
+    #[test]
+    fn test_rhai_structural_edits() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(temp_dir.path().join("src")).unwrap();
+        std::fs::write(
+            temp_dir.path().join("src/lib.rs"),
+            "fn add(a: i32, b: i32) -> i32 {\n    a - b\n}\n\nfn main() {}\n",
+        )
+        .unwrap();
+        std::fs::write(temp_dir.path().join("src/app.js"), "var x = 1;\n").unwrap();
+        let runtime = CodeModeRuntime::new(temp_dir.path().to_str().unwrap()).unwrap();
+
+        let script = r#"
+            replace_symbol("src/lib.rs", "add", "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}");
+            ast_replace("src/app.js", "var $A = $B", "let $A = $B")
+        "#;
+
+        let replaced = runtime.execute_script(script).unwrap();
+        assert_eq!(replaced.as_int().unwrap(), 1);
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("src/lib.rs")).unwrap(),
+            "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nfn main() {}\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(temp_dir.path().join("src/app.js")).unwrap(),
+            "let x = 1;\n"
+        );
+
+        // An edit that breaks parsing fails the script and leaves the file intact
+        assert!(runtime
+            .execute_script(r#"replace_symbol("src/lib.rs", "main", "fn main( {")"#)
+            .is_err());
+        assert!(std::fs::read_to_string(temp_dir.path().join("src/lib.rs"))
+            .unwrap()
+            .contains("fn main() {}"));
+    }
+
+    #[test]
+    fn test_check_script_reports_unknown_calls() {
+        let runtime = create_test_runtime();
+
+        let valid = r#"
+            fn describe(r) { "valid: " + r.valid }
+            let result = spawn_atom(AtomType::Coder, "Implement parser");
+            if !result.valid { throw "Task failed"; }
+            log(describe(result));
+            [1, 2].len()
+        "#;
+        assert!(runtime.check_script(valid).is_ok());
+
+        let err = runtime
+            .check_script("let k = 3;\nlet r = spawn_atom(\"Coder\", \"task\", k);")
+            .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("spawn_atom with 3 argument(s)"));
+        assert!(message.contains("line 2"));
+
+        let err = runtime
+            .check_script("vote(AtomType::Coder, \"task\", 3)")
+            .unwrap_err();
+        assert!(err.to_string().contains("vote with 3 argument(s)"));
+
+        // Atom types are checked where the signature asks for one
+        let err = runtime
+            .check_script("spawn_atom(\"Coder\", \"x\")")
+            .unwrap_err();
+        assert!(
+            matches!(*err, EvalAltResult::ErrorMismatchDataType(ref expected, ref found, _)
+                if expected.contains("argument 1 of spawn_atom") && found == "string"),
+            "{}",
+            err
+        );
+        let err = runtime
+            .check_script("run_consensus(AtomType::Codr, \"x\", 3)")
+            .unwrap_err();
+        assert!(err.to_string().contains("AtomType::Codr"), "{}", err);
+        let atom = "let t = AtomType::Reviewer; spawn_atom(t, \"x\"); spawn_atom(AtomType::RLMProcessor, \"y\")";
+        assert!(runtime.check_script(atom).is_ok());
+    }
+
+    #[test]
+    fn test_rhai_string_literal_escapes() {
+        let runtime = create_test_runtime();
+        let text = "Say \"hi\"\nthen C:\\temp";
+        let script = format!("let s = {}; s", rhai_string_literal(text));
+        let result = runtime.execute_script(&script).unwrap();
+        assert_eq!(result.into_string().unwrap(), text);
+    }
+
     #[test]
     fn test_atom_type_module_includes_rlm_processor() {
         let runtime = create_test_runtime();
@@ -0,0 +1,292 @@
+// Cerebras-MAKER: Sampling Schedules for Voting
+// Consensus only adds reliability if candidates fail independently. Samples drawn with the
+// same model, temperature and prompt tend to make the same mistakes, so a schedule varies them:
+//   temperatures - cycled per candidate, e.g. a ramp from 0.1 to 0.9
+//   models       - rotation across models, on the base provider or any configured provider
+//   paraphrases  - alternative phrasings of the task, as templates containing `{task}`
+// Candidate indices count through every combination of the dimensions, models fastest, then
+// temperatures, then paraphrases. Every candidate records the `SampleConfig` that produced it,
+// so winning mixes can be compared across runs.
+
+use crate::llm::{LlmConfig, LlmRouter, ProviderType};
+use serde::{Deserialize, Serialize};
+
+/// Placeholder for the original task in paraphrase templates
+pub const TASK_PLACEHOLDER: &str = "{task}";
+
+/// A model to sample candidates from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelChoice {
+    /// Settings provider ID ("openai", "anthropic", "cerebras", "openrouter", "ollama",
+    /// "llamacpp"); None keeps the provider of the atom's config
+    #[serde(default)]
+    pub provider: Option<String>,
+    pub model: String,
+}
+
+/// Evenly spaced temperatures from `from` to `to`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TemperatureRamp {
+    pub from: f32,
+    pub to: f32,
+    pub steps: usize,
+}
+
+impl TemperatureRamp {
+    pub fn temperatures(&self) -> Vec<f32> {
+        match self.steps {
+            0 => Vec::new(),
+            1 => vec![self.from],
+            steps => (0..steps)
+                .map(|i| self.from + (self.to - self.from) * i as f32 / (steps - 1) as f32)
+                .collect(),
+        }
+    }
+}
+
+/// How consensus candidates are varied; empty samples every candidate the same way
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingSchedule {
+    /// Sampling temperatures to rotate through; empty uses the config's temperature
+    #[serde(default)]
+    pub temperatures: Vec<f32>,
+    /// Models to rotate through; empty uses the config's model
+    #[serde(default)]
+    pub models: Vec<ModelChoice>,
+    /// Task templates containing `{task}`, used after the task as given
+    #[serde(default)]
+    pub paraphrases: Vec<String>,
+}
+
+/// The sampling settings that produced a candidate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SampleConfig {
+    pub provider: ProviderType,
+    pub model: String,
+    pub temperature: f32,
+    /// Prompt variant: 0 is the task as given, n is `paraphrases[n - 1]`
+    pub paraphrase: usize,
+}
+
+impl SamplingSchedule {
+    pub fn is_empty(&self) -> bool {
+        self.temperatures.is_empty() && self.models.is_empty() && self.paraphrases.is_empty()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(t) = self.temperatures.iter().find(|t| !(0.0..=2.0).contains(*t)) {
+            return Err(format!(
+                "Temperatures must be between 0.0 and 2.0, got {}",
+                t
+            ));
+        }
+        if self
+            .models
+            .iter()
+            .any(|choice| choice.model.trim().is_empty())
+        {
+            return Err("Sampling models need a model name".to_string());
+        }
+        if let Some(paraphrase) = self
+            .paraphrases
+            .iter()
+            .find(|p| !p.contains(TASK_PLACEHOLDER))
+        {
+            return Err(format!(
+                "Paraphrase must contain {}: {}",
+                TASK_PLACEHOLDER, paraphrase
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resolve the schedule's models against `base`, the config the atom would otherwise use
+    pub fn sampler(&self, base: &LlmConfig, router: &LlmRouter) -> Result<Sampler, String> {
+        self.validate()?;
+        let configs = if self.models.is_empty() {
+            vec![base.clone()]
+        } else {
+            self.models
+                .iter()
+                .map(|choice| {
+                    let mut config = match &choice.provider {
+                        None => LlmConfig {
+                            model: choice.model.clone(),
+                            ..base.clone()
+                        },
+                        Some(provider) => router
+                            .config_for_model(provider, &choice.model)
+                            .ok_or_else(|| {
+                                format!("Unknown provider '{}' in sampling schedule", provider)
+                            })?,
+                    };
+                    config.temperature = base.temperature;
+                    Ok(config)
+                })
+                .collect::<Result<Vec<_>, String>>()?
+        };
+
+        Ok(Sampler {
+            temperatures: self.temperatures.clone(),
+            paraphrases: self.paraphrases.clone(),
+            configs,
+        })
+    }
+}
+
+/// A schedule with its models resolved to LLM configs
+#[derive(Debug, Clone)]
+pub struct Sampler {
+    temperatures: Vec<f32>,
+    paraphrases: Vec<String>,
+    configs: Vec<LlmConfig>,
+}
+
+impl Sampler {
+    /// LLM config, task and sample record for a candidate
+    /// `candidate` is read as a mixed-radix number, so consecutive candidates step through
+    /// every model, temperature and paraphrase combination before any repeats.
+    pub fn sample(&self, candidate: usize, task: &str) -> (LlmConfig, String, SampleConfig) {
+        let mut rest = candidate;
+        let mut next = |len: usize| {
+            let digit = rest % len;
+            rest /= len;
+            digit
+        };
+
+        let mut config = self.configs[next(self.configs.len())].clone();
+        if !self.temperatures.is_empty() {
+            config.temperature = self.temperatures[next(self.temperatures.len())];
+        }
+
+        let paraphrase = next(self.paraphrases.len() + 1);
+        let task = match paraphrase {
+            0 => task.to_string(),
+            n => self.paraphrases[n - 1].replace(TASK_PLACEHOLDER, task),
+        };
+
+        let sample = SampleConfig {
+            provider: config.provider.clone(),
+            model: config.model.clone(),
+            temperature: config.temperature,
+            paraphrase,
+        };
+        (config, task, sample)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_step_through_every_combination() {
+        let schedule = SamplingSchedule {
+            temperatures: TemperatureRamp {
+                from: 0.0,
+                to: 1.0,
+                steps: 3,
+            }
+            .temperatures(),
+            models: vec![
+                ModelChoice {
+                    provider: None,
+                    model: "fast".to_string(),
+                },
+                ModelChoice {
+                    provider: Some("ollama".to_string()),
+                    model: "qwen2.5-coder".to_string(),
+                },
+            ],
+            paraphrases: vec!["In other words: {task}".to_string()],
+        };
+        let base = LlmConfig::mock("sampling");
+        let sampler = schedule
+            .sampler(&base, &LlmRouter::fixed(base.clone()))
+            .unwrap();
+
+        let (config, task, sample) = sampler.sample(0, "Add a cache");
+        assert_eq!(config.provider, ProviderType::Replay);
+        assert_eq!(config.model, "fast");
+        assert_eq!(task, "Add a cache");
+        assert_eq!(sample.temperature, 0.0);
+
+        let (config, task, sample) = sampler.sample(1, "Add a cache");
+        assert_eq!(config.provider, ProviderType::Ollama);
+        assert_eq!(task, "Add a cache");
+        assert_eq!(
+            sample,
+            SampleConfig {
+                provider: ProviderType::Ollama,
+                model: "qwen2.5-coder".to_string(),
+                temperature: 0.0,
+                paraphrase: 0,
+            }
+        );
+
+        let (_, task, sample) = sampler.sample(9, "Add a cache");
+        assert_eq!(task, "In other words: Add a cache");
+        assert_eq!(
+            (sample.model.as_str(), sample.temperature, sample.paraphrase),
+            ("qwen2.5-coder", 0.5, 1)
+        );
+
+        // 2 models x 3 temperatures x 2 prompts: each combination once, then again in order
+        let combinations: Vec<(String, u32, usize)> = (0..12)
+            .map(|candidate| {
+                let (_, _, sample) = sampler.sample(candidate, "Add a cache");
+                (
+                    sample.model,
+                    (sample.temperature * 10.0) as u32,
+                    sample.paraphrase,
+                )
+            })
+            .collect();
+        let distinct: std::collections::HashSet<_> = combinations.iter().collect();
+        assert_eq!(distinct.len(), 12);
+        assert_eq!(
+            sampler.sample(12, "Add a cache").2,
+            sampler.sample(0, "Add a cache").2
+        );
+    }
+
+    #[test]
+    fn test_empty_schedule_keeps_the_base_config() {
+        let mut base = LlmConfig::mock("sampling");
+        base.temperature = 0.3;
+        let sampler = SamplingSchedule::default()
+            .sampler(&base, &LlmRouter::fixed(base.clone()))
+            .unwrap();
+        let (config, task, sample) = sampler.sample(7, "task");
+        assert_eq!((config.model.as_str(), config.temperature), ("mock", 0.3));
+        assert_eq!((task.as_str(), sample.paraphrase), ("task", 0));
+    }
+
+    #[test]
+    fn test_invalid_schedules_are_rejected() {
+        let base = LlmConfig::mock("sampling");
+        let router = LlmRouter::fixed(base.clone());
+        let unknown = SamplingSchedule {
+            models: vec![ModelChoice {
+                provider: Some("gemini".to_string()),
+                model: "pro".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(unknown.sampler(&base, &router).is_err());
+
+        let no_placeholder = SamplingSchedule {
+            paraphrases: vec!["Rephrased task".to_string()],
+            ..Default::default()
+        };
+        assert!(no_placeholder.validate().is_err());
+
+        let too_hot = SamplingSchedule {
+            temperatures: vec![0.2, 2.5],
+            ..Default::default()
+        };
+        assert!(too_hot.validate().is_err());
+    }
+}
@@ -8,8 +8,13 @@ use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Maximum number of snapshots to prevent unbounded memory growth
+/// Snapshots owned by a plan run are not counted; the run releases them itself
 const MAX_SNAPSHOTS: usize = 100;
 
+/// MAKER's own state (run logs, ledgers, snapshot index) lives here and is never
+/// snapshotted, so a rollback cannot rewind it
+const MAKER_DIR: &str = ".maker";
+
 /// Snapshot metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Snapshot {
@@ -17,6 +22,9 @@ pub struct Snapshot {
     pub message: String,
     pub timestamp_ms: u64,
     pub commit_hash: Option<String>,
+    /// Plan run that owns this snapshot; such snapshots are never evicted
+    #[serde(default)]
+    pub run_id: Option<String>,
 }
 
 /// Shadow Git - provides transactional file system operations
@@ -99,9 +107,63 @@ impl ShadowGit {
                     // Initialize new repo
                     gix::init(&self.workspace_path)?;
                     self.repo = Some(gix::open(&self.workspace_path)?);
+                    self.ensure_identity()?;
                 }
             }
         }
+        self.exclude_maker_dir()
+    }
+
+    /// Give a repo we created a local committer identity when none is configured,
+    /// otherwise every snapshot commit fails
+    fn ensure_identity(&self) -> Result<()> {
+        let configured = std::process::Command::new("git")
+            .current_dir(&self.workspace_path)
+            .args(["config", "user.email"])
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false);
+        if configured {
+            return Ok(());
+        }
+        for (key, value) in [
+            ("user.name", "Cerebras-MAKER"),
+            ("user.email", "maker@localhost"),
+        ] {
+            let output = std::process::Command::new("git")
+                .current_dir(&self.workspace_path)
+                .args(["config", key, value])
+                .output()?;
+            if !output.status.success() {
+                return Err(anyhow!("Failed to set {} for the shadow repository", key));
+            }
+        }
+        Ok(())
+    }
+
+    /// Keep .maker/ out of `git status` and snapshots via .git/info/exclude
+    fn exclude_maker_dir(&self) -> Result<()> {
+        let Some(ref repo) = self.repo else {
+            return Ok(());
+        };
+        let exclude_path = repo.git_dir().join("info").join("exclude");
+        let entry = format!("/{}/", MAKER_DIR);
+        let existing = std::fs::read_to_string(&exclude_path).unwrap_or_default();
+        if existing.lines().any(|line| line.trim() == entry) {
+            return Ok(());
+        }
+        if let Some(parent) = exclude_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let separator = if existing.is_empty() || existing.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        std::fs::write(
+            &exclude_path,
+            format!("{}{}{}\n", existing, separator, entry),
+        )?;
         Ok(())
     }
 
@@ -110,17 +172,36 @@ impl ShadowGit {
     /// HIGH-14: Now persists snapshot state after creation
     /// Enforces MAX_SNAPSHOTS limit by removing oldest snapshots when exceeded
     pub fn snapshot(&mut self, message: &str) -> Result<Snapshot> {
-        // Enforce max snapshots limit by removing oldest when limit is reached
-        while self.snapshots.len() >= MAX_SNAPSHOTS {
-            self.snapshots.remove(0);
-            // Adjust current_snapshot_idx after removal
-            if let Some(idx) = self.current_snapshot_idx {
-                if idx > 0 {
-                    self.current_snapshot_idx = Some(idx - 1);
-                } else {
-                    self.current_snapshot_idx = None;
-                }
-            }
+        self.take_snapshot(message, None)
+    }
+
+    /// Create a snapshot owned by a plan run
+    /// It is kept out of the MAX_SNAPSHOTS eviction until `release_run` drops it
+    pub fn snapshot_for_run(&mut self, message: &str, run_id: &str) -> Result<Snapshot> {
+        self.take_snapshot(message, Some(run_id))
+    }
+
+    /// Forget the snapshots owned by a plan run (their commits stay in history)
+    pub fn release_run(&mut self, run_id: &str) {
+        while let Some(idx) = self
+            .snapshots
+            .iter()
+            .position(|s| s.run_id.as_deref() == Some(run_id))
+        {
+            self.remove_snapshot(idx);
+        }
+        if let Err(e) = self.persist_snapshots() {
+            eprintln!("Warning: Failed to persist snapshot state: {}", e);
+        }
+    }
+
+    fn take_snapshot(&mut self, message: &str, run_id: Option<&str>) -> Result<Snapshot> {
+        // Enforce max snapshots limit by removing the oldest shared snapshot
+        while self.snapshots.iter().filter(|s| s.run_id.is_none()).count() >= MAX_SNAPSHOTS {
+            let Some(oldest) = self.snapshots.iter().position(|s| s.run_id.is_none()) else {
+                break;
+            };
+            self.remove_snapshot(oldest);
         }
 
         let timestamp = SystemTime::now()
@@ -128,7 +209,13 @@ impl ShadowGit {
             .unwrap_or_default()
             .as_millis() as u64;
 
-        let id = format!("snap_{}", timestamp);
+        // Snapshots taken within the same millisecond still need distinct IDs
+        let mut id = format!("snap_{}", timestamp);
+        let mut suffix = 1;
+        while self.snapshots.iter().any(|s| s.id == id) {
+            id = format!("snap_{}_{}", timestamp, suffix);
+            suffix += 1;
+        }
 
         let commit_hash = if self.repo.is_some() {
             // Stage all changes and create a commit
@@ -146,6 +233,7 @@ impl ShadowGit {
             message: message.to_string(),
             timestamp_ms: timestamp,
             commit_hash,
+            run_id: run_id.map(str::to_string),
         };
 
         self.snapshots.push(snapshot.clone());
@@ -159,10 +247,30 @@ impl ShadowGit {
         Ok(snapshot)
     }
 
-    /// Stage all changes in the workspace
+    /// Drop a snapshot from the index, keeping current_snapshot_idx on the same snapshot
+    fn remove_snapshot(&mut self, idx: usize) {
+        self.snapshots.remove(idx);
+        self.current_snapshot_idx = match self.current_snapshot_idx {
+            Some(current) if current > idx => Some(current - 1),
+            Some(current) if current == idx => current.checked_sub(1),
+            other => other,
+        };
+    }
+
+    /// Stage all changes in the workspace except .maker/
     /// Uses git command for reliable cross-platform staging
     #[cfg(not(feature = "native-git"))]
     fn stage_all(&self) -> Result<()> {
+        // .maker/ is excluded from new files; also drop it from the index in case an
+        // earlier commit tracked it (the files themselves stay on disk)
+        let output = std::process::Command::new("git")
+            .current_dir(&self.workspace_path)
+            .args(["rm", "-r", "-q", "--cached", "--ignore-unmatch", MAKER_DIR])
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!("Failed to unstage {}", MAKER_DIR));
+        }
+
         let output = std::process::Command::new("git")
             .current_dir(&self.workspace_path)
             .args(["add", "-A"])
@@ -238,7 +346,7 @@ impl ShadowGit {
         let prev_snapshot = &self.snapshots[prev_idx];
 
         if let Some(ref hash) = prev_snapshot.commit_hash {
-            self.reset_hard(hash)?;
+            self.restore_snapshot(hash)?;
         }
 
         // Remove the current snapshot
@@ -270,7 +378,7 @@ impl ShadowGit {
         let snapshot = &self.snapshots[idx];
 
         if let Some(ref hash) = snapshot.commit_hash {
-            self.reset_hard(hash)?;
+            self.restore_snapshot(hash)?;
         }
 
         // Truncate snapshots after this point
@@ -285,6 +393,28 @@ impl ShadowGit {
         Ok(())
     }
 
+    /// Reset to a snapshot commit while keeping .maker/ as it is now. Snapshots taken
+    /// before .maker/ was excluded still track it, and checking those out would rewind
+    /// the voting ledger and run logs, so the directory is parked in the git dir meanwhile
+    fn restore_snapshot(&self, commit_hash: &str) -> Result<()> {
+        let maker_dir = self.workspace_path.join(MAKER_DIR);
+        let parked = match self.repo {
+            Some(ref repo) if maker_dir.exists() => repo.git_dir().join("maker-rollback"),
+            _ => return self.reset_hard(commit_hash),
+        };
+        if parked.exists() {
+            std::fs::remove_dir_all(&parked)?;
+        }
+        std::fs::rename(&maker_dir, &parked)?;
+
+        let reset = self.reset_hard(commit_hash);
+        if maker_dir.exists() {
+            std::fs::remove_dir_all(&maker_dir)?;
+        }
+        std::fs::rename(&parked, &maker_dir)?;
+        reset
+    }
+
     /// Perform a hard reset to a specific commit
     /// Uses git command for reliable reset with working directory update
     #[cfg(not(feature = "native-git"))]
@@ -308,7 +438,7 @@ impl ShadowGit {
     }
 
     /// Stage all changes using native gix index API
-    /// Walks the worktree and adds all files to the index
+    /// Walks the worktree and adds all files to the index, skipping .git/ and .maker/
     #[cfg(feature = "native-git")]
     pub fn stage_all_native(&self) -> Result<(), String> {
         let repo = self.repo.as_ref().ok_or("Repository not initialized")?;
@@ -323,8 +453,10 @@ impl ShadowGit {
         let walker = walkdir::WalkDir::new(workdir)
             .into_iter()
             .filter_entry(|e| {
-                // Skip .git directory
+                // Skip .git and .maker directories
+                let rel = e.path().strip_prefix(workdir).unwrap_or(e.path());
                 !e.path().components().any(|c| c.as_os_str() == ".git")
+                    && !rel.starts_with(MAKER_DIR)
             });
 
         for entry in walker.filter_map(|e| e.ok()) {
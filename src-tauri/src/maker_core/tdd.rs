@@ -0,0 +1,702 @@
+// Cerebras-MAKER: Test-Driven Repair Loop
+// Atoms are judged by executable acceptance criteria rather than reviewer opinion:
+//   1. a Tester atom writes tests for the task
+//   2. the tests run and must fail - tests that already pass do not capture the change
+//   3. Coder atoms implement the change under consensus
+//   4. the tests run again
+//   5. failing assertions and stack traces go into the next Coder prompt
+// until the tests pass or the iteration limit is reached. The tests and every iteration's
+// code are ShadowGit snapshots, so any step can be rolled back to.
+
+use crate::agents::CodeChange;
+use crate::handlers::testing::{
+    FailedTest, TestCaseResult, TestExecutionResult, TestFrameworkInfo, TestStatus,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// Failing tests quoted in a Coder prompt
+const MAX_FAILURES: usize = 10;
+
+/// Characters kept of each failure message
+const FAILURE_CHARS: usize = 2000;
+
+/// Characters of runner output quoted when no test result could be parsed, e.g. a build error
+const OUTPUT_CHARS: usize = 4000;
+
+/// The operations the loop is made of
+/// Implemented by the Rhai runtime and by test doubles.
+pub trait TddSteps {
+    /// Run a Tester atom and return the test files it wrote
+    fn write_tests(&mut self, prompt: &str) -> Result<Vec<CodeChange>, String>;
+    /// Run Coder consensus and return the winner's files; None if no consensus was reached
+    fn implement(&mut self, prompt: &str) -> Result<Option<Vec<CodeChange>>, String>;
+    /// Write files to the workspace
+    fn apply(&mut self, changes: &[CodeChange]) -> Result<(), String>;
+    /// Run the tests affected by `changed_files`, or the whole suite for None
+    fn run_tests(
+        &mut self,
+        changed_files: Option<&[String]>,
+    ) -> Result<TestExecutionResult, String>;
+    /// Snapshot the workspace, returning the snapshot ID
+    fn snapshot(&mut self, message: &str) -> Option<String>;
+}
+
+/// How a loop ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TddStatus {
+    /// The tests pass
+    Green,
+    /// The tests still fail after the last iteration
+    IterationLimit,
+    /// The new tests passed before any code was written, so they cannot judge the change
+    TestsAlreadyPass,
+    /// The Tester atom wrote no test files, or none of them ran
+    NoTests,
+    /// A step could not be carried out; see `TddReport::error`
+    Error,
+}
+
+/// Outcome of one test run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TddRun {
+    pub success: bool,
+    pub total_tests: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub failures: Vec<FailedTest>,
+}
+
+impl From<&TestExecutionResult> for TddRun {
+    fn from(result: &TestExecutionResult) -> Self {
+        Self {
+            success: result.success,
+            total_tests: result.total_tests,
+            passed: result.passed,
+            failed: result.failed,
+            failures: result.failed_tests.clone(),
+        }
+    }
+}
+
+/// One round of implementing and testing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TddIteration {
+    /// 1-based
+    pub iteration: usize,
+    pub consensus_reached: bool,
+    /// Files written by the winning Coder candidate
+    pub files: Vec<String>,
+    /// Test files the winner tried to rewrite; those edits were dropped
+    pub ignored_files: Vec<String>,
+    pub snapshot: Option<String>,
+    /// None if the iteration wrote nothing to test
+    pub tests: Option<TddRun>,
+}
+
+/// Result of `tdd_loop`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TddReport {
+    pub task: String,
+    /// The tests pass
+    pub success: bool,
+    pub status: TddStatus,
+    pub test_files: Vec<String>,
+    pub tests_snapshot: Option<String>,
+    /// The run confirming the new tests fail
+    pub initial_run: Option<TddRun>,
+    pub iterations: Vec<TddIteration>,
+    pub error: Option<String>,
+}
+
+impl TddReport {
+    fn finish(mut self, status: TddStatus) -> Self {
+        self.status = status;
+        self.success = status == TddStatus::Green;
+        self
+    }
+
+    fn fail(mut self, error: String) -> Self {
+        self.error = Some(error);
+        self.finish(TddStatus::Error)
+    }
+}
+
+/// Write failing tests for `task`, then let Coder consensus make them pass
+pub fn tdd_loop(
+    task: &str,
+    max_iterations: usize,
+    framework: &TestFrameworkInfo,
+    steps: &mut impl TddSteps,
+) -> TddReport {
+    let mut report = TddReport {
+        task: task.to_string(),
+        success: false,
+        status: TddStatus::Error,
+        test_files: Vec::new(),
+        tests_snapshot: None,
+        initial_run: None,
+        iterations: Vec::new(),
+        error: None,
+    };
+
+    let tests = match steps.write_tests(&tester_prompt(task, framework)) {
+        Ok(tests) => tests,
+        Err(e) => return report.fail(format!("Tester atom failed: {}", e)),
+    };
+    if tests.is_empty() {
+        return report.finish(TddStatus::NoTests);
+    }
+    report.test_files = tests.iter().map(|t| t.file_path.clone()).collect();
+    if let Err(e) = steps.apply(&tests) {
+        return report.fail(format!("Failed to write tests: {}", e));
+    }
+    report.tests_snapshot = steps.snapshot("tdd_loop: failing tests");
+
+    let initial = match run_acceptance(steps, &tests, &report.test_files) {
+        Ok(result) => result,
+        Err(e) => return report.fail(format!("Failed to run tests: {}", e)),
+    };
+    report.initial_run = Some(TddRun::from(&initial));
+    // Tests ran, but not the new ones; a build error reports no tests and goes to the Coder
+    let ran_tests = initial.success || !initial.tests.is_empty();
+    if ran_tests && !ran_all(&initial, &tests) {
+        return report.finish(TddStatus::NoTests);
+    }
+    if passes(&initial, &tests) {
+        return report.finish(TddStatus::TestsAlreadyPass);
+    }
+
+    let test_paths: BTreeSet<String> = report
+        .test_files
+        .iter()
+        .map(|path| normalize_path(path))
+        .collect();
+    let mut feedback = failure_feedback(&initial);
+    for iteration in 1..=max_iterations {
+        let prompt = coder_prompt(task, &tests, &feedback);
+        let winner = match steps.implement(&prompt) {
+            Ok(winner) => winner,
+            Err(e) => return report.fail(format!("Coder consensus failed: {}", e)),
+        };
+        let mut round = TddIteration {
+            iteration,
+            consensus_reached: winner.is_some(),
+            files: Vec::new(),
+            ignored_files: Vec::new(),
+            snapshot: None,
+            tests: None,
+        };
+
+        // The tests are the acceptance criteria; a Coder does not get to rewrite them
+        let (ignored, changes): (Vec<CodeChange>, Vec<CodeChange>) = winner
+            .unwrap_or_default()
+            .into_iter()
+            .partition(|change| test_paths.contains(&normalize_path(&change.file_path)));
+        round.ignored_files = ignored.into_iter().map(|c| c.file_path).collect();
+        if changes.is_empty() {
+            report.iterations.push(round);
+            continue;
+        }
+
+        round.files = changes.iter().map(|c| c.file_path.clone()).collect();
+        if let Err(e) = steps.apply(&changes) {
+            report.iterations.push(round);
+            return report.fail(format!("Failed to write Coder changes: {}", e));
+        }
+        round.snapshot = steps.snapshot(&format!("tdd_loop iteration {}", iteration));
+
+        let changed: Vec<String> = report
+            .test_files
+            .iter()
+            .chain(round.files.iter())
+            .cloned()
+            .collect();
+        let result = match run_acceptance(steps, &tests, &changed) {
+            Ok(result) => result,
+            Err(e) => {
+                report.iterations.push(round);
+                return report.fail(format!("Failed to run tests: {}", e));
+            }
+        };
+        round.tests = Some(TddRun::from(&result));
+        report.iterations.push(round);
+        if passes(&result, &tests) {
+            return report.finish(TddStatus::Green);
+        }
+        feedback = failure_feedback(&result);
+    }
+
+    report.finish(TddStatus::IterationLimit)
+}
+
+/// Run the tests affected by `changed`, falling back to the whole suite when the selection
+/// missed the acceptance tests (new files can be missing from the cached symbol graph, and a
+/// module filter does not match integration test names)
+fn run_acceptance(
+    steps: &mut impl TddSteps,
+    tests: &[CodeChange],
+    changed: &[String],
+) -> Result<TestExecutionResult, String> {
+    let selected = steps.run_tests(Some(changed))?;
+    if ran_all(&selected, tests) {
+        return Ok(selected);
+    }
+    steps.run_tests(None)
+}
+
+/// Green only counts if the acceptance tests were among the tests that ran
+/// Only their results count; other tests that already failed do not hold the loop back.
+fn passes(result: &TestExecutionResult, tests: &[CodeChange]) -> bool {
+    ran_all(result, tests)
+        && tests
+            .iter()
+            .all(|test| cases_from(result, test).all(|case| case.status != TestStatus::Failed))
+}
+
+fn ran_all(result: &TestExecutionResult, tests: &[CodeChange]) -> bool {
+    tests
+        .iter()
+        .all(|test| cases_from(result, test).next().is_some())
+}
+
+/// The results in a run that come from `test`: those from its file, or named after a
+/// function it declares (libtest and `go test` report no files)
+fn cases_from<'a>(
+    result: &'a TestExecutionResult,
+    test: &'a CodeChange,
+) -> impl Iterator<Item = &'a TestCaseResult> + 'a {
+    let path = normalize_path(&test.file_path);
+    let functions = declared_functions(&test.content);
+    result.tests.iter().filter(move |case| {
+        let same_file = case.file.as_deref().is_some_and(|file| {
+            let file = normalize_path(file);
+            file == path || file.ends_with(&format!("/{}", path))
+        });
+        // `module::tests::name`, `TestName/subtest` or `test_name[param]`
+        let name = case.name.split('[').next().unwrap_or_default();
+        let last = name
+            .rsplit(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .find(|segment| !segment.is_empty());
+        same_file || last.is_some_and(|last| functions.contains(last))
+    })
+}
+
+/// Names following `fn`, `def` or `func`
+fn declared_functions(content: &str) -> BTreeSet<&str> {
+    let words: Vec<&str> = content
+        .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    words
+        .windows(2)
+        .filter(|pair| matches!(pair[0], "fn" | "def" | "func"))
+        .map(|pair| pair[1])
+        .collect()
+}
+
+/// Workspace-relative path with `./` segments dropped and `/` separators
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    path.split('/')
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn tester_prompt(task: &str, framework: &TestFrameworkInfo) -> String {
+    format!(
+        "Write tests for the following change before it is implemented.\n\n\
+         Task: {}\n\n\
+         Test framework: {} (run with `{}`, test files match `{}`)\n\n\
+         The tests must fail against the current code and pass once the change is made. \
+         Test behaviour through the public interface the task describes. \
+         Output each test file in full as:\n\
+         FILE: path/relative/to/workspace\n```\n<contents>\n```",
+        task, framework.framework, framework.test_command, framework.test_pattern
+    )
+}
+
+fn coder_prompt(task: &str, tests: &[CodeChange], feedback: &str) -> String {
+    let mut prompt = format!(
+        "Implement the following change so that the tests below pass.\n\nTask: {}\n\nTests:\n",
+        task
+    );
+    for test in tests {
+        prompt.push_str(&format!(
+            "FILE: {}\n```\n{}\n```\n",
+            test.file_path, test.content
+        ));
+    }
+    prompt.push_str(&format!("\nCurrent test failures:\n{}\n\n", feedback));
+    prompt.push_str(
+        "Do not modify the test files. Output each file you change in full as:\n\
+         FILE: path/relative/to/workspace\n```\n<contents>\n```",
+    );
+    prompt
+}
+
+/// Failing tests with their assertions and stack traces, or the runner output if no
+/// per-test result was parsed (the tests did not build, for instance)
+fn failure_feedback(result: &TestExecutionResult) -> String {
+    if result.failed_tests.is_empty() {
+        let skip = result.output.chars().count().saturating_sub(OUTPUT_CHARS);
+        return result.output.chars().skip(skip).collect();
+    }
+    let mut feedback: Vec<String> = result
+        .failed_tests
+        .iter()
+        .take(MAX_FAILURES)
+        .map(|test| {
+            let location = test
+                .file
+                .as_deref()
+                .map(|file| format!(" ({})", file))
+                .unwrap_or_default();
+            let error: String = test.error.chars().take(FAILURE_CHARS).collect();
+            format!("--- {}{}\n{}", test.name, location, error)
+        })
+        .collect();
+    if result.failed_tests.len() > MAX_FAILURES {
+        feedback.push(format!(
+            "... and {} more failing tests",
+            result.failed_tests.len() - MAX_FAILURES
+        ));
+    }
+    feedback.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    const TEST_FILE: &str = "#[test]\nfn evicts() {}";
+
+    fn change(path: &str, content: &str) -> CodeChange {
+        CodeChange {
+            file_path: path.to_string(),
+            content: content.to_string(),
+            language: None,
+        }
+    }
+
+    fn test_file() -> CodeChange {
+        change("tests/cache.rs", TEST_FILE)
+    }
+
+    fn case(name: &str, error: Option<&str>) -> TestCaseResult {
+        TestCaseResult {
+            name: name.to_string(),
+            file: None,
+            status: match error {
+                Some(_) => TestStatus::Failed,
+                None => TestStatus::Passed,
+            },
+            duration_ms: None,
+            message: error.map(str::to_string),
+        }
+    }
+
+    fn result(tests: Vec<TestCaseResult>) -> TestExecutionResult {
+        let failed: Vec<FailedTest> = tests
+            .iter()
+            .filter(|t| t.status == TestStatus::Failed)
+            .map(|t| FailedTest {
+                name: t.name.clone(),
+                file: Some("tests/cache.rs".to_string()),
+                error: t.message.clone().unwrap_or_default(),
+            })
+            .collect();
+        TestExecutionResult {
+            success: failed.is_empty(),
+            runner: None,
+            command: "test".to_string(),
+            total_tests: tests.len(),
+            passed: tests.len() - failed.len(),
+            failed: failed.len(),
+            skipped: 0,
+            duration_ms: 0,
+            output: String::new(),
+            tests,
+            failed_tests: failed,
+            affected_files: None,
+        }
+    }
+
+    /// A run of an existing unit test and the acceptance test `evicts`, which fails with
+    /// `error` if given
+    fn run(error: Option<&str>) -> TestExecutionResult {
+        result(vec![
+            case("cache::tests::existing", None),
+            case("evicts", error),
+        ])
+    }
+
+    /// Scripted steps that record what the loop asked for
+    #[derive(Default)]
+    struct Scripted {
+        tests: Vec<CodeChange>,
+        winners: VecDeque<Option<Vec<CodeChange>>>,
+        runs: VecDeque<TestExecutionResult>,
+        coder_prompts: Vec<String>,
+        applied: Vec<String>,
+        test_runs: Vec<Option<Vec<String>>>,
+        snapshots: Vec<String>,
+    }
+
+    impl TddSteps for Scripted {
+        fn write_tests(&mut self, _prompt: &str) -> Result<Vec<CodeChange>, String> {
+            Ok(self.tests.clone())
+        }
+
+        fn implement(&mut self, prompt: &str) -> Result<Option<Vec<CodeChange>>, String> {
+            self.coder_prompts.push(prompt.to_string());
+            self.winners
+                .pop_front()
+                .ok_or_else(|| "no more candidates".to_string())
+        }
+
+        fn apply(&mut self, changes: &[CodeChange]) -> Result<(), String> {
+            self.applied
+                .extend(changes.iter().map(|c| c.file_path.clone()));
+            Ok(())
+        }
+
+        fn run_tests(
+            &mut self,
+            changed_files: Option<&[String]>,
+        ) -> Result<TestExecutionResult, String> {
+            self.test_runs.push(changed_files.map(<[String]>::to_vec));
+            self.runs
+                .pop_front()
+                .ok_or_else(|| "no more runs".to_string())
+        }
+
+        fn snapshot(&mut self, message: &str) -> Option<String> {
+            self.snapshots.push(message.to_string());
+            Some(format!("snap-{}", self.snapshots.len()))
+        }
+    }
+
+    fn framework() -> TestFrameworkInfo {
+        TestFrameworkInfo {
+            framework: "rust-cargo".to_string(),
+            test_command: "cargo test".to_string(),
+            test_pattern: "#[test]".to_string(),
+            check_command: None,
+            runner: None,
+        }
+    }
+
+    fn files(paths: &[&str]) -> Option<Vec<String>> {
+        Some(paths.iter().map(|p| p.to_string()).collect())
+    }
+
+    #[test]
+    fn test_failures_are_fed_back_until_green() {
+        let mut steps = Scripted {
+            tests: vec![test_file()],
+            winners: VecDeque::from([
+                None,
+                Some(vec![change("src/cache.rs", "v1")]),
+                Some(vec![
+                    change("src/cache.rs", "v2"),
+                    change("./tests/cache.rs", "// weakened"),
+                ]),
+            ]),
+            runs: VecDeque::from([
+                run(Some("cannot find type `Cache`")),
+                run(Some(
+                    "assertion `left == right` failed\n  left: 2\n right: 1",
+                )),
+                run(None),
+            ]),
+            ..Default::default()
+        };
+
+        let report = tdd_loop("Add an LRU cache", 5, &framework(), &mut steps);
+        assert_eq!(report.status, TddStatus::Green);
+        assert!(report.success);
+        assert!(!report.initial_run.as_ref().unwrap().success);
+        assert_eq!(report.iterations.len(), 3);
+        assert!(!report.iterations[0].consensus_reached);
+
+        // Each Coder prompt carries the failures of the latest run
+        assert!(steps.coder_prompts[1].contains("cannot find type `Cache`"));
+        assert!(steps.coder_prompts[2].contains("left: 2"));
+        assert!(steps.coder_prompts[2].contains(TEST_FILE));
+
+        // The Coder's edit to the test file is dropped, however the path is spelled
+        assert_eq!(report.iterations[2].files, vec!["src/cache.rs"]);
+        assert_eq!(report.iterations[2].ignored_files, vec!["./tests/cache.rs"]);
+        assert_eq!(
+            steps.applied,
+            vec!["tests/cache.rs", "src/cache.rs", "src/cache.rs"]
+        );
+        assert_eq!(
+            steps.test_runs[2],
+            files(&["tests/cache.rs", "src/cache.rs"])
+        );
+        assert_eq!(
+            steps.snapshots,
+            vec![
+                "tdd_loop: failing tests",
+                "tdd_loop iteration 2",
+                "tdd_loop iteration 3"
+            ]
+        );
+        assert_eq!(report.iterations[2].snapshot.as_deref(), Some("snap-3"));
+    }
+
+    #[test]
+    fn test_selection_that_misses_the_acceptance_tests_is_not_green() {
+        // A module filter from a stale graph runs `cache::tests::*` but not `evicts`
+        let unit_only = || result(vec![case("cache::tests::existing", None)]);
+        let mut steps = Scripted {
+            tests: vec![test_file()],
+            winners: VecDeque::from(vec![Some(vec![change("src/cache.rs", "v1")]); 2]),
+            runs: VecDeque::from([
+                run(Some("cannot find type `Cache`")),
+                unit_only(),
+                run(Some("left: 2")),
+                unit_only(),
+                run(None),
+            ]),
+            ..Default::default()
+        };
+
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut steps);
+        assert_eq!(report.status, TddStatus::Green);
+        assert_eq!(report.iterations.len(), 2);
+        assert!(steps.coder_prompts[1].contains("left: 2"));
+        let selected = files(&["tests/cache.rs", "src/cache.rs"]);
+        assert_eq!(
+            steps.test_runs,
+            vec![
+                files(&["tests/cache.rs"]),
+                selected.clone(),
+                None,
+                selected,
+                None
+            ]
+        );
+
+        // Results that name the test file count too, e.g. jest's absolute paths
+        let mut jest = result(vec![case("evicts the oldest entry", None)]);
+        jest.tests[0].file = Some("/home/dev/app/./tests/cache.rs".to_string());
+        assert!(passes(&jest, &[test_file()]));
+        assert!(!passes(&unit_only(), &[test_file()]));
+    }
+
+    #[test]
+    fn test_passing_tests_and_iteration_limit() {
+        let mut passing = Scripted {
+            tests: vec![test_file()],
+            runs: VecDeque::from([run(None)]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 3, &framework(), &mut passing);
+        assert_eq!(report.status, TddStatus::TestsAlreadyPass);
+        assert!(!report.success);
+        assert!(passing.coder_prompts.is_empty());
+
+        let mut stuck = Scripted {
+            tests: vec![test_file()],
+            winners: VecDeque::from(vec![Some(vec![change("src/cache.rs", "v1")]); 2]),
+            runs: VecDeque::from(vec![run(Some("panicked")); 3]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut stuck);
+        assert_eq!(report.status, TddStatus::IterationLimit);
+        assert_eq!(report.iterations.len(), 2);
+        assert_eq!(report.iterations[1].tests.as_ref().unwrap().failed, 1);
+
+        let mut no_tests = Scripted::default();
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut no_tests);
+        assert_eq!(report.status, TddStatus::NoTests);
+
+        // Neither the selection nor the whole suite ran the new tests
+        let mut nothing_ran = Scripted {
+            tests: vec![test_file()],
+            runs: VecDeque::from([result(Vec::new()), result(Vec::new())]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut nothing_ran);
+        assert_eq!(report.status, TddStatus::NoTests);
+    }
+
+    #[test]
+    fn test_tests_that_already_failed_do_not_decide_the_outcome() {
+        // `cache::tests::flaky` failed before the task; only `evicts` is judged
+        let with_flaky = |error: Option<&str>| {
+            let mut run = run(error);
+            run.tests
+                .push(case("cache::tests::flaky", Some("timed out")));
+            result(run.tests)
+        };
+        let mut steps = Scripted {
+            tests: vec![test_file()],
+            winners: VecDeque::from([Some(vec![change("src/cache.rs", "v1")])]),
+            runs: VecDeque::from([
+                with_flaky(Some("cannot find type `Cache`")),
+                with_flaky(None),
+            ]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut steps);
+        assert_eq!(report.status, TddStatus::Green);
+        assert!(!report.iterations[0].tests.as_ref().unwrap().success);
+
+        let mut passing = Scripted {
+            tests: vec![test_file()],
+            runs: VecDeque::from([with_flaky(None)]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut passing);
+        assert_eq!(report.status, TddStatus::TestsAlreadyPass);
+
+        // The whole suite ran, with a failure, but not the new tests
+        let flaky_only = || result(vec![case("cache::tests::flaky", Some("timed out"))]);
+        let mut missing = Scripted {
+            tests: vec![test_file()],
+            runs: VecDeque::from([flaky_only(), flaky_only()]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut missing);
+        assert_eq!(report.status, TddStatus::NoTests);
+
+        // A build error reports no tests, so the Coder gets to fix it
+        let mut build_error = result(Vec::new());
+        build_error.success = false;
+        let mut broken = Scripted {
+            tests: vec![test_file()],
+            winners: VecDeque::from([Some(vec![change("src/cache.rs", "v1")])]),
+            runs: VecDeque::from([build_error.clone(), build_error, run(None)]),
+            ..Default::default()
+        };
+        let report = tdd_loop("Add an LRU cache", 2, &framework(), &mut broken);
+        assert_eq!(report.status, TddStatus::Green);
+    }
+
+    #[test]
+    fn test_feedback_falls_back_to_runner_output() {
+        let mut build_error = result(Vec::new());
+        build_error.success = false;
+        build_error.output = format!("{}error[E0425]: cannot find function", "x".repeat(10_000));
+        let feedback = failure_feedback(&build_error);
+        assert_eq!(feedback.chars().count(), OUTPUT_CHARS);
+        assert!(feedback.ends_with("error[E0425]: cannot find function"));
+
+        let many = result(
+            (0..12)
+                .map(|i| case(&format!("t{}", i), Some("failed")))
+                .collect(),
+        );
+        let feedback = failure_feedback(&many);
+        assert!(feedback.contains("--- t9 (tests/cache.rs)"));
+        assert!(!feedback.contains("--- t10"));
+        assert!(feedback.ends_with("... and 2 more failing tests"));
+    }
+}
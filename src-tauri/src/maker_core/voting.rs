@@ -2,10 +2,16 @@
 // PRD Section 4.3: The "MAKER" Standard Library - First-to-ahead-by-k voting
 // P1-3: Now with parallel atom execution for improved throughput
 
-use super::atom::{AtomType, SpawnFlags};
-use crate::agents::{AtomExecutor, AtomInput};
-use crate::llm::LlmConfig;
-use futures::future::join_all;
+use super::atom::{AtomResult, AtomType, SpawnFlags};
+use super::compile_check::CompileCheck;
+use super::equivalence::Equivalence;
+use super::reliability::{AdaptiveK, KSelection};
+use super::sampling::{ModelChoice, SampleConfig, Sampler, SamplingSchedule, TemperatureRamp};
+use crate::agents::{AtomExecutor, AtomInput, DeltaCallback, StreamOptions};
+use crate::handlers::governance::GovernanceConfig;
+use crate::llm::{LlmConfig, LlmRouter};
+use crate::llm::replay::content_hash;
+use futures::stream::{FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -29,6 +35,28 @@ pub struct ConsensusConfig {
     pub initial_batch_size: usize,
     /// Whether to use parallel execution
     pub parallel_enabled: bool,
+    /// Stream candidate outputs instead of waiting for complete responses
+    #[serde(default)]
+    pub streaming: bool,
+    /// Abort a streaming candidate once its output exceeds this many words
+    #[serde(default)]
+    pub verbosity_limit: Option<usize>,
+    /// Stop spawning candidates once the vote has cost this many US dollars
+    #[serde(default)]
+    pub max_cost_usd: Option<f64>,
+    /// How candidates are grouped into votes; None uses `Equivalence::for_atom`
+    #[serde(default)]
+    pub equivalence: Option<Equivalence>,
+    /// How candidates vary temperature, model and prompt; empty samples them all alike
+    #[serde(default)]
+    pub sampling: SamplingSchedule,
+    /// Choose `k_threshold` from voting history instead (applied by the runtime, which
+    /// knows the workspace's voting ledger)
+    #[serde(default)]
+    pub adaptive_k: Option<AdaptiveK>,
+    /// Build Coder candidates in a scratch workspace and discard those that do not compile
+    #[serde(default)]
+    pub compile_check: Option<CompileCheck>,
 }
 
 impl Default for ConsensusConfig {
@@ -41,19 +69,143 @@ impl Default for ConsensusConfig {
             min_votes: 2,
             initial_batch_size: 3,
             parallel_enabled: true,
+            streaming: false,
+            verbosity_limit: None,
+            max_cost_usd: None,
+            equivalence: None,
+            sampling: SamplingSchedule::default(),
+            adaptive_k: None,
+            compile_check: None,
         }
     }
 }
 
+impl ConsensusConfig {
+    /// Stream candidates and abort any that trip the governance verbosity red flag
+    pub fn with_governance(mut self, governance: &GovernanceConfig) -> Self {
+        self.streaming = true;
+        self.verbosity_limit = Some(governance.max_tokens);
+        self
+    }
+}
+
+/// Options for `run_consensus_with` in Rhai; unset fields keep the `ConsensusConfig` defaults
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConsensusOptions {
+    pub k: Option<usize>,
+    pub max_atoms: Option<usize>,
+    pub timeout_ms: Option<u64>,
+    pub min_votes: Option<usize>,
+    pub batch_size: Option<usize>,
+    pub parallel: Option<bool>,
+    pub discard_red_flags: Option<bool>,
+    pub streaming: Option<bool>,
+    pub temperatures: Option<Vec<f32>>,
+    pub temperature_ramp: Option<TemperatureRamp>,
+    pub models: Option<Vec<ModelChoice>>,
+    pub paraphrases: Option<Vec<String>>,
+    pub equivalence: Option<Equivalence>,
+    pub adaptive_k: Option<AdaptiveK>,
+    pub compile_check: Option<CompileCheck>,
+}
+
+impl ConsensusOptions {
+    /// Apply the options on top of `config`
+    pub fn apply(self, mut config: ConsensusConfig) -> Result<ConsensusConfig, String> {
+        if let Some(k) = self.k {
+            config.k_threshold = k;
+        }
+        if let Some(max_atoms) = self.max_atoms {
+            config.max_atoms = max_atoms;
+        }
+        if let Some(timeout_ms) = self.timeout_ms {
+            config.timeout = Duration::from_millis(timeout_ms);
+        }
+        if let Some(min_votes) = self.min_votes {
+            config.min_votes = min_votes;
+        }
+        if let Some(batch_size) = self.batch_size {
+            config.initial_batch_size = batch_size;
+        }
+        if let Some(parallel) = self.parallel {
+            config.parallel_enabled = parallel;
+        }
+        if let Some(discard_red_flags) = self.discard_red_flags {
+            config.discard_red_flags = discard_red_flags;
+        }
+        if let Some(streaming) = self.streaming {
+            config.streaming = streaming;
+        }
+        match (self.temperatures, self.temperature_ramp) {
+            (Some(_), Some(_)) => {
+                return Err("Set temperatures or temperature_ramp, not both".to_string());
+            }
+            (Some(temperatures), None) => config.sampling.temperatures = temperatures,
+            (None, Some(ramp)) => config.sampling.temperatures = ramp.temperatures(),
+            (None, None) => {}
+        }
+        if let Some(models) = self.models {
+            config.sampling.models = models;
+        }
+        if let Some(paraphrases) = self.paraphrases {
+            config.sampling.paraphrases = paraphrases;
+        }
+        if self.equivalence.is_some() {
+            config.equivalence = self.equivalence;
+        }
+        if let Some(adaptive_k) = self.adaptive_k {
+            adaptive_k.validate()?;
+            config.adaptive_k = Some(adaptive_k);
+        }
+        if self.compile_check.is_some() {
+            config.compile_check = self.compile_check;
+        }
+
+        if config.k_threshold == 0 {
+            return Err("k must be at least 1".to_string());
+        }
+        if config.max_atoms == 0 {
+            return Err("max_atoms must be at least 1".to_string());
+        }
+        config.sampling.validate()?;
+        Ok(config)
+    }
+}
+
+/// Callback receiving streamed output deltas, tagged with the candidate index
+pub type CandidateDeltaCallback = Arc<dyn Fn(usize, &str) + Send + Sync>;
+
+/// An equivalence class of candidate outputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CandidateClass {
+    /// Key the class's outputs share (see `Equivalence::key`)
+    pub key: String,
+    /// `class_hash` of the key, as used in `CandidateVote`
+    pub hash: String,
+    /// The first output that joined the class; it stands for the class if it wins
+    pub representative: String,
+    /// How the representative was sampled
+    #[serde(default)]
+    pub sample: Option<SampleConfig>,
+    pub votes: usize,
+}
+
 /// Result of a consensus operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsensusResult {
-    /// The winning output
+    /// The winning output (the representative of the winning class)
     pub winner: Option<String>,
+    /// How the winning output was sampled
+    #[serde(default)]
+    pub winner_sample: Option<SampleConfig>,
     /// The vote count for the winner
     pub winning_votes: usize,
-    /// All candidates with their vote counts
+    /// Vote counts keyed by equivalence class key
     pub candidates: HashMap<String, usize>,
+    /// Equivalence classes with their representatives, most votes first
+    #[serde(default)]
+    pub classes: Vec<CandidateClass>,
     /// Total atoms spawned
     pub atoms_spawned: usize,
     /// Red-flagged responses that were discarded
@@ -66,6 +218,39 @@ pub struct ConsensusResult {
     pub error: Option<String>,
     /// Total time taken
     pub elapsed_ms: u64,
+    /// Prompt tokens across all spawned atoms (including discarded ones)
+    #[serde(default)]
+    pub prompt_tokens: usize,
+    /// Completion tokens across all spawned atoms (including discarded ones)
+    #[serde(default)]
+    pub completion_tokens: usize,
+    /// Total tokens across all spawned atoms (including discarded ones)
+    #[serde(default)]
+    pub total_tokens: usize,
+    /// Cost in US dollars across all spawned atoms (including discarded ones)
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// How k was chosen, when `adaptive_k` was set
+    #[serde(default)]
+    pub k_selection: Option<KSelection>,
+}
+
+/// Token usage accumulated over a consensus run
+#[derive(Debug, Clone, Copy, Default)]
+struct TokenTally {
+    prompt_tokens: usize,
+    completion_tokens: usize,
+    total_tokens: usize,
+    cost_usd: f64,
+}
+
+impl TokenTally {
+    fn add(&mut self, result: &AtomResult) {
+        self.prompt_tokens += result.prompt_tokens;
+        self.completion_tokens += result.completion_tokens;
+        self.total_tokens += result.tokens_used;
+        self.cost_usd += result.cost_usd;
+    }
 }
 
 impl ConsensusResult {
@@ -74,14 +259,21 @@ impl ConsensusResult {
                    atoms_spawned: usize, discarded_count: usize, elapsed_ms: u64) -> Self {
         Self {
             winner: Some(winner),
+            winner_sample: None,
             winning_votes,
             candidates,
+            classes: Vec::new(),
             atoms_spawned,
             discarded_count,
             reached: true,
             consensus_reached: true,
             error: None,
             elapsed_ms,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            cost_usd: 0.0,
+            k_selection: None,
         }
     }
 
@@ -90,22 +282,206 @@ impl ConsensusResult {
                    atoms_spawned: usize, discarded_count: usize, elapsed_ms: u64) -> Self {
         Self {
             winner: None,
+            winner_sample: None,
             winning_votes: 0,
             candidates,
+            classes: Vec::new(),
             atoms_spawned,
             discarded_count,
             reached: false,
             consensus_reached: false,
             error: Some(error),
             elapsed_ms,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            total_tokens: 0,
+            cost_usd: 0.0,
+            k_selection: None,
         }
     }
+
+    /// Attach the token usage of the atoms spawned for this vote
+    fn with_tokens(mut self, tally: TokenTally) -> Self {
+        self.prompt_tokens = tally.prompt_tokens;
+        self.completion_tokens = tally.completion_tokens;
+        self.total_tokens = tally.total_tokens;
+        self.cost_usd = tally.cost_usd;
+        self
+    }
+
+    /// Attach the equivalence classes that were voted on, and the winner's sample
+    fn with_classes(mut self, ballot: &Ballot) -> Self {
+        self.classes = ballot.classes();
+        self.winner_sample = self.winner_class().and_then(|class| class.sample.clone());
+        self
+    }
+
+    /// The equivalence class the winner represents
+    pub fn winner_class(&self) -> Option<&CandidateClass> {
+        let winner = self.winner.as_ref()?;
+        self.classes.iter().find(|class| &class.representative == winner)
+    }
+}
+
+/// How one candidate voted, reported as soon as it is counted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateVote {
+    pub candidate: usize,
+    /// How the candidate was sampled
+    pub sample: SampleConfig,
+    /// Hash of the candidate's equivalence class key; None if it did not vote
+    pub hash: Option<String>,
+    /// Start of the candidate's output
+    pub snippet: String,
+    /// Red-flag reason, whether or not the red flag discarded the candidate
+    pub red_flag: Option<String>,
+    /// Why the atom failed to run, if it did
+    pub error: Option<String>,
+    /// Votes per class hash after this candidate was counted
+    pub tally: HashMap<String, usize>,
 }
 
-/// Normalize an output for comparison (hash-based)
-fn normalize_output(output: &str) -> String {
-    // Remove whitespace variations and normalize
-    output.split_whitespace().collect::<Vec<_>>().join(" ")
+/// Callback receiving each candidate's vote
+pub type VoteCallback = Arc<dyn Fn(&CandidateVote) + Send + Sync>;
+
+/// Callbacks for watching a consensus run
+#[derive(Clone, Default)]
+pub struct ConsensusObserver {
+    /// Output deltas of each candidate, tagged with the candidate index
+    pub on_delta: Option<CandidateDeltaCallback>,
+    /// Each candidate's vote
+    pub on_vote: Option<VoteCallback>,
+}
+
+/// Characters of output kept in `CandidateVote::snippet`
+const SNIPPET_CHARS: usize = 200;
+
+/// Hash identifying an equivalence class in votes and reports
+pub fn class_hash(key: &str) -> String {
+    content_hash(key)
+}
+
+/// Counts candidates: votes per equivalence class, discards and token usage
+struct Ballot {
+    equivalence: Equivalence,
+    discard_red_flags: bool,
+    votes: HashMap<String, usize>,
+    /// First output cast in each class
+    representatives: HashMap<String, String>,
+    /// How each representative was sampled
+    samples: HashMap<String, SampleConfig>,
+    discarded: usize,
+    tokens: TokenTally,
+}
+
+impl Ballot {
+    fn new(equivalence: Equivalence, discard_red_flags: bool) -> Self {
+        Self {
+            equivalence,
+            discard_red_flags,
+            votes: HashMap::new(),
+            representatives: HashMap::new(),
+            samples: HashMap::new(),
+            discarded: 0,
+            tokens: TokenTally::default(),
+        }
+    }
+
+    /// Count a finished candidate
+    fn record(&mut self, candidate: usize, sample: SampleConfig, outcome: Result<AtomResult, String>) -> CandidateVote {
+        let mut vote = CandidateVote {
+            candidate,
+            sample,
+            hash: None,
+            snippet: String::new(),
+            red_flag: None,
+            error: None,
+            tally: HashMap::new(),
+        };
+
+        match outcome {
+            Ok(result) => {
+                self.tokens.add(&result);
+                vote.snippet = result.output.chars().take(SNIPPET_CHARS).collect();
+                vote.red_flag = result.metadata.get("red_flag_reason").cloned();
+                if is_discarded(&result, self.discard_red_flags) {
+                    self.discarded += 1;
+                } else {
+                    let key = self.equivalence.key(&result.output);
+                    vote.hash = Some(class_hash(&key));
+                    self.cast(key, result.output, &vote.sample);
+                }
+            }
+            Err(e) => {
+                // Log error but continue trying
+                eprintln!("Atom execution failed: {}", e);
+                self.discarded += 1;
+                vote.error = Some(e);
+            }
+        }
+
+        vote.tally = self
+            .votes
+            .iter()
+            .map(|(key, votes)| (class_hash(key), *votes))
+            .collect();
+        vote
+    }
+
+    fn cast(&mut self, key: String, output: String, sample: &SampleConfig) {
+        *self.votes.entry(key.clone()).or_insert(0) += 1;
+        if !self.representatives.contains_key(&key) {
+            self.samples.insert(key.clone(), sample.clone());
+            self.representatives.insert(key, output);
+        }
+    }
+
+    /// The winning class's representative and vote count, once consensus is reached
+    fn winner(&self, config: &ConsensusConfig) -> Option<(String, usize)> {
+        let key = check_consensus(&self.votes, config.k_threshold, config.min_votes)?;
+        let votes = self.votes.get(&key).copied().unwrap_or(0);
+        let representative = self.representatives.get(&key).cloned().unwrap_or(key);
+        Some((representative, votes))
+    }
+
+    fn classes(&self) -> Vec<CandidateClass> {
+        let mut classes: Vec<CandidateClass> = self
+            .votes
+            .iter()
+            .map(|(key, votes)| CandidateClass {
+                key: key.clone(),
+                hash: class_hash(key),
+                representative: self.representatives.get(key).cloned().unwrap_or_default(),
+                sample: self.samples.get(key).cloned(),
+                votes: *votes,
+            })
+            .collect();
+        classes.sort_by(|a, b| b.votes.cmp(&a.votes).then_with(|| a.key.cmp(&b.key)));
+        classes
+    }
+
+    /// Result for a run that reached consensus
+    fn reached(&self, winner: String, winning_votes: usize, atoms_spawned: usize, start: Instant) -> ConsensusResult {
+        ConsensusResult::success(
+            winner,
+            winning_votes,
+            self.votes.clone(),
+            atoms_spawned,
+            self.discarded,
+            start.elapsed().as_millis() as u64,
+        ).with_tokens(self.tokens).with_classes(self)
+    }
+
+    /// Result for a run that stopped without consensus
+    fn failed(&self, error: String, atoms_spawned: usize, start: Instant) -> ConsensusResult {
+        ConsensusResult::failure(
+            error,
+            self.votes.clone(),
+            atoms_spawned,
+            self.discarded,
+            start.elapsed().as_millis() as u64,
+        ).with_tokens(self.tokens).with_classes(self)
+    }
 }
 
 /// Run consensus voting on an atom task
@@ -116,49 +492,69 @@ pub async fn run_consensus(
     task: &str,
     config: ConsensusConfig,
     llm_config: &LlmConfig,
-    _workspace_path: &str,
+    workspace_path: &str,
+) -> ConsensusResult {
+    run_consensus_observed(atom_type, task, config, llm_config, workspace_path, ConsensusObserver::default()).await
+}
+
+/// Run consensus voting, forwarding each candidate's output deltas to `on_delta`
+pub async fn run_consensus_streaming(
+    atom_type: AtomType,
+    task: &str,
+    config: ConsensusConfig,
+    llm_config: &LlmConfig,
+    workspace_path: &str,
+    on_delta: Option<CandidateDeltaCallback>,
+) -> ConsensusResult {
+    let observer = ConsensusObserver { on_delta, on_vote: None };
+    run_consensus_observed(atom_type, task, config, llm_config, workspace_path, observer).await
+}
+
+/// Run consensus voting, reporting deltas and votes to `observer`
+/// Candidates stream when `config.streaming` is set or a delta callback is given; a streaming
+/// candidate that exceeds `config.verbosity_limit` is aborted and discarded. With
+/// `config.compile_check`, Coder candidates that do not build in `workspace_path` are discarded.
+pub async fn run_consensus_observed(
+    atom_type: AtomType,
+    task: &str,
+    config: ConsensusConfig,
+    llm_config: &LlmConfig,
+    workspace_path: &str,
+    observer: ConsensusObserver,
 ) -> ConsensusResult {
     let start = Instant::now();
-    let mut candidates: HashMap<String, usize> = HashMap::new();
+    let equivalence = config
+        .equivalence
+        .clone()
+        .unwrap_or_else(|| Equivalence::for_atom(atom_type));
+    let mut ballot = Ballot::new(equivalence, config.discard_red_flags);
     let mut atoms_spawned = 0;
-    let mut discarded_count = 0;
 
-    // Create atom executor (wrapped in Arc for sharing across tasks)
-    let executor = Arc::new(AtomExecutor::new(llm_config.clone()));
+    // Each candidate gets its own model, temperature and phrasing from the sampling schedule
+    let sampler = match config.sampling.sampler(llm_config, &LlmRouter::load()) {
+        Ok(sampler) => sampler,
+        Err(e) => return ballot.failed(e, atoms_spawned, start),
+    };
 
     // Build base input
-    let base_input = AtomInput::new(atom_type.clone(), task)
+    // Candidates must be sampled independently, so never serve them from a response cache
+    let base_input = AtomInput::new(atom_type, task)
         .with_flags(SpawnFlags {
             red_flag_check: config.discard_red_flags,
             ..Default::default()
-        });
+        })
+        .without_cache();
 
     // Use parallel execution if enabled
     if config.parallel_enabled && config.initial_batch_size > 1 {
         // Phase 1: Parallel initial batch
         let batch_size = config.initial_batch_size.min(config.max_atoms);
-        let (batch_candidates, batch_spawned, batch_discarded) =
-            execute_parallel_batch(&executor, &base_input, batch_size, &config).await;
-
-        atoms_spawned += batch_spawned;
-        discarded_count += batch_discarded;
-
-        // Merge batch results
-        for (output, count) in batch_candidates {
-            *candidates.entry(output).or_insert(0) += count;
-        }
+        execute_parallel_batch(&sampler, &base_input, batch_size, &config, workspace_path, &mut ballot, &observer).await;
+        atoms_spawned += batch_size;
 
         // Check if consensus reached from initial batch
-        if let Some(winner) = check_consensus(&candidates, config.k_threshold, config.min_votes) {
-            let winning_votes = *candidates.get(&winner).unwrap_or(&0);
-            return ConsensusResult::success(
-                winner,
-                winning_votes,
-                candidates,
-                atoms_spawned,
-                discarded_count,
-                start.elapsed().as_millis() as u64,
-            );
+        if let Some((winner, winning_votes)) = ballot.winner(&config) {
+            return ballot.reached(winner, winning_votes, atoms_spawned, start);
         }
     }
 
@@ -166,116 +562,138 @@ pub async fn run_consensus(
     while atoms_spawned < config.max_atoms {
         // Check timeout
         if start.elapsed() > config.timeout {
-            return ConsensusResult::failure(
-                "Timeout reached before consensus".to_string(),
-                candidates,
-                atoms_spawned,
-                discarded_count,
-                start.elapsed().as_millis() as u64,
-            );
+            return ballot.failed("Timeout reached before consensus".to_string(), atoms_spawned, start);
+        }
+
+        // Voting multiplies cost, so stop before the next candidate once the cap is reached
+        if let Some(max_cost) = config.max_cost_usd {
+            if ballot.tokens.cost_usd >= max_cost {
+                let error = format!("Cost limit (${:.4}) reached before consensus", max_cost);
+                return ballot.failed(error, atoms_spawned, start);
+            }
         }
 
         // Execute atom
-        let input = base_input.clone();
+        let candidate = atoms_spawned;
+        let (executor, input, sample) = sample_candidate(&sampler, &base_input, candidate);
         atoms_spawned += 1;
 
-        match executor.execute(input).await {
-            Ok(result) => {
-                // Check for red flags
-                if result.is_red_flagged() && config.discard_red_flags {
-                    discarded_count += 1;
-                    continue;
-                }
+        let outcome = execute_candidate(&executor, input, &config, workspace_path, candidate, &observer.on_delta).await;
+        let vote = ballot.record(candidate, sample, outcome);
+        if let Some(on_vote) = &observer.on_vote {
+            on_vote(&vote);
+        }
 
-                // Normalize and count the output
-                let normalized = normalize_output(&result.output);
-                *candidates.entry(normalized.clone()).or_insert(0) += 1;
-
-                // Check if consensus reached
-                if let Some(winner) = check_consensus(&candidates, config.k_threshold, config.min_votes) {
-                    // HIGH-5: Cleaner pattern - get vote count directly since we just found the winner
-                    let winning_votes = candidates.get(&winner).copied().unwrap_or(0);
-                    return ConsensusResult::success(
-                        winner,
-                        winning_votes,
-                        candidates,
-                        atoms_spawned,
-                        discarded_count,
-                        start.elapsed().as_millis() as u64,
-                    );
-                }
-            }
-            Err(e) => {
-                // Log error but continue trying
-                eprintln!("Atom execution failed: {}", e);
-                discarded_count += 1;
+        // Check if consensus reached
+        if vote.hash.is_some() {
+            if let Some((winner, winning_votes)) = ballot.winner(&config) {
+                return ballot.reached(winner, winning_votes, atoms_spawned, start);
             }
         }
     }
 
     // Max atoms reached without consensus
-    ConsensusResult::failure(
+    ballot.failed(
         format!("Max atoms ({}) reached without consensus", config.max_atoms),
-        candidates,
         atoms_spawned,
-        discarded_count,
-        start.elapsed().as_millis() as u64,
+        start,
     )
 }
 
+/// Executor and input for one candidate, as the sampling schedule sets them up
+fn sample_candidate(sampler: &Sampler, base_input: &AtomInput, candidate: usize) -> (AtomExecutor, AtomInput, SampleConfig) {
+    let (llm_config, task, sample) = sampler.sample(candidate, &base_input.task);
+    let input = AtomInput { task, ..base_input.clone() };
+    (AtomExecutor::new(llm_config), input, sample)
+}
+
+/// Execute one candidate atom, then compile-check it if it is a Coder and the config asks for it
+async fn execute_candidate(
+    executor: &AtomExecutor,
+    input: AtomInput,
+    config: &ConsensusConfig,
+    workspace_path: &str,
+    candidate: usize,
+    on_delta: &Option<CandidateDeltaCallback>,
+) -> Result<AtomResult, String> {
+    let atom_type = input.atom_type;
+    let mut result = generate_candidate(executor, input, config, candidate, on_delta).await?;
+    if let (AtomType::Coder, Some(check)) = (atom_type, &config.compile_check) {
+        let changes = executor.parse_code_output(&result.output);
+        check.check_candidate(workspace_path, &changes, &mut result).await;
+    }
+    Ok(result)
+}
+
+/// Generate one candidate, streaming it when the config or a delta callback asks for it
+async fn generate_candidate(
+    executor: &AtomExecutor,
+    input: AtomInput,
+    config: &ConsensusConfig,
+    candidate: usize,
+    on_delta: &Option<CandidateDeltaCallback>,
+) -> Result<AtomResult, String> {
+    if !config.streaming && on_delta.is_none() {
+        return executor.execute(input).await;
+    }
+
+    let mut options = StreamOptions {
+        verbosity_limit: config.verbosity_limit,
+        on_delta: None,
+    };
+    if let Some(callback) = on_delta.clone() {
+        let forward: DeltaCallback = Arc::new(move |delta: &str| callback(candidate, delta));
+        options = options.with_on_delta(forward);
+    }
+    executor.execute_stream(input, options).await
+}
+
+/// Whether a candidate's output should be left out of the vote
+/// Streams aborted for verbosity and code that fails its compile check never vote, even when
+/// red flags are otherwise kept.
+fn is_discarded(result: &AtomResult, discard_red_flags: bool) -> bool {
+    let aborted = result.metadata.get("aborted").map(|v| v == "true").unwrap_or(false);
+    let uncompilable = result.metadata.get("compile_check").map(|v| v == "failed").unwrap_or(false);
+    aborted || uncompilable || (result.is_red_flagged() && discard_red_flags)
+}
+
 /// Execute a batch of atoms in parallel using tokio::spawn
-/// Returns (candidates map, atoms spawned, discarded count)
+/// Candidates are counted, and reported to the observer, in the order they finish.
 async fn execute_parallel_batch(
-    executor: &Arc<AtomExecutor>,
+    sampler: &Sampler,
     base_input: &AtomInput,
     batch_size: usize,
     config: &ConsensusConfig,
-) -> (HashMap<String, usize>, usize, usize) {
-    let mut handles = Vec::with_capacity(batch_size);
+    workspace_path: &str,
+    ballot: &mut Ballot,
+    observer: &ConsensusObserver,
+) {
+    let mut pending = FuturesUnordered::new();
 
     // Spawn all atoms in parallel
-    for _ in 0..batch_size {
-        let exec = Arc::clone(executor);
-        let input = base_input.clone();
-        let discard_red_flags = config.discard_red_flags;
+    for candidate in 0..batch_size {
+        let (exec, input, sample) = sample_candidate(sampler, base_input, candidate);
+        let config = config.clone();
+        let on_delta = observer.on_delta.clone();
+        let workspace_path = workspace_path.to_string();
 
         let handle = tokio::spawn(async move {
-            match exec.execute(input).await {
-                Ok(result) => {
-                    if result.is_red_flagged() && discard_red_flags {
-                        (None, true) // Discarded
-                    } else {
-                        (Some(normalize_output(&result.output)), false)
-                    }
-                }
-                Err(_) => (None, true) // Error = discarded
-            }
+            execute_candidate(&exec, input, &config, &workspace_path, candidate, &on_delta).await
+        });
+        pending.push(async move {
+            let outcome = handle
+                .await
+                .unwrap_or_else(|e| Err(format!("Candidate task panicked: {}", e)));
+            (candidate, sample, outcome)
         });
-        handles.push(handle);
     }
 
-    // Wait for all to complete and aggregate results
-    let results = join_all(handles).await;
-
-    let mut candidates: HashMap<String, usize> = HashMap::new();
-    let mut discarded = 0;
-
-    for result in results {
-        match result {
-            Ok((Some(output), _)) => {
-                *candidates.entry(output).or_insert(0) += 1;
-            }
-            Ok((None, true)) => {
-                discarded += 1;
-            }
-            Err(_) => {
-                discarded += 1; // Task panicked
-            }
-            _ => {}
+    while let Some((candidate, sample, outcome)) = pending.next().await {
+        let vote = ballot.record(candidate, sample, outcome);
+        if let Some(on_vote) = &observer.on_vote {
+            on_vote(&vote);
         }
     }
-
-    (candidates, batch_size, discarded)
 }
 
 /// Check if consensus has been reached based on vote counts
@@ -309,6 +727,11 @@ pub fn check_consensus(votes: &HashMap<String, usize>, k_threshold: usize, min_v
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::llm::ProviderType;
+
+    fn sample(model: &str) -> SampleConfig {
+        SampleConfig { provider: ProviderType::Replay, model: model.to_string(), temperature: 0.1, paraphrase: 0 }
+    }
 
     #[test]
     fn test_check_consensus_basic() {
@@ -329,5 +752,122 @@ mod tests {
         // With k_threshold=3, diff=1 < k
         assert_eq!(check_consensus(&votes, 3, 2), None);
     }
+
+    #[test]
+    fn test_equivalent_candidates_share_a_vote_and_first_output_represents_them() {
+        let config = ConsensusConfig { k_threshold: 2, min_votes: 2, ..Default::default() };
+        let mut ballot = Ballot::new(Equivalence::for_atom(AtomType::Reviewer), true);
+        for (candidate, output) in [
+            r#"{"approved": true, "issues": []}"#,
+            r#"{"approved": false, "issues": ["off by one"]}"#,
+            r#"```json
+{"issues": ["nit: naming"], "approved": true}
+```"#,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let result = AtomResult::success(AtomType::Reviewer, output.to_string(), 0, 10);
+            ballot.record(candidate, sample(&format!("model-{}", candidate)), Ok(result));
+        }
+        assert!(ballot.winner(&config).is_none());
+
+        let vote = ballot.record(3, sample("model-3"), Ok(AtomResult::success(AtomType::Reviewer, r#"{"approved":true,"issues":[]}"#.to_string(), 0, 10)));
+        let approved = class_hash(r#"{"approved":true}"#);
+        assert_eq!(vote.hash.as_deref(), Some(approved.as_str()));
+        assert_eq!(vote.tally.get(&approved), Some(&3));
+        assert_eq!(ballot.tokens.total_tokens, 40);
+
+        let (winner, votes) = ballot.winner(&config).unwrap();
+        assert_eq!(winner, r#"{"approved": true, "issues": []}"#);
+        assert_eq!(votes, 3);
+
+        let classes = ballot.classes();
+        assert_eq!(classes.len(), 2);
+        assert_eq!(classes[0].key, r#"{"approved":true}"#);
+        assert_eq!(classes[1].representative, r#"{"approved": false, "issues": ["off by one"]}"#);
+        assert_eq!(classes[0].sample, Some(sample("model-0")));
+
+        let result = ballot.reached(winner, votes, 4, Instant::now());
+        assert_eq!(result.winner_sample, Some(sample("model-0")));
+
+        // Failed and red-flagged candidates are reported but do not vote
+        let vote = ballot.record(4, sample("model-4"), Err("LLM call failed".to_string()));
+        assert!(vote.hash.is_none());
+        assert_eq!(vote.error.as_deref(), Some("LLM call failed"));
+        let mut flagged = AtomResult::success(AtomType::Reviewer, "{}".to_string(), 0, 0);
+        flagged.set_red_flagged("Red flag: eval");
+        let vote = ballot.record(5, sample("model-5"), Ok(flagged));
+        assert_eq!(vote.red_flag.as_deref(), Some("Red flag: eval"));
+        assert_eq!(ballot.discarded, 2);
+    }
+
+    #[test]
+    fn test_consensus_options_apply_over_defaults() {
+        let options: ConsensusOptions = serde_json::from_value(serde_json::json!({
+            "k": 2,
+            "timeout_ms": 5000,
+            "temperature_ramp": { "from": 0.0, "to": 1.0, "steps": 3 },
+            "models": [{ "model": "llama3.1-8b" }, { "provider": "openrouter", "model": "qwen/qwen3-coder" }],
+            "paraphrases": ["Restated: {task}"],
+            "equivalence": { "key_fields": ["approved"] }
+        }))
+        .unwrap();
+        let config = options.apply(ConsensusConfig::default()).unwrap();
+        assert_eq!(config.k_threshold, 2);
+        assert_eq!(config.timeout, Duration::from_millis(5000));
+        assert_eq!(config.max_atoms, 10);
+        assert_eq!(config.equivalence, Some(Equivalence::KeyFields(vec!["approved".to_string()])));
+
+        assert_eq!(config.sampling.temperatures, vec![0.0, 0.5, 1.0]);
+        assert_eq!(config.sampling.models[1].provider.as_deref(), Some("openrouter"));
+        assert_eq!(config.sampling.paraphrases.len(), 1);
+
+        let both: ConsensusOptions = serde_json::from_value(serde_json::json!({
+            "temperatures": [0.1],
+            "temperature_ramp": { "from": 0.1, "to": 0.9, "steps": 2 }
+        }))
+        .unwrap();
+        assert!(both.apply(ConsensusConfig::default()).is_err());
+
+        let bad_k: ConsensusOptions = serde_json::from_value(serde_json::json!({ "k": 0 })).unwrap();
+        assert!(bad_k.apply(ConsensusConfig::default()).is_err());
+        assert!(serde_json::from_value::<ConsensusOptions>(serde_json::json!({ "max_atom": 3 })).is_err());
+
+        // Scripts may tune the compile check but not pick the program it runs
+        let check: ConsensusOptions =
+            serde_json::from_value(serde_json::json!({ "compile_check": { "timeout_ms": 1000 } })).unwrap();
+        assert_eq!(check.compile_check.unwrap().timeout_ms, 1000);
+        assert!(serde_json::from_value::<ConsensusOptions>(serde_json::json!({
+            "compile_check": { "command": "curl example.com" }
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn test_aborted_and_uncompilable_candidates_never_vote() {
+        let mut result = AtomResult::failure(AtomType::Coder, "too long".to_string(), Vec::new());
+        assert!(!is_discarded(&result, false));
+
+        result.set_red_flagged("Response too verbose");
+        assert!(is_discarded(&result, true));
+        assert!(!is_discarded(&result, false));
+
+        result.metadata.insert("aborted".to_string(), "true".to_string());
+        assert!(is_discarded(&result, false));
+
+        let mut uncompilable = AtomResult::success(AtomType::Coder, "fn broken( {".to_string(), 0, 10);
+        uncompilable.set_red_flagged("Compile check failed: mismatched closing delimiter");
+        uncompilable.metadata.insert("compile_check".to_string(), "failed".to_string());
+        assert!(is_discarded(&uncompilable, false));
+    }
+
+    #[test]
+    fn test_with_governance_enables_streaming_limit() {
+        let governance = GovernanceConfig { max_tokens: 250, previous_betti_1: 0 };
+        let config = ConsensusConfig::default().with_governance(&governance);
+        assert!(config.streaming);
+        assert_eq!(config.verbosity_limit, Some(250));
+    }
 }
 
@@ -0,0 +1,580 @@
+//! Workspace File System - Sandboxed File I/O for Rhai Scripts
+//!
+//! Gives Code Mode scripts a way to read and modify files without ever leaving
+//! the workspace root. Every path is resolved lexically (no `..` escapes) and then
+//! checked against the canonical workspace root so symlinks cannot be used to
+//! reach files outside of it.
+//!
+//! Writes are journaled: the original content of every path is captured before its
+//! first modification in a transaction, so a failed script can be restored even for
+//! files the Shadow Git snapshot does not track (untracked or newly created files).
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Directories scripts are never allowed to touch
+const PROTECTED_DIRS: &[&str] = &[".git"];
+
+/// Kind of file operation performed by a script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FileOperation {
+    Read,
+    Write,
+    Patch,
+    List,
+    Move,
+    Delete,
+}
+
+/// A single recorded file access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileAccess {
+    /// Path relative to the workspace root (forward slashes)
+    pub path: String,
+    /// The operation performed
+    pub operation: FileOperation,
+}
+
+/// Directory listing entry returned to scripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirEntryInfo {
+    pub name: String,
+    /// Path relative to the workspace root (forward slashes)
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+/// Sandboxed, journaled file system rooted at the workspace
+pub struct WorkspaceFs {
+    root: PathBuf,
+    /// Original contents of paths modified in the current transaction (None = did not exist)
+    journal: HashMap<PathBuf, Option<Vec<u8>>>,
+    /// Order in which paths were first journaled (restored in reverse)
+    journal_order: Vec<PathBuf>,
+    /// Directories created by writes in the current transaction
+    created_dirs: Vec<PathBuf>,
+    /// Every path touched since the last transaction began
+    touched: Vec<FileAccess>,
+}
+
+impl WorkspaceFs {
+    /// Create a new sandbox rooted at the given workspace path
+    pub fn new(workspace_path: &str) -> Self {
+        Self {
+            root: PathBuf::from(workspace_path),
+            journal: HashMap::new(),
+            journal_order: Vec::new(),
+            created_dirs: Vec::new(),
+            touched: Vec::new(),
+        }
+    }
+
+    /// Start a new transaction, discarding the previous journal and access log
+    pub fn begin_transaction(&mut self) {
+        self.journal.clear();
+        self.journal_order.clear();
+        self.created_dirs.clear();
+        self.touched.clear();
+    }
+
+    /// Paths touched since the last transaction began
+    pub fn touched_paths(&self) -> &[FileAccess] {
+        &self.touched
+    }
+
+    /// Whether any writes are pending in the current transaction
+    pub fn has_pending_writes(&self) -> bool {
+        !self.journal_order.is_empty()
+    }
+
+    /// Restore every path modified in the current transaction to its original state
+    /// Returns the relative paths that were restored
+    pub fn restore(&mut self) -> Result<Vec<String>, String> {
+        let mut restored = Vec::new();
+        let mut failures = Vec::new();
+
+        for path in self.journal_order.iter().rev() {
+            let original = match self.journal.get(path) {
+                Some(original) => original,
+                None => continue,
+            };
+
+            let result = match original {
+                Some(bytes) => path
+                    .parent()
+                    .map(std::fs::create_dir_all)
+                    .unwrap_or(Ok(()))
+                    .and_then(|_| std::fs::write(path, bytes)),
+                None if path.exists() => std::fs::remove_file(path),
+                None => Ok(()),
+            };
+
+            match result {
+                Ok(()) => restored.push(self.relative(path)),
+                Err(e) => failures.push(format!("{}: {}", self.relative(path), e)),
+            }
+        }
+
+        // Remove directories created during the transaction (only if now empty)
+        for dir in self.created_dirs.iter().rev() {
+            let _ = std::fs::remove_dir(dir);
+        }
+
+        self.journal.clear();
+        self.journal_order.clear();
+        self.created_dirs.clear();
+
+        if failures.is_empty() {
+            Ok(restored)
+        } else {
+            Err(format!("Failed to restore: {}", failures.join("; ")))
+        }
+    }
+
+    /// Read a UTF-8 file from the workspace
+    pub fn read_file(&mut self, path: &str) -> Result<String, String> {
+        let full = self.resolve(path)?;
+        let content = std::fs::read_to_string(&full)
+            .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+        self.record(&full, FileOperation::Read);
+        Ok(content)
+    }
+
+    /// Write a file, creating parent directories as needed
+    pub fn write_file(&mut self, path: &str, content: &str) -> Result<(), String> {
+        let full = self.resolve(path)?;
+        if full.is_dir() {
+            return Err(format!("Cannot write '{}': path is a directory", path));
+        }
+        self.journal_path(&full)?;
+        self.create_parent_dirs(&full)?;
+        std::fs::write(&full, content).map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+        self.record(&full, FileOperation::Write);
+        Ok(())
+    }
+
+    /// Apply a unified diff to a single file
+    /// Returns the number of hunks applied
+    pub fn apply_patch(&mut self, path: &str, patch: &str) -> Result<usize, String> {
+        let full = self.resolve(path)?;
+        let original = if full.exists() {
+            std::fs::read_to_string(&full)
+                .map_err(|e| format!("Failed to read '{}': {}", path, e))?
+        } else {
+            String::new()
+        };
+
+        let (patched, hunks) = apply_unified_patch(&original, patch)
+            .map_err(|e| format!("Failed to patch '{}': {}", path, e))?;
+
+        self.journal_path(&full)?;
+        self.create_parent_dirs(&full)?;
+        std::fs::write(&full, patched).map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+        self.record(&full, FileOperation::Patch);
+        Ok(hunks)
+    }
+
+    /// Rewrite an existing file in place with `edit`
+    /// The file is only written (and journaled) when the content changes
+    pub fn edit_file(
+        &mut self,
+        path: &str,
+        edit: impl FnOnce(&str) -> Result<String, String>,
+    ) -> Result<bool, String> {
+        let full = self.resolve(path)?;
+        let original = std::fs::read_to_string(&full)
+            .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+
+        let edited = edit(&original).map_err(|e| format!("Failed to edit '{}': {}", path, e))?;
+        if edited == original {
+            self.record(&full, FileOperation::Read);
+            return Ok(false);
+        }
+
+        self.journal_path(&full)?;
+        std::fs::write(&full, edited).map_err(|e| format!("Failed to write '{}': {}", path, e))?;
+        self.record(&full, FileOperation::Write);
+        Ok(true)
+    }
+
+    /// List the entries of a workspace directory (sorted by name)
+    pub fn list_dir(&mut self, path: &str) -> Result<Vec<DirEntryInfo>, String> {
+        let full = self.resolve(path)?;
+        let read_dir =
+            std::fs::read_dir(&full).map_err(|e| format!("Failed to list '{}': {}", path, e))?;
+
+        let mut entries = Vec::new();
+        for entry in read_dir.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if PROTECTED_DIRS.contains(&name.as_str()) {
+                continue;
+            }
+            let metadata = entry.metadata().ok();
+            entries.push(DirEntryInfo {
+                path: self.relative(&entry.path()),
+                name,
+                is_dir: metadata.as_ref().map(|m| m.is_dir()).unwrap_or(false),
+                size: metadata.as_ref().map(|m| m.len()).unwrap_or(0),
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        self.record(&full, FileOperation::List);
+        Ok(entries)
+    }
+
+    /// Move (rename) a file within the workspace
+    pub fn move_file(&mut self, from: &str, to: &str) -> Result<(), String> {
+        let src = self.resolve(from)?;
+        let dst = self.resolve(to)?;
+        if !src.is_file() {
+            return Err(format!("Cannot move '{}': not a file", from));
+        }
+        if dst.exists() {
+            return Err(format!(
+                "Cannot move to '{}': destination already exists",
+                to
+            ));
+        }
+
+        self.journal_path(&src)?;
+        self.journal_path(&dst)?;
+        self.create_parent_dirs(&dst)?;
+        std::fs::rename(&src, &dst)
+            .map_err(|e| format!("Failed to move '{}' to '{}': {}", from, to, e))?;
+
+        self.record(&src, FileOperation::Move);
+        self.record(&dst, FileOperation::Move);
+        Ok(())
+    }
+
+    /// Delete a file from the workspace
+    pub fn delete_file(&mut self, path: &str) -> Result<(), String> {
+        let full = self.resolve(path)?;
+        if !full.is_file() {
+            return Err(format!("Cannot delete '{}': not a file", path));
+        }
+        self.journal_path(&full)?;
+        std::fs::remove_file(&full).map_err(|e| format!("Failed to delete '{}': {}", path, e))?;
+        self.record(&full, FileOperation::Delete);
+        Ok(())
+    }
+
+    /// Resolve a script-supplied path to an absolute path inside the workspace
+    /// Rejects `..` traversal, absolute paths outside the root, protected directories
+    /// and symlinks that resolve outside of the workspace
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let root = std::fs::canonicalize(&self.root)
+            .map_err(|e| format!("Workspace root is not accessible: {}", e))?;
+
+        let requested = Path::new(path);
+        let relative = if requested.is_absolute() {
+            requested
+                .strip_prefix(&root)
+                .or_else(|_| requested.strip_prefix(&self.root))
+                .map_err(|_| format!("Path escapes workspace: {}", path))?
+        } else {
+            requested
+        };
+
+        let mut normalized = PathBuf::new();
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => normalized.push(part),
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    if !normalized.pop() {
+                        return Err(format!("Path escapes workspace: {}", path));
+                    }
+                }
+                Component::RootDir | Component::Prefix(_) => {
+                    return Err(format!("Path escapes workspace: {}", path));
+                }
+            }
+        }
+
+        if let Some(Component::Normal(first)) = normalized.components().next() {
+            if PROTECTED_DIRS.iter().any(|d| first == *d) {
+                return Err(format!("Access to protected path denied: {}", path));
+            }
+        }
+
+        let full = root.join(&normalized);
+
+        // Symlink escape check: canonicalize the deepest existing ancestor
+        let mut existing = full.as_path();
+        while std::fs::symlink_metadata(existing).is_err() {
+            existing = match existing.parent() {
+                Some(parent) => parent,
+                None => break,
+            };
+        }
+        let canonical = std::fs::canonicalize(existing)
+            .map_err(|e| format!("Failed to resolve '{}': {}", path, e))?;
+        if !canonical.starts_with(&root) {
+            return Err(format!("Path escapes workspace via symlink: {}", path));
+        }
+
+        Ok(full)
+    }
+
+    /// Capture the original state of a path before its first modification
+    fn journal_path(&mut self, full: &Path) -> Result<(), String> {
+        if self.journal.contains_key(full) {
+            return Ok(());
+        }
+        let original = if full.is_file() {
+            Some(
+                std::fs::read(full)
+                    .map_err(|e| format!("Failed to snapshot '{}': {}", self.relative(full), e))?,
+            )
+        } else {
+            None
+        };
+        self.journal.insert(full.to_path_buf(), original);
+        self.journal_order.push(full.to_path_buf());
+        Ok(())
+    }
+
+    /// Create missing parent directories, remembering them for rollback
+    fn create_parent_dirs(&mut self, full: &Path) -> Result<(), String> {
+        let parent = match full.parent() {
+            Some(parent) => parent,
+            None => return Ok(()),
+        };
+
+        let mut missing = Vec::new();
+        let mut current = parent;
+        while !current.exists() {
+            missing.push(current.to_path_buf());
+            current = match current.parent() {
+                Some(p) => p,
+                None => break,
+            };
+        }
+
+        for dir in missing.into_iter().rev() {
+            std::fs::create_dir(&dir)
+                .map_err(|e| format!("Failed to create '{}': {}", self.relative(&dir), e))?;
+            self.created_dirs.push(dir);
+        }
+        Ok(())
+    }
+
+    fn record(&mut self, full: &Path, operation: FileOperation) {
+        let path = self.relative(full);
+        self.touched.push(FileAccess { path, operation });
+    }
+
+    /// Convert an absolute path to a workspace-relative display path
+    fn relative(&self, full: &Path) -> String {
+        let root = std::fs::canonicalize(&self.root).unwrap_or_else(|_| self.root.clone());
+        let rel = full.strip_prefix(&root).unwrap_or(full);
+        let display = rel.to_string_lossy().replace('\\', "/");
+        if display.is_empty() {
+            ".".to_string()
+        } else {
+            display
+        }
+    }
+}
+
+/// A parsed unified diff hunk
+struct Hunk {
+    old_start: usize,
+    old_lines: Vec<String>,
+    new_lines: Vec<String>,
+}
+
+/// Apply a unified diff to the given content
+/// Hunks are matched at their declared position first, then searched for nearby
+/// to tolerate line drift. Returns the patched content and the number of hunks applied.
+pub fn apply_unified_patch(original: &str, patch: &str) -> Result<(String, usize), String> {
+    let hunks = parse_hunks(patch)?;
+    if hunks.is_empty() {
+        return Err("Patch contains no hunks".to_string());
+    }
+
+    let had_trailing_newline = original.is_empty() || original.ends_with('\n');
+    let mut lines: Vec<String> = original.lines().map(String::from).collect();
+    let mut offset: isize = 0;
+
+    for (idx, hunk) in hunks.iter().enumerate() {
+        let expected = (hunk.old_start.saturating_sub(1) as isize + offset).max(0) as usize;
+        let position = find_hunk_position(&lines, &hunk.old_lines, expected).ok_or_else(|| {
+            format!(
+                "Hunk {} does not match the file near line {}",
+                idx + 1,
+                hunk.old_start
+            )
+        })?;
+
+        lines.splice(
+            position..position + hunk.old_lines.len(),
+            hunk.new_lines.iter().cloned(),
+        );
+        offset += hunk.new_lines.len() as isize - hunk.old_lines.len() as isize;
+    }
+
+    let mut patched = lines.join("\n");
+    if had_trailing_newline && !patched.is_empty() {
+        patched.push('\n');
+    }
+    Ok((patched, hunks.len()))
+}
+
+fn parse_hunks(patch: &str) -> Result<Vec<Hunk>, String> {
+    let header =
+        regex::Regex::new(r"^@@ -(\d+)(?:,\d+)? \+\d+(?:,\d+)? @@").map_err(|e| e.to_string())?;
+
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+
+    for line in patch.lines() {
+        if let Some(caps) = header.captures(line) {
+            if let Some(hunk) = current.take() {
+                hunks.push(hunk);
+            }
+            current = Some(Hunk {
+                old_start: caps[1].parse().unwrap_or(0),
+                old_lines: Vec::new(),
+                new_lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let hunk = match current.as_mut() {
+            Some(hunk) => hunk,
+            // Skip file headers (---/+++/diff) before the first hunk
+            None => continue,
+        };
+
+        if let Some(rest) = line.strip_prefix('+') {
+            hunk.new_lines.push(rest.to_string());
+        } else if let Some(rest) = line.strip_prefix('-') {
+            hunk.old_lines.push(rest.to_string());
+        } else if let Some(rest) = line.strip_prefix(' ') {
+            hunk.old_lines.push(rest.to_string());
+            hunk.new_lines.push(rest.to_string());
+        } else if line.is_empty() {
+            // Editors often strip the leading space from blank context lines
+            hunk.old_lines.push(String::new());
+            hunk.new_lines.push(String::new());
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+        } else {
+            return Err(format!("Unexpected line in patch: {}", line));
+        }
+    }
+
+    if let Some(hunk) = current {
+        hunks.push(hunk);
+    }
+    Ok(hunks)
+}
+
+/// Find where a hunk's old lines match, searching outward from the expected line
+fn find_hunk_position(lines: &[String], old: &[String], expected: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    if old.len() > lines.len() {
+        return None;
+    }
+
+    let max_start = lines.len() - old.len();
+    let matches_at = |pos: usize| lines[pos..pos + old.len()] == *old;
+    let expected = expected.min(max_start);
+
+    for distance in 0..=max_start {
+        if expected + distance <= max_start && matches_at(expected + distance) {
+            return Some(expected + distance);
+        }
+        if distance > 0 && distance <= expected && matches_at(expected - distance) {
+            return Some(expected - distance);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sandbox() -> (tempfile::TempDir, WorkspaceFs) {
+        let dir = tempfile::tempdir().unwrap();
+        let fs = WorkspaceFs::new(dir.path().to_str().unwrap());
+        (dir, fs)
+    }
+
+    #[test]
+    fn test_write_and_read_roundtrip() {
+        let (_dir, mut fs) = sandbox();
+        fs.write_file("src/lib.rs", "fn main() {}\n").unwrap();
+        assert_eq!(fs.read_file("src/lib.rs").unwrap(), "fn main() {}\n");
+        assert_eq!(fs.touched_paths().len(), 2);
+        assert_eq!(fs.touched_paths()[0].path, "src/lib.rs");
+    }
+
+    #[test]
+    fn test_rejects_traversal() {
+        let (_dir, fs) = sandbox();
+        assert!(fs.resolve("../outside.txt").is_err());
+        assert!(fs.resolve("a/../../outside.txt").is_err());
+        assert!(fs.resolve("/etc/passwd").is_err());
+        assert!(fs.resolve(".git/config").is_err());
+        assert!(fs.resolve("a/../inside.txt").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_symlink_escape() {
+        let (dir, mut fs) = sandbox();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        assert!(fs.write_file("link/evil.txt", "x").is_err());
+        assert!(!outside.path().join("evil.txt").exists());
+    }
+
+    #[test]
+    fn test_restore_reverts_all_writes() {
+        let (dir, mut fs) = sandbox();
+        std::fs::write(dir.path().join("keep.txt"), "original").unwrap();
+        fs.begin_transaction();
+
+        fs.write_file("keep.txt", "changed").unwrap();
+        fs.write_file("new/dir/created.txt", "new").unwrap();
+        fs.move_file("keep.txt", "moved.txt").unwrap();
+
+        fs.restore().unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("keep.txt")).unwrap(),
+            "original"
+        );
+        assert!(!dir.path().join("moved.txt").exists());
+        assert!(!dir.path().join("new").exists());
+    }
+
+    #[test]
+    fn test_apply_unified_patch_with_drift() {
+        let original = "a\nb\nc\nd\ne\n";
+        let patch = "--- a/file\n+++ b/file\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n";
+        let (patched, hunks) = apply_unified_patch(original, patch).unwrap();
+        assert_eq!(patched, "a\nb\nC\nd\ne\n");
+        assert_eq!(hunks, 1);
+
+        // Same hunk declared at the wrong line still applies
+        let drifted = "@@ -1,3 +1,3 @@\n b\n-c\n+C\n d\n";
+        let (patched, _) = apply_unified_patch(original, drifted).unwrap();
+        assert_eq!(patched, "a\nb\nC\nd\ne\n");
+    }
+
+    #[test]
+    fn test_apply_unified_patch_mismatch() {
+        let patch = "@@ -1,1 +1,1 @@\n-missing\n+replacement\n";
+        assert!(apply_unified_patch("a\nb\n", patch).is_err());
+    }
+}
@@ -10,11 +10,14 @@ const EVENT_ICONS: Record<string, string> = {
   ScriptStart: '🚀',
   ScriptEnd: '✅',
   AtomSpawned: '⚛️',
+  AtomDelta: '✏️',
   AtomCompleted: '✨',
   ConsensusStart: '🗳️',
   ConsensusVote: '📊',
   ConsensusEnd: '🏆',
   RedFlagDetected: '🚩',
+  BudgetExceeded: '💸',
+  TestRun: '🧪',
   Snapshot: '📸',
   Rollback: '⏪',
   Error: '❌',
@@ -33,11 +36,14 @@ const EVENT_COLORS: Record<string, string> = {
   ScriptStart: '#4ade80',
   ScriptEnd: '#22c55e',
   AtomSpawned: '#60a5fa',
+  AtomDelta: '#93c5fd',
   AtomCompleted: '#3b82f6',
   ConsensusStart: '#a78bfa',
   ConsensusVote: '#8b5cf6',
   ConsensusEnd: '#7c3aed',
   RedFlagDetected: '#ef4444',
+  BudgetExceeded: '#f97316',
+  TestRun: '#14b8a6',
   Snapshot: '#fbbf24',
   Rollback: '#f97316',
   Error: '#dc2626',
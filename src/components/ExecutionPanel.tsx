@@ -104,6 +104,9 @@ const ExecutionPanel: React.FC = () => {
           <div className="mt-2 text-xs text-cyan-400 font-mono">
             Total: {((metrics?.total_tokens || 0) / 1000).toFixed(1)}K tokens
           </div>
+          <div className="text-xs text-zinc-500 font-mono">
+            Cache: {metrics?.cache_hits || 0} hits / {metrics?.cache_misses || 0} misses
+          </div>
         </div>
         <div className="bg-zinc-900 border border-zinc-800 p-4 rounded-lg">
           <div className="flex justify-between items-start">
@@ -146,7 +149,7 @@ const ExecutionPanel: React.FC = () => {
               VOTING IN PROGRESS
             </span>
           )}
-          {votingState?.winner_id && (
+          {votingState?.winner_id != null && (
             <span className="px-2 py-1 bg-emerald-500/10 text-emerald-400 text-xs border border-emerald-500/20 rounded font-mono hidden sm:inline-block">
               CONSENSUS REACHED
             </span>
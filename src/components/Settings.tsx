@@ -2,7 +2,7 @@ import React, { useState, useEffect } from 'react';
 import { X, Save, RotateCcw, Key, Cpu, Loader2, ChevronDown, Check, Link as LinkIcon, Zap, Sparkles, Bot, Code, Eye, TestTube, Thermometer, RefreshCw, Layers, Hash, FileText } from 'lucide-react';
 import { DEFAULT_AGENT_CONFIG } from '../constants';
 import { AgentConfig, ProviderConfig } from '../types';
-import { saveSettings, loadSettings, ApiKeys, AppSettings } from '../tauri-api';
+import { saveSettings, loadSettings, listLocalModels, ApiKeys, AppSettings, Budget, CacheConfig, ProviderLimits } from '../tauri-api';
 import { RLMConfig, useMakerStore } from '../store/makerStore';
 
 
@@ -71,6 +71,18 @@ const PROVIDERS_CONFIG: Record<string, ProviderData> = {
     models: ['llama3.3', 'llama3.2', 'qwen2.5-coder', 'deepseek-coder-v2', 'codellama', 'mistral', 'phi3'],
     fields: ['baseUrl'],
   },
+  llamacpp: {
+    id: 'llamacpp',
+    name: 'llama.cpp',
+    color: 'from-stone-400 to-stone-600',
+    bgColor: 'bg-stone-500',
+    textColor: 'text-stone-400',
+    borderColor: 'border-stone-500/50',
+    ringColor: 'ring-stone-500/30',
+    description: 'Local GGUF models via llama-server.',
+    models: [],
+    fields: ['baseUrl'],
+  },
   google: {
     id: 'google',
     name: 'Google',
@@ -231,9 +243,23 @@ interface ExtendedApiKeys extends ApiKeys {
   [key: string]: string | undefined;
 }
 
+const LOCAL_PROVIDERS = ['ollama', 'llamacpp'] as const;
+type LocalProviderId = typeof LOCAL_PROVIDERS[number];
+const isLocalProvider = (provider: string): provider is LocalProviderId =>
+  (LOCAL_PROVIDERS as readonly string[]).includes(provider);
+
+const LOCAL_URL_PLACEHOLDERS: Record<LocalProviderId, string> = {
+  ollama: 'http://localhost:11434',
+  llamacpp: 'http://localhost:8080',
+};
+
+// Helper to get the server URL for a local provider
+const getLocalUrl = (keys: ExtendedApiKeys, provider: LocalProviderId): string =>
+  (provider === 'ollama' ? keys.ollama_url : keys.llamacpp_url) || '';
+
 // Helper to get API key for a provider
 const getProviderApiKey = (keys: ExtendedApiKeys, provider: string): string => {
-  if (provider === 'ollama') return keys.ollama_url || '';
+  if (isLocalProvider(provider)) return getLocalUrl(keys, provider);
   return (keys as Record<string, string | undefined>)[provider] || '';
 };
 
@@ -242,9 +268,19 @@ const setProviderApiKey = (keys: ExtendedApiKeys, provider: string, value: strin
   if (provider === 'ollama') {
     return { ...keys, ollama_url: value };
   }
+  if (provider === 'llamacpp') {
+    return { ...keys, llamacpp_url: value };
+  }
   return { ...keys, [provider]: value };
 };
 
+// Token and atom limits are integers on the backend; only the cost takes a fraction
+const BUDGET_FIELDS: { key: keyof Budget; label: string; step: string; parse: (value: string) => number }[] = [
+  { key: 'max_tokens', label: 'Max Tokens', step: '1000', parse: (value) => parseInt(value, 10) },
+  { key: 'max_cost_usd', label: 'Max Cost ($)', step: '0.5', parse: parseFloat },
+  { key: 'max_atoms', label: 'Max Atoms', step: '1', parse: (value) => parseInt(value, 10) },
+];
+
 const Settings: React.FC = () => {
   const { settingsOpen: isOpen, setSettingsOpen } = useMakerStore();
   const onClose = () => setSettingsOpen(false);
@@ -257,6 +293,10 @@ const Settings: React.FC = () => {
     google: '',
     openrouter: '',
   });
+  const [budget, setBudget] = useState<Budget>({});
+  // Not editable here, but kept so saving does not turn the cache off or reset the limits
+  const [responseCache, setResponseCache] = useState<CacheConfig | null>(null);
+  const [providerLimits, setProviderLimits] = useState<ProviderLimits | undefined>(undefined);
   const [selectedProvider, setSelectedProvider] = useState<ProviderId>('openai');
   const [isSaving, setIsSaving] = useState(false);
   const [isLoading, setIsLoading] = useState(true);
@@ -294,6 +334,19 @@ const Settings: React.FC = () => {
 
     fetchModels();
   }, []);
+
+  // Local servers report their own models
+  useEffect(() => {
+    if (!isLocalProvider(selectedProvider)) return;
+    const provider = selectedProvider;
+    listLocalModels(provider, getLocalUrl(apiKeys, provider))
+      .then(models => {
+        if (models.length > 0) {
+          setProviderModels(prev => ({ ...prev, [provider]: models }));
+        }
+      })
+      .catch(err => console.warn(`Could not list ${provider} models:`, err));
+  }, [selectedProvider, apiKeys.ollama_url, apiKeys.llamacpp_url]);
   const [rlmConfig, setRlmConfig] = useState<RLMConfig>({
     max_depth: 3,
     max_iterations: 10,
@@ -310,6 +363,9 @@ const Settings: React.FC = () => {
         if (settings) {
           setConfig(settings.agent_config);
           setApiKeys(prev => ({ ...prev, ...settings.api_keys }));
+          setBudget(settings.budget ?? {});
+          setResponseCache(settings.response_cache ?? null);
+          setProviderLimits(settings.provider_limits);
         }
         setIsLoading(false);
       }).catch(() => setIsLoading(false));
@@ -325,7 +381,13 @@ const Settings: React.FC = () => {
   const handleSave = async () => {
     setIsSaving(true);
     try {
-      await saveSettings({ agent_config: config, api_keys: apiKeys });
+      await saveSettings({
+        agent_config: config,
+        api_keys: apiKeys,
+        budget,
+        provider_limits: providerLimits,
+        response_cache: responseCache,
+      });
       onClose();
     } catch (error) {
       console.error('Failed to save settings:', error);
@@ -338,6 +400,7 @@ const Settings: React.FC = () => {
   const handleReset = () => {
     setConfig(DEFAULT_AGENT_CONFIG as AgentConfig);
     setApiKeys({ openai: '', anthropic: '', cerebras: '', ollama_url: 'http://localhost:11434', google: '', openrouter: '' });
+    setBudget({});
   };
 
   const currentProviderData = PROVIDERS_CONFIG[selectedProvider];
@@ -495,8 +558,8 @@ const Settings: React.FC = () => {
                 {currentProviderData.fields.includes('baseUrl') && (
                   <div className="group">
                     <label className="block text-xs font-semibold text-zinc-400 mb-2 group-focus-within:text-white transition-colors">
-                      {selectedProvider === 'ollama' ? 'Server URL' : 'Base URL'}
-                      {selectedProvider !== 'ollama' && <span className="text-zinc-600 font-normal ml-1">(Optional)</span>}
+                      {isLocalProvider(selectedProvider) ? 'Server URL' : 'Base URL'}
+                      {!isLocalProvider(selectedProvider) && <span className="text-zinc-600 font-normal ml-1">(Optional)</span>}
                     </label>
                     <div className="relative">
                       <div className="absolute inset-y-0 left-0 pl-4 flex items-center pointer-events-none text-zinc-500">
@@ -504,9 +567,11 @@ const Settings: React.FC = () => {
                       </div>
                       <input
                         type="text"
-                        value={apiKeys.ollama_url || ''}
-                        onChange={(e) => setApiKeys(prev => ({ ...prev, ollama_url: e.target.value }))}
-                        placeholder={selectedProvider === 'ollama' ? 'http://localhost:11434' : 'https://api.openai.com/v1'}
+                        value={isLocalProvider(selectedProvider) ? getLocalUrl(apiKeys, selectedProvider) : apiKeys.ollama_url || ''}
+                        onChange={(e) => setApiKeys(prev => isLocalProvider(selectedProvider)
+                          ? setProviderApiKey(prev, selectedProvider, e.target.value)
+                          : { ...prev, ollama_url: e.target.value })}
+                        placeholder={isLocalProvider(selectedProvider) ? LOCAL_URL_PLACEHOLDERS[selectedProvider] : 'https://api.openai.com/v1'}
                         className="w-full bg-zinc-950 border border-zinc-700 text-white rounded-xl py-3 pl-12 pr-4 focus:outline-none focus:ring-2 focus:ring-zinc-600 focus:border-transparent transition-all placeholder:text-zinc-600"
                       />
                     </div>
@@ -545,6 +610,36 @@ const Settings: React.FC = () => {
                   onChange={handleAgentChange}
                 />
               ))}
+
+              {/* Run Budget */}
+              <div className="bg-zinc-900/50 rounded-2xl border border-zinc-800/50 p-6 space-y-4 mt-6">
+                <div className="flex items-center gap-2">
+                  <Hash size={16} className="text-indigo-400" />
+                  <h4 className="text-sm font-semibold text-white">Run Budget</h4>
+                </div>
+                <p className="text-xs text-zinc-500">
+                  Hard limits per script run. A run that exceeds them is aborted and rolled back. Leave empty for no limit.
+                </p>
+                <div className="grid grid-cols-3 gap-4">
+                  {BUDGET_FIELDS.map(({ key, label, step, parse }) => (
+                    <div key={key}>
+                      <label className="block text-xs text-zinc-400 mb-2">{label}</label>
+                      <input
+                        type="number"
+                        min="0"
+                        step={step}
+                        value={budget[key] ?? ''}
+                        placeholder="Unlimited"
+                        onChange={(e) => {
+                          const value = parse(e.target.value);
+                          setBudget(prev => ({ ...prev, [key]: value > 0 ? value : null }));
+                        }}
+                        className="w-full bg-zinc-800 border border-zinc-700 rounded-lg px-3 py-2 text-white text-sm focus:outline-none focus:ring-2 focus:ring-indigo-500/50"
+                      />
+                    </div>
+                  ))}
+                </div>
+              </div>
             </div>
           ) : (
             /* RLM Configuration Tab */
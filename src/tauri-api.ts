@@ -38,11 +38,39 @@ export interface ApiKeys {
   anthropic: string;
   cerebras: string;
   ollama_url: string;
+  llamacpp_url?: string;
+  openrouter?: string;
+}
+
+/** Per-run limits; null or missing means unlimited */
+export interface Budget {
+  max_tokens?: number | null;
+  max_cost_usd?: number | null;
+  max_atoms?: number | null;
+}
+
+/** Response cache limits */
+export interface CacheConfig {
+  ttl_secs: number;
+  max_entries: number;
+  max_bytes: number;
+}
+
+/** Limits for each provider endpoint; null or missing means unlimited */
+export interface ProviderLimits {
+  max_concurrent?: number | null;
+  requests_per_minute?: number | null;
+  tokens_per_minute?: number | null;
+  breaker?: { failure_threshold: number; open_secs: number };
 }
 
 export interface AppSettings {
   agent_config: AgentConfig;
   api_keys: ApiKeys;
+  budget?: Budget;
+  provider_limits?: ProviderLimits;
+  /** Cache spawn_atom responses in .maker/cache; null or missing disables it */
+  response_cache?: CacheConfig | null;
 }
 
 export interface PRDAnalysisResult {
@@ -74,6 +102,11 @@ export async function loadSettings(): Promise<AppSettings | null> {
   }
 }
 
+/** List models on a local Ollama or llama.cpp server (empty baseUrl = default URL) */
+export async function listLocalModels(provider: 'ollama' | 'llamacpp', baseUrl?: string): Promise<string[]> {
+  return await invoke<string[]>('list_local_models', { provider, baseUrl: baseUrl || null });
+}
+
 // ============================================================================
 // L1 Interrogation Commands
 // ============================================================================
@@ -198,13 +231,13 @@ export async function initRuntime(workspacePath: string): Promise<string> {
 // Grits Commands
 // ============================================================================
 
-// P3-1: Layer violation type matching Rust LayerViolation struct (analysis.rs:758)
+// P3-1: Layer violation type matching Rust LayerViolationDetail struct (virtual_apply.rs)
 export interface LayerViolation {
-  from_node: string;
+  from_symbol: string;
   from_layer: string;
-  to_node: string;
+  to_symbol: string;
   to_layer: string;
-  violation_type: string; // "upstream_dependency", "cycle", etc.
+  message: string;
 }
 
 // P3-1: Updated to match Rust RedFlagResult struct
@@ -217,6 +250,7 @@ export interface RedFlagResult {
   solid_score: number;
   cycles_detected: string[][];
   layer_violations: LayerViolation[];
+  betti_1_delta: number;
   layer_config_loaded: boolean;
 }
 
@@ -279,6 +313,8 @@ export interface ExecutionMetrics {
   red_flag_count: number;
   shadow_commits: number;
   last_updated_ms: number;
+  cache_hits?: number;
+  cache_misses?: number;
 }
 
 export async function getExecutionMetrics(): Promise<ExecutionMetrics> {
@@ -324,6 +360,7 @@ export interface VotingCandidate {
   red_flags: string[];
   status: string; // "pending", "accepted", "rejected"
   votes: number;
+  hash?: string | null; // equivalence class; null if discarded
 }
 
 export interface VotingState {
@@ -334,8 +371,14 @@ export interface VotingState {
   winner_id: number | null;
 }
 
-export async function getVotingState(): Promise<VotingState> {
-  return await invoke<VotingState>('get_voting_state');
+/** Voting state of a consensus run; without a task id, of the run that began last */
+export async function getVotingState(taskId?: string): Promise<VotingState> {
+  return await invoke<VotingState>('get_voting_state', { task_id: taskId ?? null });
+}
+
+/** Voting state of every tracked consensus run, oldest first */
+export async function getVotingStates(): Promise<VotingState[]> {
+  return await invoke<VotingState[]>('get_voting_states');
 }
 
 export async function startVoting(taskId: string, taskDescription: string): Promise<void> {
@@ -362,6 +405,34 @@ export async function clearVotingState(): Promise<void> {
   await invoke('clear_voting_state');
 }
 
+/** Consensus outcomes of one atom type, from the voting ledger */
+export interface AtomTypeStats {
+  runs: number;
+  converged: number;
+  convergence_rate: number;
+  avg_atoms_to_consensus: number | null;
+  avg_tokens: number;
+}
+
+/** Candidate outcomes of one model, from the voting ledger */
+export interface ModelStats {
+  candidates: number;
+  red_flagged: number;
+  failed: number;
+  red_flag_rate: number;
+  wins: number;
+}
+
+export interface LedgerStats {
+  runs: number;
+  atom_types: Record<string, AtomTypeStats>;
+  models: Record<string, ModelStats>;
+}
+
+export async function getConsensusStats(workspacePath: string): Promise<LedgerStats> {
+  return await invoke<LedgerStats>('get_consensus_stats', { workspace_path: workspacePath });
+}
+
 // ============================================================================
 // Shadow Git Commands - Transactional File System
 // ============================================================================
@@ -371,6 +442,8 @@ export interface Snapshot {
   message: string;
   timestamp_ms: number;
   commit_hash: string | null;
+  /** Plan run that owns this snapshot */
+  run_id?: string | null;
 }
 
 export interface HistoryEntry {
@@ -498,6 +571,66 @@ export async function parsePlan(planContent: string): Promise<ParsedPlan> {
   return await invoke<ParsedPlan>('parse_plan', { plan_content: planContent });
 }
 
+export type TaskState = 'Pending' | 'Running' | 'Completed' | 'Skipped' | { Failed: string };
+
+export interface TaskOutcome {
+  atom_result: AtomResult;
+  governance: RedFlagResult;
+  context_error: string | null;
+}
+
+export interface TaskReport {
+  task_id: string;
+  description: string;
+  state: TaskState;
+  reason: string | null;
+  outcome: TaskOutcome | null;
+  duration_ms: number;
+}
+
+export interface KSelection {
+  atom_type: string;
+  k: number;
+  success_rate: number | null;
+  samples: number;
+  step_reliability: number | null;
+  plan_reliability: number | null;
+  plan_steps: number;
+  target_reliability: number;
+  meets_target: boolean;
+}
+
+export interface PlanExecutionReport {
+  plan_id: string;
+  tasks: TaskReport[];
+  completed: number;
+  failed: number;
+  skipped: number;
+  tokens_used: number;
+  cost_usd: number;
+  consensus_k: KSelection[];
+  duration_ms: number;
+}
+
+/**
+ * Run a PLAN.md as a dependency DAG: independent tasks run in parallel and a failed
+ * task only skips its dependents. Progress is saved under .maker/runs/<plan_id>/.
+ */
+export async function executePlan(planContent: string, workspacePath: string): Promise<PlanExecutionReport> {
+  return await invokeWithErrorHandling<PlanExecutionReport>('execute_plan', {
+    plan_content: planContent,
+    workspace_path: workspacePath,
+  });
+}
+
+/** Resume an interrupted plan run from its last snapshot */
+export async function resumePlan(planId: string, workspacePath: string): Promise<PlanExecutionReport> {
+  return await invokeWithErrorHandling<PlanExecutionReport>('resume_plan', {
+    plan_id: planId,
+    workspace_path: workspacePath,
+  });
+}
+
 // ============================================================================
 // L3 Context Engineer Commands
 // ============================================================================
@@ -633,6 +766,9 @@ export interface AtomResult {
   errors: string[];
   execution_time_ms: number;
   tokens_used: number;
+  prompt_tokens: number;
+  completion_tokens: number;
+  cost_usd?: number;
   metadata: Record<string, string>;
 }
 
@@ -985,6 +1121,8 @@ export interface TestFrameworkInfo {
   test_command: string;
   test_pattern: string;
   config_file: string | null;
+  check_command?: string | null;
+  runner?: TestRunner | null;
 }
 
 export interface FailedTest {
@@ -993,15 +1131,29 @@ export interface FailedTest {
   error: string;
 }
 
+export type TestRunner = 'cargo' | 'nextest' | 'jest' | 'vitest' | 'pytest' | 'go_test';
+
+export interface TestCaseResult {
+  name: string;
+  file: string | null;
+  status: 'passed' | 'failed' | 'skipped';
+  duration_ms: number | null;
+  message: string | null;
+}
+
 export interface TestExecutionResult {
   success: boolean;
+  runner: TestRunner | null;
+  command: string;
   total_tests: number;
   passed: number;
   failed: number;
   skipped: number;
   duration_ms: number;
   output: string;
+  tests: TestCaseResult[];
   failed_tests: FailedTest[];
+  affected_files: string[] | null;
 }
 
 export interface GeneratedTest {
@@ -1019,12 +1171,14 @@ export async function detectTestFramework(workspacePath: string): Promise<TestFr
 export async function runTests(
   workspacePath: string,
   testPattern?: string,
-  timeoutSeconds?: number
+  timeoutSeconds?: number,
+  changedFiles?: string[]
 ): Promise<TestExecutionResult> {
   return await invoke<TestExecutionResult>('run_tests', {
     workspace_path: workspacePath,
     test_pattern: testPattern ?? null,
-    timeout_seconds: timeoutSeconds ?? null
+    timeout_seconds: timeoutSeconds ?? null,
+    changed_files: changedFiles ?? null
   });
 }
 
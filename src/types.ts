@@ -56,7 +56,7 @@ export interface VoteCandidate {
 
 // Settings types
 export interface ProviderConfig {
-  provider: 'openai' | 'anthropic' | 'cerebras' | 'ollama';
+  provider: 'openai' | 'anthropic' | 'cerebras' | 'ollama' | 'llamacpp';
   model: string;
   temperature: number;
   apiKey?: string;